@@ -0,0 +1,141 @@
+//! Abort path analysis, which checks that the flight can never get stuck after launch.
+//!
+//! Every state that can be entered after launch is detected must have a path through checks and
+//! timeouts to a state marked `abort_safe`, so that whatever goes wrong in flight, the state
+//! machine has somewhere safe to go. A state with no such path warns with the `abort_path` lint.
+//!
+//! Configs opt in by marking at least one state `abort_safe`. Without one there is nothing to
+//! reach, and the analysis is skipped.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    duration::{reachable, successors},
+    lint, upper, Context, Span,
+};
+
+/// Finds every state that can be entered after launch is detected
+fn post_launch_states<'a>(
+    config: &'a upper::ConfigFile,
+    states: &HashMap<&'a str, &'a upper::State>,
+) -> HashSet<&'a str> {
+    let pre_launch = lint::pre_launch_states(config);
+    let launched = pre_launch
+        .keys()
+        .flat_map(|name| &states[name].checks)
+        .map(|c| c.get_ref())
+        .filter(|c| lint::is_launch_detection(c))
+        .filter_map(|c| c.transition.as_ref().map(|t| t.get_ref().as_str()));
+    reachable(config, states, launched, |_| false)
+}
+
+/// Warns about every state that can be entered after launch but has no path to an abort safe
+/// state
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let states: HashMap<&str, &upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| (s.get_ref().name.get_ref().as_str(), s.get_ref()))
+        .collect();
+    let mut safe: HashSet<&str> = states
+        .iter()
+        .filter(|(_, s)| s.abort_safe)
+        .map(|(name, _)| *name)
+        .collect();
+    if safe.is_empty() {
+        return;
+    }
+    // Grow the set of states that can reach an abort safe state until nothing changes
+    loop {
+        let reaching: Vec<&str> = states
+            .iter()
+            .filter(|(name, _)| !safe.contains(*name))
+            .filter(|(_, s)| successors(config, s).iter().any(|n| safe.contains(n)))
+            .map(|(name, _)| *name)
+            .collect();
+        if reaching.is_empty() {
+            break;
+        }
+        safe.extend(reaching);
+    }
+
+    let post_launch = post_launch_states(config, &states);
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref().as_str();
+        if !post_launch.contains(name) || safe.contains(name) {
+            continue;
+        }
+        let span = Span::from_spanned(context, &state.name);
+        context
+            .warn(format!("state `{name}` has no path to an abort safe state"))
+            .set_primary_span(span, "can be entered after launch")
+            .lint("abort_path")
+            .emit();
+        context
+            .help("add a check or timeout that leads towards a state with `abort_safe = true`, or mark this state `abort_safe` if it is safe to end the flight in")
+            .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    fn warnings(config: &str) -> Vec<String> {
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        diagnostics.into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn abort_paths() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Descent"
+
+[[states]]
+name = "Descent"
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+        assert_eq!(
+            warnings(config),
+            [
+                "state `Flight` has no path to an abort safe state",
+                "add a check or timeout that leads towards a state with `abort_safe = true`, or mark this state `abort_safe` if it is safe to end the flight in",
+                "state `Descent` has no path to an abort safe state",
+                "add a check or timeout that leads towards a state with `abort_safe = true`, or mark this state `abort_safe` if it is safe to end the flight in",
+            ]
+        );
+
+        let timeout = config.replace(
+            "name = \"Descent\"\n",
+            "name = \"Descent\"\n\n[states.timeout]\nseconds = 300.0\ntransition = \"Safe\"\n",
+        );
+        assert!(warnings(&timeout).is_empty());
+
+        // Without an abort safe state there is nothing to reach
+        assert!(warnings(&config.replace("abort_safe = true", "")).is_empty());
+    }
+}
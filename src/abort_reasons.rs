@@ -0,0 +1,154 @@
+//! Reason codes recorded by aborts.
+//!
+//! An abort written as `abort = { state = "Safe", reason = "NO_CONTINUITY" }` records why the
+//! flight was aborted. Reasons come from [`registry::ABORT_REASONS`], which gives each a stable
+//! code. The encoded config has nowhere to store a reason yet, so the codes of every abort are
+//! exported with `--emit abort-reasons` for ground stations and post-flight analysis to look up
+//! by state and check name.
+
+use serde::Serialize;
+
+use crate::{registry, upper, Context, ErrorCode, Span};
+
+/// The version of the document produced by [`to_json`]
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// Returns the reason called `name`, emitting an error at `span` if there is none
+pub fn lookup(
+    name: &str,
+    span: Span,
+    context: &mut Context,
+) -> Result<&'static registry::AbortReason, ()> {
+    if let Some(reason) = registry::ABORT_REASONS.iter().find(|r| r.name == name) {
+        return Ok(reason);
+    }
+    context
+        .error(format!("unknown abort reason `{name}`"))
+        .code(ErrorCode::UnknownAbortReason)
+        .set_primary_span(span, "not a registered reason")
+        .emit();
+    let names: Vec<_> = registry::ABORT_REASONS
+        .iter()
+        .map(|r| format!("`{}`", r.name))
+        .collect();
+    context
+        .help(format!("the known reasons are {}", names.join(", ")))
+        .emit();
+    Err(())
+}
+
+#[derive(Serialize)]
+struct Reason {
+    code: u8,
+    name: &'static str,
+    description: &'static str,
+}
+
+#[derive(Serialize)]
+struct Abort<'a> {
+    state: &'a str,
+    check: &'a str,
+    code: u8,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    format_version: u32,
+    reasons: Vec<Reason>,
+    aborts: Vec<Abort<'a>>,
+}
+
+/// Returns the code of every check in `config` that aborts, along with every registered reason,
+/// as JSON. Aborts without a reason have the code of `UNSPECIFIED`.
+///
+/// `config` must have been lowered successfully, so that every reason is registered
+pub fn to_json(config: &upper::ConfigFile) -> String {
+    let code = |name: &str| {
+        registry::ABORT_REASONS
+            .iter()
+            .find(|r| r.name == name)
+            .map_or(0, |r| r.code)
+    };
+    let mut aborts = Vec::new();
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        for check in state.checks.iter().map(|c| c.get_ref()) {
+            let abort = match check.abort.as_ref().map(|a| a.get_ref()) {
+                None | Some(upper::AbortTarget::Global(false)) => continue,
+                Some(abort) => abort,
+            };
+            aborts.push(Abort {
+                state: state.name.get_ref(),
+                check: check.name.get_ref(),
+                code: abort.reason().map_or(0, code),
+            });
+        }
+    }
+    let reasons = registry::ABORT_REASONS
+        .iter()
+        .map(|r| Reason {
+            code: r.code,
+            name: r.name,
+            description: r.description,
+        })
+        .collect();
+    let document = Document {
+        format_version: JSON_FORMAT_VERSION,
+        reasons,
+        aborts,
+    };
+    // All keys are strings and all values are plain data, so this cannot fail
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use crate::Session;
+
+    const CONFIG: &str = r#"
+abort_state = "Safe"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Continuity"
+check = "pyro1_continuity"
+flag = "unset"
+abort = { reason = "REASON" }
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+abort = "Safe"
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+
+    #[test]
+    fn reasons() {
+        let mut session = Session::new();
+        let config = CONFIG.replace("REASON", "NO_CONTINUITY");
+        let (upper, _, _) = crate::load_config(&mut session, config, "a.toml".to_owned()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&super::to_json(&upper)).unwrap();
+        let codes: Vec<_> = json["aborts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| (a["check"].as_str().unwrap(), a["code"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(codes, [("Continuity", 1), ("Apogee", 0)]);
+
+        let mut session = Session::new();
+        let config = CONFIG.replace("REASON", "NO_CONTINUTY");
+        let err = crate::load_config(&mut session, config, "a.toml".to_owned()).unwrap_err();
+        assert!(matches!(err, crate::VerifyError::Lower(_)));
+        let errors = err.diagnostics();
+        assert_eq!(errors[0].level, Level::Error);
+        assert_eq!(errors[0].message, "unknown abort reason `NO_CONTINUTY`");
+    }
+}
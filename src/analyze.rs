@@ -0,0 +1,405 @@
+//! Post-flight analysis of state transition logs.
+//!
+//! The flight computer logs a record every time it enters a state. Comparing that log against the
+//! config tells us which checks actually fired, which transitions happened that the config does not
+//! explain, and which commands never got a chance to run.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::upper::{self, AbortTarget};
+
+/// A single state entry recorded by the flight computer
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry {
+    /// Seconds since the flight computer powered on
+    pub time: f32,
+
+    /// The name of the state that was entered
+    pub state: String,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct LogError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parses a flight log with one `time,state` record per line, where `time` is in seconds and
+/// `state` is either a state name or its index in `config`.
+/// Blank lines, lines starting with `#`, and a `time,state` header are ignored
+pub fn parse_log(text: &str, config: &upper::ConfigFile) -> Result<Vec<LogEntry>, LogError> {
+    let states = config.states.get_ref();
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line == "time,state" {
+            continue;
+        }
+        let err = |message: String| LogError {
+            line: i + 1,
+            message,
+        };
+        let (time, state) = line
+            .split_once(',')
+            .ok_or_else(|| err(format!("expected `time,state`, found `{line}`")))?;
+        let time: f32 = time
+            .trim()
+            .parse()
+            .map_err(|_| err(format!("invalid time `{}`", time.trim())))?;
+        let state = state.trim();
+
+        let name = match state.parse::<usize>() {
+            Ok(index) => states
+                .get(index)
+                .map(|s| s.get_ref().name.get_ref().clone())
+                .ok_or_else(|| err(format!("state index {index} is out of range")))?,
+            Err(_) if states.iter().any(|s| s.get_ref().name.get_ref() == state) => {
+                state.to_owned()
+            }
+            Err(_) => return Err(err(format!("unknown state `{state}`"))),
+        };
+        if let Some(last) = entries.last() {
+            if time < last.time {
+                return Err(err(format!("time {time} is before the previous record")));
+            }
+        }
+        entries.push(LogEntry { time, state: name });
+    }
+    Ok(entries)
+}
+
+/// Something in the config that explains a transition
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cause {
+    /// The named check transitioned or aborted to the new state
+    Check(String),
+
+    /// The state's timeout expired
+    Timeout,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservedTransition {
+    pub time: f32,
+    pub from: String,
+    pub to: String,
+
+    /// Everything in the config that could have caused this transition.
+    /// If this is empty, the transition diverged from the config
+    pub causes: Vec<Cause>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotExecuted {
+    /// The command's state was never entered
+    StateNeverEntered,
+
+    /// The state was left before the command's delay elapsed.
+    /// Contains the longest time spent in the state
+    LeftBeforeDelay(f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnexecutedCommand {
+    pub state: String,
+
+    /// The index of the command within its state
+    pub index: usize,
+
+    pub delay: f32,
+    pub reason: NotExecuted,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// Set if the log does not start in the config's default state
+    pub unexpected_initial_state: Option<String>,
+
+    pub transitions: Vec<ObservedTransition>,
+
+    /// `(state, check)` pairs for checks in visited states that never fired
+    pub unfired_checks: Vec<(String, String)>,
+
+    pub unexecuted_commands: Vec<UnexecutedCommand>,
+}
+
+fn abort_target<'a>(config: &'a upper::ConfigFile, check: &'a upper::Check) -> Option<&'a str> {
+    match check.abort.as_ref()?.get_ref() {
+        AbortTarget::State(name)
+        | AbortTarget::Detailed(upper::AbortDetails {
+            state: Some(name), ..
+        }) => Some(name),
+        AbortTarget::Global(true) | AbortTarget::Detailed(_) => {
+            config.abort_state.as_ref().map(|s| s.get_ref().as_str())
+        }
+        AbortTarget::Global(false) => None,
+    }
+}
+
+fn find_state<'a>(config: &'a upper::ConfigFile, name: &str) -> Option<&'a upper::State> {
+    config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .find(|s| s.name.get_ref() == name)
+}
+
+/// Compares the state entries in `log` against `config`.
+/// Every state in `log` must exist in `config`, which [`parse_log`] guarantees
+pub fn analyze(config: &upper::ConfigFile, log: &[LogEntry]) -> Analysis {
+    let default_state = match &config.default_state {
+        Some(name) => name.get_ref().as_str(),
+        None => config.states.get_ref()[0].get_ref().name.get_ref().as_str(),
+    };
+    let unexpected_initial_state = log
+        .first()
+        .filter(|entry| entry.state != default_state)
+        .map(|entry| entry.state.clone());
+
+    let mut transitions = Vec::new();
+    let mut fired: Vec<(&str, &str)> = Vec::new();
+    for pair in log.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        let state = find_state(config, &from.state).unwrap();
+        let mut causes = Vec::new();
+        for check in &state.checks {
+            let check = check.get_ref();
+            let target = check.transition.as_ref().map(|t| t.get_ref().as_str());
+            if target == Some(to.state.as_str()) || abort_target(config, check) == Some(&to.state) {
+                causes.push(Cause::Check(check.name.get_ref().clone()));
+                fired.push((&from.state, check.name.get_ref()));
+            }
+        }
+        let timeout_target = state
+            .timeout
+            .as_ref()
+            .and_then(|t| t.get_ref().transition.as_ref());
+        if timeout_target.map(|t| t.get_ref()) == Some(&to.state) {
+            causes.push(Cause::Timeout);
+        }
+        transitions.push(ObservedTransition {
+            time: to.time,
+            from: from.state.clone(),
+            to: to.state.clone(),
+            causes,
+        });
+    }
+
+    let mut unfired_checks = Vec::new();
+    let mut unexecuted_commands = Vec::new();
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref();
+        // How long each visit to this state lasted. `None` for the final state in the log, which we
+        // never saw the flight computer leave
+        let visits: Vec<Option<f32>> = log
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| &entry.state == name)
+            .map(|(i, entry)| log.get(i + 1).map(|next| next.time - entry.time))
+            .collect();
+
+        if !visits.is_empty() {
+            for check in &state.checks {
+                let check_name = check.get_ref().name.get_ref();
+                if !fired.contains(&(name.as_str(), check_name.as_str())) {
+                    unfired_checks.push((name.clone(), check_name.clone()));
+                }
+            }
+        }
+
+        for (index, command) in state.commands.iter().enumerate() {
+            let delay = command
+                .get_ref()
+                .delay
+                .as_ref()
+                .map_or(0.0, |d| *d.get_ref());
+            let executed = visits
+                .iter()
+                .any(|duration| !matches!(duration, Some(duration) if *duration < delay));
+            if executed {
+                continue;
+            }
+            let reason = match visits.iter().flatten().copied().reduce(f32::max) {
+                Some(longest) => NotExecuted::LeftBeforeDelay(longest),
+                None => NotExecuted::StateNeverEntered,
+            };
+            unexecuted_commands.push(UnexecutedCommand {
+                state: name.clone(),
+                index,
+                delay,
+                reason,
+            });
+        }
+    }
+
+    Analysis {
+        unexpected_initial_state,
+        transitions,
+        unfired_checks,
+        unexecuted_commands,
+    }
+}
+
+impl Analysis {
+    /// Returns true if the flight did anything the config does not explain
+    pub fn diverged(&self) -> bool {
+        self.unexpected_initial_state.is_some()
+            || self.transitions.iter().any(|t| t.causes.is_empty())
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(state) = &self.unexpected_initial_state {
+            writeln!(f, "warning: log starts in `{state}`, not the default state")?;
+        }
+        writeln!(f, "transitions:")?;
+        for t in &self.transitions {
+            write!(f, "  {:>9.3}s  {} -> {}", t.time, t.from, t.to)?;
+            if t.causes.is_empty() {
+                writeln!(
+                    f,
+                    "  DIVERGED: nothing in the config causes this transition"
+                )?;
+                continue;
+            }
+            let causes: Vec<String> = t
+                .causes
+                .iter()
+                .map(|c| match c {
+                    Cause::Check(name) => format!("check `{name}`"),
+                    Cause::Timeout => "timeout".to_owned(),
+                })
+                .collect();
+            writeln!(f, "  ({})", causes.join(" or "))?;
+        }
+        if !self.unfired_checks.is_empty() {
+            writeln!(f, "checks that never fired:")?;
+            for (state, check) in &self.unfired_checks {
+                writeln!(f, "  {state}: {check}")?;
+            }
+        }
+        if !self.unexecuted_commands.is_empty() {
+            writeln!(f, "commands that never executed:")?;
+            for c in &self.unexecuted_commands {
+                let reason = match c.reason {
+                    NotExecuted::StateNeverEntered => "state never entered".to_owned(),
+                    NotExecuted::LeftBeforeDelay(longest) => {
+                        format!("state left after {longest:.3}s at most")
+                    }
+                };
+                writeln!(
+                    f,
+                    "  {}: command {} with delay {}s ({reason})",
+                    c.state, c.index, c.delay
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze, parse_log, Cause, NotExecuted};
+    use crate::{upper, Session};
+
+    const CONFIG: &str = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 100.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Descent"
+
+[states.timeout]
+seconds = 30.0
+transition = "Descent"
+
+[[states]]
+name = "Descent"
+
+[[states.commands]]
+pyro1 = true
+delay = 5.0
+
+[[states]]
+name = "Recovery"
+
+[[states.commands]]
+beacon = true
+"#;
+
+    #[test]
+    fn analyze_flight() {
+        let mut session = Session::new();
+        let mut context = session.testing(CONFIG);
+        let config = upper::verify(&mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        let log = parse_log(
+            "time,state\n0.0,Armed\n12.5,1\n40.0,Descent\n42.0,Armed\n",
+            &config,
+        )
+        .unwrap();
+        let analysis = analyze(&config, &log);
+
+        assert_eq!(analysis.unexpected_initial_state, None);
+        assert_eq!(
+            analysis.transitions[0].causes,
+            [Cause::Check("Launch".to_owned())]
+        );
+        assert_eq!(
+            analysis.transitions[1].causes,
+            [Cause::Check("Apogee".to_owned()), Cause::Timeout]
+        );
+        assert!(analysis.transitions[2].causes.is_empty());
+        assert!(analysis.diverged());
+
+        let unexecuted: Vec<_> = analysis
+            .unexecuted_commands
+            .iter()
+            .map(|c| (c.state.as_str(), c.reason.clone()))
+            .collect();
+        assert_eq!(
+            unexecuted,
+            [
+                ("Descent", NotExecuted::LeftBeforeDelay(2.0)),
+                ("Recovery", NotExecuted::StateNeverEntered)
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_logs() {
+        let mut session = Session::new();
+        let mut context = session.testing(CONFIG);
+        let config = upper::verify(&mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        assert_eq!(parse_log("0.0,Nope", &config).unwrap_err().line, 1);
+        assert_eq!(parse_log("0.0,Armed\n1.0,9", &config).unwrap_err().line, 2);
+        assert_eq!(
+            parse_log("5.0,Armed\n1.0,Flight", &config)
+                .unwrap_err()
+                .line,
+            2
+        );
+        assert_eq!(parse_log("\n\nArmed", &config).unwrap_err().line, 3);
+    }
+}
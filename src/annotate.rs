@@ -0,0 +1,219 @@
+//! Renders config source with annotations underneath the values they point at.
+//!
+//! Diagnostics are printed by `codemap_diagnostic`, which only knows how to write whole
+//! diagnostics to a terminal. Reports, editor hovers, and diff summaries need the same view of the
+//! source with labels under values, but as a string and without the diagnostic around it:
+//!
+//! ```text
+//! 3 | seconds = 10.0
+//!   |           ^^^^ too long
+//! ```
+//!
+//! Annotations are byte ranges into the source, the same ranges that [`SpannedValue`] records.
+
+use crate::spanned::SpannedValue;
+use codemap_diagnostic::{Diagnostic, SpanStyle};
+
+/// How an annotation is underlined
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Underlined with `^`, for what the annotation is about
+    Primary,
+
+    /// Underlined with `-`, for related values
+    Secondary,
+}
+
+impl Style {
+    fn underline(self) -> char {
+        match self {
+            Style::Primary => '^',
+            Style::Secondary => '-',
+        }
+    }
+}
+
+/// A label attached to a byte range of the source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub start: usize,
+    pub end: usize,
+    pub label: Option<String>,
+    pub style: Style,
+}
+
+impl Annotation {
+    pub fn primary(span: (usize, usize), label: impl Into<String>) -> Self {
+        Self {
+            start: span.0,
+            end: span.1,
+            label: Some(label.into()),
+            style: Style::Primary,
+        }
+    }
+
+    pub fn secondary(span: (usize, usize), label: impl Into<String>) -> Self {
+        Self {
+            start: span.0,
+            end: span.1,
+            label: Some(label.into()),
+            style: Style::Secondary,
+        }
+    }
+
+    /// Annotates the value of `spanned`
+    pub fn spanned<T>(spanned: &SpannedValue<T>, label: impl Into<String>, style: Style) -> Self {
+        Self {
+            style,
+            ..Self::primary(spanned.span(), label)
+        }
+    }
+}
+
+/// The labels of `diagnostic` that point into `file`, as annotations of its source
+pub fn from_diagnostic(diagnostic: &Diagnostic, file: &codemap::File) -> Vec<Annotation> {
+    let (low, high) = (file.span.low(), file.span.high());
+    diagnostic
+        .spans
+        .iter()
+        .filter(|s| s.span.low() >= low && s.span.high() <= high)
+        .map(|s| Annotation {
+            start: (s.span.low() - low) as usize,
+            end: (s.span.high() - low) as usize,
+            label: s.label.clone(),
+            style: match s.style {
+                SpanStyle::Primary => Style::Primary,
+                _ => Style::Secondary,
+            },
+        })
+        .collect()
+}
+
+/// How much of the source [`render`] shows
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Options {
+    /// How many lines to show around each annotated line. The whole source is shown if this is
+    /// `None`, and skipped lines are replaced with `...`
+    pub context: Option<usize>,
+}
+
+/// Renders `source` with each of `annotations` underlined below the line it starts on. An
+/// annotation that spans several lines is underlined to the end of its first line
+pub fn render(source: &str, annotations: &[Annotation], options: Options) -> String {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        lines.push((start, line.trim_end_matches(['\n', '\r'])));
+        start += line.len();
+    }
+    let line_of = |offset: usize| {
+        lines
+            .iter()
+            .rposition(|(start, _)| *start <= offset)
+            .unwrap_or(0)
+    };
+
+    let mut by_line: Vec<Vec<&Annotation>> = vec![Vec::new(); lines.len()];
+    for annotation in annotations {
+        if annotation.start <= source.len() && !lines.is_empty() {
+            by_line[line_of(annotation.start)].push(annotation);
+        }
+    }
+    let shown: Vec<bool> = match options.context {
+        None => vec![true; lines.len()],
+        Some(context) => (0..lines.len())
+            .map(|i| {
+                let range = i.saturating_sub(context)..=(i + context).min(lines.len() - 1);
+                range.into_iter().any(|j| !by_line[j].is_empty())
+            })
+            .collect(),
+    };
+
+    let width = lines.len().to_string().len();
+    let mut out = String::new();
+    let mut skipped = false;
+    for (i, (start, line)) in lines.iter().enumerate() {
+        if !shown[i] {
+            skipped = true;
+            continue;
+        }
+        if std::mem::take(&mut skipped) {
+            out.push_str("...\n");
+        }
+        out.push_str(&format!("{:>width$} | {line}\n", i + 1).replace(" | \n", " |\n"));
+        let mut annotations = by_line[i].clone();
+        annotations.sort_by_key(|a| a.start);
+        for annotation in annotations {
+            let column = annotation.start - start;
+            let end = (annotation.end.max(annotation.start) - start).min(line.len());
+            let indent = line.get(..column).map_or(column, |s| s.chars().count());
+            let len = line
+                .get(column..end)
+                .map_or(0, |s| s.chars().count())
+                .max(1);
+            let underline = annotation.style.underline().to_string().repeat(len);
+            let mut row = format!("{:width$} | {:indent$}{underline}", "", "");
+            if let Some(label) = &annotation.label {
+                row.push(' ');
+                row.push_str(label);
+            }
+            out.push_str(&row);
+            out.push('\n');
+        }
+    }
+    if skipped {
+        out.push_str("...\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Annotation, Options};
+    use crate::{ErrorCode, Session};
+
+    #[test]
+    fn annotations() {
+        let source = "[[states]]\nname = \"Coast\"\n\n[states.timeout]\nseconds = 10.0\n";
+        let seconds = source.find("10.0").unwrap();
+        let name = source.find("\"Coast\"").unwrap();
+        let annotations = [
+            Annotation::primary((seconds, seconds + 4), "too long"),
+            Annotation::secondary((name, name + 7), "in this state"),
+        ];
+        let expected = "\
+...
+2 | name = \"Coast\"
+  |        ------- in this state
+...
+5 | seconds = 10.0
+  |           ^^^^ too long
+";
+        let options = Options { context: Some(0) };
+        assert_eq!(render(source, &annotations, options), expected);
+
+        let whole = render(source, &annotations, Options::default());
+        assert!(whole.starts_with("1 | [[states]]\n2 |"));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let source = "default_state = \"Pad\"\n";
+        let mut session = Session::new();
+        let mut context = session.testing(source);
+        let span = context.subspan(16, 21);
+        context
+            .error("state not found `Pad`")
+            .code(ErrorCode::UnknownState)
+            .set_primary_span(span, "not found")
+            .emit();
+        let diagnostics = match context.end_phase_and_emit() {
+            Ok(d) | Err(d) => d,
+        };
+        let rendered = context.render_annotated(&diagnostics[0], Options::default());
+        assert_eq!(
+            rendered,
+            "1 | default_state = \"Pad\"\n  |                 ^^^^^ not found\n"
+        );
+    }
+}
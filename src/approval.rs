@@ -0,0 +1,397 @@
+//! Two person review of compiled config files.
+//!
+//! Once a config is compiled, reviewers sign the SHA-256 hash of the `.ncf` bytes with their
+//! ed25519 key. Approvals are stored in a JSON sidecar next to the config (see
+//! [`Metadata::sidecar_path`]) and are chained: each signature also covers the signature of the
+//! approval before it, so approvals cannot be reordered or dropped from the middle of the list
+//! without invalidating everything after them.
+//!
+//! [`crate::protocol::upload`] refuses to send a config to the rocket unless the session's
+//! [`Policy`] is met.
+
+use std::collections::HashSet;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ApprovalError {
+    #[error("failed to access `{path}`: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse `{path}`: {source}")]
+    Metadata {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("approval {index} by `{reviewer}` is for a different config")]
+    WrongConfig { index: usize, reviewer: String },
+
+    #[error("approval {index} by `{reviewer}` has an invalid signature")]
+    InvalidSignature { index: usize, reviewer: String },
+
+    #[error("`{0}` has already approved this config")]
+    AlreadyApproved(String),
+
+    #[error("config has {found} valid approvals, but {required} are required")]
+    NotEnoughApprovals { found: usize, required: usize },
+}
+
+/// Who must approve a config before it is uploaded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policy {
+    /// The keys of the reviewers whose approvals count
+    pub trusted: Vec<VerifyingKey>,
+
+    /// How many distinct trusted reviewers must approve
+    pub required: usize,
+}
+
+impl Default for Policy {
+    /// Two person review, with no trusted reviewers until some are configured
+    fn default() -> Self {
+        Self {
+            trusted: Vec::new(),
+            required: 2,
+        }
+    }
+}
+
+/// Returns the hash that reviewers sign for the encoded config `config`
+pub fn config_hash(config: &[u8]) -> [u8; 32] {
+    Sha256::digest(config).into()
+}
+
+/// Metadata stored alongside a compiled config
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// Approvals in the order they were made
+    #[serde(default)]
+    pub approvals: Vec<Approval>,
+
+    /// Set for configs built with `--ground-test`, which must never be flown, see
+    /// [`crate::ground_test`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ground_test: bool,
+
+    /// The deny by default lints the config overrides, with their justifications, see
+    /// [`crate::overrides`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<crate::overrides::Record>,
+
+    /// The local override file that was merged over the config when it was built, see
+    /// [`crate::overlay`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_override: Option<String>,
+}
+
+/// A single reviewer's signature over a config
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Approval {
+    /// The name of the reviewer, for display only
+    pub reviewer: String,
+
+    /// Hex encoded ed25519 public key of the reviewer
+    pub public_key: String,
+
+    /// Hex encoded SHA-256 hash of the approved config
+    pub config_hash: String,
+
+    /// Hex encoded ed25519 signature over the config hash followed by the previous approval's
+    /// signature
+    pub signature: String,
+}
+
+impl Metadata {
+    /// Returns the path of the metadata sidecar for the config at `config_path`
+    pub fn sidecar_path(config_path: &str) -> String {
+        format!("{config_path}.meta.json")
+    }
+
+    /// Loads metadata from `path`. A missing file is treated as empty metadata
+    pub fn load(path: &str) -> Result<Self, ApprovalError> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).map_err(|source| ApprovalError::Metadata {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ApprovalError::Io {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    /// Records in the metadata sidecar of the config at `config_path` whether it is a ground test
+    /// build, the lints it overrides, and the local override file merged over it. Call this before
+    /// writing the config, so that a ground test config is never written without its marker.
+    ///
+    /// Approvals of an earlier build that encoded to different bytes than `config` are dropped, so
+    /// that reviewers approve the rebuilt config from the start of a new chain
+    pub fn mark_build(
+        config_path: &str,
+        config: &[u8],
+        ground_test: bool,
+        overrides: &[crate::overrides::Record],
+        local_override: Option<&str>,
+    ) -> Result<(), ApprovalError> {
+        let sidecar = Self::sidecar_path(config_path);
+        let mut metadata = Self::load(&sidecar)?;
+        let hash = hex::encode(config_hash(config));
+        let stale = metadata.approvals.iter().any(|a| a.config_hash != hash);
+        if stale {
+            metadata.approvals.clear();
+        }
+        if stale
+            || metadata.ground_test != ground_test
+            || metadata.overrides != overrides
+            || metadata.local_override.as_deref() != local_override
+        {
+            metadata.ground_test = ground_test;
+            metadata.overrides = overrides.to_vec();
+            metadata.local_override = local_override.map(str::to_owned);
+            metadata.save(&sidecar)?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ApprovalError> {
+        // Metadata only contains strings, so this cannot fail
+        let s = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(path, s).map_err(|source| ApprovalError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    }
+}
+
+fn decode_hex<const N: usize>(s: &str, what: &str) -> Result<[u8; N], ApprovalError> {
+    let bytes =
+        hex::decode(s.trim()).map_err(|e| ApprovalError::InvalidKey(format!("{what}: {e}")))?;
+    bytes
+        .try_into()
+        .map_err(|_| ApprovalError::InvalidKey(format!("{what}: expected {N} bytes")))
+}
+
+/// Parses a hex encoded ed25519 public key
+pub fn parse_public_key(s: &str) -> Result<VerifyingKey, ApprovalError> {
+    let bytes = decode_hex::<32>(s, "public key")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| ApprovalError::InvalidKey(e.to_string()))
+}
+
+/// Loads a reviewer's secret key from a file containing the hex encoded 32 byte seed
+pub fn load_signing_key(path: &str) -> Result<SigningKey, ApprovalError> {
+    let s = std::fs::read_to_string(path).map_err(|source| ApprovalError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    Ok(SigningKey::from_bytes(&decode_hex::<32>(&s, "secret key")?))
+}
+
+/// The bytes signed by an approval that follows `previous`
+fn signed_message(hash: &[u8; 32], previous: Option<&Approval>) -> Result<Vec<u8>, ApprovalError> {
+    let mut message = hash.to_vec();
+    if let Some(previous) = previous {
+        message.extend_from_slice(&decode_hex::<64>(&previous.signature, "signature")?);
+    }
+    Ok(message)
+}
+
+/// Checks every approval in `metadata` against `config`, returning how many distinct keys from
+/// `trusted` have approved it.
+///
+/// Any approval that is for a different config or has a bad signature is an error, even if it was
+/// made with an untrusted key, since it means the sidecar has been tampered with or is stale
+pub fn verify_approvals(
+    metadata: &Metadata,
+    config: &[u8],
+    trusted: &[VerifyingKey],
+) -> Result<usize, ApprovalError> {
+    let hash = config_hash(config);
+    let mut approved_by = HashSet::new();
+    let mut previous = None;
+    for (index, approval) in metadata.approvals.iter().enumerate() {
+        if approval.config_hash != hex::encode(hash) {
+            return Err(ApprovalError::WrongConfig {
+                index,
+                reviewer: approval.reviewer.clone(),
+            });
+        }
+        let invalid = || ApprovalError::InvalidSignature {
+            index,
+            reviewer: approval.reviewer.clone(),
+        };
+        let key = parse_public_key(&approval.public_key)?;
+        let signature = Signature::from_bytes(
+            &decode_hex::<64>(&approval.signature, "signature").map_err(|_| invalid())?,
+        );
+        let message = signed_message(&hash, previous)?;
+        key.verify(&message, &signature).map_err(|_| invalid())?;
+
+        if trusted.contains(&key) {
+            approved_by.insert(key.to_bytes());
+        }
+        previous = Some(approval);
+    }
+    Ok(approved_by.len())
+}
+
+/// Returns an error unless at least `required` distinct trusted reviewers have approved `config`
+pub fn require_approvals(
+    metadata: &Metadata,
+    config: &[u8],
+    trusted: &[VerifyingKey],
+    required: usize,
+) -> Result<(), ApprovalError> {
+    let found = verify_approvals(metadata, config, trusted)?;
+    if found < required {
+        return Err(ApprovalError::NotEnoughApprovals { found, required });
+    }
+    Ok(())
+}
+
+/// Signs `config` with `key` and appends the approval to `metadata`.
+/// The existing approvals must be valid for `config`
+pub fn approve(
+    metadata: &mut Metadata,
+    config: &[u8],
+    reviewer: String,
+    key: &SigningKey,
+) -> Result<(), ApprovalError> {
+    let public_key = key.verifying_key();
+    verify_approvals(metadata, config, &[])?;
+    if metadata
+        .approvals
+        .iter()
+        .any(|a| a.public_key == hex::encode(public_key.to_bytes()))
+    {
+        return Err(ApprovalError::AlreadyApproved(reviewer));
+    }
+
+    let hash = config_hash(config);
+    let message = signed_message(&hash, metadata.approvals.last())?;
+    let signature = key.sign(&message);
+    metadata.approvals.push(Approval {
+        reviewer,
+        public_key: hex::encode(public_key.to_bytes()),
+        config_hash: hex::encode(hash),
+        signature: hex::encode(signature.to_bytes()),
+    });
+    Ok(())
+}
+
+/// Trusts a test reviewer in `session`, and returns a sidecar in which they approved `config`, so
+/// that `config` can be uploaded
+#[cfg(test)]
+pub(crate) fn approve_for_testing(session: &mut crate::Session, config: &[u8]) -> Metadata {
+    let key = SigningKey::from_bytes(&[0xA5; 32]);
+    session.set_approval_policy(Policy {
+        trusted: vec![key.verifying_key()],
+        required: 1,
+    });
+    let mut metadata = Metadata::default();
+    approve(&mut metadata, config, "tester".to_owned(), &key).unwrap();
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+
+    use super::{approve, require_approvals, verify_approvals, ApprovalError, Metadata};
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn two_person_approval() {
+        let config = b"config bytes";
+        let (alice, bob, mallory) = (key(1), key(2), key(3));
+        let trusted = [alice.verifying_key(), bob.verifying_key()];
+
+        let mut metadata = Metadata::default();
+        approve(&mut metadata, config, "alice".to_owned(), &alice).unwrap();
+        assert!(matches!(
+            require_approvals(&metadata, config, &trusted, 2),
+            Err(ApprovalError::NotEnoughApprovals { found: 1, .. })
+        ));
+
+        // Untrusted approvals are valid but do not count
+        approve(&mut metadata, config, "mallory".to_owned(), &mallory).unwrap();
+        assert_eq!(verify_approvals(&metadata, config, &trusted).unwrap(), 1);
+
+        approve(&mut metadata, config, "bob".to_owned(), &bob).unwrap();
+        require_approvals(&metadata, config, &trusted, 2).unwrap();
+
+        assert!(matches!(
+            approve(&mut metadata, config, "alice".to_owned(), &alice),
+            Err(ApprovalError::AlreadyApproved(_))
+        ));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let config = b"config bytes";
+        let (alice, bob) = (key(1), key(2));
+        let trusted = [alice.verifying_key(), bob.verifying_key()];
+
+        let mut metadata = Metadata::default();
+        approve(&mut metadata, config, "alice".to_owned(), &alice).unwrap();
+        approve(&mut metadata, config, "bob".to_owned(), &bob).unwrap();
+
+        assert!(matches!(
+            verify_approvals(&metadata, b"other config", &trusted),
+            Err(ApprovalError::WrongConfig { index: 0, .. })
+        ));
+
+        // Reordering breaks the chain
+        let mut reordered = metadata.clone();
+        reordered.approvals.swap(0, 1);
+        assert!(matches!(
+            verify_approvals(&reordered, config, &trusted),
+            Err(ApprovalError::InvalidSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn rebuild_starts_a_new_chain() {
+        let dir = std::env::temp_dir().join(format!("nova-approval-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rocket.ncf").display().to_string();
+        let sidecar = Metadata::sidecar_path(&path);
+        let (old, new) = (b"old config", b"new config");
+        let (alice, bob) = (key(1), key(2));
+        let trusted = [alice.verifying_key(), bob.verifying_key()];
+
+        Metadata::mark_build(&path, old, false, &[], None).unwrap();
+        let mut metadata = Metadata::load(&sidecar).unwrap();
+        approve(&mut metadata, old, "alice".to_owned(), &alice).unwrap();
+        metadata.save(&sidecar).unwrap();
+
+        // Rebuilding the same bytes keeps the approval
+        Metadata::mark_build(&path, old, false, &[], None).unwrap();
+        let metadata = Metadata::load(&sidecar).unwrap();
+        assert_eq!(verify_approvals(&metadata, old, &trusted).unwrap(), 1);
+
+        // Rebuilding an edited config drops it, and the new config can be approved
+        Metadata::mark_build(&path, new, false, &[], None).unwrap();
+        let mut metadata = Metadata::load(&sidecar).unwrap();
+        assert!(metadata.approvals.is_empty());
+        approve(&mut metadata, new, "alice".to_owned(), &alice).unwrap();
+        approve(&mut metadata, new, "bob".to_owned(), &bob).unwrap();
+        require_approvals(&metadata, new, &trusted, 2).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+//! Telemetry bandwidth analysis.
+//!
+//! A `data_rate` command sets how many telemetry packets are sent each second, and stays in effect
+//! after its state is left. If the `[radio]` section says how large each packet is, the bandwidth
+//! each state needs can be checked against the link budget of the target, so that a rate the
+//! radio cannot keep up with is caught before flight rather than as missing telemetry.
+
+use std::collections::HashMap;
+
+use crate::{duration, upper, Context, Span};
+
+/// The telemetry rate that can be in effect while in a state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateBandwidth<'a> {
+    pub name: &'a str,
+
+    /// The highest `data_rate` that can be in effect, set either by this state or by a state
+    /// before it. `None` if no state that can lead here sets a rate, so the firmware's default
+    /// rate is used
+    pub data_rate: Option<u16>,
+}
+
+impl StateBandwidth<'_> {
+    /// The bandwidth needed in bytes per second, when each packet is `packet_bytes` long
+    pub fn bytes_per_second(&self, packet_bytes: u32) -> Option<u32> {
+        self.data_rate
+            .map(|rate| u32::from(rate).saturating_mul(packet_bytes))
+    }
+}
+
+/// Returns the highest `data_rate` set by the commands of `state`
+fn state_rate(state: &upper::State) -> Option<u16> {
+    state
+        .commands
+        .iter()
+        .filter_map(|c| c.get_ref().data_rate.as_ref())
+        .map(|rate| *rate.get_ref())
+        .max()
+}
+
+/// Returns the highest telemetry rate that can be in effect in every state of `config`, in source
+/// order.
+///
+/// A state that sets a rate uses the highest rate it sets. Any other state keeps the rate of
+/// whichever state it was entered from, so it uses the highest rate of the states that lead to it
+pub fn state_bandwidths(config: &upper::ConfigFile) -> Vec<StateBandwidth<'_>> {
+    let states: Vec<_> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .collect();
+    let mut rates: HashMap<&str, Option<u16>> = states
+        .iter()
+        .map(|s| (s.name.get_ref().as_str(), state_rate(s)))
+        .collect();
+
+    // Rates only ever increase, so this settles after at most one pass per state
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for state in &states {
+            let rate = rates[state.name.get_ref().as_str()];
+            for next in duration::successors(config, state) {
+                let next_state = states.iter().find(|s| s.name.get_ref() == next);
+                let sets_own_rate = next_state.is_none_or(|s| state_rate(s).is_some());
+                if sets_own_rate {
+                    continue;
+                }
+                let entry = rates.get_mut(next).unwrap();
+                if rate > *entry {
+                    *entry = rate;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    states
+        .iter()
+        .map(|s| {
+            let name = s.name.get_ref().as_str();
+            StateBandwidth {
+                name,
+                data_rate: rates[name],
+            }
+        })
+        .collect()
+}
+
+/// Emits an error for every `data_rate` command that needs more bandwidth than the target's link
+/// budget
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let packet_bytes = match config
+        .radio
+        .as_ref()
+        .and_then(|r| r.get_ref().packet_bytes.as_ref())
+    {
+        Some(packet_bytes) => packet_bytes,
+        None => return,
+    };
+    let budget = context.session().limits().link_budget;
+    let target = context.session().target();
+
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let rates = state
+            .commands
+            .iter()
+            .filter_map(|c| c.get_ref().data_rate.as_ref());
+        for rate in rates {
+            let needed = u32::from(*rate.get_ref()).saturating_mul(*packet_bytes.get_ref());
+            if needed <= budget {
+                continue;
+            }
+            let span = Span::from_spanned(context, rate);
+            let packet_span = Span::from_spanned(context, packet_bytes);
+            context
+                .error(format!(
+                    "state `{}` needs {needed} bytes per second of telemetry, but the `{target}` radio link carries at most {budget}",
+                    state.name.get_ref()
+                ))
+                .set_primary_span(
+                    span,
+                    format!("{} packets per second", rate.get_ref()),
+                )
+                .span_label(
+                    packet_span,
+                    format!("of {} bytes each", packet_bytes.get_ref()),
+                )
+                .lint("telemetry_bandwidth")
+                .emit();
+        }
+    }
+}
+
+/// Emits a note with the bandwidth needed in each state that sets or inherits a data rate
+pub fn emit_notes(config: &upper::ConfigFile, context: &mut Context) {
+    let packet_bytes = match config
+        .radio
+        .as_ref()
+        .and_then(|r| r.get_ref().packet_bytes.as_ref())
+    {
+        Some(packet_bytes) => *packet_bytes.get_ref(),
+        None => return,
+    };
+    let budget = context.session().limits().link_budget;
+    for state in state_bandwidths(config) {
+        if let Some(needed) = state.bytes_per_second(packet_bytes) {
+            context
+                .note(format!(
+                    "state `{}` needs up to {needed} of {budget} bytes per second of telemetry",
+                    state.name
+                ))
+                .emit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use super::state_bandwidths;
+    use crate::{upper, Session};
+
+    const CONFIG: &str = r#"
+default_state = "Armed"
+
+[radio]
+packet_bytes = 32
+
+[[states]]
+name = "Armed"
+
+[[states.commands]]
+data_rate = 10
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+data_rate = RATE
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Descent"
+
+[[states]]
+name = "Descent"
+"#;
+
+    #[test]
+    fn link_budget() {
+        let mut session = Session::new();
+        let config = CONFIG.replace("RATE", "100");
+        let mut context = session.testing(&config);
+        let config = upper::verify(&mut context).unwrap();
+        let rates: Vec<_> = state_bandwidths(&config)
+            .iter()
+            .map(|s| s.bytes_per_second(32))
+            .collect();
+        assert_eq!(rates, [Some(320), Some(3200), Some(3200)]);
+        super::check(&config, &mut context);
+        assert!(!context.has_error());
+
+        let mut session = Session::new();
+        let config = CONFIG.replace("RATE", "1000");
+        let mut context = session.testing(&config);
+        let config = upper::verify(&mut context).unwrap();
+        super::check(&config, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Error)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .message
+            .starts_with("state `Flight` needs 32000 bytes"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Renders a C header describing the encoded config, for `--emit c-header`.
+//!
+//! Ground support equipment that is not written in Rust still needs to know which index each state
+//! was encoded at, to show telemetry or send commands by name. The header defines the index and
+//! name of every state, along with the CRC-32 from the `.ncf` header so that the equipment can
+//! tell whether the flight computer is running the config the header was generated from:
+//!
+//! ```c
+//! #define NOVA_CONFIG_CRC 0x1c291ca3u
+//! #define NOVA_STATE_COUNT 2
+//! #define NOVA_DEFAULT_STATE NOVA_STATE_PRE_FLIGHT
+//!
+//! #define NOVA_STATE_PRE_FLIGHT 0
+//! #define NOVA_STATE_PRE_FLIGHT_NAME "PreFlight"
+//! ```
+//!
+//! State names are converted from `CamelCase` to `SCREAMING_SNAKE_CASE`.
+
+use std::fmt::Write;
+
+use nova_software_common::index;
+use thiserror::Error;
+
+use crate::{decompile::Names, ncf};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CodegenError {
+    #[error("states `{0}` and `{1}` would both be defined as `{2}` in C")]
+    Collision(String, String, String),
+}
+
+/// The name of `state` in `SCREAMING_SNAKE_CASE`, such as `PRE_FLIGHT` for `PreFlight`
+fn screaming_snake(state: &str) -> String {
+    let mut out = String::new();
+    let mut previous: Option<char> = None;
+    for c in state.chars() {
+        if let Some(p) = previous {
+            if c.is_ascii_uppercase() && (p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                out.push('_');
+            }
+        }
+        out.push(c.to_ascii_uppercase());
+        previous = Some(c);
+    }
+    out
+}
+
+/// Renders a C header for `config`, which was encoded with `header` and has the states `names`
+pub fn c_header(
+    config: &index::ConfigFile,
+    names: &Names,
+    header: &ncf::Header,
+) -> Result<String, CodegenError> {
+    let macros: Vec<String> = names
+        .states
+        .iter()
+        .map(|name| format!("NOVA_STATE_{}", screaming_snake(name)))
+        .collect();
+    for (i, a) in macros.iter().enumerate() {
+        if let Some(j) = macros[..i].iter().position(|b| b == a) {
+            return Err(CodegenError::Collision(
+                names.states[j].clone(),
+                names.states[i].clone(),
+                a.clone(),
+            ));
+        }
+    }
+
+    let mut out = format!(
+        "/* Generated by nova-verifier {}. Do not edit */\n\n#ifndef NOVA_CONFIG_H\n#define NOVA_CONFIG_H\n\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(out, "#define NOVA_CONFIG_CRC 0x{:08x}u", header.crc);
+    let _ = writeln!(
+        out,
+        "#define NOVA_CONFIG_FORMAT_VERSION {}",
+        header.format_version
+    );
+    let _ = writeln!(out, "#define NOVA_STATE_COUNT {}", config.states.len());
+    let _ = writeln!(
+        out,
+        "#define NOVA_DEFAULT_STATE {}",
+        macros[usize::from(config.default_state)]
+    );
+    for (i, (name, macro_name)) in names.states.iter().zip(&macros).enumerate() {
+        let _ = write!(
+            out,
+            "\n#define {macro_name} {i}\n#define {macro_name}_NAME \"{name}\"\n"
+        );
+    }
+    let quoted: Vec<String> = names.states.iter().map(|n| format!("\"{n}\"")).collect();
+    let _ = write!(
+        out,
+        "\n/* The name of each state, indexed by state */\n#define NOVA_STATE_NAMES {{ {} }}\n\n#endif /* NOVA_CONFIG_H */\n",
+        quoted.join(", ")
+    );
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decompile::Names, ncf, Session};
+
+    #[test]
+    fn c_header() {
+        let config = r#"
+default_state = "PreFlight"
+
+[[states]]
+name = "PreFlight"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight2"
+
+[[states]]
+name = "Flight2"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let (mid, lower, _) =
+            crate::load_config(&mut session, config.to_owned(), "test.toml".to_owned()).unwrap();
+        let names = Names::from_source(&mid, &lower).unwrap();
+        let header = ncf::split(&ncf::encode(&lower).unwrap()).unwrap().0;
+        let header_text = super::c_header(&lower, &names, &header).unwrap();
+        assert!(header_text.contains("#define NOVA_DEFAULT_STATE NOVA_STATE_PRE_FLIGHT\n"));
+        assert!(header_text.contains("#define NOVA_STATE_FLIGHT2 1\n"));
+        assert!(header_text.contains("#define NOVA_STATE_FLIGHT2_NAME \"Flight2\"\n"));
+        assert!(header_text.contains(&format!("0x{:08x}u", header.crc)));
+
+        let clashing = Names {
+            states: vec!["PreFlight".into(), "Pre_Flight".into()],
+            checks: vec![vec![], vec![]],
+        };
+        assert_eq!(
+            super::c_header(&lower, &clashing, &header).unwrap_err().to_string(),
+            "states `PreFlight` and `Pre_Flight` would both be defined as `NOVA_STATE_PRE_FLIGHT` in C"
+        );
+    }
+}
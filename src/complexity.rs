@@ -0,0 +1,260 @@
+//! Complexity metrics for the state machine of a config.
+//!
+//! Flight logic that is hard to follow is hard to review, and a config nobody fully understands is
+//! a safety risk of its own. These metrics are shown with `--stats`, and teams can set ceilings
+//! for them with `--complexity-limit` so that the verifier pushes back on configs that grow too
+//! complex.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{duration, upper, Context, Span};
+
+/// Metrics that describe how complex a config is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Metric {
+    /// The number of states
+    States,
+
+    /// The number of checks and timeouts that move to another state
+    Transitions,
+
+    /// The cyclomatic complexity of the transition graph, `transitions - states + 2 * components`.
+    /// This is the number of independent paths through the flight
+    Cyclomatic,
+
+    /// The largest number of checks in a single state
+    ChecksPerState,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 4] = [
+        Metric::States,
+        Metric::Transitions,
+        Metric::Cyclomatic,
+        Metric::ChecksPerState,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::States => "states",
+            Metric::Transitions => "transitions",
+            Metric::Cyclomatic => "cyclomatic",
+            Metric::ChecksPerState => "checks-per-state",
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown complexity metric `{s}`"))
+    }
+}
+
+/// The most a metric may be, parsed from `METRIC=MAX`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ceiling {
+    pub metric: Metric,
+    pub max: usize,
+}
+
+impl FromStr for Ceiling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (metric, max) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `METRIC=MAX`, found `{s}`"))?;
+        let max = max
+            .parse()
+            .map_err(|_| format!("invalid complexity limit `{max}`"))?;
+        Ok(Self {
+            metric: metric.parse()?,
+            max,
+        })
+    }
+}
+
+/// The value of every [`Metric`] for a config
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Metrics {
+    pub states: usize,
+    pub transitions: usize,
+    pub cyclomatic: usize,
+    pub checks_per_state: usize,
+}
+
+impl Metrics {
+    /// Measures `config`
+    pub fn measure(config: &upper::ConfigFile) -> Self {
+        let states: Vec<_> = config
+            .states
+            .get_ref()
+            .iter()
+            .map(|s| s.get_ref())
+            .collect();
+
+        // Union find over state indices, to count the connected components of the graph
+        let index: HashMap<&str, usize> = states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name.get_ref().as_str(), i))
+            .collect();
+        let mut parent: Vec<usize> = (0..states.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut transitions = 0;
+        for (i, state) in states.iter().enumerate() {
+            for next in duration::successors(config, state) {
+                transitions += 1;
+                if let Some(&j) = index.get(next) {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a] = b;
+                }
+            }
+        }
+        let components = (0..states.len())
+            .filter(|&i| root(&mut parent, i) == i)
+            .count();
+
+        Self {
+            states: states.len(),
+            transitions,
+            cyclomatic: (transitions + 2 * components).saturating_sub(states.len()),
+            checks_per_state: states.iter().map(|s| s.checks.len()).max().unwrap_or(0),
+        }
+    }
+
+    pub fn get(&self, metric: Metric) -> usize {
+        match metric {
+            Metric::States => self.states,
+            Metric::Transitions => self.transitions,
+            Metric::Cyclomatic => self.cyclomatic,
+            Metric::ChecksPerState => self.checks_per_state,
+        }
+    }
+}
+
+/// Warns about every metric that is above its ceiling in the session
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let ceilings = context.session().complexity_limits().to_vec();
+    if ceilings.is_empty() {
+        return;
+    }
+    let metrics = Metrics::measure(config);
+    for ceiling in ceilings {
+        let value = metrics.get(ceiling.metric);
+        if value <= ceiling.max {
+            continue;
+        }
+        // Point at the state with the most checks, other metrics are about the whole config
+        let busiest = config
+            .states
+            .get_ref()
+            .iter()
+            .map(|s| s.get_ref())
+            .find(|s| s.checks.len() == value)
+            .filter(|_| ceiling.metric == Metric::ChecksPerState)
+            .map(|s| Span::from_spanned(context, &s.name));
+        let mut warning = context
+            .warn(format!(
+                "complexity metric `{}` is {value}, above the limit of {}",
+                ceiling.metric, ceiling.max
+            ))
+            .lint("complexity");
+        if let Some(span) = busiest {
+            warning = warning.set_primary_span(span, format!("has {value} checks"));
+        }
+        warning.emit();
+        context
+            .help("consider splitting the flight logic into simpler states, or raise the limit with `--complexity-limit`")
+            .emit();
+    }
+}
+
+/// Emits a note with every metric
+pub fn emit_notes(config: &upper::ConfigFile, context: &mut Context) {
+    let metrics = Metrics::measure(config);
+    let values: Vec<_> = Metric::ALL
+        .into_iter()
+        .map(|m| format!("{m} {}", metrics.get(m)))
+        .collect();
+    context
+        .note(format!("complexity: {}", values.join(", ")))
+        .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ceiling, Metric, Metrics};
+    use crate::{upper, Session};
+
+    #[test]
+    fn metrics() {
+        let config = r#"
+default_state = "Armed"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states.checks]]
+name = "Continuity"
+check = "pyro1_continuity"
+flag = "unset"
+abort = "Safe"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Safe"
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let config = upper::verify(&mut session.testing(config)).unwrap();
+        let metrics = Metrics::measure(&config);
+        assert_eq!(
+            metrics,
+            Metrics {
+                states: 3,
+                transitions: 3,
+                cyclomatic: 2,
+                checks_per_state: 2,
+            }
+        );
+
+        let ceiling: Ceiling = "checks-per-state=1".parse().unwrap();
+        assert_eq!(ceiling.metric, Metric::ChecksPerState);
+        assert!("cyclomatic".parse::<Ceiling>().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Detects unresolved git merge conflicts in a config before it is parsed.
+//!
+//! A half merged config fails to parse with an error pointing somewhere inside the conflict,
+//! which says nothing about the actual problem. Checking for conflict markers first lets us point
+//! at each marker instead.
+
+use crate::{Context, ErrorCode};
+
+/// The markers git writes around a conflict. Each starts a line and is followed by a space and a
+/// label, or by the end of the line
+const START: &str = "<<<<<<<";
+const BASE: &str = "|||||||";
+const SEPARATOR: &str = "=======";
+const END: &str = ">>>>>>>";
+
+/// The byte ranges of the markers of a single conflict. Conflicts cut off by the end of the file
+/// have no separator or end marker
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Conflict {
+    pub start: (usize, usize),
+
+    /// The marker before the common ancestor, written when git uses the `diff3` conflict style
+    pub base: Option<(usize, usize)>,
+    pub separator: Option<(usize, usize)>,
+    pub end: Option<(usize, usize)>,
+}
+
+/// Returns true if `line` is the conflict marker `marker`
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Finds every conflict in `source`, in the order they appear
+pub fn find(source: &str) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut current: Option<Conflict> = None;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let range = (start, start + line.len());
+
+        if is_marker(line, START) {
+            conflicts.extend(current.take());
+            current = Some(Conflict {
+                start: range,
+                ..Default::default()
+            });
+            continue;
+        }
+        let conflict = match &mut current {
+            Some(conflict) => conflict,
+            None => continue,
+        };
+        if is_marker(line, BASE) && conflict.separator.is_none() {
+            conflict.base = Some(range);
+        } else if is_marker(line, SEPARATOR) && conflict.separator.is_none() {
+            conflict.separator = Some(range);
+        } else if is_marker(line, END) {
+            conflict.end = Some(range);
+            conflicts.extend(current.take());
+        }
+    }
+    conflicts.extend(current);
+    conflicts
+}
+
+/// Emits an error for every unresolved conflict in the file in `context`.
+///
+/// Returns `Err(())` if there were any, in which case the file should not be parsed
+pub fn check(context: &mut Context) -> Result<(), ()> {
+    let conflicts = find(context.source());
+    for conflict in &conflicts {
+        let span = |(start, end)| context.subspan(start, end);
+        let start = span(conflict.start);
+        let base = conflict.base.map(span);
+        let separator = conflict.separator.map(span);
+        let end = conflict.end.map(span);
+
+        let mut error = context
+            .error("unresolved merge conflict")
+            .code(ErrorCode::MergeConflict)
+            .set_primary_span(start, "conflict starts here");
+        if let Some(base) = base {
+            error = error.span_label(base, "common ancestor");
+        }
+        if let Some(separator) = separator {
+            error = error.span_label(separator, "changes are separated here");
+        }
+        error = match end {
+            Some(end) => error.span_label(end, "conflict ends here"),
+            None => error.span_label(start, "this conflict is never closed"),
+        };
+        error.emit();
+    }
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        context
+            .help("keep one side of each conflict and delete the markers, then verify again")
+            .emit();
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, Conflict};
+
+    #[test]
+    fn markers() {
+        let source = "\
+default_state = \"Armed\"
+<<<<<<< HEAD
+main_altitude = 300.0
+=======
+main_altitude = 250.0
+>>>>>>> other-branch
+# ======= is fine outside of a conflict
+<<<<<<<
+a = 1
+||||||| base
+a = 2
+=======
+";
+        let conflicts = find(source);
+        let text = |(start, end): (usize, usize)| &source[start..end];
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(text(conflicts[0].start), "<<<<<<< HEAD");
+        assert_eq!(text(conflicts[0].separator.unwrap()), "=======");
+        assert_eq!(text(conflicts[0].end.unwrap()), ">>>>>>> other-branch");
+        assert_eq!(conflicts[0].base, None);
+        assert_eq!(text(conflicts[1].base.unwrap()), "||||||| base");
+        assert_eq!(conflicts[1].end, None);
+
+        assert_eq!(find("a = \"<<<<<<< \"\n=======\n"), Vec::<Conflict>::new());
+    }
+}
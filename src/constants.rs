@@ -0,0 +1,355 @@
+//! Named constants and arithmetic in float values.
+//!
+//! A value that several checks share, such as the altitude the main parachute deploys at, can be
+//! named once in a top level `[constants]` table. Any float value can then be written as a string
+//! holding a constant or a simple expression:
+//!
+//! ```toml
+//! [constants]
+//! main_deploy_alt = 300.0
+//!
+//! [[states.checks]]
+//! name = "Main"
+//! check = "altitude"
+//! less_than = "main_deploy_alt + 50"
+//! ```
+//!
+//! Expressions support `+`, `-`, `*`, `/`, unary minus, and parentheses. They are replaced with
+//! their value by [`substitute`] before the config is deserialized, keeping the span of the string,
+//! so every later diagnostic about the value points at the expression it came from.
+
+use std::collections::BTreeMap;
+
+use crate::tree::{Node, Value};
+use crate::{Context, ErrorCode};
+
+/// The keys whose values are floats, and so may be written as an expression
+pub const FLOAT_KEYS: [&str; 9] = [
+    "greater_than",
+    "less_than",
+    "upper_bound",
+    "lower_bound",
+    "launch_altitude",
+    "main_altitude",
+    "seconds",
+    "delay",
+    "time",
+];
+
+/// Returns true if `config` has a top level `[constants]` table
+pub fn declared(config: &Node) -> bool {
+    match &config.value {
+        Value::Table(entries) => entries.iter().any(|(key, _)| key == "constants"),
+        _ => false,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let word = &expression[start..end];
+            if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                let number = word
+                    .parse()
+                    .map_err(|_| format!("`{word}` is not a number"))?;
+                tokens.push(Token::Number(number));
+            } else {
+                tokens.push(Token::Name(word.to_owned()));
+            }
+        } else {
+            return Err(format!("unexpected `{c}`"));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Evaluates a tokenized expression against the constants it may use
+struct Evaluator<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    constants: &'a BTreeMap<String, f64>,
+    used: &'a mut Vec<String>,
+}
+
+impl Evaluator<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Op(op)) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.position += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek_op() {
+            self.position += 1;
+            let rhs = self.factor()?;
+            value = if op == '*' { value * rhs } else { value / rhs };
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(Token::Name(name)) => match self.constants.get(&name) {
+                Some(value) => {
+                    self.used.push(name);
+                    Ok(*value)
+                }
+                None => Err(format!("unknown constant `{name}`")),
+            },
+            Some(Token::Op('-')) => Ok(-self.factor()?),
+            Some(Token::Op('(')) => {
+                let value = self.expression()?;
+                match self.next() {
+                    Some(Token::Op(')')) => Ok(value),
+                    _ => Err("expected `)`".to_owned()),
+                }
+            }
+            Some(Token::Op(op)) => Err(format!("expected a number or constant, found `{op}`")),
+            None => Err("expected a number or constant".to_owned()),
+        }
+    }
+}
+
+/// Evaluates `expression`, recording the constants it uses in `used`
+fn evaluate(
+    expression: &str,
+    constants: &BTreeMap<String, f64>,
+    used: &mut Vec<String>,
+) -> Result<f64, String> {
+    let mut evaluator = Evaluator {
+        tokens: tokenize(expression)?,
+        position: 0,
+        constants,
+        used,
+    };
+    let value = evaluator.expression()?;
+    match evaluator.next() {
+        None if value.is_finite() => Ok(value),
+        None => Err(format!("evaluates to {value}")),
+        Some(_) => Err("expected an operator or the end of the expression".to_owned()),
+    }
+}
+
+/// Replaces every float value in `node` that is written as a string with the value of its
+/// expression
+fn substitute_into(
+    context: &mut Context,
+    node: &mut Node,
+    constants: &BTreeMap<String, f64>,
+    used: &mut Vec<String>,
+) -> Result<(), ()> {
+    let mut result = Ok(());
+    match &mut node.value {
+        Value::Table(entries) => {
+            for (key, value) in entries {
+                if let (true, Value::String(expression)) =
+                    (FLOAT_KEYS.contains(&key.as_str()), &value.value)
+                {
+                    match evaluate(expression, constants, used) {
+                        Ok(number) => value.value = Value::Float(number),
+                        Err(message) => {
+                            let span = context.subspan(value.span.0, value.span.1);
+                            context
+                                .error(format!("invalid value `{expression}` for `{key}`"))
+                                .code(ErrorCode::InvalidExpression)
+                                .set_primary_span(span, message)
+                                .emit();
+                            result = Err(());
+                        }
+                    }
+                } else {
+                    result = result.and(substitute_into(context, value, constants, used));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                result = result.and(substitute_into(context, item, constants, used));
+            }
+        }
+        _ => {}
+    }
+    result
+}
+
+/// Removes the `[constants]` table from `config`, and replaces every float value written as an
+/// expression with its value. Emits an error for every expression that cannot be evaluated
+pub fn substitute(context: &mut Context, config: &mut Node) -> Result<(), ()> {
+    let table = match &mut config.value {
+        Value::Table(entries) => {
+            let position = entries.iter().position(|(key, _)| key == "constants");
+            position.map(|i| entries.remove(i).1)
+        }
+        _ => None,
+    };
+
+    let mut result = Ok(());
+    let mut constants = BTreeMap::new();
+    let mut spans = BTreeMap::new();
+    match table.map(|t| (t.span, t.value)) {
+        Some((_, Value::Table(entries))) => {
+            for (name, value) in entries {
+                let number = match value.value {
+                    Value::Integer(i) => i as f64,
+                    Value::Float(f) => f,
+                    _ => {
+                        let span = context.subspan(value.span.0, value.span.1);
+                        context
+                            .error(format!("constant `{name}` is not a number"))
+                            .code(ErrorCode::InvalidConstant)
+                            .set_primary_span(span, "expected a number")
+                            .emit();
+                        result = Err(());
+                        continue;
+                    }
+                };
+                spans.insert(name.clone(), value.span);
+                constants.insert(name, number);
+            }
+        }
+        Some((span, _)) => {
+            let span = context.subspan(span.0, span.1);
+            context
+                .error("`constants` must be a table")
+                .code(ErrorCode::InvalidConstant)
+                .set_primary_span(span, "expected a table of numbers")
+                .emit();
+            result = Err(());
+        }
+        None => {}
+    }
+
+    let mut used = Vec::new();
+    result = result.and(substitute_into(context, config, &constants, &mut used));
+    for (name, span) in spans {
+        if used.contains(&name) {
+            continue;
+        }
+        let (lines, text) = context.lines(span.0, span.1);
+        let removable = text.trim_start().starts_with(name.as_str());
+        let span = context.subspan(span.0, span.1);
+        let warning = context
+            .warn(format!("constant `{name}` is never used"))
+            .set_primary_span(span, "defined here")
+            .lint("unused_constant");
+        if removable {
+            warning
+                .suggest_replacement(lines, "", "remove the unused constant")
+                .emit();
+        } else {
+            warning.emit();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::evaluate;
+    use crate::{upper, Session};
+
+    #[test]
+    fn expressions() {
+        let constants = BTreeMap::from([("main".to_owned(), 300.0)]);
+        let mut used = Vec::new();
+        let mut eval = |e: &str| evaluate(e, &constants, &mut used);
+        assert_eq!(eval("main"), Ok(300.0));
+        assert_eq!(eval("main + 50"), Ok(350.0));
+        assert_eq!(eval("-(main - 100) * 2 / 4"), Ok(-100.0));
+        assert_eq!(eval("1.5e2"), Ok(150.0));
+        assert_eq!(eval("mian"), Err("unknown constant `mian`".to_owned()));
+        assert!(eval("main +").is_err());
+        assert!(eval("(main").is_err());
+        assert!(eval("main 2").is_err());
+        assert!(eval("1 / 0").is_err());
+    }
+
+    #[test]
+    fn substitute() {
+        let toml = r#"
+[constants]
+main_deploy_alt = 300
+unused = 1.0
+
+[[states]]
+name = "Drogue"
+
+[[states.checks]]
+name = "Main"
+check = "altitude"
+less_than = "main_deploy_alt + 50"
+transition = "Main"
+
+[[states]]
+name = "Main"
+
+[states.timeout]
+seconds = "main_deploy_alt / 10"
+transition = "Drogue"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let config = upper::verify(&mut context).unwrap();
+        let states = config.states.get_ref();
+        let check = states[0].get_ref().checks[0].get_ref();
+        let less_than = check.less_than.as_ref().unwrap();
+        assert_eq!(*less_than.get_ref(), 350.0);
+        assert_eq!(
+            &toml[less_than.start()..less_than.end()],
+            "\"main_deploy_alt + 50\""
+        );
+        let timeout = states[1].get_ref().timeout.as_ref().unwrap().get_ref();
+        assert_eq!(*timeout.seconds.as_ref().unwrap().get_ref(), 30.0);
+
+        let start = toml.find("unused = 1.0").unwrap();
+        let line = context.subspan(start, start + "unused = 1.0\n".len());
+
+        let warnings = context.end_phase_and_emit().unwrap();
+        assert_eq!(warnings[0].message, "constant `unused` is never used");
+        assert_eq!(warnings[1].spans[0].label.as_deref(), Some("remove this"));
+        assert_eq!(session.suggestions()[0].span, line);
+    }
+}
@@ -0,0 +1,99 @@
+//! An audit of what happens to every key the config language accepts.
+//!
+//! A key that is parsed and then discarded is worse than one that is rejected, since the config
+//! looks like it does something that it does not. Every key in [`crate::registry::SECTIONS`] is
+//! listed in [`USES`] with where its value ends up, and no key may be [`Use::Ignored`]: a key that
+//! the flight computer cannot act on yet is rejected instead.
+
+/// Where the value of a key ends up
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Use {
+    /// The value is encoded into the .ncf file that the flight computer reads
+    Encoded,
+
+    /// The value is encoded into a sidecar that is uploaded alongside the config, such as the self
+    /// test or the mission event schedule
+    Sidecar,
+
+    /// The value only identifies something, or is only used by the verifier's own checks. Names
+    /// of states and checks are used by transitions and diagnostics, but are not encoded
+    Verifier,
+
+    /// The value is parsed, but has no effect on what the flight computer does
+    Ignored,
+}
+
+/// Every key in [`crate::registry::SECTIONS`], as `(section, key, use)`
+pub const USES: &[(&str, &str, Use)] = &[
+    ("top level", "default_state", Use::Encoded),
+    ("top level", "abort_state", Use::Encoded),
+    ("top level", "tick_rate", Use::Sidecar),
+    ("[[override]]", "lint", Use::Verifier),
+    ("[[override]]", "justification", Use::Verifier),
+    ("[[path_policy]]", "name", Use::Verifier),
+    ("[[path_policy]]", "order", Use::Verifier),
+    ("[lints]", "<name>", Use::Verifier),
+    ("[constants]", "<name>", Use::Encoded),
+    ("[checks]", "<name>", Use::Encoded),
+    ("[recovery]", "launch_altitude", Use::Encoded),
+    ("[recovery]", "main_altitude", Use::Encoded),
+    ("[crosscheck]", "allow", Use::Verifier),
+    ("[radio]", "packet_bytes", Use::Verifier),
+    ("[self_test]", "steps", Use::Sidecar),
+    ("[[self_test.steps]]", "name", Use::Sidecar),
+    ("[[self_test.steps]]", "check", Use::Sidecar),
+    ("[[mission_events]]", "name", Use::Verifier),
+    ("[[mission_events]]", "time", Use::Sidecar),
+    ("[[mission_events]]", "command", Use::Sidecar),
+    ("[fault_handling]", "barometer", Use::Sidecar),
+    ("[fault_handling]", "backup_barometer", Use::Sidecar),
+    ("[fault_handling]", "continuity", Use::Sidecar),
+    ("fallbacks in [fault_handling]", "action", Use::Sidecar),
+    ("fallbacks in [fault_handling]", "state", Use::Sidecar),
+    ("fallbacks in [fault_handling]", "backup", Use::Sidecar),
+    ("[[stages]]", "name", Use::Verifier),
+    ("[[stages]]", "states", Use::Encoded),
+    ("[[states]]", "name", Use::Verifier),
+    ("[[states]]", "abort_safe", Use::Verifier),
+    ("[[states]]", "checks", Use::Encoded),
+    ("[[states]]", "commands", Use::Encoded),
+    ("[[states]]", "timeout", Use::Encoded),
+    ("[[states]]", "use_check", Use::Encoded),
+    ("[[states.checks]]", "name", Use::Verifier),
+    ("[[states.checks]]", "check", Use::Encoded),
+    ("[[states.checks]]", "transition", Use::Encoded),
+    ("[[states.checks]]", "abort", Use::Encoded),
+    ("[[states.checks]]", "separate", Use::Encoded),
+    ("[[states.checks]]", "greater_than", Use::Encoded),
+    ("[[states.checks]]", "less_than", Use::Encoded),
+    ("[[states.checks]]", "upper_bound", Use::Encoded),
+    ("[[states.checks]]", "lower_bound", Use::Encoded),
+    ("[[states.checks]]", "flag", Use::Encoded),
+    // Rejected while lowering, since no target can encode them
+    ("[[states.checks]]", "requires", Use::Verifier),
+    ("[[states.commands]]", "delay", Use::Encoded),
+    ("[states.timeout]", "seconds", Use::Encoded),
+    ("[states.timeout]", "transition", Use::Encoded),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{Use, USES};
+    use crate::registry::SECTIONS;
+
+    #[test]
+    fn every_key_is_audited() {
+        for section in SECTIONS {
+            for (key, _) in section.keys {
+                let audited = USES.iter().any(|(s, k, _)| *s == section.name && k == key);
+                assert!(audited, "`{key}` in `{}`", section.name);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_is_ignored() {
+        let ignored: Vec<_> = USES.iter().filter(|(_, _, u)| *u == Use::Ignored).collect();
+        assert!(ignored.is_empty(), "parsed but ignored: {ignored:?}");
+    }
+}
@@ -0,0 +1,539 @@
+//! Consistency checks between the configs of redundant flight computers.
+//!
+//! Airframes with more than one flight computer normally fly the same logic on each, so that either
+//! one can recover the rocket alone. A typo in one of the configs means the computers disagree
+//! about when to deploy, which is easy to miss when reading the files side by side. This module
+//! compares the settings that matter for recovery: deployment altitudes, pyro commands, and abort
+//! logic. Differences that are on purpose, such as a backup charge that fires a little later, are
+//! declared in the `[crosscheck]` section of either config.
+
+use crate::spanned::SpannedValue;
+use codemap_diagnostic::{Diagnostic, Level};
+
+use crate::{upper, ErrorCode, Session, Span};
+
+/// One config's side of a [`Difference`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Side {
+    /// A description of the setting's value, or `None` if the config does not have it
+    pub value: Option<String>,
+
+    /// Where the setting is, or where it would be if it is missing
+    pub span: Option<(usize, usize)>,
+}
+
+/// A critical setting that is not the same in both configs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    /// The dotted name of the setting, such as `states.Drogue.checks.Apogee.flag`
+    pub key: String,
+
+    pub first: Side,
+    pub second: Side,
+}
+
+impl Side {
+    fn describe(&self) -> &str {
+        self.value.as_deref().unwrap_or("missing")
+    }
+}
+
+struct Compare {
+    differences: Vec<Difference>,
+}
+
+impl Compare {
+    fn push(&mut self, key: String, first: Side, second: Side) {
+        if first.value != second.value {
+            self.differences.push(Difference { key, first, second });
+        }
+    }
+
+    /// Compares a setting that may be missing from either config. `parents` is used as the span
+    /// of a missing setting
+    fn setting<T: PartialEq>(
+        &mut self,
+        key: String,
+        values: [Option<&SpannedValue<T>>; 2],
+        parents: [Option<(usize, usize)>; 2],
+        render: impl Fn(&T) -> String,
+    ) {
+        let [first, second] = [0, 1].map(|i| Side {
+            value: values[i].map(|v| format!("`{}`", render(v.get_ref()))),
+            span: values[i].map(|v| v.span()).or(parents[i]),
+        });
+        self.push(key, first, second);
+    }
+}
+
+fn state_names(config: &upper::ConfigFile) -> Vec<&str> {
+    config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref().name.get_ref().as_str())
+        .collect()
+}
+
+fn find_state<'a>(config: &'a upper::ConfigFile, name: &str) -> Option<&'a upper::State> {
+    config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .find(|s| s.name.get_ref() == name)
+}
+
+fn render_abort(abort: &upper::AbortTarget) -> String {
+    match abort {
+        upper::AbortTarget::State(state) => state.clone(),
+        upper::AbortTarget::Global(global) => global.to_string(),
+        upper::AbortTarget::Detailed(details) => {
+            let state = details.state.as_deref().unwrap_or("true");
+            match &details.reason {
+                Some(reason) => format!("{state} ({reason})"),
+                None => state.to_owned(),
+            }
+        }
+    }
+}
+
+/// Describes the pyro channels that a state fires, in order, along with the span of the first one
+fn pyro_commands(state: &upper::State) -> (String, Option<(usize, usize)>) {
+    let mut fired = Vec::new();
+    let mut span = None;
+    for command in state.commands.iter().map(|c| c.get_ref()) {
+        let channels = [
+            ("pyro1", &command.pyro1),
+            ("pyro2", &command.pyro2),
+            ("pyro3", &command.pyro3),
+        ];
+        for (channel, value) in channels {
+            if let Some(value) = value {
+                span.get_or_insert(value.span());
+                let on = if bool::from(*value.get_ref()) {
+                    "on"
+                } else {
+                    "off"
+                };
+                let delay = command.delay.as_ref().map_or(0.0, |d| *d.get_ref());
+                fired.push(format!("{channel} {on} after {delay}s"));
+            }
+        }
+    }
+    if fired.is_empty() {
+        ("no pyro commands".to_owned(), span)
+    } else {
+        (format!("`{}`", fired.join(", ")), span)
+    }
+}
+
+fn compare_checks(
+    compare: &mut Compare,
+    prefix: &str,
+    states: [&upper::State; 2],
+    parents: [Option<(usize, usize)>; 2],
+) {
+    let mut names: Vec<&str> = Vec::new();
+    for state in states {
+        for check in &state.checks {
+            let name = check.get_ref().name.get_ref().as_str();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    for name in names {
+        let key = format!("{prefix}.checks.{name}");
+        let checks = states.map(|s| {
+            s.checks
+                .iter()
+                .map(|c| c.get_ref())
+                .find(|c| c.name.get_ref() == name)
+        });
+        let [first, second] = match checks {
+            [Some(first), Some(second)] => [first, second],
+            _ => {
+                let [first, second] = [0, 1].map(|i| Side {
+                    value: checks[i].map(|_| "defined".to_owned()),
+                    span: checks[i].map(|c| c.name.span()).or(parents[i]),
+                });
+                compare.push(key, first, second);
+                continue;
+            }
+        };
+
+        let parents = [Some(first.name.span()), Some(second.name.span())];
+        compare.setting(
+            format!("{key}.check"),
+            [Some(&first.check), Some(&second.check)],
+            parents,
+            String::clone,
+        );
+        compare.setting(
+            format!("{key}.transition"),
+            [first.transition.as_ref(), second.transition.as_ref()],
+            parents,
+            String::clone,
+        );
+        compare.setting(
+            format!("{key}.abort"),
+            [first.abort.as_ref(), second.abort.as_ref()],
+            parents,
+            render_abort,
+        );
+        let floats = [
+            ("greater_than", &first.greater_than, &second.greater_than),
+            ("less_than", &first.less_than, &second.less_than),
+            ("upper_bound", &first.upper_bound, &second.upper_bound),
+            ("lower_bound", &first.lower_bound, &second.lower_bound),
+        ];
+        for (field, a, b) in floats {
+            compare.setting(
+                format!("{key}.{field}"),
+                [a.as_ref(), b.as_ref()],
+                parents,
+                f32::to_string,
+            );
+        }
+        compare.setting(
+            format!("{key}.flag"),
+            [first.flag.as_ref(), second.flag.as_ref()],
+            parents,
+            String::clone,
+        );
+    }
+}
+
+/// Returns every critical setting that differs between `first` and `second`.
+///
+/// Spans in each [`Side`] are byte offsets into the source of the config they came from
+pub fn differences(first: &upper::ConfigFile, second: &upper::ConfigFile) -> Vec<Difference> {
+    let mut compare = Compare {
+        differences: Vec::new(),
+    };
+    let configs = [first, second];
+
+    compare.setting(
+        "default_state".to_owned(),
+        configs.map(|c| c.default_state.as_ref()),
+        [None, None],
+        String::clone,
+    );
+    compare.setting(
+        "abort_state".to_owned(),
+        configs.map(|c| c.abort_state.as_ref()),
+        [None, None],
+        String::clone,
+    );
+    let recovery = configs.map(|c| c.recovery.as_ref());
+    let recovery_spans = recovery.map(|r| r.map(|r| r.span()));
+    compare.setting(
+        "recovery.launch_altitude".to_owned(),
+        recovery.map(|r| r.and_then(|r| r.get_ref().launch_altitude.as_ref())),
+        recovery_spans,
+        f32::to_string,
+    );
+    compare.setting(
+        "recovery.main_altitude".to_owned(),
+        recovery.map(|r| r.and_then(|r| r.get_ref().main_altitude.as_ref())),
+        recovery_spans,
+        f32::to_string,
+    );
+
+    let mut names = state_names(first);
+    for name in state_names(second) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    for name in names {
+        let key = format!("states.{name}");
+        let states = configs.map(|c| find_state(c, name));
+        let states = match states {
+            [Some(first), Some(second)] => [first, second],
+            _ => {
+                let [first, second] = states.map(|s| Side {
+                    value: s.map(|_| "defined".to_owned()),
+                    span: s.map(|s| s.name.span()),
+                });
+                compare.push(key, first, second);
+                continue;
+            }
+        };
+
+        let parents = states.map(|s| Some(s.name.span()));
+        compare.push(
+            format!("{key}.abort_safe"),
+            Side {
+                value: Some(format!("`{}`", states[0].abort_safe)),
+                span: parents[0],
+            },
+            Side {
+                value: Some(format!("`{}`", states[1].abort_safe)),
+                span: parents[1],
+            },
+        );
+
+        let timeouts = states.map(|s| s.timeout.as_ref().map(|t| t.get_ref()));
+        compare.setting(
+            format!("{key}.timeout.seconds"),
+            timeouts.map(|t| t.and_then(|t| t.seconds.as_ref())),
+            parents,
+            f32::to_string,
+        );
+        compare.setting(
+            format!("{key}.timeout.transition"),
+            timeouts.map(|t| t.and_then(|t| t.transition.as_ref())),
+            parents,
+            String::clone,
+        );
+
+        let [first, second] = [0, 1].map(|i| {
+            let (value, span) = pyro_commands(states[i]);
+            Side {
+                value: Some(value),
+                span: span.or(parents[i]),
+            }
+        });
+        compare.push(format!("{key}.commands"), first, second);
+
+        compare_checks(&mut compare, &key, states, parents);
+    }
+
+    compare.differences
+}
+
+/// Returns true if `allow` names `key`, or a setting that contains it
+fn allows(allow: &str, key: &str) -> bool {
+    key.strip_prefix(allow)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Verifies two configs, then reports every critical setting that differs between them as an
+/// error with spans in both files. Differences allowed by the `[crosscheck]` section of either
+/// config are not reported.
+///
+/// Returns `Ok(diagnostics)` if the configs agree, or `Err(diagnostics)` if either config fails
+/// to verify or they disagree.
+pub fn crosscheck_inner(
+    session: &mut Session,
+    first: (String, String),
+    second: (String, String),
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
+
+    let (first_toml, first_path) = first;
+    let mut context = session.add_file(first_toml, first_path.clone());
+    let (first, _) = crate::lower_phases(&mut context, &mut all_diagnostics)
+        .map_err(crate::VerifyError::into_diagnostics)?;
+    let first_file = context.span();
+
+    let (second_toml, second_path) = second;
+    let mut context = session.add_file(second_toml, second_path.clone());
+    let (second, _) = crate::lower_phases(&mut context, &mut all_diagnostics)
+        .map_err(crate::VerifyError::into_diagnostics)?;
+    let files = [first_file, context.span()];
+    let span_in = |file: usize, (start, end): (usize, usize)| -> Span {
+        files[file].subspan(start as u64, end as u64).into()
+    };
+
+    let allowed: Vec<(usize, &SpannedValue<String>)> = [&first, &second]
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, c)| c.crosscheck.as_ref().map(|section| (i, section)))
+        .flat_map(|(i, section)| section.get_ref().allow.iter().map(move |a| (i, a)))
+        .collect();
+    let mut used = vec![false; allowed.len()];
+
+    for difference in differences(&first, &second) {
+        let allowance = allowed
+            .iter()
+            .position(|(_, allow)| allows(allow.get_ref(), &difference.key));
+        if let Some(i) = allowance {
+            used[i] = true;
+            continue;
+        }
+
+        let mut error = context
+            .error(format!(
+                "`{}` is {} in `{first_path}` but {} in `{second_path}`",
+                difference.key,
+                difference.first.describe(),
+                difference.second.describe(),
+            ))
+            .code(ErrorCode::CrosscheckMismatch);
+        if let Some(span) = difference.second.span {
+            error = error.set_primary_span(
+                span_in(1, span),
+                format!("{} here", difference.second.describe()),
+            );
+        }
+        if let Some(span) = difference.first.span {
+            error = error.span_label(
+                span_in(0, span),
+                format!("{} in `{first_path}`", difference.first.describe()),
+            );
+        }
+        error.emit();
+        context
+            .help(format!(
+                "if this is intentional, add `\"{}\"` to `allow` in the `[crosscheck]` section",
+                difference.key
+            ))
+            .emit();
+    }
+
+    for ((file, allow), used) in allowed.iter().zip(used) {
+        if !used {
+            context
+                .warn(format!(
+                    "allowed difference `{}` does not match any difference",
+                    allow.get_ref()
+                ))
+                .set_primary_span(span_in(*file, allow.span()), "remove this entry")
+                .emit();
+        }
+    }
+
+    let warnings = context.end_phase_and_emit()?;
+    all_diagnostics.extend(warnings);
+    Ok(all_diagnostics)
+}
+
+/// Loads the configs at `first_path` and `second_path` and compares them, see
+/// [`crosscheck_inner`]
+pub fn crosscheck_files(
+    session: &mut Session,
+    first_path: String,
+    second_path: String,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let read = |path: String| match std::fs::read_to_string(&path) {
+        Ok(toml) => Ok((toml, path)),
+        Err(err) => Err(vec![Diagnostic {
+            level: Level::Error,
+            message: format!("failed to read file `{path}`: {err:?}"),
+            code: None,
+            spans: vec![],
+        }]),
+    };
+    let first = read(first_path)?;
+    let second = read(second_path)?;
+    crosscheck_inner(session, first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crosscheck_inner, differences};
+    use crate::{upper, Session};
+
+    const CONFIG: &str = r#"
+default_state = "Armed"
+
+[recovery]
+launch_altitude = 30.0
+main_altitude = 150.0
+
+[self_test]
+steps = [
+    { name = "Drogue", check = "pyro1_continuity", flag = "set" },
+    { name = "Main", check = "pyro2_continuity", flag = "set" },
+]
+
+[lints]
+unused_pyro = "allow"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "launch_detected"
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee_passed"
+transition = "Drogue"
+
+[[states]]
+name = "Drogue"
+
+[[states.commands]]
+pyro1 = true
+
+[[states.checks]]
+name = "Main"
+check = "below_main_altitude"
+transition = "Main"
+
+[[states]]
+name = "Main"
+
+[[states.commands]]
+pyro2 = true
+"#;
+
+    fn keys(first: &str, second: &str) -> Vec<String> {
+        let mut session = Session::new();
+        let first = upper::verify(&mut session.testing(first)).unwrap();
+        let second = upper::verify(&mut session.testing(second)).unwrap();
+        differences(&first, &second)
+            .into_iter()
+            .map(|d| d.key)
+            .collect()
+    }
+
+    #[test]
+    fn finds_differences() {
+        assert!(keys(CONFIG, CONFIG).is_empty());
+
+        let backup = CONFIG
+            .replace("main_altitude = 150.0", "main_altitude = 120.0")
+            .replace("pyro2 = true", "pyro2 = true\ndelay = 1.0")
+            .replace("transition = \"Drogue\"", "abort = \"Armed\"");
+        assert_eq!(
+            keys(CONFIG, &backup),
+            [
+                "recovery.main_altitude",
+                "states.Flight.checks.Apogee.transition",
+                "states.Flight.checks.Apogee.abort",
+                "states.Main.commands",
+            ]
+        );
+
+        let renamed = CONFIG.replace("\"Main\"", "\"Landing\"");
+        assert_eq!(
+            keys(CONFIG, &renamed),
+            [
+                "states.Drogue.checks.Main",
+                "states.Drogue.checks.Landing",
+                "states.Main",
+                "states.Landing",
+            ]
+        );
+    }
+
+    #[test]
+    fn allowed_differences() {
+        let later = CONFIG.replace("pyro2 = true", "pyro2 = true\ndelay = 1.0");
+        let check = |first: &str, second: &str| {
+            let mut session = Session::new();
+            let first = (first.to_owned(), "a.toml".to_owned());
+            let second = (second.to_owned(), "b.toml".to_owned());
+            crosscheck_inner(&mut session, first, second)
+        };
+        assert!(check(CONFIG, &later).is_err());
+
+        let allowed = format!("{later}\n[crosscheck]\nallow = [\"states.Main\"]\n");
+        assert!(check(CONFIG, &allowed).unwrap().is_empty());
+
+        // An allowance that matches nothing is stale
+        let stale = format!("{CONFIG}\n[crosscheck]\nallow = [\"states.Drogue\"]\n");
+        assert_eq!(check(&stale, CONFIG).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,246 @@
+//! Converts an encoded config back into a config file.
+//!
+//! Configs that are already on a flight computer have to be audited from their bytes, since the
+//! file they were built from may have changed since. [`decompile`] reconstructs a config file that
+//! builds into the same bytes wherever the config language can express it.
+//!
+//! The encoded format does not store names. They are taken from the config the bytes were built
+//! from when it is available, see [`Names::from_source`], and are otherwise made up from the
+//! position of each state and check.
+
+use std::fmt::Write;
+
+use nova_software_common::{
+    index::{self, StateTransition},
+    CheckData, CommandObject, FloatCondition,
+};
+
+use crate::upper;
+
+/// The names of the states and checks of a config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Names {
+    pub states: Vec<String>,
+
+    /// The names of the checks of each state
+    pub checks: Vec<Vec<String>>,
+}
+
+impl Names {
+    /// Names made up from the position of each state and check, such as `State2` and `State2Check0`
+    pub fn generic(config: &index::ConfigFile) -> Self {
+        let states: Vec<String> = (0..config.states.len())
+            .map(|i| format!("State{i}"))
+            .collect();
+        let checks = config
+            .states
+            .iter()
+            .zip(&states)
+            .map(|(s, name)| {
+                (0..s.checks.len())
+                    .map(|j| format!("{name}Check{j}"))
+                    .collect()
+            })
+            .collect();
+        Self { states, checks }
+    }
+
+    /// The names in `source`, the config that `config` was lowered from. Returns `None` if
+    /// `source` does not have the same shape as `config`
+    pub fn from_source(source: &upper::ConfigFile, config: &index::ConfigFile) -> Option<Self> {
+        let states = source.states.get_ref();
+        let same_shape = states.len() == config.states.len()
+            && states
+                .iter()
+                .zip(&config.states)
+                .all(|(s, lowered)| s.get_ref().checks.len() == lowered.checks.len());
+        if !same_shape {
+            return None;
+        }
+        Some(Self {
+            states: states
+                .iter()
+                .map(|s| s.get_ref().name.get_ref().clone())
+                .collect(),
+            checks: states
+                .iter()
+                .map(|s| {
+                    let checks = s.get_ref().checks.iter();
+                    checks.map(|c| c.get_ref().name.get_ref().clone()).collect()
+                })
+                .collect(),
+        })
+    }
+}
+
+/// A decompiled config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decompiled {
+    /// The config file, in toml
+    pub toml: String,
+
+    /// Everything in the encoded config that the config language cannot express. Each is also
+    /// left as a comment in [`Self::toml`]
+    pub lossy: Vec<String>,
+}
+
+/// Writes `value` so that it reads back as a float
+fn float(value: f32) -> String {
+    format!("{value:?}")
+}
+
+/// Writes `value` as a toml string
+fn string(value: &str) -> String {
+    // Names are identifiers, but names from a source file have not necessarily been verified
+    toml::Value::String(value.to_owned()).to_string()
+}
+
+/// Reconstructs a config file from `config`, naming states and checks with `names`
+pub fn decompile(config: &index::ConfigFile, names: &Names) -> Decompiled {
+    let mut out = String::new();
+    let mut lossy = Vec::new();
+    let state_name = |index: index::StateIndex| string(&names.states[usize::from(index)]);
+
+    // The first less than altitude check is assumed to be main deployment, and is written as
+    // `below_main_altitude` with its threshold in `recovery.main_altitude`
+    let mut main_altitude = None;
+    for check in config.states.iter().flat_map(|s| &s.checks) {
+        if let CheckData::Altitude(FloatCondition::LessThan(altitude)) = check.data {
+            main_altitude.get_or_insert(altitude);
+        }
+    }
+
+    let aborted_to: Vec<index::StateIndex> = config
+        .states
+        .iter()
+        .flat_map(|s| s.checks.iter().filter_map(|c| c.transition))
+        .filter_map(|t| match t {
+            StateTransition::Abort(index) => Some(index),
+            StateTransition::Transition(_) => None,
+        })
+        .collect();
+
+    let _ = writeln!(out, "default_state = {}", state_name(config.default_state));
+    if let Some(altitude) = main_altitude {
+        let _ = writeln!(out, "\n[recovery]\nmain_altitude = {}", float(altitude));
+    }
+
+    for (i, state) in config.states.iter().enumerate() {
+        let _ = writeln!(out, "\n[[states]]\nname = {}", string(&names.states[i]));
+        if aborted_to.iter().any(|a| usize::from(*a) == i) {
+            let _ = writeln!(out, "abort_safe = true");
+        }
+
+        for (j, check) in state.checks.iter().enumerate() {
+            let name = &names.checks[i][j];
+            let mut body = format!("name = {}\n", string(name));
+            let condition = match check.data {
+                CheckData::Altitude(FloatCondition::LessThan(altitude))
+                    if Some(altitude) == main_altitude =>
+                {
+                    "check = \"below_main_altitude\"".to_owned()
+                }
+                CheckData::Altitude(condition) => float_check("altitude", condition),
+                CheckData::ApogeeFlag(flag) => flag_check("apogee", flag.0),
+                CheckData::Pyro1Continuity(flag) => flag_check("pyro1_continuity", flag.0),
+                CheckData::Pyro2Continuity(flag) => flag_check("pyro2_continuity", flag.0),
+                CheckData::Pyro3Continuity(flag) => flag_check("pyro3_continuity", flag.0),
+            };
+            body.push_str(&condition);
+            match check.transition {
+                Some(StateTransition::Transition(to)) => {
+                    let _ = write!(body, "\ntransition = {}", state_name(to));
+                }
+                Some(StateTransition::Abort(to)) => {
+                    let _ = write!(body, "\nabort = {}", state_name(to));
+                }
+                None => {}
+            }
+            let _ = writeln!(out, "\n[[states.checks]]\n{body}");
+        }
+
+        for command in &state.commands {
+            let action = match command.object {
+                CommandObject::Pyro1(fire) => format!("pyro1 = {fire}"),
+                CommandObject::Pyro2(fire) => format!("pyro2 = {fire}"),
+                CommandObject::Pyro3(fire) => format!("pyro3 = {fire}"),
+                CommandObject::Beacon(on) => format!("beacon = {on}"),
+                CommandObject::DataRate(rate) => format!("data_rate = {rate}"),
+            };
+            let _ = writeln!(out, "\n[[states.commands]]\n{action}");
+            if command.delay.0 != 0.0 {
+                let _ = writeln!(out, "delay = {}", float(command.delay.0));
+            }
+        }
+
+        if let Some(timeout) = &state.timeout {
+            let _ = writeln!(out, "\n[states.timeout]\nseconds = {}", float(timeout.time));
+            match timeout.transition {
+                StateTransition::Transition(to) => {
+                    let _ = writeln!(out, "transition = {}", state_name(to));
+                }
+                StateTransition::Abort(to) => {
+                    lossy.push(format!(
+                        "the timeout of `{}` aborts, but timeouts can only transition",
+                        names.states[i]
+                    ));
+                    let _ = writeln!(
+                        out,
+                        "# Cannot be expressed: aborts rather than transitions\ntransition = {}",
+                        state_name(to)
+                    );
+                }
+            }
+        }
+    }
+    Decompiled { toml: out, lossy }
+}
+
+fn float_check(kind: &str, condition: FloatCondition) -> String {
+    let condition = match condition {
+        FloatCondition::GreaterThan(value) => format!("greater_than = {}", float(value)),
+        FloatCondition::LessThan(value) => format!("less_than = {}", float(value)),
+        FloatCondition::Between {
+            upper_bound,
+            lower_bound,
+        } => format!(
+            "upper_bound = {}\nlower_bound = {}",
+            float(upper_bound),
+            float(lower_bound)
+        ),
+    };
+    format!("check = \"{kind}\"\n{condition}")
+}
+
+fn flag_check(kind: &str, set: bool) -> String {
+    let flag = if set { "set" } else { "unset" };
+    format!("check = \"{kind}\"\nflag = \"{flag}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompile, Names};
+    use crate::{presets::Preset, Session};
+
+    #[test]
+    fn presets_round_trip() {
+        for preset in Preset::ALL {
+            let mut session = Session::new();
+            let source = preset.source().to_owned();
+            let (upper, lower, _) =
+                crate::load_config(&mut session, source, "a.toml".to_owned()).unwrap();
+
+            for names in [
+                Names::generic(&lower),
+                Names::from_source(&upper, &lower).unwrap(),
+            ] {
+                let decompiled = decompile(&lower, &names);
+                assert!(decompiled.lossy.is_empty(), "{preset}");
+                let mut session = Session::new();
+                let result = crate::load_config(&mut session, decompiled.toml, "b.toml".to_owned());
+                let (_, again, _) = result.unwrap_or_else(|d| panic!("{preset}: {d:#?}"));
+                assert_eq!(again, lower, "{preset}");
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Renamed check kinds and keys.
+//!
+//! The config language has to evolve without breaking every archived config at once. When a check
+//! kind is renamed, its old name is added to [`crate::registry::DEPRECATIONS`]. Configs using the
+//! old name still verify, but [`rewrite`] warns about every use with the name to write instead,
+//! and replaces it so that the rest of the verifier only sees the new name. Old names are only
+//! dropped when [`crate::registry::SCHEMA_VERSION`] is bumped.
+
+use crate::{
+    registry::{Deprecation, DEPRECATIONS},
+    upper, Context, Span,
+};
+
+/// Replaces every deprecated check kind in `config` with its replacement, warning about each one
+pub fn rewrite(config: &mut upper::ConfigFile, context: &mut Context) {
+    rewrite_with(config, DEPRECATIONS, context);
+}
+
+fn rewrite_with(
+    config: &mut upper::ConfigFile,
+    deprecations: &[Deprecation],
+    context: &mut Context,
+) {
+    let states = config.states.get_mut().iter_mut();
+    let checks = states.flat_map(|s| s.get_mut().checks.iter_mut());
+    let steps = config.self_test.iter_mut();
+    let steps = steps.flat_map(|t| t.get_mut().steps.iter_mut());
+    let templates = config.checks.values_mut();
+    for check in checks.chain(steps).chain(templates) {
+        let kind = &mut check.get_mut().check;
+        let deprecation = match deprecations.iter().find(|d| d.name == kind.get_ref()) {
+            Some(deprecation) => deprecation,
+            None => continue,
+        };
+        let span = Span::from_spanned(context, kind);
+        context
+            .warn(format!("check kind `{}` is deprecated", deprecation.name))
+            .set_primary_span(span, format!("renamed to `{}`", deprecation.replacement))
+            .lint("deprecated")
+            .emit();
+        context
+            .help(format!(
+                "write `check = \"{}\"`, the old name will be rejected once the config schema is newer than version {}",
+                deprecation.replacement, deprecation.since
+            ))
+            .emit();
+        *kind.get_mut() = deprecation.replacement.to_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use super::rewrite_with;
+    use crate::{
+        registry::{self, Deprecation, CHECK_KINDS, DEPRECATIONS, SCHEMA_VERSION},
+        upper, Session,
+    };
+
+    #[test]
+    fn renamed_kind() {
+        let toml = r#"
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Deploy"
+check = "pyro_one"
+flag = "set"
+transition = "Flight"
+
+[self_test]
+steps = [{ name = "Continuity", check = "pyro_one", flag = "set" }]
+"#;
+        let renames = [Deprecation {
+            name: "pyro_one",
+            replacement: "pyro1",
+            since: registry::SCHEMA_VERSION,
+        }];
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let mut config = upper::verify(&mut context).unwrap();
+        rewrite_with(&mut config, &renames, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings = diagnostics.iter().filter(|d| d.level == Level::Warning);
+        assert_eq!(warnings.count(), 2);
+
+        let state = config.states.get_ref()[0].get_ref();
+        assert_eq!(state.checks[0].get_ref().check.get_ref(), "pyro1");
+        let steps = &config.self_test.unwrap().into_inner().steps;
+        assert_eq!(steps[0].get_ref().check.get_ref(), "pyro1");
+    }
+
+    #[test]
+    fn deprecations_are_current() {
+        for deprecation in DEPRECATIONS {
+            // Bumping the schema version is when old names are dropped
+            assert_eq!(deprecation.since, SCHEMA_VERSION, "{}", deprecation.name);
+            assert!(CHECK_KINDS
+                .iter()
+                .any(|k| k.name == deprecation.replacement));
+            assert!(CHECK_KINDS.iter().all(|k| k.name != deprecation.name));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! A registry of every diagnostic the verifier can produce, so that tools can explain a
+//! diagnostic from its code without running the verifier. The `explain` subcommand and
+//! [`crate::docs`] are built on [`all`].
+//!
+//! Diagnostics are registered by their code, which is shown in brackets after the level, such as
+//! `error[pre_launch_pyro]` or `error[E0001]`. Lints are named by [`crate::lint::LINTS`], and
+//! other errors are numbered by [`ErrorCode`].
+
+use codemap_diagnostic::Level;
+
+use crate::{lint, ErrorCode};
+
+/// A diagnostic the verifier can produce
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticInfo {
+    /// The code shown with the diagnostic
+    pub code: &'static str,
+
+    /// The level the diagnostic is emitted at, unless it is overridden
+    pub level: Level,
+
+    /// The message of the diagnostic, with the parts that change written as `{placeholder}`
+    pub message: &'static str,
+    pub description: &'static str,
+}
+
+impl DiagnosticInfo {
+    /// The link to the diagnostic's section in the language reference rendered by
+    /// [`crate::docs::language_docs`], relative to the reference
+    pub fn docs_link(&self) -> String {
+        format!("#{}", self.code)
+    }
+}
+
+/// The name of `level`, as it is shown before a diagnostic's message
+pub fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Bug => "bug",
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note => "note",
+        Level::Help => "help",
+    }
+}
+
+/// Every diagnostic the verifier can produce, ordered by code
+pub fn all() -> Vec<DiagnosticInfo> {
+    let mut all: Vec<_> = lint::LINTS
+        .iter()
+        .map(|lint| DiagnosticInfo {
+            code: lint.name,
+            level: lint.level,
+            message: lint.message,
+            description: lint.description,
+        })
+        .collect();
+    all.extend(ErrorCode::ALL.into_iter().map(|code| DiagnosticInfo {
+        code: code.name(),
+        level: Level::Error,
+        message: code.message(),
+        description: code.description(),
+    }));
+    all.sort_by_key(|d| d.code);
+    all
+}
+
+/// Returns the diagnostic with `code`
+pub fn find(code: &str) -> Option<DiagnosticInfo> {
+    all().into_iter().find(|d| d.code == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{all, find};
+    use crate::ErrorCode;
+
+    /// The name and source of every file of the verifier, without its tests
+    fn sources() -> Vec<(String, String)> {
+        let src = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        let mut sources = Vec::new();
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "rs") {
+                continue;
+            }
+            let mut source = std::fs::read_to_string(&path).unwrap();
+            if let Some(tests) = source.find("#[cfg(test)]\nmod tests") {
+                source.truncate(tests);
+            }
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            sources.push((name, source));
+        }
+        sources
+    }
+
+    /// Every code passed to `DiagnosticBuilder::lint` or `DiagnosticBuilder::code` in the source
+    /// of the verifier
+    fn emitted_codes() -> BTreeSet<String> {
+        let mut codes = BTreeSet::new();
+        for (_, source) in sources() {
+            let mut rest = source.as_str();
+            while let Some(start) = rest.find(".lint(\"") {
+                rest = &rest[start + ".lint(\"".len()..];
+                codes.insert(rest[..rest.find('"').unwrap()].to_owned());
+            }
+            let mut rest = source.as_str();
+            while let Some(start) = rest.find(".code(") {
+                rest = &rest[start + ".code(".len()..];
+                let argument = &rest[..rest.find(')').unwrap()];
+                let variant = argument.rsplit("ErrorCode::").next().unwrap();
+                if let Some(code) = ErrorCode::ALL.iter().find(|c| format!("{c:?}") == variant) {
+                    codes.insert(code.name().to_owned());
+                }
+            }
+        }
+        codes
+    }
+
+    /// The `file:line` of every error in the source of the verifier that is emitted without a
+    /// code. [`crate::DiagnosticBuilder::emit`] also panics on these in debug builds, but only
+    /// for the errors that tests happen to reach
+    fn uncoded_errors() -> Vec<String> {
+        let mut uncoded = Vec::new();
+        for (name, source) in sources() {
+            let mut offset = 0;
+            while let Some(start) = source[offset..].find(".error(") {
+                let start = offset + start;
+                offset = start + ".error(".len();
+                // Parsers have `error` methods of their own, diagnostics are always built from a
+                // context
+                let receiver = source[..start].trim_end();
+                let chain = match source[start..].find(".emit()") {
+                    Some(end) if receiver.ends_with("context") => &source[start..start + end],
+                    _ => continue,
+                };
+                if !chain.contains(".code(") && !chain.contains(".lint(") {
+                    let line = source[..start].lines().count();
+                    uncoded.push(format!("{name}:{line}"));
+                }
+            }
+        }
+        uncoded
+    }
+
+    #[test]
+    fn every_diagnostic_is_registered() {
+        assert_eq!(
+            uncoded_errors(),
+            Vec::<String>::new(),
+            "every error must have an `ErrorCode` or come from a lint"
+        );
+        let registered: BTreeSet<String> = all().iter().map(|d| d.code.to_owned()).collect();
+        assert_eq!(registered.len(), all().len(), "codes are not unique");
+        assert_eq!(
+            emitted_codes(),
+            registered,
+            "every code that is emitted must be registered, and every registered code emitted"
+        );
+        assert_eq!(
+            find("pre_launch_pyro").unwrap().docs_link(),
+            "#pre_launch_pyro"
+        );
+        assert!(find("pre-launch-pyro").is_none());
+        assert_eq!(find("E0001").unwrap().message, "state not found `{name}`");
+    }
+}
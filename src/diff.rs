@@ -0,0 +1,294 @@
+//! A structural diff of two lowered configs, for `diff`.
+//!
+//! Reviewers signing off on a config before flight need to see exactly what changed since the last
+//! one they accepted. Comparing the lowered configs rather than the source ignores changes that do
+//! not reach the flight computer, such as comments, reordered keys, or a constant that was renamed.
+//!
+//! States and checks are matched by name when both configs were built from source. A compiled
+//! `.ncf` file has no names, so if either side is one they are matched by position instead.
+
+use std::fmt::Write;
+
+use nova_software_common::index::{self, StateTransition};
+
+use crate::decompile::Names;
+use crate::export::{describe_check, describe_command};
+
+/// One side of a diff
+#[derive(Copy, Clone)]
+pub struct Side<'a> {
+    pub config: &'a index::ConfigFile,
+    pub names: &'a Names,
+}
+
+/// Pairs up the items named `old` and `new`, by name if `by_name` is set and by position
+/// otherwise. Items only on one side are paired with `None`, after every matched pair
+fn pair(old: &[String], new: &[String], by_name: bool) -> Vec<(Option<usize>, Option<usize>)> {
+    if !by_name {
+        return (0..old.len().max(new.len()))
+            .map(|i| ((i < old.len()).then_some(i), (i < new.len()).then_some(i)))
+            .collect();
+    }
+    let mut pairs: Vec<_> = old
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (Some(i), new.iter().position(|n| n == name)))
+        .collect();
+    for (j, name) in new.iter().enumerate() {
+        if !old.contains(name) {
+            pairs.push((None, Some(j)));
+        }
+    }
+    pairs
+}
+
+/// Where a transition leads, as `-> Name` or `abort -> Name`
+fn describe_transition(transition: Option<StateTransition>, names: &Names) -> String {
+    match transition {
+        Some(StateTransition::Transition(to)) => format!("-> {}", names.states[usize::from(to)]),
+        Some(StateTransition::Abort(to)) => format!("abort -> {}", names.states[usize::from(to)]),
+        None => "no transition".to_owned(),
+    }
+}
+
+struct Differ<'a> {
+    old: Side<'a>,
+    new: Side<'a>,
+    states: Vec<(Option<usize>, Option<usize>)>,
+    by_name: bool,
+    out: String,
+}
+
+impl Differ<'_> {
+    /// The name of the states in pair `i`, preferring the new name
+    fn label(&self, i: usize) -> &str {
+        match self.states[i] {
+            (_, Some(new)) => &self.new.names.states[new],
+            (Some(old), None) => &self.old.names.states[old],
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// The pair that state `index` on the old side if `old`, or the new side otherwise, is in
+    fn pair_of(&self, old: bool, index: usize) -> Option<usize> {
+        self.states.iter().position(|&(o, n)| {
+            if old {
+                o == Some(index)
+            } else {
+                n == Some(index)
+            }
+        })
+    }
+
+    /// Whether two transitions lead to the same state in the same way
+    fn same_transition(&self, old: Option<StateTransition>, new: Option<StateTransition>) -> bool {
+        match (old, new) {
+            (None, None) => true,
+            (Some(StateTransition::Transition(a)), Some(StateTransition::Transition(b)))
+            | (Some(StateTransition::Abort(a)), Some(StateTransition::Abort(b))) => {
+                self.pair_of(true, usize::from(a)) == self.pair_of(false, usize::from(b))
+            }
+            _ => false,
+        }
+    }
+
+    fn line(&mut self, line: String) {
+        let _ = writeln!(self.out, "{line}");
+    }
+
+    fn state(&mut self, label: &str, old: usize, new: usize) {
+        let (old_names, new_names) = (self.old.names, self.new.names);
+        let (old_state, new_state) = (&self.old.config.states[old], &self.new.config.states[new]);
+
+        let checks = pair(&old_names.checks[old], &new_names.checks[new], self.by_name);
+        for (a, b) in checks {
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    let name = &new_names.checks[new][b];
+                    let (before, after) = (&old_state.checks[a], &new_state.checks[b]);
+                    if before.data != after.data {
+                        self.line(format!(
+                            "~ {label} check {name}: {} => {}",
+                            describe_check(before.data),
+                            describe_check(after.data)
+                        ));
+                    }
+                    if !self.same_transition(before.transition, after.transition) {
+                        self.line(format!(
+                            "~ {label} check {name}: {} => {}",
+                            describe_transition(before.transition, old_names),
+                            describe_transition(after.transition, new_names)
+                        ));
+                    }
+                }
+                (Some(a), None) => {
+                    let check = &old_state.checks[a];
+                    self.line(format!(
+                        "- {label} check {}: {} {}",
+                        old_names.checks[old][a],
+                        describe_check(check.data),
+                        describe_transition(check.transition, old_names)
+                    ));
+                }
+                (None, Some(b)) => {
+                    let check = &new_state.checks[b];
+                    self.line(format!(
+                        "+ {label} check {}: {} {}",
+                        new_names.checks[new][b],
+                        describe_check(check.data),
+                        describe_transition(check.transition, new_names)
+                    ));
+                }
+                (None, None) => {}
+            }
+        }
+
+        let before: Vec<String> = old_state.commands.iter().map(describe_command).collect();
+        let mut after: Vec<String> = new_state.commands.iter().map(describe_command).collect();
+        for command in before {
+            match after.iter().position(|c| *c == command) {
+                Some(i) => {
+                    after.remove(i);
+                }
+                None => self.line(format!("- {label} command {command}")),
+            }
+        }
+        for command in after {
+            self.line(format!("+ {label} command {command}"));
+        }
+
+        let describe_timeout = |timeout: &index::Timeout, names| {
+            format!(
+                "after {} s {}",
+                timeout.time,
+                describe_transition(Some(timeout.transition), names)
+            )
+        };
+        match (&old_state.timeout, &new_state.timeout) {
+            (Some(a), Some(b)) => {
+                if a.time != b.time || !self.same_transition(Some(a.transition), Some(b.transition))
+                {
+                    self.line(format!(
+                        "~ {label} timeout: {} => {}",
+                        describe_timeout(a, old_names),
+                        describe_timeout(b, new_names)
+                    ));
+                }
+            }
+            (Some(a), None) => {
+                self.line(format!(
+                    "- {label} timeout: {}",
+                    describe_timeout(a, old_names)
+                ));
+            }
+            (None, Some(b)) => {
+                self.line(format!(
+                    "+ {label} timeout: {}",
+                    describe_timeout(b, new_names)
+                ));
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Lists every difference from `old` to `new`, one per line. Lines start with `+` for something
+/// added, `-` for something removed, and `~` for something changed. Returns an empty string if
+/// the configs are the same.
+///
+/// States and checks are matched by name if `by_name` is set, which is only meaningful when both
+/// sides have names from their source
+pub fn diff(old: Side, new: Side, by_name: bool) -> String {
+    let states = pair(&old.names.states, &new.names.states, by_name);
+    let mut differ = Differ {
+        old,
+        new,
+        states,
+        by_name,
+        out: String::new(),
+    };
+
+    let old_default = differ.pair_of(true, usize::from(old.config.default_state));
+    let new_default = differ.pair_of(false, usize::from(new.config.default_state));
+    if old_default != new_default {
+        differ.line(format!(
+            "~ default state: {} => {}",
+            old.names.states[usize::from(old.config.default_state)],
+            new.names.states[usize::from(new.config.default_state)]
+        ));
+    }
+
+    for i in 0..differ.states.len() {
+        let label = differ.label(i).to_owned();
+        match differ.states[i] {
+            (Some(a), Some(b)) => differ.state(&label, a, b),
+            (Some(_), None) => differ.line(format!("- state {label}")),
+            (None, Some(_)) => differ.line(format!("+ state {label}")),
+            (None, None) => {}
+        }
+    }
+    differ.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Side;
+    use crate::{decompile::Names, Session};
+
+    const OLD: &str = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+abort_safe = true
+
+[[states.commands]]
+pyro1 = true
+
+[states.timeout]
+seconds = 10.0
+transition = "Armed"
+"#;
+
+    #[test]
+    fn structural_diff() {
+        let new = OLD
+            .replace("greater_than = 30.0", "greater_than = 40.0")
+            .replace("pyro1 = true", "pyro2 = true")
+            .replace("seconds = 10.0", "seconds = 20.0")
+            + "\n[[states]]\nname = \"Safe\"\nabort_safe = true\n";
+        let mut session = Session::new();
+        let (old_upper, old, _) =
+            crate::load_config(&mut session, OLD.to_owned(), "old.toml".to_owned()).unwrap();
+        let (new_upper, new, _) =
+            crate::load_config(&mut session, new, "new.toml".to_owned()).unwrap();
+        let old_names = Names::from_source(&old_upper, &old).unwrap();
+        let new_names = Names::from_source(&new_upper, &new).unwrap();
+        let side = |config, names| Side { config, names };
+
+        assert_eq!(
+            super::diff(side(&old, &old_names), side(&old, &old_names), true),
+            ""
+        );
+        assert_eq!(
+            super::diff(side(&old, &old_names), side(&new, &new_names), true),
+            "~ Armed check Launch: altitude > 30 => altitude > 40\n\
+             - Flight command pyro1 = true\n\
+             + Flight command pyro2 = true\n\
+             ~ Flight timeout: after 10 s -> Armed => after 20 s -> Armed\n\
+             + state Safe\n"
+        );
+
+        // A compiled config has no names, so states are matched by position
+        let generic = Names::generic(&old);
+        let diff = super::diff(side(&old, &generic), side(&new, &new_names), false);
+        assert!(diff.starts_with("~ Armed check Launch: altitude > 30 => altitude > 40\n"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Generates Markdown documentation of the config language from the [`crate::registry`], the
+//! [`crate::diagnostics`], and the limits of a target, so that handbooks can include exactly what
+//! the verifier accepts.
+
+use std::fmt::Write;
+
+use crate::{diagnostics, limits::Target, registry, upper::InputFormat};
+
+/// Renders the documentation of the config language for configs built for `target`
+pub fn language_docs(target: Target) -> String {
+    // Writing to a `String` cannot fail
+    let mut out = String::new();
+    let _ = writeln!(out, "# Config language reference\n");
+    let _ = writeln!(
+        out,
+        "Generated by nova-verifier {} for the `{target}` target.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let _ = writeln!(out, "## Input formats\n");
+    let _ = writeln!(
+        out,
+        "Configs can be written in any of these formats, which all accept the same sections. The format is chosen by the file's extension, or with `--input-format`, and files with unknown extensions are read as toml.\n"
+    );
+    let _ = writeln!(out, "| Format | Extensions |");
+    let _ = writeln!(out, "|--------|------------|");
+    for format in InputFormat::ALL {
+        let extensions: Vec<_> = format
+            .extensions()
+            .iter()
+            .map(|e| format!("`.{e}`"))
+            .collect();
+        let available = match format {
+            InputFormat::Yaml if !cfg!(feature = "yaml") => ", not supported by this build",
+            _ => "",
+        };
+        let _ = writeln!(out, "| `{format}` | {}{available} |", extensions.join(", "));
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Sections\n");
+    for section in registry::SECTIONS {
+        let _ = writeln!(out, "### `{}`\n", section.name);
+        let _ = writeln!(out, "{}.\n", section.description);
+        let _ = writeln!(out, "| Key | Description |");
+        let _ = writeln!(out, "|-----|-------------|");
+        for (key, description) in section.keys {
+            let _ = writeln!(out, "| `{key}` | {description} |");
+        }
+        let _ = writeln!(out);
+    }
+
+    let _ = writeln!(out, "## Check kinds\n");
+    let _ = writeln!(out, "| Kind | Conditions | Description |");
+    let _ = writeln!(out, "|------|------------|-------------|");
+    for kind in registry::CHECK_KINDS {
+        let conditions = if kind.conditions.is_empty() {
+            "none".to_owned()
+        } else {
+            kind.conditions
+                .iter()
+                .map(|c| format!("`{c}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+                .replace(" and ", "` and `")
+        };
+        let _ = writeln!(
+            out,
+            "| `{}` | {conditions} | {} |",
+            kind.name, kind.description
+        );
+    }
+    let _ = writeln!(out);
+
+    if !registry::DEPRECATIONS.is_empty() {
+        let _ = writeln!(out, "## Deprecated check kinds\n");
+        let _ = writeln!(
+            out,
+            "These are still accepted with a warning, until the schema version is bumped past the one they were deprecated in. The current schema version is {}.\n",
+            registry::SCHEMA_VERSION
+        );
+        let _ = writeln!(out, "| Check kind | Replacement | Deprecated in |");
+        let _ = writeln!(out, "|------------|-------------|---------------|");
+        for deprecation in registry::DEPRECATIONS {
+            let _ = writeln!(
+                out,
+                "| `{}` | `{}` | {} |",
+                deprecation.name, deprecation.replacement, deprecation.since
+            );
+        }
+        let _ = writeln!(out);
+    }
+
+    let _ = writeln!(out, "## Sensors\n");
+    let _ = writeln!(out, "| Sensor | Measures | Read by | Description |");
+    let _ = writeln!(out, "|--------|----------|---------|-------------|");
+    for sensor in registry::SENSORS {
+        let checks: Vec<_> = sensor.checks.iter().map(|c| format!("`{c}`")).collect();
+        let _ = writeln!(
+            out,
+            "| `{}` | {} | {} | {} |",
+            sensor.name,
+            sensor.measures,
+            checks.join(", "),
+            sensor.description
+        );
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Command actions\n");
+    let _ = writeln!(out, "Each command performs exactly one of these actions.\n");
+    let _ = writeln!(out, "| Action | Value | Description |");
+    let _ = writeln!(out, "|--------|-------|-------------|");
+    for action in registry::COMMAND_ACTIONS {
+        let _ = writeln!(
+            out,
+            "| `{}` | {} | {} |",
+            action.name, action.value, action.description
+        );
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Abort reasons\n");
+    let _ = writeln!(out, "| Reason | Code | Description |");
+    let _ = writeln!(out, "|--------|------|-------------|");
+    for reason in registry::ABORT_REASONS {
+        let _ = writeln!(
+            out,
+            "| `{}` | {} | {} |",
+            reason.name, reason.code, reason.description
+        );
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Diagnostics\n");
+    for diagnostic in diagnostics::all() {
+        let _ = writeln!(out, "### `{}`\n", diagnostic.code);
+        let _ = writeln!(
+            out,
+            "**{}**: {}\n",
+            diagnostics::level_name(diagnostic.level),
+            diagnostic.message
+        );
+        let _ = writeln!(out, "{}.\n", diagnostic.description);
+    }
+
+    let limits = target.limits();
+    let _ = writeln!(
+        out,
+        "## Limits of the `{target}` target on the `{}` board\n",
+        target.board()
+    );
+    let _ = writeln!(out, "| Limit | Value |");
+    let _ = writeln!(out, "|-------|-------|");
+    let _ = writeln!(out, "| States | {} |", limits.max_states);
+    let _ = writeln!(
+        out,
+        "| Checks per state | {} |",
+        limits.max_checks_per_state
+    );
+    let _ = writeln!(
+        out,
+        "| Commands per state | {} |",
+        limits.max_commands_per_state
+    );
+    let _ = writeln!(out, "| Pyro channels | {} |", limits.pyro_channels);
+    let _ = writeln!(out, "| `data_rate` | {} |", limits.data_rates);
+    let _ = writeln!(
+        out,
+        "| Radio link | {} bytes per second |",
+        limits.link_budget
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::language_docs;
+    use crate::{diagnostics, limits::Target, registry};
+
+    #[test]
+    fn documents_everything() {
+        let docs = language_docs(Target::Nova);
+        let names = registry::CHECK_KINDS
+            .iter()
+            .map(|k| k.name)
+            .chain(registry::COMMAND_ACTIONS.iter().map(|a| a.name))
+            .chain(crate::upper::InputFormat::ALL.iter().map(|f| f.name()));
+        for name in names {
+            assert!(docs.contains(&format!("| `{name}` |")), "{name}");
+        }
+        for diagnostic in diagnostics::all() {
+            let heading = format!("### `{}`", diagnostic.code);
+            assert!(docs.contains(&heading), "{}", diagnostic.code);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Worst case timing analysis.
+//!
+//! A state is only left when one of its checks trips or its timeout expires. If a state after
+//! launch has no timeout, the flight computer can wait in it forever for a condition that never
+//! comes, such as an apogee that is never detected. This module works out how long each state can
+//! last, and how long the flight can take from launch until it settles in a final state.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{lint, upper, Context, Span};
+
+/// How long the flight computer can stay in a state
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StateBound {
+    /// The state's timeout expires after this many seconds, if no check trips first
+    Timeout(f32),
+
+    /// The state has checks but no timeout, so it lasts until a check trips
+    Unbounded,
+
+    /// The state never transitions anywhere. This is where a flight ends
+    Final,
+}
+
+/// Returns how long the flight computer can stay in `state`
+pub fn state_bound(config: &upper::ConfigFile, state: &upper::State) -> StateBound {
+    let timeout = state
+        .timeout
+        .as_ref()
+        .and_then(|t| t.get_ref().seconds.as_ref());
+    match timeout {
+        Some(seconds) => StateBound::Timeout(*seconds.get_ref()),
+        None if successors(config, state).is_empty() => StateBound::Final,
+        None => StateBound::Unbounded,
+    }
+}
+
+/// Every state that `state` can transition to
+pub(crate) fn successors<'a>(
+    config: &'a upper::ConfigFile,
+    state: &'a upper::State,
+) -> Vec<&'a str> {
+    let mut next: Vec<&str> = state
+        .checks
+        .iter()
+        .flat_map(|c| lint::check_targets(config, c.get_ref()))
+        .collect();
+    let timeout = state.timeout.as_ref().map(|t| t.get_ref());
+    next.extend(
+        timeout
+            .and_then(|t| t.transition.as_ref())
+            .map(|t| t.get_ref().as_str()),
+    );
+    next
+}
+
+/// Every state reached by following transitions from the states named in `start`, which are
+/// included. States for which `stop` returns true are included, but not followed
+pub(crate) fn reachable<'a>(
+    config: &'a upper::ConfigFile,
+    states: &HashMap<&'a str, &'a upper::State>,
+    start: impl IntoIterator<Item = &'a str>,
+    stop: impl Fn(&upper::State) -> bool,
+) -> HashSet<&'a str> {
+    let mut queue: VecDeque<&str> = start.into_iter().collect();
+    let mut reached = HashSet::new();
+    while let Some(name) = queue.pop_front() {
+        // Lowering already checked that every target exists
+        if let Some(state) = states.get(name) {
+            if reached.insert(name) && !stop(state) {
+                queue.extend(successors(config, state));
+            }
+        }
+    }
+    reached
+}
+
+/// The longest the flight can take from launch until it reaches a final state
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlightDuration<'a> {
+    /// The flight always ends within this many seconds, the worst case taking `path`
+    Bounded { seconds: f32, path: Vec<&'a str> },
+
+    /// The flight can go on forever by following `path`. The last state in the path either has no
+    /// timeout, or closes a loop
+    Unbounded { path: Vec<&'a str> },
+}
+
+struct Search<'a> {
+    config: &'a upper::ConfigFile,
+    states: HashMap<&'a str, &'a upper::State>,
+    done: HashMap<&'a str, FlightDuration<'a>>,
+    on_path: HashSet<&'a str>,
+}
+
+impl<'a> Search<'a> {
+    fn longest_from(&mut self, name: &'a str) -> FlightDuration<'a> {
+        if let Some(done) = self.done.get(name) {
+            return done.clone();
+        }
+        if !self.on_path.insert(name) {
+            return FlightDuration::Unbounded { path: vec![name] };
+        }
+        let state = self.states[name];
+        let prepend = |mut path: Vec<&'a str>| {
+            path.insert(0, name);
+            path
+        };
+        let result = match state_bound(self.config, state) {
+            StateBound::Final => FlightDuration::Bounded {
+                seconds: 0.0,
+                path: vec![name],
+            },
+            StateBound::Unbounded => FlightDuration::Unbounded { path: vec![name] },
+            StateBound::Timeout(seconds) => {
+                let mut worst = FlightDuration::Bounded {
+                    seconds,
+                    path: vec![name],
+                };
+                for next in successors(self.config, state) {
+                    if !self.states.contains_key(next) {
+                        continue;
+                    }
+                    match self.longest_from(next) {
+                        FlightDuration::Unbounded { path } => {
+                            worst = FlightDuration::Unbounded {
+                                path: prepend(path),
+                            };
+                            break;
+                        }
+                        FlightDuration::Bounded {
+                            seconds: rest,
+                            path,
+                        } => match &worst {
+                            FlightDuration::Bounded {
+                                seconds: longest, ..
+                            } if seconds + rest >= *longest => {
+                                worst = FlightDuration::Bounded {
+                                    seconds: seconds + rest,
+                                    path: prepend(path),
+                                }
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+                worst
+            }
+        };
+        self.on_path.remove(name);
+        // A loop found below this state passes back through it, so the result does not depend on
+        // how this state was reached
+        self.done.insert(name, result.clone());
+        result
+    }
+}
+
+/// Finds the longest the flight can take from launch detection until it reaches a final state.
+/// Returns `None` if the config never detects a launch
+pub fn flight_duration(config: &upper::ConfigFile) -> Option<FlightDuration<'_>> {
+    let pre_launch = lint::pre_launch_states(config);
+    let states: HashMap<&str, &upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| (s.get_ref().name.get_ref().as_str(), s.get_ref()))
+        .collect();
+
+    // The states entered when launch is detected
+    let launched: Vec<&str> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .filter(|s| pre_launch.contains_key(s.name.get_ref().as_str()))
+        .flat_map(|s| s.checks.iter().map(|c| c.get_ref()))
+        .filter(|c| lint::is_launch_detection(c))
+        .filter_map(|c| c.transition.as_ref().map(|t| t.get_ref().as_str()))
+        .filter(|name| states.contains_key(name))
+        .collect();
+
+    let mut search = Search {
+        config,
+        states,
+        done: HashMap::new(),
+        on_path: HashSet::new(),
+    };
+    let mut worst: Option<FlightDuration<'_>> = None;
+    for name in launched {
+        let duration = search.longest_from(name);
+        worst = match (worst, duration) {
+            (Some(unbounded @ FlightDuration::Unbounded { .. }), _) => Some(unbounded),
+            (
+                Some(FlightDuration::Bounded { seconds: a, path }),
+                FlightDuration::Bounded { seconds: b, .. },
+            ) if a >= b => Some(FlightDuration::Bounded { seconds: a, path }),
+            (_, duration) => Some(duration),
+        };
+    }
+    worst
+}
+
+/// Emits a note stating how long each state that can be entered after launch can last
+pub fn emit_notes(config: &upper::ConfigFile, context: &mut Context) {
+    let pre_launch = lint::pre_launch_states(config);
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref();
+        if pre_launch.contains_key(name.as_str()) {
+            continue;
+        }
+        let span = Span::from_spanned(context, &state.name);
+        let (message, label) = match state_bound(config, state) {
+            StateBound::Timeout(seconds) => (
+                format!("state `{name}` lasts at most {seconds} seconds"),
+                "bounded by its timeout".to_owned(),
+            ),
+            StateBound::Unbounded => (
+                format!("state `{name}` has no timeout"),
+                "lasts until one of its checks trips".to_owned(),
+            ),
+            StateBound::Final => continue,
+        };
+        context.note(message).set_primary_span(span, label).emit();
+    }
+
+    match flight_duration(config) {
+        Some(FlightDuration::Bounded { seconds, path }) => context
+            .note(format!(
+                "the flight reaches a final state at most {seconds} seconds after launch, through {}",
+                path.join(" -> ")
+            ))
+            .emit(),
+        Some(FlightDuration::Unbounded { path }) => context
+            .note(format!(
+                "the flight has no time limit after launch, through {}",
+                path.join(" -> ")
+            ))
+            .emit(),
+        None => {}
+    }
+}
+
+/// Warns if the flight can take longer than the session's mission duration to reach a final state
+pub(crate) fn check_mission_duration(config: &upper::ConfigFile, context: &mut Context) {
+    let limit = match context.session().mission_duration() {
+        Some(limit) => limit,
+        None => return,
+    };
+    let (message, path) = match flight_duration(config) {
+        Some(FlightDuration::Bounded { seconds, path }) if seconds > limit => (
+            format!(
+                "the flight can take {seconds} seconds after launch, longer than the mission duration of {limit} seconds"
+            ),
+            path,
+        ),
+        Some(FlightDuration::Unbounded { path }) => (
+            format!(
+                "the flight can take forever after launch, longer than the mission duration of {limit} seconds"
+            ),
+            path,
+        ),
+        _ => return,
+    };
+
+    let last = *path.last().unwrap();
+    let span = config
+        .states
+        .get_ref()
+        .iter()
+        .find(|s| s.get_ref().name.get_ref() == last)
+        .map(|s| Span::from_spanned(context, &s.get_ref().name));
+    let mut warning = context.warn(message).lint("mission_duration");
+    if let Some(span) = span {
+        warning = warning.set_primary_span(span, "add a timeout to this state");
+    }
+    warning.emit();
+    context
+        .note(format!("the longest path is {}", path.join(" -> ")))
+        .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flight_duration, FlightDuration};
+    use crate::{upper, Session};
+
+    const CONFIG: &str = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Drogue"
+
+[states.timeout]
+seconds = 40.0
+transition = "Drogue"
+
+[[states]]
+name = "Drogue"
+
+[states.timeout]
+seconds = 100.0
+transition = "Recovery"
+
+[[states]]
+name = "Recovery"
+"#;
+
+    fn duration(toml: &str) -> FlightDuration<'static> {
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let config = upper::verify(&mut context).unwrap();
+        let config: &'static upper::ConfigFile = Box::leak(Box::new(config));
+        flight_duration(config).unwrap()
+    }
+
+    #[test]
+    fn bounded_flight() {
+        assert_eq!(
+            duration(CONFIG),
+            FlightDuration::Bounded {
+                seconds: 140.0,
+                path: vec!["Flight", "Drogue", "Recovery"]
+            }
+        );
+    }
+
+    #[test]
+    fn unbounded_flight() {
+        // Without a timeout, the flight waits for apogee forever
+        let no_timeout = CONFIG.replacen(
+            "[states.timeout]\nseconds = 40.0\ntransition = \"Drogue\"\n",
+            "",
+            1,
+        );
+        assert_eq!(
+            duration(&no_timeout),
+            FlightDuration::Unbounded {
+                path: vec!["Flight"]
+            }
+        );
+
+        // Timeouts that loop never reach a final state
+        let looping = CONFIG.replace("transition = \"Recovery\"", "transition = \"Flight\"");
+        assert!(matches!(
+            duration(&looping),
+            FlightDuration::Unbounded { .. }
+        ));
+    }
+}
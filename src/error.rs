@@ -1,12 +1,18 @@
-use std::sync::Arc;
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
 
 use codemap::CodeMap;
 use codemap_diagnostic::{ColorConfig, Diagnostic, Emitter, Level, SpanLabel, SpanStyle};
 use log::*;
+use thiserror::Error;
+
+use crate::lint::LintLevel;
+use crate::spanned::SpannedValue;
 
 #[must_use]
 pub struct DiagnosticBuilder<'s, 'c> {
     diagnostic: Diagnostic,
+    suggestions: Vec<Suggestion>,
     context: &'c mut Context<'s>,
     cancelled: bool,
 }
@@ -28,6 +34,7 @@ impl<'s, 'c> DiagnosticBuilder<'s, 'c> {
 
         Self {
             diagnostic,
+            suggestions: Vec::new(),
             cancelled: false,
             context,
         }
@@ -60,6 +67,19 @@ impl<'s, 'c> DiagnosticBuilder<'s, 'c> {
         self
     }
 
+    /// Marks this diagnostic as coming from the lint `name` in [`crate::lint::LINTS`], which is
+    /// shown as its code and lets [`crate::overrides`] find it
+    pub fn lint(mut self, name: &'static str) -> Self {
+        self.diagnostic.code = Some(name.to_owned());
+        self
+    }
+
+    /// Marks this diagnostic as an error of the kind `code`, which is shown as its code
+    pub fn code(mut self, code: ErrorCode) -> Self {
+        self.diagnostic.code = Some(code.name().to_owned());
+        self
+    }
+
     /// Adds an addition label and span to this diagnostic
     pub fn span_label(mut self, span: impl Into<Span>, label: impl Into<String>) -> Self {
         let span = span.into();
@@ -83,6 +103,37 @@ impl<'s, 'c> DiagnosticBuilder<'s, 'c> {
         self
     }
 
+    /// Suggests replacing the source at `span` with `replacement` to fix this diagnostic. The
+    /// suggestion is emitted as a help with `message` right after this diagnostic, see
+    /// [`Suggestion`]
+    pub fn suggest_replacement(
+        self,
+        span: impl Into<Span>,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.suggest_edits(span, replacement, Vec::new(), message)
+    }
+
+    /// Like [`Self::suggest_replacement`], for a fix that also needs `edits` elsewhere, each
+    /// replacing the source at a span with a string
+    pub fn suggest_edits(
+        mut self,
+        span: impl Into<Span>,
+        replacement: impl Into<String>,
+        edits: Vec<(Span, String)>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span: span.into(),
+            replacement: replacement.into(),
+            message: message.into(),
+            edits,
+        });
+
+        self
+    }
+
     /*
     /// Adds a note message to the diagnostic
     pub fn note(&mut self, message: impl Into<String>) -> &mut Self {
@@ -117,8 +168,14 @@ impl<'s, 'c> DiagnosticBuilder<'s, 'c> {
     }
     */
 
-    /// Emits this diagnostic to the current session, consuming it
+    /// Emits this diagnostic to the current session, consuming it. Every error must have a code,
+    /// see [`Self::code`], so that it can be explained and overridden
     pub fn emit(mut self) {
+        let error = self.diagnostic.level == Level::Error;
+        if cfg!(debug_assertions) && error && self.diagnostic.code.is_none() {
+            self.cancel();
+            panic!("error `{}` has no code", self.diagnostic.message);
+        }
         let empty = Diagnostic {
             level: Level::Bug,
             message: String::new(),
@@ -127,6 +184,10 @@ impl<'s, 'c> DiagnosticBuilder<'s, 'c> {
         };
         let diagnostic = std::mem::replace(&mut self.diagnostic, empty);
         self.context.session.add_diagnostic(diagnostic);
+        for suggestion in std::mem::take(&mut self.suggestions) {
+            self.context.session.add_diagnostic(suggestion.diagnostic());
+            self.context.session.suggestions.push(suggestion);
+        }
         self.cancel();
     }
 
@@ -149,10 +210,69 @@ impl<'s, 'c> Drop for DiagnosticBuilder<'s, 'c> {
     }
 }
 
+/// Why verifying a config failed. The variant is the phase that failed, and holds the
+/// diagnostics emitted during that phase, which include at least one error.
+///
+/// Phases within the verifier still return `Result<_, ()>`, since their diagnostics are emitted to
+/// a [`Context`] as they are found. The public entry points such as [`crate::verify_inner`] turn
+/// the diagnostics of the phase that failed into this error
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// A file could not be read or written
+    #[error("failed to read or write a file")]
+    Io(Vec<Diagnostic>),
+
+    /// The config is not valid toml, or does not have the shape of a config
+    #[error("failed to parse the config")]
+    Parse(Vec<Diagnostic>),
+
+    /// The config parsed, but cannot be converted into what the flight computer runs, such as a
+    /// transition to a state that does not exist
+    #[error("the config is invalid")]
+    Lower(Vec<Diagnostic>),
+
+    /// A lint with the error level fired, see [`crate::lint::LINTS`]
+    #[error("the config failed a lint")]
+    Lint(Vec<Diagnostic>),
+
+    /// The config could not be encoded, or does not fit in the flash budget
+    #[error("failed to encode the config")]
+    Encode(Vec<Diagnostic>),
+
+    /// An intermediate artifact requested with [`Session::add_emit`] could not be produced
+    #[error("failed to emit an artifact")]
+    Artifact(Vec<Diagnostic>),
+}
+
+impl VerifyError {
+    /// The diagnostics emitted during the phase that failed
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            VerifyError::Io(d)
+            | VerifyError::Parse(d)
+            | VerifyError::Lower(d)
+            | VerifyError::Lint(d)
+            | VerifyError::Encode(d)
+            | VerifyError::Artifact(d) => d,
+        }
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            VerifyError::Io(d)
+            | VerifyError::Parse(d)
+            | VerifyError::Lower(d)
+            | VerifyError::Lint(d)
+            | VerifyError::Encode(d)
+            | VerifyError::Artifact(d) => d,
+        }
+    }
+}
+
 /// The top level helper struct for opening and verifing toml files.
 ///
-/// First, open a file with [`Self::open_file`], any errors generated when opening the file will be
-/// saved into this session, and will be available when calling [`Self::end_phase`].
+/// First, open a file with [`Self::open_file`], which returns [`VerifyError::Io`] if the file
+/// cannot be read.
 ///
 /// Once a file is open, phase processing can begin.
 /// Verification/compilation works as usual, with the calling code doing as much work as possible
@@ -164,6 +284,32 @@ impl<'s, 'c> Drop for DiagnosticBuilder<'s, 'c> {
 pub struct Session {
     map: codemap::CodeMap,
     diagnostics: Vec<Diagnostic>,
+    flash_budget: Option<usize>,
+    emit_stats: bool,
+    float_precision_notes: bool,
+    strict_floats: bool,
+    strict_keys: bool,
+    require_default_state: bool,
+    warnings_as_errors: bool,
+    lint_levels: Vec<(&'static str, LintLevel)>,
+    config_lint_levels: Vec<(&'static str, LintLevel)>,
+    ground_test: bool,
+    emit: Vec<(crate::EmitKind, String)>,
+    target: crate::limits::Target,
+    board: crate::hardware::Profile,
+    patch_base: Option<String>,
+    input_format: Option<crate::upper::InputFormat>,
+    mission_duration: Option<f32>,
+    override_file: Option<String>,
+    includes: Vec<String>,
+    summary: Option<crate::summary::Summary>,
+    offline: bool,
+    approval_policy: crate::approval::Policy,
+    error_format: ErrorFormat,
+    complexity_limits: Vec<crate::complexity::Ceiling>,
+    printed: Cell<Tally>,
+    suggestions: Vec<Suggestion>,
+    quiet: bool,
 }
 
 impl Session {
@@ -171,33 +317,349 @@ impl Session {
         Self {
             map: codemap::CodeMap::new(),
             diagnostics: Vec::new(),
+            flash_budget: None,
+            emit_stats: false,
+            float_precision_notes: false,
+            strict_floats: false,
+            strict_keys: false,
+            require_default_state: false,
+            warnings_as_errors: false,
+            lint_levels: Vec::new(),
+            config_lint_levels: Vec::new(),
+            ground_test: false,
+            emit: Vec::new(),
+            target: crate::limits::Target::default(),
+            board: crate::hardware::Profile::default(),
+            patch_base: None,
+            input_format: None,
+            mission_duration: None,
+            override_file: None,
+            includes: Vec::new(),
+            summary: None,
+            offline: false,
+            approval_policy: crate::approval::Policy::default(),
+            error_format: ErrorFormat::default(),
+            complexity_limits: Vec::new(),
+            printed: Cell::new(Tally::default()),
+            suggestions: Vec::new(),
+            quiet: false,
         }
     }
 
-    pub fn open_file(&mut self, file_path: String) -> Result<Context<'_>, ()> {
-        let data = match std::fs::read_to_string(&file_path) {
-            Ok(t) => t,
-            Err(e) => {
-                self.diagnostics.push(Diagnostic {
-                    level: Level::Error,
-                    message: format!("Failed to open file `{file_path}`: {e}"),
-                    code: None,
-                    spans: Vec::new(),
-                });
-                return Err(());
-            }
-        };
-        self.add_file(data, file_path)
+    /// Sets the maximum number of bytes the encoded config may occupy.
+    /// Configs that encode to more bytes than this fail verification
+    pub fn set_flash_budget(&mut self, budget: Option<usize>) {
+        self.flash_budget = budget;
+    }
+
+    pub fn flash_budget(&self) -> Option<usize> {
+        self.flash_budget
+    }
+
+    /// If set, a note is emitted for every state, check, and command stating how many bytes it
+    /// takes up in the encoded config
+    pub fn set_emit_stats(&mut self, emit_stats: bool) {
+        self.emit_stats = emit_stats;
+    }
+
+    pub fn emit_stats(&self) -> bool {
+        self.emit_stats
+    }
+
+    /// If set, a note is emitted for every float literal that cannot be stored exactly as an
+    /// `f32`, showing the value that will actually be used
+    pub fn set_float_precision_notes(&mut self, enabled: bool) {
+        self.float_precision_notes = enabled;
+    }
+
+    /// Sets the ceilings that the config's complexity metrics are checked against, see
+    /// [`crate::complexity`]
+    pub fn set_complexity_limits(&mut self, limits: Vec<crate::complexity::Ceiling>) {
+        self.complexity_limits = limits;
+    }
+
+    pub fn complexity_limits(&self) -> &[crate::complexity::Ceiling] {
+        &self.complexity_limits
+    }
+
+    /// Sets how diagnostics are printed when a phase ends
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.error_format = format;
+    }
+
+    pub fn error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    /// Stops diagnostics from being printed when a phase ends, for tools that embed the verifier
+    /// and show the diagnostics it returns themselves
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Resolves the spans of `diagnostic`, which was emitted in this session, to lines and columns
+    pub fn locate(&self, diagnostic: &Diagnostic) -> LocatedDiagnostic {
+        let suggestion = self
+            .suggestions
+            .iter()
+            .find(|s| s.diagnostic() == *diagnostic);
+        LocatedDiagnostic::new(diagnostic, &self.map, suggestion)
+    }
+
+    /// Returns how many errors and warnings this session has printed
+    pub fn printed(&self) -> Tally {
+        self.printed.get()
+    }
+
+    /// Returns every suggestion emitted in this session, in the order they were emitted
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    pub fn float_precision_notes(&self) -> bool {
+        self.float_precision_notes
+    }
+
+    /// If set, a warning is emitted for every decimal value written as an integer, such as
+    /// `delay = 1` rather than `delay = 1.0`
+    pub fn set_strict_floats(&mut self, enabled: bool) {
+        self.strict_floats = enabled;
+    }
+
+    pub fn strict_floats(&self) -> bool {
+        self.strict_floats
+    }
+
+    /// If set, every key the config language does not know is an error, such as `delay_secs = 3`
+    /// in a command. Otherwise they are ignored, see [`crate::strict_keys`]
+    pub fn set_strict_keys(&mut self, enabled: bool) {
+        self.strict_keys = enabled;
+    }
+
+    pub fn strict_keys(&self) -> bool {
+        self.strict_keys
+    }
+
+    /// If set, a config without a `default_state` is an error. Otherwise the first state is the
+    /// default, and a note says so
+    pub fn set_require_default_state(&mut self, enabled: bool) {
+        self.require_default_state = enabled;
     }
 
-    pub fn add_file(&mut self, data: String, file_path: String) -> Result<Context<'_>, ()> {
+    pub fn require_default_state(&self) -> bool {
+        self.require_default_state
+    }
+
+    /// If set, every warning is an error, so a config that produces any warning fails to verify.
+    /// Errors from overridden lints are warnings, so they fail too
+    pub fn set_warnings_as_errors(&mut self, enabled: bool) {
+        self.warnings_as_errors = enabled;
+    }
+
+    pub fn warnings_as_errors(&self) -> bool {
+        self.warnings_as_errors
+    }
+
+    /// Sets the level of `lint` from the command line, such as `--deny infinite_loop`. A later
+    /// level for the same lint replaces an earlier one, and every level set here takes precedence
+    /// over the config's `[lints]` table
+    pub fn set_lint_level(&mut self, lint: &crate::lint::Lint, level: LintLevel) {
+        self.lint_levels.retain(|(name, _)| *name != lint.name);
+        self.lint_levels.push((lint.name, level));
+    }
+
+    pub fn lint_levels(&self) -> &[(&'static str, LintLevel)] {
+        &self.lint_levels
+    }
+
+    /// Sets the levels from the `[lints]` table of the config being verified, replacing those of
+    /// any config verified before it, see [`crate::lint::configure`]
+    pub(crate) fn set_config_lint_levels(&mut self, levels: Vec<(&'static str, LintLevel)>) {
+        self.config_lint_levels = levels;
+    }
+
+    /// The level that the command line or the config sets for the lint called `name`, if any
+    pub fn lint_level(&self, name: &str) -> Option<LintLevel> {
+        self.lint_levels
+            .iter()
+            .chain(&self.config_lint_levels)
+            .find(|(lint, _)| *lint == name)
+            .map(|(_, level)| *level)
+    }
+
+    /// The level set for the lint that emitted `diagnostic`, if it is a warning from a lint
+    fn warning_level(&self, diagnostic: &Diagnostic) -> Option<LintLevel> {
+        match (diagnostic.level, &diagnostic.code) {
+            (Level::Warning, Some(code)) => self.lint_level(code),
+            _ => None,
+        }
+    }
+
+    /// Returns true if `diagnostic` is a warning that is reported as an error
+    fn is_denied(&self, diagnostic: &Diagnostic) -> bool {
+        let lint_denied = self.warning_level(diagnostic) == Some(LintLevel::Deny);
+        diagnostic.level == Level::Warning && (self.warnings_as_errors || lint_denied)
+    }
+
+    /// If set, the config is built for a lab dry run, with pyro commands replaced by indications,
+    /// see [`crate::ground_test`]
+    pub fn set_ground_test(&mut self, enabled: bool) {
+        self.ground_test = enabled;
+    }
+
+    pub fn ground_test(&self) -> bool {
+        self.ground_test
+    }
+
+    /// Sets the flight computer that the config is being built for
+    pub fn set_target(&mut self, target: crate::limits::Target) {
+        self.target = target;
+    }
+
+    pub fn target(&self) -> crate::limits::Target {
+        self.target
+    }
+
+    /// Sets the board that the config is verified against. Defaults to the target's board
+    pub fn set_board(&mut self, board: crate::hardware::Profile) {
+        self.board = board;
+    }
+
+    pub fn board(&self) -> &crate::hardware::Profile {
+        &self.board
+    }
+
+    /// Returns the limits enforced for the current board
+    pub fn limits(&self) -> crate::limits::Limits {
+        self.board.limits.clone()
+    }
+
+    /// Sets the path of the previously uploaded config, which [`crate::EmitKind::Patch`] diffs
+    /// against
+    pub fn set_patch_base(&mut self, path: Option<String>) {
+        self.patch_base = path;
+    }
+
+    pub fn patch_base(&self) -> Option<&str> {
+        self.patch_base.as_deref()
+    }
+
+    /// Sets the longest time in seconds that the flight may take from launch until it reaches a
+    /// final state. Configs that can take longer, or forever, are warned about
+    pub fn set_mission_duration(&mut self, seconds: Option<f32>) {
+        self.mission_duration = seconds;
+    }
+
+    pub fn mission_duration(&self) -> Option<f32> {
+        self.mission_duration
+    }
+
+    /// Sets the syntax that input files are parsed as.
+    /// If unset, the format is chosen from each file's extension
+    pub fn set_input_format(&mut self, format: Option<crate::upper::InputFormat>) {
+        self.input_format = format;
+    }
+
+    pub fn input_format(&self) -> Option<crate::upper::InputFormat> {
+        self.input_format
+    }
+
+    /// Sets a file that is merged over every config this session verifies, so that values can be
+    /// changed locally without editing the reviewed config. See [`crate::overlay`]
+    pub fn set_override_file(&mut self, path: Option<String>) {
+        self.override_file = path;
+    }
+
+    pub fn override_file(&self) -> Option<&str> {
+        self.override_file.as_deref()
+    }
+
+    /// Adds a file whose states and sections are added to every config this session verifies, so
+    /// that a config can be split across several files. See [`crate::include`]
+    pub fn add_include(&mut self, path: String) {
+        self.includes.push(path);
+    }
+
+    pub fn includes(&self) -> &[String] {
+        &self.includes
+    }
+
+    /// If set, nothing run with this session may talk to a flight computer or the network, see
+    /// [`crate::offline`]
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Returns an error if the session is offline. Call this before talking to a flight computer
+    /// or the network, where `action` describes what is about to happen
+    pub fn require_online(&self, action: &str) -> Result<(), crate::offline::OfflineError> {
+        if self.offline {
+            return Err(crate::offline::OfflineError {
+                action: action.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Sets who must approve a config before it is uploaded, see [`crate::approval`]
+    pub fn set_approval_policy(&mut self, policy: crate::approval::Policy) {
+        self.approval_policy = policy;
+    }
+
+    pub fn approval_policy(&self) -> &crate::approval::Policy {
+        &self.approval_policy
+    }
+
+    /// Returns a summary of the last config that was encoded successfully in this session
+    pub fn summary(&self) -> Option<&crate::summary::Summary> {
+        self.summary.as_ref()
+    }
+
+    pub(crate) fn set_summary(&mut self, summary: crate::summary::Summary) {
+        self.summary = Some(summary);
+    }
+
+    /// Requests that the artifact `kind` is written to `path` during verification.
+    /// Requesting the same kind twice replaces the previous path
+    pub fn add_emit(&mut self, kind: crate::EmitKind, path: String) {
+        self.emit.retain(|(k, _)| *k != kind);
+        self.emit.push((kind, path));
+    }
+
+    /// Returns the path that the artifact `kind` should be written to, if it was requested
+    pub fn emit_path(&self, kind: crate::EmitKind) -> Option<&str> {
+        self.emit
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, path)| path.as_str())
+    }
+
+    /// Reads the file at `file_path` and adds it to the session, see [`Self::add_file`]
+    pub fn open_file(&mut self, file_path: String) -> Result<Context<'_>, VerifyError> {
+        match std::fs::read_to_string(&file_path) {
+            Ok(data) => Ok(self.add_file(data, file_path)),
+            Err(e) => Err(VerifyError::Io(vec![Diagnostic {
+                level: Level::Error,
+                message: format!("Failed to open file `{file_path}`: {e}"),
+                code: Some(ErrorCode::ReadFailed.name().to_owned()),
+                spans: Vec::new(),
+            }])),
+        }
+    }
+
+    /// Adds a file with the contents `data` to the session, returning the context that phases
+    /// verifying it emit their diagnostics to
+    pub fn add_file(&mut self, data: String, file_path: String) -> Context<'_> {
         let file = self.map.add_file(file_path, data);
-        let context = Context {
+        Context {
             session: self,
             file,
-        };
-
-        Ok(context)
+            layers: Vec::new(),
+        }
     }
 
     #[cfg(test)]
@@ -206,11 +668,21 @@ impl Session {
         let context = Context {
             session: self,
             file,
+            layers: Vec::new(),
         };
 
         context
     }
 
+    /// Prints diagnostics that were not emitted through a [`Context`], such as ones built by the
+    /// caller, in the session's error format
+    pub fn print(&self, diagnostics: &[Diagnostic]) {
+        if !diagnostics.is_empty() {
+            let (format, printed) = (self.error_format, &self.printed);
+            print_diagnostics(diagnostics, &self.map, format, printed, &self.suggestions);
+        }
+    }
+
     /// Adds a diagnostic to this session.
     /// Most users should perfer the high level interface via [`DiagnosticBuilder`]
     pub fn add_diagnostic(&mut self, diagnostic: impl Into<Diagnostic>) {
@@ -218,33 +690,1177 @@ impl Session {
     }
 }
 
+/// A stable code for a kind of error, shown in brackets after the level, such as
+/// `error[E0001]`. Codes are never reused, so they can be searched for and explained with
+/// `nova-verifier explain E0001` long after the message of the error changes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// `E0001`: state not found `{name}`
+    UnknownState,
+
+    /// `E0002`: too many command actions
+    TooManyCommandActions,
+
+    /// `E0003`: mismatched check type
+    MismatchedCheckType,
+
+    /// `E0004`: command action missing
+    MissingCommandAction,
+
+    /// `E0005`: check condition missing
+    MissingCheckCondition,
+
+    /// `E0006`: too many check conditions
+    TooManyCheckConditions,
+
+    /// `E0007`: no check with name `{name}` exists
+    UnknownCheck,
+
+    /// `E0008`: flag values must be `set` or `unset`
+    UnknownFlag,
+
+    /// `E0009`: abort and transition cannot be active in the same check
+    AbortAndTransition,
+
+    /// `E0010`: abort to the top level `abort_state` used without declaring one
+    MissingAbortState,
+
+    /// `E0011`: abort state `{state}` is not marked abort safe
+    AbortStateNotSafe,
+
+    /// `E0012`: states missing
+    NoStates,
+
+    /// `E0013`: too many states
+    TooManyStates,
+
+    /// `E0014`: too many checks in state `{state}`
+    TooManyChecks,
+
+    /// `E0015`: too many commands in state `{state}`
+    TooManyCommands,
+
+    /// `E0016`: invalid timeout `{value}`
+    InvalidTimeout,
+
+    /// `E0017`: unsupported data rate {rate}
+    UnsupportedDataRate,
+
+    /// `E0018`: failed to parse config file
+    InvalidConfig,
+
+    /// `E0019`: unknown key `{key}`
+    UnknownKey,
+
+    /// `E0020`: no `default_state` declared
+    MissingDefaultState,
+
+    /// `E0021`: state `{name}` is declared twice
+    DuplicateState,
+
+    /// `E0022`: {kind} name cannot be empty
+    EmptyName,
+
+    /// `E0023`: {kind} name `{name}` is too long
+    NameTooLong,
+
+    /// `E0024`: {kind} name `{name}` contains invalid characters
+    InvalidNameCharacters,
+
+    /// `E0025`: the timeout of `{state}` has no `{key}`
+    IncompleteTimeout,
+
+    /// `E0026`: stage `{name}` is declared twice
+    DuplicateStage,
+
+    /// `E0027`: stage `{name}` has no states
+    EmptyStage,
+
+    /// `E0028`: `separate` cannot be combined with `transition` or `abort`
+    SeparateAndTransition,
+
+    /// `E0029`: unknown stage `{name}`
+    UnknownStage,
+
+    /// `E0030`: `{key}` is set in two files
+    DeclaredInTwoFiles,
+
+    /// `E0031`: unknown abort reason `{name}`
+    UnknownAbortReason,
+
+    /// `E0032`: unresolved merge conflict
+    MergeConflict,
+
+    /// `E0033`: invalid value `{expression}` for `{key}`
+    InvalidExpression,
+
+    /// `E0034`: constant `{name}` is not a number
+    InvalidConstant,
+
+    /// `E0035`: `{key}` is {value} in `{first}` but {value} in `{second}`
+    CrosscheckMismatch,
+
+    /// `E0036`: unknown sensor `{name}`
+    UnknownSensor,
+
+    /// `E0037`: `{action}` fallback has no state to abort to
+    IncompleteFallback,
+
+    /// `E0038`: `{backup}` cannot back up `{sensor}`
+    InvalidBackup,
+
+    /// `E0039`: `{key}` is only used by `{action}` fallbacks
+    MisplacedFallbackKey,
+
+    /// `E0040`: unknown fallback action `{action}`
+    UnknownFallbackAction,
+
+    /// `E0041`: pyro channel {channel} does not exist on the `{board}` board
+    UnknownPyroChannel,
+
+    /// `E0042`: internal verifier error: {problem}
+    InternalError,
+
+    /// `E0043`: failed to write to file `{path}`: {error}
+    WriteFailed,
+
+    /// `E0044`: failed to encode the {artifact}: {error}
+    ArtifactEncodingFailed,
+
+    /// `E0045`: cannot emit the tick rate, the config does not declare one
+    MissingTickRate,
+
+    /// `E0046`: encoded config is {size} bytes, which exceeds the flash budget of {budget} bytes
+    FlashBudgetExceeded,
+
+    /// `E0047`: unknown lint `{name}`
+    UnknownLint,
+
+    /// `E0048`: invalid lint level `{level}`
+    InvalidLintLevel,
+
+    /// `E0049`: `{name}` is deny by default, so it cannot be lowered to `{level}`
+    LoweredDenyLint,
+
+    /// `E0050`: `{name}` is not a deny by default lint
+    InvalidOverride,
+
+    /// `E0051`: the override of `{name}` has no justification
+    MissingJustification,
+
+    /// `E0052`: `{name}` is overridden twice
+    DuplicateOverride,
+
+    /// `E0053`: emitting a patch requires the previous config to diff against
+    MissingPatchBase,
+
+    /// `E0054`: failed to read {kind} `{path}`: {error}
+    ReadFailed,
+
+    /// `E0055`: `{field}` must be above the pad
+    RecoveryAltitudeBelowPad,
+
+    /// `E0056`: `{check}` checks require `{field}` in the `[recovery]` section
+    MissingRecoveryAltitude,
+
+    /// `E0057`: `{kind}` checks do not take a condition
+    UnexpectedCheckCondition,
+
+    /// `E0058`: mission event time {time} is not after launch
+    EventBeforeLaunch,
+
+    /// `E0059`: mission events are not in time order
+    EventsOutOfOrder,
+
+    /// `E0060`: mission event commands cannot have a `delay`
+    DelayedEventCommand,
+
+    /// `E0061`: state `{name}` is listed twice in path policy `{policy}`
+    DuplicatePathPolicyState,
+
+    /// `E0062`: too many self test steps
+    TooManySelfTestSteps,
+
+    /// `E0063`: self test steps cannot use `{key}`
+    InvalidSelfTestStep,
+
+    /// `E0064`: `{kind}` cannot be used in the self test
+    InvalidSelfTestCheck,
+
+    /// `E0065`: unknown check `{name}`
+    UnknownCheckTemplate,
+
+    /// `E0066`: the tick rate must be at least 1
+    InvalidTickRate,
+
+    /// `E0067`: YAML configs are not supported by this build
+    YamlUnsupported,
+
+    /// `E0068`: unknown target `{target}`
+    UnknownTarget,
+
+    /// `E0069`: member name `{name}` cannot be used as a directory name
+    InvalidMemberName,
+
+    /// `E0070`: two members are named `{name}`
+    DuplicateMember,
+
+    /// `E0071`: no check named `{name}` exists
+    UnknownRequiredCheck,
+
+    /// `E0072`: check `{name}` requires itself
+    RequirementCycle,
+
+    /// `E0073`: check `{name}` cannot require another check on the `{target}` target
+    UnsupportedRequirement,
+
+    /// `E0074`: `{key}` is set without `{missing}`
+    UnmatchedBound,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 74] = [
+        ErrorCode::UnknownState,
+        ErrorCode::TooManyCommandActions,
+        ErrorCode::MismatchedCheckType,
+        ErrorCode::MissingCommandAction,
+        ErrorCode::MissingCheckCondition,
+        ErrorCode::TooManyCheckConditions,
+        ErrorCode::UnknownCheck,
+        ErrorCode::UnknownFlag,
+        ErrorCode::AbortAndTransition,
+        ErrorCode::MissingAbortState,
+        ErrorCode::AbortStateNotSafe,
+        ErrorCode::NoStates,
+        ErrorCode::TooManyStates,
+        ErrorCode::TooManyChecks,
+        ErrorCode::TooManyCommands,
+        ErrorCode::InvalidTimeout,
+        ErrorCode::UnsupportedDataRate,
+        ErrorCode::InvalidConfig,
+        ErrorCode::UnknownKey,
+        ErrorCode::MissingDefaultState,
+        ErrorCode::DuplicateState,
+        ErrorCode::EmptyName,
+        ErrorCode::NameTooLong,
+        ErrorCode::InvalidNameCharacters,
+        ErrorCode::IncompleteTimeout,
+        ErrorCode::DuplicateStage,
+        ErrorCode::EmptyStage,
+        ErrorCode::SeparateAndTransition,
+        ErrorCode::UnknownStage,
+        ErrorCode::DeclaredInTwoFiles,
+        ErrorCode::UnknownAbortReason,
+        ErrorCode::MergeConflict,
+        ErrorCode::InvalidExpression,
+        ErrorCode::InvalidConstant,
+        ErrorCode::CrosscheckMismatch,
+        ErrorCode::UnknownSensor,
+        ErrorCode::IncompleteFallback,
+        ErrorCode::InvalidBackup,
+        ErrorCode::MisplacedFallbackKey,
+        ErrorCode::UnknownFallbackAction,
+        ErrorCode::UnknownPyroChannel,
+        ErrorCode::InternalError,
+        ErrorCode::WriteFailed,
+        ErrorCode::ArtifactEncodingFailed,
+        ErrorCode::MissingTickRate,
+        ErrorCode::FlashBudgetExceeded,
+        ErrorCode::UnknownLint,
+        ErrorCode::InvalidLintLevel,
+        ErrorCode::LoweredDenyLint,
+        ErrorCode::InvalidOverride,
+        ErrorCode::MissingJustification,
+        ErrorCode::DuplicateOverride,
+        ErrorCode::MissingPatchBase,
+        ErrorCode::ReadFailed,
+        ErrorCode::RecoveryAltitudeBelowPad,
+        ErrorCode::MissingRecoveryAltitude,
+        ErrorCode::UnexpectedCheckCondition,
+        ErrorCode::EventBeforeLaunch,
+        ErrorCode::EventsOutOfOrder,
+        ErrorCode::DelayedEventCommand,
+        ErrorCode::DuplicatePathPolicyState,
+        ErrorCode::TooManySelfTestSteps,
+        ErrorCode::InvalidSelfTestStep,
+        ErrorCode::InvalidSelfTestCheck,
+        ErrorCode::UnknownCheckTemplate,
+        ErrorCode::InvalidTickRate,
+        ErrorCode::YamlUnsupported,
+        ErrorCode::UnknownTarget,
+        ErrorCode::InvalidMemberName,
+        ErrorCode::DuplicateMember,
+        ErrorCode::UnknownRequiredCheck,
+        ErrorCode::RequirementCycle,
+        ErrorCode::UnsupportedRequirement,
+        ErrorCode::UnmatchedBound,
+    ];
+
+    /// The code, such as `E0001`
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownState => "E0001",
+            ErrorCode::TooManyCommandActions => "E0002",
+            ErrorCode::MismatchedCheckType => "E0003",
+            ErrorCode::MissingCommandAction => "E0004",
+            ErrorCode::MissingCheckCondition => "E0005",
+            ErrorCode::TooManyCheckConditions => "E0006",
+            ErrorCode::UnknownCheck => "E0007",
+            ErrorCode::UnknownFlag => "E0008",
+            ErrorCode::AbortAndTransition => "E0009",
+            ErrorCode::MissingAbortState => "E0010",
+            ErrorCode::AbortStateNotSafe => "E0011",
+            ErrorCode::NoStates => "E0012",
+            ErrorCode::TooManyStates => "E0013",
+            ErrorCode::TooManyChecks => "E0014",
+            ErrorCode::TooManyCommands => "E0015",
+            ErrorCode::InvalidTimeout => "E0016",
+            ErrorCode::UnsupportedDataRate => "E0017",
+            ErrorCode::InvalidConfig => "E0018",
+            ErrorCode::UnknownKey => "E0019",
+            ErrorCode::MissingDefaultState => "E0020",
+            ErrorCode::DuplicateState => "E0021",
+            ErrorCode::EmptyName => "E0022",
+            ErrorCode::NameTooLong => "E0023",
+            ErrorCode::InvalidNameCharacters => "E0024",
+            ErrorCode::IncompleteTimeout => "E0025",
+            ErrorCode::DuplicateStage => "E0026",
+            ErrorCode::EmptyStage => "E0027",
+            ErrorCode::SeparateAndTransition => "E0028",
+            ErrorCode::UnknownStage => "E0029",
+            ErrorCode::DeclaredInTwoFiles => "E0030",
+            ErrorCode::UnknownAbortReason => "E0031",
+            ErrorCode::MergeConflict => "E0032",
+            ErrorCode::InvalidExpression => "E0033",
+            ErrorCode::InvalidConstant => "E0034",
+            ErrorCode::CrosscheckMismatch => "E0035",
+            ErrorCode::UnknownSensor => "E0036",
+            ErrorCode::IncompleteFallback => "E0037",
+            ErrorCode::InvalidBackup => "E0038",
+            ErrorCode::MisplacedFallbackKey => "E0039",
+            ErrorCode::UnknownFallbackAction => "E0040",
+            ErrorCode::UnknownPyroChannel => "E0041",
+            ErrorCode::InternalError => "E0042",
+            ErrorCode::WriteFailed => "E0043",
+            ErrorCode::ArtifactEncodingFailed => "E0044",
+            ErrorCode::MissingTickRate => "E0045",
+            ErrorCode::FlashBudgetExceeded => "E0046",
+            ErrorCode::UnknownLint => "E0047",
+            ErrorCode::InvalidLintLevel => "E0048",
+            ErrorCode::LoweredDenyLint => "E0049",
+            ErrorCode::InvalidOverride => "E0050",
+            ErrorCode::MissingJustification => "E0051",
+            ErrorCode::DuplicateOverride => "E0052",
+            ErrorCode::MissingPatchBase => "E0053",
+            ErrorCode::ReadFailed => "E0054",
+            ErrorCode::RecoveryAltitudeBelowPad => "E0055",
+            ErrorCode::MissingRecoveryAltitude => "E0056",
+            ErrorCode::UnexpectedCheckCondition => "E0057",
+            ErrorCode::EventBeforeLaunch => "E0058",
+            ErrorCode::EventsOutOfOrder => "E0059",
+            ErrorCode::DelayedEventCommand => "E0060",
+            ErrorCode::DuplicatePathPolicyState => "E0061",
+            ErrorCode::TooManySelfTestSteps => "E0062",
+            ErrorCode::InvalidSelfTestStep => "E0063",
+            ErrorCode::InvalidSelfTestCheck => "E0064",
+            ErrorCode::UnknownCheckTemplate => "E0065",
+            ErrorCode::InvalidTickRate => "E0066",
+            ErrorCode::YamlUnsupported => "E0067",
+            ErrorCode::UnknownTarget => "E0068",
+            ErrorCode::InvalidMemberName => "E0069",
+            ErrorCode::DuplicateMember => "E0070",
+            ErrorCode::UnknownRequiredCheck => "E0071",
+            ErrorCode::RequirementCycle => "E0072",
+            ErrorCode::UnsupportedRequirement => "E0073",
+            ErrorCode::UnmatchedBound => "E0074",
+        }
+    }
+
+    /// The message of the error, with the parts that change written as `{placeholder}`
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownState => "state not found `{name}`",
+            ErrorCode::TooManyCommandActions => "too many command actions",
+            ErrorCode::MismatchedCheckType => "mismatched check type",
+            ErrorCode::MissingCommandAction => "command action missing",
+            ErrorCode::MissingCheckCondition => "check condition missing",
+            ErrorCode::TooManyCheckConditions => "too many check conditions",
+            ErrorCode::UnknownCheck => "no check with name `{name}` exists",
+            ErrorCode::UnknownFlag => "flag values must be `set` or `unset`",
+            ErrorCode::AbortAndTransition => {
+                "abort and transition cannot be active in the same check"
+            }
+            ErrorCode::MissingAbortState => {
+                "abort to the top level `abort_state` used without declaring one"
+            }
+            ErrorCode::AbortStateNotSafe => "abort state `{state}` is not marked abort safe",
+            ErrorCode::NoStates => "states missing",
+            ErrorCode::TooManyStates => "too many states",
+            ErrorCode::TooManyChecks => "too many checks in state `{state}`",
+            ErrorCode::TooManyCommands => "too many commands in state `{state}`",
+            ErrorCode::InvalidTimeout => "invalid timeout `{value}`",
+            ErrorCode::UnsupportedDataRate => "unsupported data rate {rate}",
+            ErrorCode::InvalidConfig => "failed to parse config file",
+            ErrorCode::UnknownKey => "unknown key `{key}`",
+            ErrorCode::MissingDefaultState => "no `default_state` declared",
+            ErrorCode::DuplicateState => "state `{name}` is declared twice",
+            ErrorCode::EmptyName => "{kind} name cannot be empty",
+            ErrorCode::NameTooLong => "{kind} name `{name}` is too long",
+            ErrorCode::InvalidNameCharacters => "{kind} name `{name}` contains invalid characters",
+            ErrorCode::IncompleteTimeout => "the timeout of `{state}` has no `{key}`",
+            ErrorCode::DuplicateStage => "stage `{name}` is declared twice",
+            ErrorCode::EmptyStage => "stage `{name}` has no states",
+            ErrorCode::SeparateAndTransition => {
+                "`separate` cannot be combined with `transition` or `abort`"
+            }
+            ErrorCode::UnknownStage => "unknown stage `{name}`",
+            ErrorCode::DeclaredInTwoFiles => "`{key}` is set in two files",
+            ErrorCode::UnknownAbortReason => "unknown abort reason `{name}`",
+            ErrorCode::MergeConflict => "unresolved merge conflict",
+            ErrorCode::InvalidExpression => "invalid value `{expression}` for `{key}`",
+            ErrorCode::InvalidConstant => "constant `{name}` is not a number",
+            ErrorCode::CrosscheckMismatch => {
+                "`{key}` is {value} in `{first}` but {value} in `{second}`"
+            }
+            ErrorCode::UnknownSensor => "unknown sensor `{name}`",
+            ErrorCode::IncompleteFallback => "`{action}` fallback has no state to abort to",
+            ErrorCode::InvalidBackup => "`{backup}` cannot back up `{sensor}`",
+            ErrorCode::MisplacedFallbackKey => "`{key}` is only used by `{action}` fallbacks",
+            ErrorCode::UnknownFallbackAction => "unknown fallback action `{action}`",
+            ErrorCode::UnknownPyroChannel => {
+                "pyro channel {channel} does not exist on the `{board}` board"
+            }
+            ErrorCode::InternalError => "internal verifier error: {problem}",
+            ErrorCode::WriteFailed => "failed to write to file `{path}`: {error}",
+            ErrorCode::ArtifactEncodingFailed => "failed to encode the {artifact}: {error}",
+            ErrorCode::MissingTickRate => {
+                "cannot emit the tick rate, the config does not declare one"
+            }
+            ErrorCode::FlashBudgetExceeded => {
+                "encoded config is {size} bytes, which exceeds the flash budget of {budget} bytes"
+            }
+            ErrorCode::UnknownLint => "unknown lint `{name}`",
+            ErrorCode::InvalidLintLevel => "invalid lint level `{level}`",
+            ErrorCode::LoweredDenyLint => {
+                "`{name}` is deny by default, so it cannot be lowered to `{level}`"
+            }
+            ErrorCode::InvalidOverride => "`{name}` is not a deny by default lint",
+            ErrorCode::MissingJustification => "the override of `{name}` has no justification",
+            ErrorCode::DuplicateOverride => "`{name}` is overridden twice",
+            ErrorCode::MissingPatchBase => {
+                "emitting a patch requires the previous config to diff against"
+            }
+            ErrorCode::ReadFailed => "failed to read {kind} `{path}`: {error}",
+            ErrorCode::RecoveryAltitudeBelowPad => "`{field}` must be above the pad",
+            ErrorCode::MissingRecoveryAltitude => {
+                "`{check}` checks require `{field}` in the `[recovery]` section"
+            }
+            ErrorCode::UnexpectedCheckCondition => "`{kind}` checks do not take a condition",
+            ErrorCode::EventBeforeLaunch => "mission event time {time} is not after launch",
+            ErrorCode::EventsOutOfOrder => "mission events are not in time order",
+            ErrorCode::DelayedEventCommand => "mission event commands cannot have a `delay`",
+            ErrorCode::DuplicatePathPolicyState => {
+                "state `{name}` is listed twice in path policy `{policy}`"
+            }
+            ErrorCode::TooManySelfTestSteps => "too many self test steps",
+            ErrorCode::InvalidSelfTestStep => "self test steps cannot use `{key}`",
+            ErrorCode::InvalidSelfTestCheck => "`{kind}` cannot be used in the self test",
+            ErrorCode::UnknownCheckTemplate => "unknown check `{name}`",
+            ErrorCode::InvalidTickRate => "the tick rate must be at least 1",
+            ErrorCode::YamlUnsupported => "YAML configs are not supported by this build",
+            ErrorCode::UnknownTarget => "unknown target `{target}`",
+            ErrorCode::InvalidMemberName => {
+                "member name `{name}` cannot be used as a directory name"
+            }
+            ErrorCode::DuplicateMember => "two members are named `{name}`",
+            ErrorCode::UnknownRequiredCheck => "no check named `{name}` exists",
+            ErrorCode::RequirementCycle => "check `{name}` requires itself",
+            ErrorCode::UnsupportedRequirement => {
+                "check `{name}` cannot require another check on the `{target}` target"
+            }
+            ErrorCode::UnmatchedBound => "`{key}` is set without `{missing}`",
+        }
+    }
+
+    /// Why the error happens and how to fix it, shown by `explain`
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownState => "A transition, abort, or `default_state` names a state that is not declared in `[[states]]`. State names are case sensitive, and the closest existing name is suggested when there is one",
+            ErrorCode::TooManyCommandActions => "A command sets more than one of `pyro1`, `pyro2`, `pyro3`, `beacon`, and `data_rate`. Each command does exactly one thing, so split it into one command per action, using `delay` if they should not happen at once",
+            ErrorCode::MismatchedCheckType => "A check's condition does not fit its kind, such as `flag` on an `altitude` check or `greater_than` on a pyro continuity check. Altitude checks take `greater_than`, `less_than`, or `upper_bound` with `lower_bound`. Apogee and continuity checks take `flag`",
+            ErrorCode::MissingCommandAction => "A command sets none of `pyro1`, `pyro2`, `pyro3`, `beacon`, and `data_rate`, so it does nothing. Add the action it should perform, or remove it",
+            ErrorCode::MissingCheckCondition => "A check has no condition, so it can never trip. Add one of `greater_than`, `less_than`, `upper_bound` with `lower_bound`, or `flag`, or use a check kind that implies its condition, such as `apogee`",
+            ErrorCode::TooManyCheckConditions => "A check sets more than one condition, such as both `greater_than` and `flag`. Each check has exactly one condition, so split it into one check per condition",
+            ErrorCode::UnknownCheck => "A check's `check` is not a check kind. The kinds are listed in the language reference, and the closest kind is suggested when there is one",
+            ErrorCode::UnknownFlag => "A check's `flag` is neither `set` nor `unset`",
+            ErrorCode::AbortAndTransition => "A check sets both `transition` and `abort`, but a check that trips can only go to one state. Remove one of them",
+            ErrorCode::MissingAbortState => "A check uses `abort = true`, which aborts to the top level `abort_state`, but the config does not declare one. Declare `abort_state`, or name the state to abort to",
+            ErrorCode::AbortStateNotSafe => "The top level `abort_state`, and any state that a fault handling fallback aborts to, must be a state that is safe to end the flight in. Mark it with `abort_safe = true` once it is",
+            ErrorCode::NoStates => "The config declares no `[[states]]`, so there is nothing for the flight computer to run",
+            ErrorCode::TooManyStates => "The config declares more states than the flight computer has room for",
+            ErrorCode::TooManyChecks => "A state has more checks, including those added with `use_check`, than the flight computer can run in one state",
+            ErrorCode::TooManyCommands => "A state has more commands than the flight computer can run in one state",
+            ErrorCode::InvalidTimeout => "A timeout's `seconds` is zero, negative, or not a number. Timeouts must be a positive number of seconds",
+            ErrorCode::UnsupportedDataRate => "A `data_rate` command sets a rate that the board's logger does not support. The error gives the supported rates and suggests the nearest one",
+            ErrorCode::InvalidConfig => "The config is not valid TOML, or a value has the wrong type, such as a string where a number is expected",
+            ErrorCode::UnknownKey => "A table has a key that the config language does not know, such as a misspelled `delay`. Only checked with `--strict-keys`, since the key would otherwise be ignored",
+            ErrorCode::MissingDefaultState => "The config does not say which state the flight computer starts in, so it would start in the first state. Only an error with `--require-default-state`",
+            ErrorCode::DuplicateState => "Two states have the same name, so transitions to it would be ambiguous. The states of a `[[stages]]` entry are named with the stage's name in front, such as `Booster_Boost` for the state `Boost` of the stage `Booster`, and can collide with a top level state. Rename one of them",
+            ErrorCode::EmptyName => "A state, check, or other named item has an empty name. Give it a name that starts with a letter",
+            ErrorCode::NameTooLong => "A name is longer than the 32 characters that generated code and telemetry dictionaries allow. Shorten it",
+            ErrorCode::InvalidNameCharacters => "A name does not start with a letter, or contains something other than letters, digits, and `_`. Names are used as identifiers by downstream tooling, so they are limited to those characters",
+            ErrorCode::IncompleteTimeout => "A `[states.timeout]` table is missing `seconds` or `transition`. A timeout needs both, how long to wait and which state to enter when it expires",
+            ErrorCode::DuplicateStage => "Two `[[stages]]` entries have the same name, so `separate` could not tell them apart and their states would collide. Rename one of them",
+            ErrorCode::EmptyStage => "A `[[stages]]` entry declares no states, so there is nothing to run and nothing for `separate` to transition to. Add a `[[stages.states]]` entry, or remove the stage",
+            ErrorCode::SeparateAndTransition => "A check uses `separate`, which transitions to the first state of the named stage, and also sets `transition` or `abort`. A check that trips can only go to one state, so remove one of them",
+            ErrorCode::UnknownStage => "A check's `separate` names a stage that is not declared in `[[stages]]`",
+            ErrorCode::DeclaredInTwoFiles => "A value is set, or a state or other entry is declared, both in the config and in a file it includes with `include`. Each value may only come from one file. Use an override file to change a value instead",
+            ErrorCode::UnknownAbortReason => "An abort's `reason` is not one of the reasons the flight computer reports in telemetry. The registered reasons are listed in the language reference",
+            ErrorCode::MergeConflict => "The config still has the `<<<<<<<`, `=======`, and `>>>>>>>` markers of a merge conflict. Resolve the conflict and remove the markers",
+            ErrorCode::InvalidExpression => "A number was written as an expression, but the expression uses an unknown constant, has a syntax error, or does not evaluate to a finite number",
+            ErrorCode::InvalidConstant => "`[constants]` must be a table whose values are all numbers. Constants are only used in expressions, so strings, booleans, and tables cannot be constants",
+            ErrorCode::CrosscheckMismatch => "Two configs that are cross checked differ in a value that has not been allowed to differ. Make them agree, or list the key in the allowed differences if the difference is intended",
+            ErrorCode::UnknownSensor => "A `[fault_handling]` key, or the sensor a `backup` fallback switches to, is not a sensor of the flight computer. The sensors are listed in the language reference",
+            ErrorCode::IncompleteFallback => "An `abort` fallback does not name the state to abort to, or a `backup` fallback does not name the sensor to switch to",
+            ErrorCode::InvalidBackup => "A `backup` fallback switches to the sensor that failed, or to a sensor that does not measure the same thing as the one that failed",
+            ErrorCode::MisplacedFallbackKey => "A fallback sets a key that belongs to a different action, such as `state` on a `backup` fallback. Remove it, or change the action",
+            ErrorCode::UnknownFallbackAction => "A fallback's `action` is not `abort`, `backup`, or `hold`",
+            ErrorCode::UnknownPyroChannel => "A command fires or disarms a pyro channel that the board selected with `--board` does not have",
+            ErrorCode::InternalError => "The verifier checked its own output and found it wrong, such as an encoded config that does not decode to the config that was verified. This is a bug in the verifier, not in the config. Do not fly the output, and report the bug",
+            ErrorCode::WriteFailed => "An artifact requested with `--emit` could not be written, such as when its directory does not exist",
+            ErrorCode::ArtifactEncodingFailed => "An artifact requested with `--emit` could not be encoded, such as a self test with more steps than its encoding can hold",
+            ErrorCode::MissingTickRate => "The tick rate was requested with `--emit`, but the config has no `tick_rate`. Declare one at the top of the file",
+            ErrorCode::FlashBudgetExceeded => "The encoded config is larger than the budget given with `--flash-budget`. The largest parts of the config are listed after the error",
+            ErrorCode::UnknownLint => "`[lints]` or `[[overrides]]` names a lint that does not exist. The lints are listed in the language reference",
+            ErrorCode::InvalidLintLevel => "A lint in `[lints]` is set to something other than `allow`, `warn`, or `deny`",
+            ErrorCode::LoweredDenyLint => "A lint that is an error by default was set to `allow` or `warn`. These lints catch configs that are unsafe to fly, so they can only be overridden one finding at a time in `[[overrides]]`, with a justification",
+            ErrorCode::InvalidOverride => "An `[[overrides]]` entry names a lint that only warns. Warnings can be allowed in `[lints]` instead",
+            ErrorCode::MissingJustification => "An `[[overrides]]` entry does not say why the config is safe to fly despite the lint. Overrides are reviewed before flight, so the justification is required",
+            ErrorCode::DuplicateOverride => "Two `[[overrides]]` entries override the same lint at the same place. Merge their justifications into one entry",
+            ErrorCode::MissingPatchBase => "A patch was requested with `--emit`, but not the previous encoded config to build it against",
+            ErrorCode::ReadFailed => "A file that the config or the command line refers to, such as an overlay or the base of a patch, could not be read",
+            ErrorCode::RecoveryAltitudeBelowPad => "`recovery.launch_altitude` or `recovery.main_altitude` is zero, negative, or not a number. Altitudes are measured from the pad, so they must be greater than 0",
+            ErrorCode::MissingRecoveryAltitude => "A high level check such as `launch_detected` takes its threshold from `[recovery]`, but the config does not set it",
+            ErrorCode::UnexpectedCheckCondition => "A high level check such as `apogee_passed` also sets a condition such as `flag`. The condition is implied by the check kind, so remove it",
+            ErrorCode::EventBeforeLaunch => "A mission event's `time` is negative. Event times are seconds after launch is detected",
+            ErrorCode::EventsOutOfOrder => "`[[mission_events]]` must be listed in the order they run. Sort them by `time`",
+            ErrorCode::DelayedEventCommand => "A mission event's command sets `delay`. The event's `time` already says when it runs, so use that instead",
+            ErrorCode::DuplicatePathPolicyState => "A `[[path_policy]]` entry lists the same state twice. Remove one of them",
+            ErrorCode::TooManySelfTestSteps => "`[self_test]` has more steps than a state can have checks, which is as many as the flight computer can run",
+            ErrorCode::InvalidSelfTestStep => "A self test step sets `transition`, `abort`, or another key that only makes sense in flight. The self test only reports the results of its checks",
+            ErrorCode::InvalidSelfTestCheck => "A self test step uses a check kind, such as `altitude`, that only makes sense in flight. The self test runs on the pad before arming",
+            ErrorCode::UnknownCheckTemplate => "A state's `use_check` names a check that is not declared in the `[checks]` table",
+            ErrorCode::InvalidTickRate => "`tick_rate` is 0, so the control loop would never run",
+            ErrorCode::YamlUnsupported => "The config is YAML, but the verifier was built without the `yaml` feature. Rebuild it with `--features yaml`, or convert the config to TOML",
+            ErrorCode::UnknownTarget => "A workspace member's `target` is not a flight computer this verifier supports",
+            ErrorCode::InvalidMemberName => "A workspace member's name, which names the directory of its artifacts, is empty or contains something other than letters, digits, `_`, and `-`",
+            ErrorCode::DuplicateMember => "Two workspace members have the same name, so their artifacts would be written to the same directory. Set `name` on one of them",
+            ErrorCode::UnknownRequiredCheck => "A check's `requires` names a check that is not declared in any state",
+            ErrorCode::RequirementCycle => "The `requires` of some checks form a cycle, so none of them can ever trip",
+            ErrorCode::UnsupportedRequirement => "The target's encoding has no way for a check to require another, so `requires` is verified but cannot be lowered for it",
+            ErrorCode::UnmatchedBound => "A check sets only one of `upper_bound` and `lower_bound`. A range needs both, so add the missing bound, or use `greater_than` or `less_than` for a one sided condition",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for ErrorCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| format!("unknown error code `{s}`"))
+    }
+}
+
+/// How diagnostics are printed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum ErrorFormat {
+    /// Each diagnostic with the source it points at
+    #[default]
+    Human,
+
+    /// One line per diagnostic, as `file:line:col: level: message`, for tools that parse
+    /// make style output
+    Short,
+
+    /// One JSON object per line for each diagnostic, for editors, see [`JsonFormatter`]
+    Json,
+
+    /// A single SARIF log with every diagnostic, for code scanning, see [`SarifFormatter`]
+    Sarif,
+}
+
+impl ErrorFormat {
+    pub const ALL: [ErrorFormat; 4] = [
+        ErrorFormat::Human,
+        ErrorFormat::Short,
+        ErrorFormat::Json,
+        ErrorFormat::Sarif,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorFormat::Human => "human",
+            ErrorFormat::Short => "short",
+            ErrorFormat::Json => "json",
+            ErrorFormat::Sarif => "sarif",
+        }
+    }
+
+    /// The formatter that prints diagnostics in this format
+    pub fn formatter(self) -> Box<dyn DiagnosticFormatter> {
+        match self {
+            ErrorFormat::Human => Box::new(HumanFormatter),
+            ErrorFormat::Short => Box::new(ShortFormatter),
+            ErrorFormat::Json => Box::new(JsonFormatter),
+            ErrorFormat::Sarif => Box::new(SarifFormatter),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorFormat::ALL
+            .into_iter()
+            .find(|f| f.name() == s)
+            .ok_or_else(|| format!("unknown error format `{s}`"))
+    }
+}
+
+/// Formats `diagnostic` on a single line for [`ErrorFormat::Short`]. The location is the start of
+/// the primary span, and is left out if the diagnostic has no span. Labels are not shown
+fn short_line(diagnostic: &Diagnostic, codemap: &CodeMap) -> String {
+    let span = diagnostic
+        .spans
+        .iter()
+        .find(|s| s.style == SpanStyle::Primary)
+        .or_else(|| diagnostic.spans.first());
+    // Multi line messages, such as lists of suggestions, are joined to keep to a single line
+    let message: Vec<_> = diagnostic.message.lines().map(str::trim).collect();
+    let message = message.join(" ");
+    match span {
+        Some(label) => {
+            let loc = codemap.look_up_pos(label.span.low());
+            format!("{loc}: {}: {message}", diagnostic.level)
+        }
+        None => format!("{}: {message}", diagnostic.level),
+    }
+}
+
 pub struct Diagnostics<'c> {
     diagnostics: Vec<Diagnostic>,
     codemap: &'c CodeMap,
+    format: ErrorFormat,
+    printed: &'c Cell<Tally>,
+    suggestions: &'c [Suggestion],
+    quiet: bool,
 }
 
 impl<'c> Diagnostics<'c> {
     /// Emits all diagnostics to stderr
     pub fn emit(self) {
-        let mut emitter = Emitter::stderr(ColorConfig::Auto, Some(self.codemap));
-        emitter.emit(&self.diagnostics);
+        self.print();
     }
 
     /// Emits all diagnostics to stderr, and appends them to `to_add`
     pub fn emit_and_extend(self, to_add: &mut Vec<Diagnostic>) {
-        if to_add.is_empty() {
+        if self.diagnostics.is_empty() {
             //Emitting an empty vec still causes newlines to be printed in `Emitter::emit()`
             return;
         }
-        let mut emitter = Emitter::stderr(ColorConfig::Auto, Some(self.codemap));
-        emitter.emit(&self.diagnostics);
+        self.print();
         to_add.extend(self.diagnostics);
     }
+
+    fn print(&self) {
+        if self.quiet {
+            return;
+        }
+        print_diagnostics(
+            &self.diagnostics,
+            self.codemap,
+            self.format,
+            self.printed,
+            self.suggestions,
+        );
+    }
+}
+
+/// Prints `diagnostics` to stderr in `format`, adding them to the count in `printed`.
+/// `suggestions` are the suggestions emitted so far, which JSON output lists the replacement of
+fn print_diagnostics(
+    diagnostics: &[Diagnostic],
+    codemap: &CodeMap,
+    format: ErrorFormat,
+    printed: &Cell<Tally>,
+    suggestions: &[Suggestion],
+) {
+    let suggestion =
+        |diagnostic: &Diagnostic| suggestions.iter().find(|s| s.diagnostic() == *diagnostic);
+    if format == ErrorFormat::Json {
+        for diagnostic in diagnostics {
+            let json =
+                JsonFormatter::format_suggestion(diagnostic, codemap, suggestion(diagnostic));
+            eprintln!("{json}");
+        }
+    } else if format == ErrorFormat::Sarif {
+        let located = diagnostics
+            .iter()
+            .map(|d| LocatedDiagnostic::new(d, codemap, suggestion(d)));
+        SARIF_RESULTS.lock().unwrap().extend(located);
+    } else {
+        format.formatter().emit(diagnostics, codemap);
+    }
+    let mut tally = printed.get();
+    tally.count(diagnostics);
+    printed.set(tally);
+}
+
+/// A fix-it that resolves a diagnostic by replacing the source at `span` with `replacement`, such
+/// as a misspelled name with the closest existing one, or by removing it if `replacement` is
+/// empty. `Diagnostic` has no room for it, so it is emitted as a help after the diagnostic it
+/// fixes, labelling `span` with the replacement:
+///
+/// ```text
+/// help: a check with a similar name exists
+///   --> rocket.toml:9:9
+///    |
+/// 9  | check = "pyro1_continutiy"
+///    |         ^^^^^^^^^^^^^^^^^^ replace with `"pyro1_continuity"`
+/// ```
+///
+/// The session keeps every suggestion, so that JSON output can list the replacement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+
+    /// Edits that are made along with the replacement at `span`, such as declaring a constant
+    /// that the replacement names. They are shown as secondary labels of the help
+    pub edits: Vec<(Span, String)>,
+}
+
+impl Suggestion {
+    /// The help that shows this suggestion
+    pub fn diagnostic(&self) -> Diagnostic {
+        let label = |span: Span, replacement: &str| {
+            let label = match replacement {
+                "" => "remove this".to_owned(),
+                _ if span.0.low() == span.0.high() => {
+                    let lines: Vec<&str> = replacement.trim().lines().collect();
+                    format!("insert `{}`", lines.join(" "))
+                }
+                _ => format!("replace with `{replacement}`"),
+            };
+            Some(label)
+        };
+        let mut spans = vec![SpanLabel {
+            span: self.span.0,
+            label: label(self.span, &self.replacement),
+            style: SpanStyle::Primary,
+        }];
+        spans.extend(self.edits.iter().map(|(span, replacement)| SpanLabel {
+            span: span.0,
+            label: label(*span, replacement),
+            style: SpanStyle::Secondary,
+        }));
+        Diagnostic {
+            level: Level::Help,
+            message: self.message.clone(),
+            code: None,
+            spans,
+        }
+    }
+
+    /// Every edit the fix makes, starting with the replacement at `span`
+    pub fn all_edits(&self) -> impl Iterator<Item = (Span, &str)> + '_ {
+        let edits = self.edits.iter().map(|(span, r)| (*span, r.as_str()));
+        std::iter::once((self.span, self.replacement.as_str())).chain(edits)
+    }
+}
+
+/// A count of the errors and warnings that were printed, used to pick the exit code of the
+/// command line and to summarize its output
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl Tally {
+    /// Adds the errors and warnings in `diagnostics`. Notes and helps are not counted
+    pub fn count(&mut self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            match diagnostic.level {
+                Level::Bug | Level::Error => self.errors += 1,
+                Level::Warning => self.warnings += 1,
+                Level::Note | Level::Help => {}
+            }
+        }
+    }
+
+    /// The line that ends the output, such as `aborting due to 3 previous errors; 1 warning
+    /// emitted`, or `None` if nothing was printed
+    pub fn summary(&self) -> Option<Diagnostic> {
+        fn plural(n: usize, word: &str) -> String {
+            match n {
+                1 => format!("1 {word}"),
+                n => format!("{n} {word}s"),
+            }
+        }
+
+        let warnings = plural(self.warnings, "warning");
+        let (level, message) = match (self.errors, self.warnings) {
+            (0, 0) => return None,
+            (0, _) => (Level::Warning, format!("{warnings} emitted")),
+            (errors, 0) => (
+                Level::Error,
+                format!("aborting due to {}", plural(errors, "previous error")),
+            ),
+            (errors, _) => (
+                Level::Error,
+                format!(
+                    "aborting due to {}; {warnings} emitted",
+                    plural(errors, "previous error")
+                ),
+            ),
+        };
+        Some(Diagnostic {
+            level,
+            message,
+            code: None,
+            spans: Vec::new(),
+        })
+    }
+}
+
+impl std::ops::AddAssign for Tally {
+    fn add_assign(&mut self, other: Tally) {
+        self.errors += other.errors;
+        self.warnings += other.warnings;
+    }
+}
+
+/// Prints diagnostics in one of the [`ErrorFormat`]s
+pub trait DiagnosticFormatter {
+    /// Prints `diagnostics`, whose spans point into files in `codemap`, to stderr
+    fn emit(&self, diagnostics: &[Diagnostic], codemap: &CodeMap);
+}
+
+/// Prints [`ErrorFormat::Human`]
+pub struct HumanFormatter;
+
+impl DiagnosticFormatter for HumanFormatter {
+    fn emit(&self, diagnostics: &[Diagnostic], codemap: &CodeMap) {
+        let mut emitter = Emitter::stderr(ColorConfig::Auto, Some(codemap));
+        emitter.emit(diagnostics);
+    }
+}
+
+/// Prints [`ErrorFormat::Short`]
+pub struct ShortFormatter;
+
+impl DiagnosticFormatter for ShortFormatter {
+    fn emit(&self, diagnostics: &[Diagnostic], codemap: &CodeMap) {
+        for diagnostic in diagnostics {
+            eprintln!("{}", short_line(diagnostic, codemap));
+        }
+    }
+}
+
+/// Prints [`ErrorFormat::Json`], one object per diagnostic and line, such as
+///
+/// ```json
+/// {"level":"error","code":null,"message":"state not found `Fligth`","spans":[{"file":"rocket.toml","line":9,"column":14,"end_line":9,"end_column":22,"label":"not found","primary":true}]}
+/// ```
+///
+/// Lines and columns start at 1, and the end of a span is exclusive. Notes and helps are separate
+/// objects that follow the diagnostic they belong to. A help that is a [`Suggestion`] has a
+/// `replacement` field with the text that should replace its primary span, and each of its spans
+/// has the `replacement` of that span
+pub struct JsonFormatter;
+
+impl JsonFormatter {
+    /// Formats `diagnostic` as a single line of JSON
+    pub fn format(diagnostic: &Diagnostic, codemap: &CodeMap) -> String {
+        Self::format_suggestion(diagnostic, codemap, None)
+    }
+
+    /// Formats `diagnostic`, which shows `suggestion` if it is set, as a single line of JSON
+    pub fn format_suggestion(
+        diagnostic: &Diagnostic,
+        codemap: &CodeMap,
+        suggestion: Option<&Suggestion>,
+    ) -> String {
+        let located = LocatedDiagnostic::new(diagnostic, codemap, suggestion);
+        // Only strings and numbers are serialized, so this cannot fail
+        serde_json::to_string(&located).unwrap()
+    }
+}
+
+/// A diagnostic with its spans resolved to files, lines, and columns, so that it can be shown
+/// without the [`CodeMap`] of the session that emitted it. [`JsonFormatter`] prints these
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LocatedDiagnostic {
+    #[serde(serialize_with = "serialize_level")]
+    pub level: Level,
+    pub code: Option<String>,
+    pub message: String,
+    pub spans: Vec<LocatedSpan>,
+
+    /// The text that should replace the primary span, if this is a help from a [`Suggestion`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+/// A span of a [`LocatedDiagnostic`]. Lines and columns start at 1, and the end is exclusive
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LocatedSpan {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub label: Option<String>,
+    pub primary: bool,
+
+    /// The text that should replace this span, if the diagnostic is a help from a [`Suggestion`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+fn serialize_level<S: serde::Serializer>(level: &Level, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(crate::diagnostics::level_name(*level))
+}
+
+impl LocatedDiagnostic {
+    /// Resolves the spans of `diagnostic` with `codemap`. `suggestion` is the suggestion that
+    /// `diagnostic` shows, if any
+    pub fn new(
+        diagnostic: &Diagnostic,
+        codemap: &CodeMap,
+        suggestion: Option<&Suggestion>,
+    ) -> Self {
+        let spans = diagnostic
+            .spans
+            .iter()
+            .map(|label| {
+                let start = codemap.look_up_pos(label.span.low());
+                let end = codemap.look_up_pos(label.span.high());
+                LocatedSpan {
+                    file: start.file.name().to_owned(),
+                    line: start.position.line + 1,
+                    column: start.position.column + 1,
+                    end_line: end.position.line + 1,
+                    end_column: end.position.column + 1,
+                    label: label.label.clone(),
+                    primary: label.style == SpanStyle::Primary,
+                    replacement: suggestion.and_then(|s| {
+                        s.all_edits()
+                            .find(|(span, _)| span.0 == label.span)
+                            .map(|(_, replacement)| replacement.to_owned())
+                    }),
+                }
+            })
+            .collect();
+        Self {
+            level: diagnostic.level,
+            code: diagnostic.code.clone(),
+            message: diagnostic.message.clone(),
+            spans,
+            replacement: suggestion.map(|s| s.replacement.clone()),
+        }
+    }
+}
+
+impl DiagnosticFormatter for JsonFormatter {
+    fn emit(&self, diagnostics: &[Diagnostic], codemap: &CodeMap) {
+        for diagnostic in diagnostics {
+            eprintln!("{}", Self::format(diagnostic, codemap));
+        }
+    }
+}
+
+/// The diagnostics printed in [`ErrorFormat::Sarif`] so far, which [`SarifFormatter::finish`]
+/// prints as one log
+static SARIF_RESULTS: Mutex<Vec<LocatedDiagnostic>> = Mutex::new(Vec::new());
+
+/// Prints [`ErrorFormat::Sarif`], a [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+/// log that code scanning, such as GitHub's, shows on the lines of a pull request it points at.
+///
+/// A log holds every result of a run, so diagnostics are collected as phases end and printed
+/// together by [`Self::finish`]. Errors, warnings, and notes become results of the same level.
+/// Notes and helps that follow a diagnostic are added to its message instead, and a help that is a
+/// [`Suggestion`] becomes a fix. Lints are the rules of the log, and their results have the lint's
+/// name as their `ruleId`
+pub struct SarifFormatter;
+
+impl SarifFormatter {
+    /// Formats `diagnostics` as a SARIF log
+    pub fn log(diagnostics: &[LocatedDiagnostic]) -> String {
+        fn level(level: Level) -> &'static str {
+            match level {
+                Level::Bug | Level::Error => "error",
+                Level::Warning => "warning",
+                Level::Note | Level::Help => "note",
+            }
+        }
+        fn location(span: &LocatedSpan) -> serde_json::Value {
+            serde_json::json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": span.file },
+                    "region": {
+                        "startLine": span.line,
+                        "startColumn": span.column,
+                        "endLine": span.end_line,
+                        "endColumn": span.end_column,
+                    },
+                },
+            })
+        }
+
+        let mut results: Vec<serde_json::Value> = Vec::new();
+        for diagnostic in diagnostics {
+            let follows = matches!(diagnostic.level, Level::Note | Level::Help);
+            if let (true, Some(result)) = (follows, results.last_mut()) {
+                let text = result["message"]["text"].as_str().unwrap_or_default();
+                let name = crate::diagnostics::level_name(diagnostic.level);
+                result["message"]["text"] =
+                    format!("{text}\n{name}: {}", diagnostic.message).into();
+                // The replacements of a fix, grouped by the file they change
+                let mut changes: Vec<(&str, Vec<serde_json::Value>)> = Vec::new();
+                for span in &diagnostic.spans {
+                    let Some(replacement) = &span.replacement else {
+                        continue;
+                    };
+                    let replacement = serde_json::json!({
+                        "deletedRegion": location(span)["physicalLocation"]["region"],
+                        "insertedContent": { "text": replacement },
+                    });
+                    match changes.iter_mut().find(|(file, _)| *file == span.file) {
+                        Some((_, replacements)) => replacements.push(replacement),
+                        None => changes.push((&span.file, vec![replacement])),
+                    }
+                }
+                if !changes.is_empty() {
+                    let changes: Vec<_> = changes
+                        .into_iter()
+                        .map(|(file, replacements)| {
+                            serde_json::json!({
+                                "artifactLocation": { "uri": file },
+                                "replacements": replacements,
+                            })
+                        })
+                        .collect();
+                    let fix = serde_json::json!({
+                        "description": { "text": diagnostic.message },
+                        "artifactChanges": changes,
+                    });
+                    match result["fixes"].as_array_mut() {
+                        Some(fixes) => fixes.push(fix),
+                        None => result["fixes"] = serde_json::json!([fix]),
+                    }
+                }
+                continue;
+            }
+
+            let mut result = serde_json::json!({
+                "level": level(diagnostic.level),
+                "message": { "text": diagnostic.message },
+                "locations": diagnostic.spans.iter().filter(|s| s.primary).map(location).collect::<Vec<_>>(),
+            });
+            if let Some(code) = &diagnostic.code {
+                result["ruleId"] = code.as_str().into();
+            }
+            let related: Vec<_> = diagnostic
+                .spans
+                .iter()
+                .filter(|s| !s.primary)
+                .enumerate()
+                .map(|(id, span)| {
+                    let mut related = location(span);
+                    related["id"] = id.into();
+                    if let Some(label) = &span.label {
+                        related["message"] = serde_json::json!({ "text": label });
+                    }
+                    related
+                })
+                .collect();
+            if !related.is_empty() {
+                result["relatedLocations"] = related.into();
+            }
+            results.push(result);
+        }
+
+        let rules: Vec<_> = crate::lint::LINTS
+            .iter()
+            .map(|lint| {
+                serde_json::json!({
+                    "id": lint.name,
+                    "shortDescription": { "text": lint.description },
+                    "defaultConfiguration": { "level": level(lint.level) },
+                })
+            })
+            .collect();
+        let log = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        });
+        // Only strings, numbers, and booleans are serialized, so this cannot fail
+        serde_json::to_string_pretty(&log).unwrap()
+    }
+
+    /// Formats every diagnostic printed in [`ErrorFormat::Sarif`] since the last call as a SARIF
+    /// log
+    pub fn finish() -> String {
+        let diagnostics = std::mem::take(&mut *SARIF_RESULTS.lock().unwrap());
+        Self::log(&diagnostics)
+    }
+}
+
+impl DiagnosticFormatter for SarifFormatter {
+    fn emit(&self, diagnostics: &[Diagnostic], codemap: &CodeMap) {
+        let located = diagnostics
+            .iter()
+            .map(|d| LocatedDiagnostic::new(d, codemap, None));
+        SARIF_RESULTS.lock().unwrap().extend(located);
+    }
 }
 
 pub struct Context<'session> {
     session: &'session mut Session,
     file: Arc<codemap::File>,
+
+    /// Files layered over `file`, see [`Self::add_layer`]. Each one is paired with the offset that
+    /// spans into it start at
+    layers: Vec<(usize, Arc<codemap::File>)>,
 }
 
 impl<'session> Context<'session> {
@@ -264,24 +1880,112 @@ impl<'session> Context<'session> {
         DiagnosticBuilder::new(Level::Help, message.into(), self)
     }
 
-    /// Returns true if this phase contains errors
+    /// Returns true if this phase contains errors, or warnings if the session treats them as
+    /// errors
     pub fn has_error(&self) -> bool {
-        for d in &self.session.diagnostics {
-            if d.level == Level::Error {
-                return true;
-            }
-        }
-        false
+        self.session
+            .diagnostics
+            .iter()
+            .any(|d| d.level == Level::Error || self.session.is_denied(d))
     }
 
     pub fn span(&self) -> codemap::Span {
         self.file.span
     }
 
+    /// The diagnostics emitted so far in this phase
+    pub(crate) fn phase_diagnostics_mut(&mut self) -> &mut Vec<Diagnostic> {
+        &mut self.session.diagnostics
+    }
+
+    pub fn session(&self) -> &Session {
+        self.session
+    }
+
+    /// Sets the levels from the `[lints]` table of the config being verified, see
+    /// [`Session::set_config_lint_levels`]
+    pub(crate) fn set_config_lint_levels(&mut self, levels: Vec<(&'static str, LintLevel)>) {
+        self.session.set_config_lint_levels(levels);
+    }
+
+    /// Returns the 1 indexed line number that `span` starts on
+    pub fn line_number(&self, span: Span) -> usize {
+        self.session.map.look_up_pos(span.0.low()).position.line + 1
+    }
+
     pub fn source(&self) -> &str {
         self.file.source()
     }
 
+    /// Adds a file whose values are layered over the current file, such as a local override.
+    ///
+    /// Returns the offset that the new file starts at. Spans past the end of the current file
+    /// refer to its layers, so a value read from the layer at `offset + i` can be spanned like any
+    /// other value with [`Self::subspan`] or [`Span::from_spanned`]
+    pub fn add_layer(&mut self, data: String, file_path: String) -> usize {
+        let offset = match self.layers.last() {
+            Some((offset, file)) => offset + file.source().len() + 1,
+            None => self.file.source().len() + 1,
+        };
+        let file = self.session.map.add_file(file_path, data);
+        self.layers.push((offset, file));
+        offset
+    }
+
+    /// Returns the file that `offset` is in, along with the offset that the file starts at
+    fn locate(&self, offset: usize) -> (&Arc<codemap::File>, usize) {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map_or((&self.file, 0), |(start, file)| (file, *start))
+    }
+
+    /// Returns the span from byte `start` to `end` in the current file or one of its layers
+    pub fn subspan(&self, start: usize, end: usize) -> Span {
+        let (file, offset) = self.locate(start);
+        let span = file
+            .span
+            .subspan((start - offset) as u64, (end - offset) as u64);
+        Span(span)
+    }
+
+    /// Returns the span of the whole lines that bytes `start` to `end` are on, including the line
+    /// break that ends them, along with their text, in whichever file `start` is in
+    pub fn lines(&self, start: usize, end: usize) -> (Span, &str) {
+        let (file, offset) = self.locate(start);
+        let source = file.source();
+        let (start, end) = (start - offset, end - offset);
+        let first = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let last = source[end..]
+            .find('\n')
+            .map_or(source.len(), |i| end + i + 1);
+        let span = file.span.subspan(first as u64, last as u64);
+        (Span(span), &source[first..last])
+    }
+
+    /// Returns the source text that comes before byte `end`, in whichever file `end` is in
+    pub fn source_before(&self, end: usize) -> Option<&str> {
+        let (file, offset) = self.locate(end);
+        file.source().get(..end - offset)
+    }
+
+    /// Returns the path or name of the current file
+    pub fn file_name(&self) -> &str {
+        self.file.name()
+    }
+
+    /// Renders the current file with the labels of `diagnostic` that point into it, see
+    /// [`crate::annotate`]
+    pub fn render_annotated(
+        &self,
+        diagnostic: &Diagnostic,
+        options: crate::annotate::Options,
+    ) -> String {
+        let annotations = crate::annotate::from_diagnostic(diagnostic, &self.file);
+        crate::annotate::render(self.source(), &annotations, options)
+    }
+
     /// Ends the current phase, returning all diagnostics encountered in the process.
     /// If the current phase has diagnostics that are errors, Err(...) will be returned,
     /// otherwise Ok(...) will be returned contaiting errors and notes
@@ -289,10 +1993,16 @@ impl<'session> Context<'session> {
     where
         'session: 's,
     {
+        self.allow_warnings();
         let error = self.has_error();
+        self.deny_warnings();
         let result = Diagnostics {
             diagnostics: std::mem::take(&mut self.session.diagnostics),
             codemap: &self.session.map,
+            format: self.session.error_format,
+            printed: &self.session.printed,
+            suggestions: &self.session.suggestions,
+            quiet: self.session.quiet,
         };
         if error {
             Err(result)
@@ -301,6 +2011,59 @@ impl<'session> Context<'session> {
         }
     }
 
+    /// Removes the warnings of allowed lints from this phase, along with the notes and helps that
+    /// follow them
+    fn allow_warnings(&mut self) {
+        let session = &mut *self.session;
+        let mut allowed = false;
+        let diagnostics = std::mem::take(&mut session.diagnostics);
+        for diagnostic in diagnostics {
+            match diagnostic.level {
+                Level::Note | Level::Help if allowed => continue,
+                Level::Note | Level::Help => {}
+                _ => allowed = session.warning_level(&diagnostic) == Some(LintLevel::Allow),
+            }
+            if !allowed {
+                session.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    /// Turns the denied warnings of this phase into errors, followed by a note that says why
+    fn deny_warnings(&mut self) {
+        let session = &mut *self.session;
+        let denied: Vec<usize> = (0..session.diagnostics.len())
+            .filter(|i| session.is_denied(&session.diagnostics[*i]))
+            .collect();
+        let mut lints: Vec<String> = Vec::new();
+        for i in denied {
+            let diagnostic = &mut session.diagnostics[i];
+            diagnostic.level = Level::Error;
+            let lint = diagnostic.code.clone().unwrap_or_default();
+            if !lints.contains(&lint) {
+                lints.push(lint);
+            }
+        }
+        let messages = if lints.is_empty() {
+            Vec::new()
+        } else if session.warnings_as_errors {
+            vec!["warnings are denied, so every warning is reported as an error".to_owned()]
+        } else {
+            lints
+                .iter()
+                .map(|l| format!("`{l}` is denied, so its warnings are reported as errors"))
+                .collect()
+        };
+        for message in messages {
+            session.diagnostics.push(Diagnostic {
+                level: Level::Note,
+                message,
+                code: None,
+                spans: Vec::new(),
+            });
+        }
+    }
+
     /// Ends the current phase, emitting all diagnostics, and returning them as a Vector.
     /// The value within the `Result` is the same, but Err(...) is used to convey that the current
     /// phase failed.
@@ -333,13 +2096,8 @@ impl<'session> Context<'session> {
 pub struct Span(codemap::Span);
 
 impl Span {
-    pub fn from_spanned<T>(context: &Context, span: &toml::Spanned<T>) -> Self {
-        Self(
-            context
-                .file
-                .span
-                .subspan(span.start() as u64, span.end() as u64),
-        )
+    pub fn from_spanned<T>(context: &Context, span: &SpannedValue<T>) -> Self {
+        context.subspan(span.start(), span.end())
     }
 }
 
@@ -375,8 +2133,217 @@ mod tests {
     fn basic1() {
         let mut session = Session::new();
         let mut context = session.testing("");
-        let _ = context.error("Test").emit();
+        context.error("Test").code(ErrorCode::InvalidConfig).emit();
         let res = context.end_phase_and_emit();
         assert_eq!(res.unwrap_err().len(), 1);
     }
+
+    #[test]
+    fn short_format() {
+        let mut session = Session::new();
+        let mut context = session.testing("a = 1\nb = 2\n");
+        let span = context.subspan(6, 7);
+        context
+            .error("bad key")
+            .code(ErrorCode::UnknownKey)
+            .set_primary_span(span, "here")
+            .emit();
+        context.help("remove it\n  or rename it").emit();
+        let diagnostics = match context.end_phase() {
+            Ok(d) | Err(d) => d.diagnostics,
+        };
+        let lines: Vec<_> = diagnostics
+            .iter()
+            .map(|d| short_line(d, &session.map))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "<anonymous>:2:1: error: bad key",
+                "help: remove it or rename it"
+            ]
+        );
+    }
+
+    #[test]
+    fn tally() {
+        let mut session = Session::new();
+        let mut context = session.testing("");
+        context.error("first").code(ErrorCode::InvalidConfig).emit();
+        context.warn("second").emit();
+        context.help("third").emit();
+        context
+            .error("fourth")
+            .code(ErrorCode::InvalidConfig)
+            .emit();
+        let _ = context.end_phase_and_emit();
+        let printed = session.printed();
+        assert_eq!(
+            printed,
+            Tally {
+                errors: 2,
+                warnings: 1
+            }
+        );
+        let summary = |errors, warnings| Tally { errors, warnings }.summary().map(|d| d.message);
+        assert_eq!(
+            summary(2, 1).as_deref(),
+            Some("aborting due to 2 previous errors; 1 warning emitted")
+        );
+        assert_eq!(
+            summary(1, 0).as_deref(),
+            Some("aborting due to 1 previous error")
+        );
+        assert_eq!(summary(0, 3).as_deref(), Some("3 warnings emitted"));
+        assert_eq!(summary(0, 0), None);
+    }
+
+    #[test]
+    fn warnings_as_errors() {
+        let mut session = Session::new();
+        session.set_warnings_as_errors(true);
+        let mut context = session.testing("");
+        context.warn("suspicious").emit();
+        assert!(context.has_error());
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        let levels: Vec<_> = diagnostics.iter().map(|d| d.level).collect();
+        assert_eq!(levels, [Level::Error, Level::Note]);
+        assert_eq!(session.printed().errors, 1);
+    }
+
+    #[test]
+    fn denied_lints() {
+        let mut session = Session::new();
+        session.set_lint_level(&"infinite_loop".parse().unwrap(), LintLevel::Deny);
+        session.set_lint_level(&"abort_path".parse().unwrap(), LintLevel::Allow);
+        let mut context = session.testing("");
+        context.warn("suspicious").emit();
+        assert!(!context.has_error());
+        context.warn("loops").lint("infinite_loop").emit();
+        assert!(context.has_error());
+        context.warn("stuck").lint("abort_path").emit();
+        context.help("mark a state abort safe").emit();
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        let levels: Vec<_> = diagnostics.iter().map(|d| d.level).collect();
+        assert_eq!(levels, [Level::Warning, Level::Error, Level::Note]);
+        assert_eq!(
+            diagnostics[2].message,
+            "`infinite_loop` is denied, so its warnings are reported as errors"
+        );
+    }
+
+    #[test]
+    fn json_format() {
+        let mut session = Session::new();
+        let mut context = session.testing("a = 1\nbee = 2\n");
+        let span = context.subspan(6, 9);
+        context
+            .warn("bad key")
+            .set_primary_span(span, "here")
+            .lint("unreachable_state")
+            .emit();
+        let diagnostics = match context.end_phase() {
+            Ok(d) | Err(d) => d.diagnostics,
+        };
+        let json = JsonFormatter::format(&diagnostics[0], &session.map);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "level": "warning",
+                "code": "unreachable_state",
+                "message": "bad key",
+                "spans": [{
+                    "file": "<anonymous>",
+                    "line": 2,
+                    "column": 1,
+                    "end_line": 2,
+                    "end_column": 4,
+                    "label": "here",
+                    "primary": true,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn suggestion() {
+        let mut session = Session::new();
+        let mut context = session.testing("check = \"pyro1_continutiy\"\n");
+        let span = context.subspan(8, 26);
+        context
+            .error("unknown check `pyro1_continutiy`")
+            .code(ErrorCode::UnknownCheck)
+            .set_primary_span(span, "not a check")
+            .suggest_replacement(
+                span,
+                "\"pyro1_continuity\"",
+                "a check with a similar name exists",
+            )
+            .emit();
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(diagnostics[1].level, Level::Help);
+        assert_eq!(
+            diagnostics[1].spans[0].label.as_deref(),
+            Some("replace with `\"pyro1_continuity\"`")
+        );
+
+        let suggestion = &session.suggestions()[0];
+        assert_eq!(suggestion.diagnostic(), diagnostics[1]);
+        let json =
+            JsonFormatter::format_suggestion(&diagnostics[1], &session.map, Some(suggestion));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["replacement"], "\"pyro1_continuity\"");
+        let json = JsonFormatter::format(&diagnostics[0], &session.map);
+        assert!(!json.contains("replacement"));
+    }
+
+    #[test]
+    fn sarif() {
+        let mut session = Session::new();
+        let mut context = session.testing("check = \"pyro1_continutiy\"\n");
+        let span = context.subspan(8, 26);
+        let start = context.subspan(0, 0);
+        context
+            .warn("unknown check `pyro1_continutiy`")
+            .set_primary_span(span, "not a check")
+            .lint("infinite_loop")
+            .suggest_edits(
+                span,
+                "\"pyro1_continuity\"",
+                vec![(start, "# renamed\n".to_owned())],
+                "a check with a similar name exists",
+            )
+            .emit();
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let located: Vec<_> = diagnostics.iter().map(|d| session.locate(d)).collect();
+        let log: serde_json::Value = serde_json::from_str(&SarifFormatter::log(&located)).unwrap();
+
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert!(run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["id"] == "infinite_loop"));
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(results[0]["ruleId"], "infinite_loop");
+        assert_eq!(
+            results[0]["message"]["text"],
+            "unknown check `pyro1_continutiy`\nhelp: a check with a similar name exists"
+        );
+        let region = &results[0]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(
+            (&region["startLine"], &region["startColumn"]),
+            (&1.into(), &9.into())
+        );
+        let replacements = &results[0]["fixes"][0]["artifactChanges"][0]["replacements"];
+        assert_eq!(
+            replacements[0]["insertedContent"]["text"],
+            "\"pyro1_continuity\""
+        );
+        assert_eq!(replacements[1]["insertedContent"]["text"], "# renamed\n");
+    }
 }
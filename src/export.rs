@@ -0,0 +1,158 @@
+//! Renders the lowered config as a Graphviz digraph, for `--emit dot`.
+//!
+//! Each state is a node listing its commands, and each check and timeout that leaves a state is a
+//! labeled edge. Aborts are drawn dashed and red, and timeouts dotted, so that reviewers can tell
+//! the nominal flight apart from the contingencies. Render it with `dot -Tsvg config.dot`.
+
+use std::fmt::Write;
+
+use nova_software_common::{
+    index::{self, StateTransition},
+    CheckData, CommandObject, FloatCondition,
+};
+
+use crate::decompile::Names;
+
+/// Quotes `value` as a Graphviz string, where a newline starts a new centered line
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn describe_float(kind: &str, condition: FloatCondition) -> String {
+    match condition {
+        FloatCondition::GreaterThan(value) => format!("{kind} > {value}"),
+        FloatCondition::LessThan(value) => format!("{kind} < {value}"),
+        FloatCondition::Between {
+            upper_bound,
+            lower_bound,
+        } => format!("{lower_bound} < {kind} < {upper_bound}"),
+    }
+}
+
+/// A short description of what a check reads, such as `altitude > 30`
+pub(crate) fn describe_check(data: CheckData) -> String {
+    let flag = |kind: &str, set: bool| format!("{kind} {}", if set { "set" } else { "unset" });
+    match data {
+        CheckData::Altitude(condition) => describe_float("altitude", condition),
+        CheckData::ApogeeFlag(f) => flag("apogee", f.0),
+        CheckData::Pyro1Continuity(f) => flag("pyro1 continuity", f.0),
+        CheckData::Pyro2Continuity(f) => flag("pyro2 continuity", f.0),
+        CheckData::Pyro3Continuity(f) => flag("pyro3 continuity", f.0),
+    }
+}
+
+pub(crate) fn describe_command(command: &index::Command) -> String {
+    let action = match command.object {
+        CommandObject::Pyro1(fire) => format!("pyro1 = {fire}"),
+        CommandObject::Pyro2(fire) => format!("pyro2 = {fire}"),
+        CommandObject::Pyro3(fire) => format!("pyro3 = {fire}"),
+        CommandObject::Beacon(on) => format!("beacon = {on}"),
+        CommandObject::DataRate(rate) => format!("data_rate = {rate}"),
+    };
+    if command.delay.0 == 0.0 {
+        action
+    } else {
+        format!("{action} after {} s", command.delay.0)
+    }
+}
+
+/// Renders `config` as a Graphviz digraph, naming states and checks with `names`
+pub fn dot(config: &index::ConfigFile, names: &Names) -> String {
+    let mut out = String::from("digraph config {\n    node [shape = box];\n");
+    for (i, state) in config.states.iter().enumerate() {
+        let mut label = names.states[i].clone();
+        for command in &state.commands {
+            let _ = write!(label, "\n{}", describe_command(command));
+        }
+        let style = if usize::from(config.default_state) == i {
+            ", peripheries = 2"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "    {} [label = {}{style}];",
+            quote(&names.states[i]),
+            quote(&label)
+        );
+    }
+
+    for (i, state) in config.states.iter().enumerate() {
+        let from = quote(&names.states[i]);
+        let edges = state.checks.iter().enumerate().filter_map(|(j, check)| {
+            let label = format!("{}\n{}", names.checks[i][j], describe_check(check.data));
+            check.transition.map(|t| (t, label, ""))
+        });
+        let timeout = state.timeout.map(|t| {
+            (
+                t.transition,
+                format!("after {} s", t.time),
+                ", style = dotted",
+            )
+        });
+        for (transition, label, style) in edges.chain(timeout) {
+            let (to, style) = match transition {
+                StateTransition::Transition(to) => (to, style.to_owned()),
+                StateTransition::Abort(to) => (to, ", style = dashed, color = red".to_owned()),
+            };
+            let _ = writeln!(
+                out,
+                "    {from} -> {} [label = {}{style}];",
+                quote(&names.states[usize::from(to)]),
+                quote(&label)
+            );
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decompile::Names, Session};
+
+    #[test]
+    fn dot() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+abort_safe = true
+
+[[states.commands]]
+pyro1 = true
+delay = 1.5
+
+[states.timeout]
+seconds = 60.0
+transition = "Armed"
+"#;
+        let mut session = Session::new();
+        let (upper, lower, _) =
+            crate::load_config(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap();
+        let names = Names::from_source(&upper, &lower).unwrap();
+        assert_eq!(
+            super::dot(&lower, &names),
+            r#"digraph config {
+    node [shape = box];
+    "Armed" [label = "Armed", peripheries = 2];
+    "Flight" [label = "Flight\npyro1 = true after 1.5 s"];
+    "Armed" -> "Flight" [label = "Launch\naltitude > 30"];
+    "Flight" -> "Armed" [label = "after 60 s", style = dotted];
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! What the flight computer does when a sensor is declared failed.
+//!
+//! Every check kind reads a sensor, see [`crate::registry::SENSORS`]. The `[fault_handling]`
+//! section says what happens to those checks when the sensor they read fails:
+//!
+//! ```toml
+//! [fault_handling]
+//! barometer = { action = "backup", backup = "backup_barometer" }
+//! continuity = { action = "abort", state = "Safe" }
+//! ```
+//!
+//! The section is lowered into a [`FaultPlan`]. The `.ncf` format has no room for it, so it is
+//! encoded separately with `--emit fault-handling`. Once a config has the section, the
+//! `missing_fallback` lint warns about every sensor that a check reads without a fallback.
+
+use crate::spanned::SpannedValue;
+use serde::Serialize;
+
+use crate::{
+    ncf,
+    registry::{self, SENSORS},
+    upper, Context, ErrorCode, Span,
+};
+
+/// The bytes every encoded fault plan starts with
+pub const MAGIC: [u8; 4] = *b"NFH\0";
+
+/// What happens to the checks that read a failed sensor
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Action {
+    /// Abort to the state at this index
+    Abort(u8),
+
+    /// Read the sensor at this index in [`SENSORS`] instead
+    Backup(u8),
+
+    /// Stop the checks from tripping, so only other checks and timeouts leave the state
+    Hold,
+}
+
+/// The fallback of a single sensor
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Fallback {
+    /// The index of the sensor in [`SENSORS`]
+    pub sensor: u8,
+    pub action: Action,
+}
+
+/// Every fallback of a config, ordered by sensor name
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FaultPlan {
+    pub fallbacks: Vec<Fallback>,
+}
+
+/// Looks up the sensor `name`, emitting an error with the known sensors if there is none
+fn lookup_sensor(name: &str, span: Span, context: &mut Context) -> Result<u8, ()> {
+    match SENSORS.iter().position(|s| s.name == name) {
+        // There are only a handful of sensors
+        Some(i) => Ok(i as u8),
+        None => {
+            context
+                .error(format!("unknown sensor `{name}`"))
+                .code(ErrorCode::UnknownSensor)
+                .set_primary_span(span, "not a sensor")
+                .emit();
+            let names: Vec<_> = SENSORS.iter().map(|s| format!("`{}`", s.name)).collect();
+            context
+                .help(format!("the known sensors are {}", names.join(", ")))
+                .emit();
+            Err(())
+        }
+    }
+}
+
+/// Resolves the state that an `abort` fallback aborts to
+fn abort_state(
+    config: &upper::ConfigFile,
+    fallback: &upper::Fallback,
+    span: Span,
+    context: &mut Context,
+) -> Result<Action, ()> {
+    let name = match fallback.state.as_ref().or(config.abort_state.as_ref()) {
+        Some(name) => name,
+        None => {
+            context
+                .error("`abort` fallback has no state to abort to")
+                .code(ErrorCode::IncompleteFallback)
+                .set_primary_span(span, "aborts nowhere")
+                .emit();
+            context
+                .help("add `state = \"...\"` to the fallback, or a top level `abort_state`")
+                .emit();
+            return Err(());
+        }
+    };
+    let name_span = Span::from_spanned(context, name);
+    let states = config.states.get_ref();
+    let index = states
+        .iter()
+        .position(|s| s.get_ref().name.get_ref() == name.get_ref());
+    let index = match index {
+        Some(index) => index,
+        None => {
+            context
+                .error(format!("state not found `{}`", name.get_ref()))
+                .code(ErrorCode::UnknownState)
+                .set_primary_span(name_span, "not found")
+                .emit();
+            return Err(());
+        }
+    };
+    let state = states[index].get_ref();
+    if !state.abort_safe {
+        let state_span = Span::from_spanned(context, &state.name);
+        context
+            .error(format!(
+                "fallback aborts to `{}`, which is not marked abort safe",
+                name.get_ref()
+            ))
+            .code(ErrorCode::AbortStateNotSafe)
+            .set_primary_span(name_span, "aborts here")
+            .span_label(state_span, "add `abort_safe = true` to this state")
+            .emit();
+        return Err(());
+    }
+    // The number of states was checked against the target's limits while lowering
+    Ok(Action::Abort(index as u8))
+}
+
+/// Resolves the sensor that a `backup` fallback for `sensor` switches to
+fn backup_sensor(
+    sensor: u8,
+    fallback: &upper::Fallback,
+    span: Span,
+    context: &mut Context,
+) -> Result<Action, ()> {
+    let failed = &SENSORS[sensor as usize];
+    let backup = match &fallback.backup {
+        Some(backup) => backup,
+        None => {
+            context
+                .error("`backup` fallback does not name a sensor to switch to")
+                .code(ErrorCode::IncompleteFallback)
+                .set_primary_span(span, "backs up to nothing")
+                .emit();
+            context
+                .help(format!(
+                    "add `backup = \"...\"` with a sensor that measures {}",
+                    failed.measures
+                ))
+                .emit();
+            return Err(());
+        }
+    };
+    let backup_span = Span::from_spanned(context, backup);
+    let index = lookup_sensor(backup.get_ref(), backup_span, context)?;
+    let replacement = &SENSORS[index as usize];
+    if index == sensor {
+        context
+            .error(format!("`{}` cannot back itself up", failed.name))
+            .code(ErrorCode::InvalidBackup)
+            .set_primary_span(backup_span, "the same sensor that failed")
+            .emit();
+        return Err(());
+    }
+    if replacement.measures != failed.measures {
+        context
+            .error(format!(
+                "`{}` cannot back up `{}`",
+                replacement.name, failed.name
+            ))
+            .code(ErrorCode::InvalidBackup)
+            .set_primary_span(
+                backup_span,
+                format!("measures {}, not {}", replacement.measures, failed.measures),
+            )
+            .emit();
+        return Err(());
+    }
+    Ok(Action::Backup(index))
+}
+
+/// Verifies and lowers the `[fault_handling]` section of `config`, emitting errors to `context`
+pub fn lower(config: &upper::ConfigFile, context: &mut Context) -> Result<FaultPlan, ()> {
+    let section = match &config.fault_handling {
+        Some(section) => section.get_ref(),
+        None => return Ok(FaultPlan::default()),
+    };
+    let mut result = Ok(FaultPlan::default());
+    for (name, fallback) in section {
+        // Keys are not spanned, so errors point at the fallback instead
+        let span = Span::from_spanned(context, fallback);
+        let fallback = fallback.get_ref();
+        let sensor = match lookup_sensor(name, span, context) {
+            Ok(sensor) => sensor,
+            Err(()) => {
+                result = Err(());
+                continue;
+            }
+        };
+
+        let kind = fallback.action.get_ref().as_str();
+        let action_span = Span::from_spanned(context, &fallback.action);
+        let options: [(&str, &str, &Option<SpannedValue<String>>); 2] = [
+            ("state", "abort", &fallback.state),
+            ("backup", "backup", &fallback.backup),
+        ];
+        for (key, owner, value) in options {
+            if let Some(value) = value.as_ref().filter(|_| kind != owner) {
+                let span = Span::from_spanned(context, value);
+                context
+                    .error(format!("`{key}` is only used by `{owner}` fallbacks"))
+                    .code(ErrorCode::MisplacedFallbackKey)
+                    .set_primary_span(span, "remove this")
+                    .span_label(action_span, format!("this fallback is `{kind}`"))
+                    .emit();
+                result = Err(());
+            }
+        }
+
+        let action = match kind {
+            "abort" => abort_state(config, fallback, action_span, context),
+            "backup" => backup_sensor(sensor, fallback, action_span, context),
+            "hold" => Ok(Action::Hold),
+            _ => {
+                context
+                    .error(format!("unknown fallback action `{kind}`"))
+                    .code(ErrorCode::UnknownFallbackAction)
+                    .set_primary_span(action_span, "expected `abort`, `backup`, or `hold`")
+                    .emit();
+                Err(())
+            }
+        };
+        match action {
+            Ok(action) => {
+                if let Ok(plan) = &mut result {
+                    plan.fallbacks.push(Fallback { sensor, action });
+                }
+            }
+            Err(()) => result = Err(()),
+        }
+    }
+    result
+}
+
+/// Warns about every sensor that a check reads without a fallback, once `config` has a
+/// `[fault_handling]` section. Each sensor is reported once, at the first check that reads it
+pub fn check_coverage(config: &upper::ConfigFile, context: &mut Context) {
+    let section = match &config.fault_handling {
+        Some(section) => section.get_ref(),
+        None => return,
+    };
+    let mut reported = Vec::new();
+    let checks = config.states.get_ref().iter();
+    let checks = checks.flat_map(|s| s.get_ref().checks.iter().map(|c| c.get_ref()));
+    for check in checks {
+        let sensor = match registry::sensor_for(check.check.get_ref()) {
+            Some(sensor) => sensor.name,
+            None => continue,
+        };
+        if section.contains_key(sensor) || reported.contains(&sensor) {
+            continue;
+        }
+        reported.push(sensor);
+        let span = Span::from_spanned(context, &check.check);
+        context
+            .warn(format!("sensor `{sensor}` has no fallback"))
+            .set_primary_span(span, format!("this check reads `{sensor}`"))
+            .lint("missing_fallback")
+            .emit();
+        context
+            .help(format!(
+                "add `{sensor} = {{ action = \"...\" }}` to `[fault_handling]`"
+            ))
+            .emit();
+    }
+}
+
+/// Encodes `plan` for the flight computer
+pub fn encode(plan: &FaultPlan) -> Result<Vec<u8>, postcard::Error> {
+    ncf::encode_tagged(MAGIC, plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use super::{check_coverage, lower, Action, Fallback};
+    use crate::{upper, Session};
+
+    const CONFIG: &str = r#"
+abort_state = "Safe"
+
+[fault_handling]
+barometer = FALLBACK
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Safe"
+
+[[states.checks]]
+name = "Continuity"
+check = "pyro1_continuity"
+flag = "unset"
+abort = true
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+
+    fn run(fallback: &str) -> (Result<super::FaultPlan, ()>, Vec<Level>) {
+        let mut session = Session::new();
+        let mut context = session.testing(&CONFIG.replace("FALLBACK", fallback));
+        let config = upper::verify(&mut context).unwrap();
+        let plan = lower(&config, &mut context);
+        if plan.is_ok() {
+            check_coverage(&config, &mut context);
+        }
+        let diagnostics = match context.end_phase_and_emit() {
+            Ok(d) | Err(d) => d,
+        };
+        (plan, diagnostics.iter().map(|d| d.level).collect())
+    }
+
+    #[test]
+    fn fallbacks() {
+        let (plan, levels) = run(r#"{ action = "backup", backup = "backup_barometer" }"#);
+        let expected = [Fallback {
+            sensor: 0,
+            action: Action::Backup(1),
+        }];
+        assert_eq!(plan.unwrap().fallbacks, expected);
+        // Continuity has no fallback
+        assert_eq!(levels, [Level::Warning, Level::Help]);
+
+        let (plan, _) = run(r#"{ action = "abort" }"#);
+        assert_eq!(plan.unwrap().fallbacks[0].action, Action::Abort(1));
+        let (plan, _) = run(r#"{ action = "hold" }"#);
+        assert_eq!(plan.unwrap().fallbacks[0].action, Action::Hold);
+    }
+
+    #[test]
+    fn invalid_fallbacks() {
+        for fallback in [
+            r#"{ action = "backup", backup = "continuity" }"#,
+            r#"{ action = "backup", backup = "barometer" }"#,
+            r#"{ action = "backup" }"#,
+            r#"{ action = "abort", state = "Flight" }"#,
+            r#"{ action = "hold", state = "Safe" }"#,
+            r#"{ action = "retry" }"#,
+        ] {
+            let (plan, levels) = run(fallback);
+            assert!(plan.is_err(), "{fallback}");
+            assert!(levels.contains(&Level::Error), "{fallback}");
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Ground test builds of a config.
+//!
+//! Full dry runs in the lab exercise the real flight logic without live charges on the pyro
+//! channels. `--ground-test` builds a config with the same states, checks, and transitions as the
+//! flight config, but with every command that fires a pyro channel replaced by one that turns the
+//! beacon on, which is the only indication the flight computer can give. The build is marked in
+//! its metadata sidecar with [`crate::approval::Metadata::ground_test`], and the verifier refuses
+//! to write the config if the marker cannot be written.
+
+use nova_software_common::{index, CommandObject};
+
+/// Replaces every command in `config` that fires a pyro channel with one that turns the beacon on,
+/// keeping its delay. Returns the number of commands replaced
+pub fn disarm(config: &mut index::ConfigFile) -> usize {
+    let mut replaced = 0;
+    for state in config.states.iter_mut() {
+        for command in state.commands.iter_mut() {
+            if let CommandObject::Pyro1(true)
+            | CommandObject::Pyro2(true)
+            | CommandObject::Pyro3(true) = command.object
+            {
+                command.object = CommandObject::Beacon(true);
+                replaced += 1;
+            }
+        }
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use nova_software_common::CommandObject;
+
+    use super::disarm;
+    use crate::Session;
+
+    #[test]
+    fn replaces_pyro_fires() {
+        let config = r#"
+[[states]]
+name = "Pad"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 100.0
+transition = "Deploy"
+
+[[states]]
+name = "Deploy"
+
+[[states.commands]]
+pyro1 = true
+delay = 0.5
+
+[[states.commands]]
+pyro2 = false
+
+[[states.commands]]
+data_rate = 10
+"#;
+        let mut session = Session::new();
+        let (_, mut lower, _) =
+            crate::load_config(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap();
+        assert_eq!(disarm(&mut lower), 1);
+        let objects: Vec<_> = lower.states[1]
+            .commands
+            .iter()
+            .map(|c| (c.object, c.delay.0))
+            .collect();
+        assert_eq!(
+            objects,
+            [
+                (CommandObject::Beacon(true), 0.5),
+                (CommandObject::Pyro2(false), 0.0),
+                (CommandObject::DataRate(10), 0.0),
+            ]
+        );
+    }
+}
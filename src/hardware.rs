@@ -0,0 +1,271 @@
+//! Profiles of the boards that configs are flown on, selected with `--board`.
+//!
+//! Revisions of the flight computer differ in how many pyro channels they have, and in
+//! the rates their logger supports. A profile describes one board, and the limits checked while
+//! lowering come from the profile instead of being fixed for the target. A profile is a TOML file,
+//! which records in `source` where its limits were taken from, such as the schematic of the
+//! board:
+//!
+//! ```toml
+//! name = "bench-fc"
+//! source = "bench-fc schematic, rev B"
+//! pyro_channels = 2
+//! max_states = 16
+//! max_checks_per_state = 3
+//! max_commands_per_state = 3
+//! data_rates = [1, 10, 100]
+//! link_budget = 5760
+//! ```
+//!
+//! The boards in `boards/` are built in, see [`Board`], and any other board is selected by the
+//! path of its profile.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nova_software_common as common;
+use thiserror::Error;
+
+use crate::{limits::Limits, Context, ErrorCode, Span};
+
+/// The number of pyro channels that the encoded format can address
+pub const FORMAT_PYRO_CHANNELS: u8 = 3;
+
+/// A revision of the flight computer with a built-in profile
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Board {
+    /// The previous revision, which has fewer pyro channels
+    NovaFcV1,
+
+    /// The current revision
+    #[default]
+    NovaFcV2,
+}
+
+impl Board {
+    pub const ALL: [Board; 2] = [Board::NovaFcV1, Board::NovaFcV2];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Board::NovaFcV1 => "nova-fc-v1",
+            Board::NovaFcV2 => "nova-fc-v2",
+        }
+    }
+
+    /// The text of the board's profile
+    pub fn toml(self) -> &'static str {
+        match self {
+            Board::NovaFcV1 => include_str!("../boards/nova-fc-v1.toml"),
+            Board::NovaFcV2 => include_str!("../boards/nova-fc-v2.toml"),
+        }
+    }
+
+    pub fn profile(self) -> Profile {
+        // Every built-in profile is parsed by the tests
+        Profile::parse(self.toml()).expect("invalid built-in board profile")
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Board::ALL
+            .into_iter()
+            .find(|b| b.name() == s)
+            .ok_or_else(|| format!("unknown board `{s}`"))
+    }
+}
+
+/// The hardware that a config is verified against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// The name of the board, used in diagnostics
+    pub name: String,
+
+    /// Where the limits of the board were taken from
+    pub source: String,
+
+    /// The limits of the board
+    pub limits: Limits,
+}
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("failed to read `{0}`: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("invalid board profile: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("invalid board profile: missing `name`")]
+    MissingName,
+
+    #[error(
+        "invalid board profile: missing `source`, which says where its limits were taken from"
+    )]
+    MissingSource,
+
+    #[error(
+        "invalid board profile: `{key}` is {value}, but the encoded format allows at most {max}"
+    )]
+    OverFormat {
+        key: &'static str,
+        value: usize,
+        max: usize,
+    },
+
+    #[error("invalid board profile: `data_rates` must be a range or a list of rates above 0, from slowest to fastest")]
+    DataRates,
+}
+
+impl Profile {
+    /// Parses a TOML profile, checking that its limits fit in the encoded format
+    pub fn parse(text: &str) -> Result<Profile, ProfileError> {
+        let mut table: toml::value::Table = toml::from_str(text)?;
+        let name = match table.remove("name") {
+            Some(toml::Value::String(name)) => name,
+            _ => return Err(ProfileError::MissingName),
+        };
+        let source = match table.remove("source") {
+            Some(toml::Value::String(source)) => source,
+            _ => return Err(ProfileError::MissingSource),
+        };
+        let limits: Limits = toml::Value::Table(table).try_into()?;
+
+        let (checks, commands) = crate::limits::format_capacity();
+        let capacity = [
+            ("max_states", limits.max_states, common::MAX_STATES),
+            ("max_checks_per_state", limits.max_checks_per_state, checks),
+            (
+                "max_commands_per_state",
+                limits.max_commands_per_state,
+                commands,
+            ),
+            (
+                "pyro_channels",
+                limits.pyro_channels.into(),
+                FORMAT_PYRO_CHANNELS.into(),
+            ),
+        ];
+        for (key, value, max) in capacity {
+            if value > max {
+                return Err(ProfileError::OverFormat { key, value, max });
+            }
+        }
+        if !limits.data_rates.is_valid() {
+            return Err(ProfileError::DataRates);
+        }
+        Ok(Profile {
+            name,
+            source,
+            limits,
+        })
+    }
+
+    /// Loads the profile of `board`, which is either the name of a built-in [`Board`] or the path
+    /// of a TOML profile
+    pub fn load(board: &str) -> Result<Profile, ProfileError> {
+        if let Ok(board) = board.parse::<Board>() {
+            return Ok(board.profile());
+        }
+        let text = std::fs::read_to_string(board)
+            .map_err(|err| ProfileError::Read(board.to_owned(), err))?;
+        Profile::parse(&text)
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Board::default().profile()
+    }
+}
+
+/// Checks that the session's board has pyro channel `channel`, which is numbered from 1 and was
+/// used at `span`
+pub fn check_pyro_channel(channel: u8, span: Span, context: &mut Context) -> Result<(), ()> {
+    let board = context.session().board();
+    let count = board.limits.pyro_channels;
+    if (1..=count).contains(&channel) {
+        return Ok(());
+    }
+    let has = match count {
+        0 => "the board has no pyro channels".to_owned(),
+        1 => "the board has 1 pyro channel".to_owned(),
+        _ => format!("the board has pyro channels 1 to {count}"),
+    };
+    let name = board.name.clone();
+    context
+        .error(format!(
+            "pyro channel {channel} does not exist on the `{name}` board"
+        ))
+        .code(ErrorCode::UnknownPyroChannel)
+        .set_primary_span(span, has)
+        .emit();
+    Err(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, Profile, ProfileError};
+    use crate::limits::DataRates;
+    use crate::{lower, upper, Session};
+
+    #[test]
+    fn board_profiles() {
+        for board in Board::ALL {
+            assert_eq!(board.name().parse::<Board>().unwrap(), board);
+        }
+        let text = "name = \"bench\"\nsource = \"test\"\npyro_channels = 2\nmax_states = 4\nmax_checks_per_state = 3\nmax_commands_per_state = 3\ndata_rates = [1, 10]\nlink_budget = 100\n";
+        let profile = Profile::parse(text).unwrap();
+        assert_eq!(profile.name, "bench");
+        assert_eq!(profile.limits.data_rates, DataRates::Table(vec![1, 10]));
+        assert!(matches!(
+            Profile::parse(&text.replace("max_states = 4", "max_states = 400")),
+            Err(ProfileError::OverFormat {
+                key: "max_states",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Profile::parse(&text.replace("[1, 10]", "[10, 1]")),
+            Err(ProfileError::DataRates)
+        ));
+        assert!(Profile::parse(&format!("{text}pyro_chanels = 2\n")).is_err());
+        assert!(matches!(
+            Profile::parse(&text.replace("source = \"test\"\n", "")),
+            Err(ProfileError::MissingSource)
+        ));
+
+        // A board with two pyro channels has no third
+        let config = "[[states]]\nname = \"Deploy\"\n\n[[states.commands]]\npyro3 = true\n";
+        let mut session = Session::new();
+        session.set_board(profile);
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(lower::verify(&mid, &mut context).is_err());
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "pyro channel 3 does not exist on the `bench` board"
+        );
+    }
+
+    #[test]
+    fn pyro_channels_per_board() {
+        let config = "[[states]]\nname = \"Deploy\"\n\n[[states.commands]]\npyro3 = true\n";
+        for (board, valid) in [(Board::NovaFcV2, true), (Board::NovaFcV1, false)] {
+            let mut session = Session::new();
+            session.set_board(board.profile());
+            let mut context = session.testing(config);
+            let mid = upper::verify(&mut context).unwrap();
+            assert_eq!(lower::verify(&mid, &mut context).is_ok(), valid, "{board}");
+        }
+    }
+}
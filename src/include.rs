@@ -0,0 +1,159 @@
+//! Configs split across several files.
+//!
+//! A large config can be split into parts, such as `states.toml` and `abort.toml`, that are given
+//! alongside the main config with `--include`. Each part is parsed as its own file, and added to the
+//! main config before it is deserialized:
+//! - Tables are merged key by key
+//! - Arrays are concatenated, so the states of each part follow the states of the files before it
+//! - A value set in two files, or a state declared in two files, is an error pointing into both
+//!
+//! Unlike an override file, see [`crate::overlay`], a part can only add to a config, never change
+//! it.
+
+use crate::overlay::entry_name_node;
+use crate::tree::{Node, Value};
+use crate::{Context, ErrorCode};
+
+/// Something declared both in the config and in a part
+struct Duplicate {
+    /// The dotted name of the value, such as `recovery.main_altitude`
+    key: String,
+
+    /// The name of the duplicated array entry, if it was an entry of a named array
+    name: Option<String>,
+
+    first: (usize, usize),
+    second: (usize, usize),
+}
+
+fn child_key(key: &str, child: &str) -> String {
+    if key.is_empty() {
+        child.to_owned()
+    } else {
+        format!("{key}.{child}")
+    }
+}
+
+fn add_into(base: &mut Node, part: Node, key: &str, duplicates: &mut Vec<Duplicate>) {
+    let Node { span, value } = part;
+    match (&mut base.value, value) {
+        (Value::Table(entries), Value::Table(part)) => {
+            for (name, value) in part {
+                match entries.iter_mut().find(|(k, _)| *k == name) {
+                    Some((_, existing)) => {
+                        add_into(existing, value, &child_key(key, &name), duplicates)
+                    }
+                    None => entries.push((name, value)),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(part)) => {
+            for value in part {
+                let name = entry_name_node(&value).and_then(|n| match &n.value {
+                    Value::String(name) => Some((name.clone(), n.span)),
+                    _ => None,
+                });
+                let existing = name.as_ref().and_then(|(name, _)| {
+                    items.iter().find_map(|item| match entry_name_node(item) {
+                        Some(n) if n.value == Value::String(name.clone()) => Some(n.span),
+                        _ => None,
+                    })
+                });
+                if let (Some(first), Some((name, second))) = (existing, &name) {
+                    duplicates.push(Duplicate {
+                        key: key.to_owned(),
+                        name: Some(name.clone()),
+                        first,
+                        second: *second,
+                    });
+                }
+                items.push(value);
+            }
+        }
+        (_, _) => duplicates.push(Duplicate {
+            key: key.to_owned(),
+            name: None,
+            first: base.span,
+            second: span,
+        }),
+    }
+}
+
+/// Adds `part`, whose spans must already point into its layer of `context`, to `base`. Emits an
+/// error for everything that both declare
+pub(crate) fn add(context: &mut Context, base: &mut Node, part: Node) -> Result<(), ()> {
+    let mut duplicates = Vec::new();
+    add_into(base, part, "", &mut duplicates);
+    for duplicate in &duplicates {
+        let first = context.subspan(duplicate.first.0, duplicate.first.1);
+        let second = context.subspan(duplicate.second.0, duplicate.second.1);
+        let message = match (&duplicate.name, duplicate.key.as_str()) {
+            (Some(name), "states") => format!("state `{name}` is declared in two files"),
+            (Some(name), key) => format!("`{key}` entry `{name}` is declared in two files"),
+            (None, key) => format!("`{key}` is set in two files"),
+        };
+        context
+            .error(message)
+            .code(ErrorCode::DeclaredInTwoFiles)
+            .set_primary_span(second, "declared again here")
+            .span_label(first, "first declared here")
+            .emit();
+    }
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        context
+            .help("each value may only be set in one file. Use an override file to change a value")
+            .emit();
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree::parse_toml;
+    use crate::Session;
+
+    const MAIN: &str = r#"
+default_state = "Armed"
+
+[recovery]
+main_altitude = 150.0
+
+[[states]]
+name = "Armed"
+"#;
+
+    const PART: &str = r#"
+[recovery]
+launch_altitude = 30.0
+
+[[states]]
+name = "Flight"
+"#;
+
+    #[test]
+    fn add() {
+        let mut session = Session::new();
+        let mut context = session.testing(MAIN);
+        let mut base = parse_toml(MAIN).unwrap();
+        let offset = context.add_layer(PART.to_owned(), "states.toml".to_owned());
+        let mut part = parse_toml(PART).unwrap();
+        crate::overlay::shift(&mut part, offset);
+        super::add(&mut context, &mut base, part).unwrap();
+        let config: crate::upper::ConfigFile = crate::tree::from_node(&base).unwrap();
+        assert_eq!(config.states.get_ref().len(), 2);
+        let recovery = config.recovery.unwrap().into_inner();
+        assert!(recovery.launch_altitude.is_some() && recovery.main_altitude.is_some());
+
+        let again = "default_state = \"Flight\"\n[[states]]\nname = \"Armed\"\n";
+        let offset = context.add_layer(again.to_owned(), "again.toml".to_owned());
+        let mut part = parse_toml(again).unwrap();
+        crate::overlay::shift(&mut part, offset);
+        assert!(super::add(&mut context, &mut base, part).is_err());
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(errors[0].message, "`default_state` is set in two files");
+        assert_eq!(errors[1].message, "state `Armed` is declared in two files");
+        assert_eq!(errors[1].spans.len(), 2);
+    }
+}
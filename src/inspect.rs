@@ -0,0 +1,161 @@
+//! A human readable table of a compiled `.ncf` file, for `inspect`.
+//!
+//! Lists every state with its checks, commands, and timeout, where each one leads, and how many
+//! encoded bytes it takes up. Like [`crate::size`], a state's own bytes only count its overhead and
+//! timeout, since its checks and commands are listed as separate rows.
+
+use std::fmt::Write;
+
+use nova_software_common::index::{self, StateTransition};
+
+use crate::decompile::Names;
+use crate::export::{describe_check, describe_command};
+use crate::ncf::Header;
+use crate::size::encoded_len;
+
+/// A line of the table
+struct Row {
+    state: String,
+    item: String,
+    outcome: String,
+    bytes: Option<usize>,
+}
+
+fn outcome(transition: StateTransition, names: &Names) -> String {
+    match transition {
+        StateTransition::Transition(to) => format!("-> {}", names.states[usize::from(to)]),
+        StateTransition::Abort(to) => format!("abort -> {}", names.states[usize::from(to)]),
+    }
+}
+
+/// Renders `config`, which was read with `header`, naming states and checks with `names`
+pub fn inspect(header: &Header, config: &index::ConfigFile, names: &Names) -> String {
+    let mut rows = Vec::new();
+    for (i, state) in config.states.iter().enumerate() {
+        let mut children = Vec::new();
+        for (j, check) in state.checks.iter().enumerate() {
+            children.push(Row {
+                state: String::new(),
+                item: format!(
+                    "check {}: {}",
+                    names.checks[i][j],
+                    describe_check(check.data)
+                ),
+                outcome: check
+                    .transition
+                    .map_or_else(String::new, |t| outcome(t, names)),
+                bytes: Some(encoded_len(check)),
+            });
+        }
+        for command in &state.commands {
+            children.push(Row {
+                state: String::new(),
+                item: format!("command {}", describe_command(command)),
+                outcome: String::new(),
+                bytes: Some(encoded_len(command)),
+            });
+        }
+        if let Some(timeout) = state.timeout {
+            children.push(Row {
+                state: String::new(),
+                item: format!("timeout after {} s", timeout.time),
+                outcome: outcome(timeout.transition, names),
+                bytes: None,
+            });
+        }
+
+        let children_bytes: usize = children.iter().filter_map(|row| row.bytes).sum();
+        let default = if usize::from(config.default_state) == i {
+            " (default)"
+        } else {
+            ""
+        };
+        rows.push(Row {
+            state: format!("{}{default}", names.states[i]),
+            item: String::new(),
+            outcome: String::new(),
+            bytes: Some(encoded_len(state).saturating_sub(children_bytes)),
+        });
+        rows.extend(children);
+    }
+
+    let width = |f: fn(&Row) -> &str, title: &str| {
+        rows.iter()
+            .map(|row| f(row).len())
+            .max()
+            .unwrap_or(0)
+            .max(title.len())
+    };
+    let state_width = width(|r| &r.state, "state");
+    let item_width = width(|r| &r.item, "item");
+    let outcome_width = width(|r| &r.outcome, "leads to");
+
+    // Writing to a `String` cannot fail
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<state_width$}  {:<item_width$}  {:<outcome_width$}  {:>5}",
+        "state", "item", "leads to", "bytes"
+    );
+    for row in &rows {
+        let bytes = row.bytes.map_or_else(String::new, |b| b.to_string());
+        let line = format!(
+            "{:<state_width$}  {:<item_width$}  {:<outcome_width$}  {bytes:>5}",
+            row.state, row.item, row.outcome
+        );
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    let _ = writeln!(
+        out,
+        "{} states, encoded in {} bytes with format version {} by verifier {}",
+        config.states.len(),
+        encoded_len(config) + crate::ncf::HEADER_LEN,
+        header.format_version,
+        header.compiler_version()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{decompile::Names, ncf, Session};
+
+    #[test]
+    fn inspect() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+abort_safe = true
+
+[[states.commands]]
+pyro1 = true
+delay = 1.5
+
+[states.timeout]
+seconds = 60.0
+transition = "Armed"
+"#;
+        let mut session = Session::new();
+        let (bytes, _) =
+            crate::verify_inner(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap();
+        let (header, lower) = ncf::decode(&bytes).unwrap();
+        let table = super::inspect(&header, &lower, &Names::generic(&lower));
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[1].starts_with("State0 (default)"));
+        assert!(lines[2].contains("check State0Check0: altitude > 30  -> State1"));
+        assert!(lines[4].contains("command pyro1 = true after 1.5 s"));
+        assert!(lines[5].contains("timeout after 60 s") && lines[5].ends_with("-> State0"));
+        let total = format!("2 states, encoded in {} bytes", bytes.len());
+        assert!(lines[6].starts_with(&total), "{table}");
+    }
+}
@@ -0,0 +1,193 @@
+//! Reporting of internal verifier errors.
+//!
+//! A panic anywhere in the verifier is a bug in the verifier rather than a problem with the config
+//! being checked. [`catch`] turns a panic into a [`PanicReport`] so that the command line can print
+//! it as a diagnostic with everything needed to file an issue, rather than a raw panic message.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+
+use codemap_diagnostic::{Diagnostic, Level};
+use nova_software_common::index;
+
+use crate::{ncf, Context, ErrorCode};
+
+/// Where bugs in the verifier are reported
+pub const ISSUE_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues/new");
+
+/// A panic caught by [`catch`]
+#[derive(Debug)]
+pub struct PanicReport {
+    pub message: String,
+
+    /// The file, line, and column that panicked
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+/// The report for the most recent panic, written by the panic hook installed by [`catch`]
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+
+/// Runs `f`, returning a report instead of unwinding if it panics. The default panic message is
+/// not printed, the caller is expected to print the report instead
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, PanicReport> {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+        let report = PanicReport {
+            message,
+            location: info.location().map(|l| l.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+        if let Ok(mut last) = LAST_PANIC.lock() {
+            *last = Some(report);
+        }
+    }));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    panic::set_hook(previous);
+
+    result.map_err(|_| {
+        let report = LAST_PANIC.lock().ok().and_then(|mut last| last.take());
+        report.unwrap_or_else(|| PanicReport {
+            message: "unknown panic".to_owned(),
+            location: None,
+            backtrace: String::new(),
+        })
+    })
+}
+
+/// Percent encodes `s` for use in a URL query
+fn encode_query(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+impl PanicReport {
+    /// A link that opens a new issue with the title and body filled in from this report. The
+    /// backtrace is left out, since it would make the link too long for most browsers
+    pub fn issue_link(&self) -> String {
+        let title = format!("Internal verifier error: {}", self.message);
+        let body = format!(
+            "nova-verifier {} panicked at {}:\n\n```\n{}\n```\n\nThe config that caused this:\n\n```toml\n\n```\n",
+            env!("CARGO_PKG_VERSION"),
+            self.location.as_deref().unwrap_or("an unknown location"),
+            self.message
+        );
+        format!(
+            "{ISSUE_URL}?title={}&body={}",
+            encode_query(&title),
+            encode_query(&body)
+        )
+    }
+
+    /// The diagnostics that describe this report
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let diagnostic = |level, message: String| Diagnostic {
+            level,
+            message,
+            code: None,
+            spans: Vec::new(),
+        };
+        let location = self.location.as_deref().unwrap_or("an unknown location");
+        vec![
+            diagnostic(
+                Level::Error,
+                format!("internal verifier error: {}", self.message),
+            ),
+            diagnostic(
+                Level::Note,
+                format!(
+                    "this is a bug in the verifier, not in your config. It panicked at {location}, with backtrace:\n{}",
+                    self.backtrace.trim_end()
+                ),
+            ),
+            diagnostic(
+                Level::Help,
+                format!(
+                    "please report it along with the config that caused it: {}",
+                    self.issue_link()
+                ),
+            ),
+        ]
+    }
+}
+
+/// Checks that `bytes` decode back into `lower`, the config they were encoded from. The firmware
+/// only ever sees the bytes, so a mismatch means that what was verified is not what will fly.
+/// A mismatch is a bug in the verifier or the encoding, and is emitted as an error to `context`
+pub(crate) fn check_round_trip(lower: &index::ConfigFile, bytes: &[u8], context: &mut Context) {
+    let problem = match ncf::decode(bytes) {
+        Ok((_, decoded)) if decoded == *lower => return,
+        Ok(_) => "decodes to a different config than the one that was verified".to_owned(),
+        Err(err) => format!("cannot be decoded: {err}"),
+    };
+    context
+        .error(format!(
+            "internal verifier error: the encoded config {problem}"
+        ))
+        .code(ErrorCode::InternalError)
+        .emit();
+    context
+        .note("this is a bug in the verifier, not in your config. Do not fly the encoded config")
+        .emit();
+    context
+        .help(format!(
+            "please report it along with the config that caused it: {ISSUE_URL}"
+        ))
+        .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use super::{catch, check_round_trip, encode_query};
+    use crate::{ncf, Session};
+
+    #[test]
+    fn catches_panics() {
+        assert_eq!(catch(|| 5).unwrap(), 5);
+        let report = catch(|| -> u8 { panic!("index {} out of range", 3) }).unwrap_err();
+        assert_eq!(report.message, "index 3 out of range");
+        assert!(report.location.unwrap().contains("internal.rs"));
+        assert_eq!(encode_query("a b&c=é"), "a%20b%26c%3D%C3%A9");
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = crate::presets::Preset::DualDeploy.source().to_owned();
+        let mut session = Session::new();
+        let (_, lower, _) =
+            crate::load_config(&mut session, source, "rocket.toml".to_owned()).unwrap();
+        let bytes = ncf::encode(&lower).unwrap();
+
+        let mut context = session.testing("");
+        check_round_trip(&lower, &bytes, &mut context);
+        assert!(!context.has_error());
+
+        let mut other = lower.clone();
+        other.states.pop();
+        check_round_trip(&other, &bytes, &mut context);
+        check_round_trip(&lower, &bytes[..bytes.len() - 1], &mut context);
+        let errors = context.end_phase_and_emit().unwrap_err();
+        let errors: Vec<_> = errors.iter().filter(|d| d.level == Level::Error).collect();
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,281 @@
+//! JSON front end.
+//!
+//! Configs can be written in JSON with exactly the same structure as the toml format, which is
+//! convenient for tools that generate them. `serde_json` does not expose where values came from,
+//! so this is a small parser that records the byte span of every value in a [`Node`].
+
+use crate::tree::{Error, Node, Value};
+
+/// Parses `source` as a single JSON value
+pub fn parse(source: &str) -> Result<Node, Error> {
+    let mut parser = Parser {
+        source,
+        bytes: source.as_bytes(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    let node = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.bytes.len() {
+        return Err(parser.error("trailing characters after the end of the document"));
+    }
+    Ok(node)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// An error spanning the character at the current position
+    fn error(&self, message: impl Into<String>) -> Error {
+        let end = self.source[self.pos..]
+            .chars()
+            .next()
+            .map_or(self.pos, |c| self.pos + c.len_utf8());
+        Error::new(message, (self.pos, end))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", byte as char)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, Error> {
+        let start = self.pos;
+        let value = match self.peek() {
+            Some(b'{') => self.object()?,
+            Some(b'[') => self.array()?,
+            Some(b'"') => Value::String(self.string()?),
+            Some(b't') => self.keyword("true", Value::Bool(true))?,
+            Some(b'f') => self.keyword("false", Value::Bool(false))?,
+            Some(b'n') => self.keyword("null", Value::Null)?,
+            Some(b'-' | b'0'..=b'9') => self.number()?,
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of file")),
+        };
+        Ok(Node::new((start, self.pos), value))
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, Error> {
+        if self.source[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, Error> {
+        self.expect(b'{')?;
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Table(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key_start = self.pos;
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(Error::new(
+                    format!("duplicate key `{key}`"),
+                    (key_start, self.pos),
+                ));
+            }
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Table(entries));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.source[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.pos += 1;
+                            s.push(self.unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(escaped);
+                    self.pos += 1;
+                }
+                c if (c as u32) < 0x20 => {
+                    return Err(self.error("control characters must be escaped in strings"))
+                }
+                c => {
+                    s.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Parses the hex digits of a `\u` escape, including a following low surrogate if needed
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let start = self.pos - 2;
+        let hex = |p: &mut Self| -> Result<u32, Error> {
+            let digits = p
+                .source
+                .get(p.pos..p.pos + 4)
+                .and_then(|d| u32::from_str_radix(d, 16).ok())
+                .ok_or_else(|| p.error("expected 4 hex digits"))?;
+            p.pos += 4;
+            Ok(digits)
+        };
+        let high = hex(self)?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.source[self.pos..].starts_with("\\u") {
+                return Err(Error::new("unpaired surrogate", (start, self.pos)));
+            }
+            self.pos += 2;
+            let low = hex(self)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(Error::new("unpaired surrogate", (start, self.pos)));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| Error::new("invalid unicode escape", (start, self.pos)))
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        let mut is_float = false;
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => is_float = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let text = &self.source[start..self.pos];
+        let invalid = || Error::new(format!("invalid number `{text}`"), (start, self.pos));
+        if !is_float {
+            if let Ok(i) = text.parse::<i64>() {
+                return Ok(Value::Integer(i));
+            }
+        }
+        text.parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Value::Float)
+            .ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::tree::Value;
+
+    #[test]
+    fn parse_with_spans() {
+        let source = r#"{ "a": [1, -2.5e1, "x\né"], "b": { "c": null, "d": true } }"#;
+        let node = parse(source).unwrap();
+        let entries = match &node.value {
+            Value::Table(entries) => entries,
+            _ => panic!(),
+        };
+        assert_eq!(node.span, (0, source.len()));
+        let a = &entries[0].1;
+        assert_eq!(&source[a.span.0..a.span.1], r#"[1, -2.5e1, "x\né"]"#);
+        match &a.value {
+            Value::Array(items) => {
+                assert_eq!(items[0].value, Value::Integer(1));
+                assert_eq!(items[1].value, Value::Float(-25.0));
+                assert_eq!(&source[items[1].span.0..items[1].span.1], "-2.5e1");
+                assert_eq!(items[2].value, Value::String("x\né".to_owned()));
+            }
+            _ => panic!(),
+        }
+
+        for (bad, at) in [
+            ("{\"a\": }", 6),
+            ("[1, 2", 5),
+            ("{\"a\": 1, \"a\": 2}", 9),
+            ("\"abc", 4),
+            ("1 2", 2),
+        ] {
+            assert_eq!(parse(bad).unwrap_err().span.unwrap().0, at, "{bad}");
+        }
+    }
+}
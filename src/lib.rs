@@ -2,79 +2,571 @@
 // In many places errors are emitted to a context, so we return `Result<_, ()>`. This is fine
 #![allow(clippy::result_unit_err)]
 
+// The stable API, which only changes with a major version. Most consumers only need `prelude`
+pub mod diagnostics;
 pub mod error;
-pub mod lower;
+pub mod limits;
+pub mod ncf;
+pub mod prelude;
 pub mod upper;
 
+// Used by the `nova-verifier` binary and the integration tests. These are not part of the stable
+// API, and change whenever the verifier needs them to
+#[doc(hidden)]
+pub mod analyze;
+#[doc(hidden)]
+pub mod annotate;
+#[doc(hidden)]
+pub mod approval;
+#[doc(hidden)]
+pub mod complexity;
+#[doc(hidden)]
+pub mod consumed;
+#[doc(hidden)]
+pub mod crosscheck;
+#[doc(hidden)]
+pub mod decompile;
+#[doc(hidden)]
+pub mod diff;
+#[doc(hidden)]
+pub mod hardware;
+#[doc(hidden)]
+pub mod inspect;
+#[doc(hidden)]
+pub mod internal;
+#[doc(hidden)]
+pub mod lint;
+#[doc(hidden)]
+#[cfg(any(test, feature = "mock-fc"))]
+pub mod mock_fc;
+#[doc(hidden)]
+pub mod offline;
+#[doc(hidden)]
+pub mod overlay;
+#[doc(hidden)]
+pub mod overrides;
+#[doc(hidden)]
+pub mod presets;
+#[doc(hidden)]
+pub mod protocol;
+#[doc(hidden)]
+pub mod registry;
+#[doc(hidden)]
+pub mod sim;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod watch;
+#[doc(hidden)]
+pub mod workspace;
+
+mod abort_paths;
+mod abort_reasons;
+mod bandwidth;
+mod codegen;
+mod conflict;
+mod constants;
+mod deprecation;
+mod docs;
+mod duration;
+mod export;
+mod fault_handling;
+mod ground_test;
+mod include;
+mod json;
+mod loops;
+mod lower;
+mod mission_events;
+mod patch;
+mod path_policy;
+mod pyro_usage;
+mod requires;
+mod ron;
+mod schema;
+mod self_test;
+mod size;
+mod spanned;
+mod spelling;
+mod stages;
+mod strict_keys;
+mod templates;
+mod tick;
+mod timeline;
+mod tree;
+#[cfg(feature = "yaml")]
+mod yaml;
+
 use codemap_diagnostic::{Diagnostic, Level};
 pub use error::*;
 use log::*;
+use nova_software_common::index;
+use thiserror::Error;
+
+/// Intermediate artifacts that can be written while verifying, in addition to the encoded config.
+/// Request them with [`Session::add_emit`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EmitKind {
+    /// The parsed config after the upper phase as JSON, see [`upper::to_json`]
+    UpperJson,
+
+    /// A delta patch from the config set with [`Session::set_patch_base`] to the new config,
+    /// see [`patch`]
+    Patch,
+
+    /// Markdown documentation of the config language for the session's target,
+    /// see [`docs::language_docs`]
+    LangDocs,
+
+    /// The reason code of every abort as JSON, see [`abort_reasons::to_json`]
+    AbortReasons,
+
+    /// The pre-launch self test encoded for the flight computer, see [`self_test::encode`]
+    SelfTest,
+
+    /// The mission events encoded for the flight computer, see [`mission_events::encode`]
+    Schedule,
+
+    /// The sensor fallbacks encoded for the flight computer, see [`fault_handling::encode`]
+    FaultHandling,
+
+    /// The declared tick rate encoded for the flight computer, see [`tick::encode`]
+    TickRate,
+
+    /// The state machine as a Graphviz digraph, see [`export::dot`]
+    Dot,
+
+    /// A JSON Schema of the config language for the session's target, for editors,
+    /// see [`schema::json_schema`]
+    JsonSchema,
+
+    /// A C header defining the index and name of each state, for ground support equipment,
+    /// see [`codegen::c_header`]
+    CHeader,
+}
+
+impl EmitKind {
+    /// The extension used for this kind when no path is given, replacing the output's extension
+    pub fn default_extension(self) -> &'static str {
+        match self {
+            EmitKind::UpperJson => "upper.json",
+            EmitKind::Patch => "patch",
+            EmitKind::LangDocs => "lang.md",
+            EmitKind::AbortReasons => "aborts.json",
+            EmitKind::SelfTest => "selftest",
+            EmitKind::Schedule => "schedule",
+            EmitKind::FaultHandling => "faults",
+            EmitKind::TickRate => "tick",
+            EmitKind::Dot => "dot",
+            EmitKind::JsonSchema => "schema.json",
+            EmitKind::CHeader => "h",
+        }
+    }
+}
+
+/// Writes an emitted artifact to `path`, emitting an error to `context` on failure
+fn write_artifact(context: &mut Context, path: &str, contents: impl AsRef<[u8]>) {
+    if let Err(err) = std::fs::write(path, contents) {
+        context
+            .error(format!("failed to write to file `{path}`: {err:?}"))
+            .code(ErrorCode::WriteFailed)
+            .emit();
+    }
+}
+
+/// Ends the current phase of `context`, appending its diagnostics to `all_diagnostics`, or
+/// returning them as `phase` if the phase failed
+fn end_phase(
+    context: &mut Context,
+    all_diagnostics: &mut Vec<Diagnostic>,
+    phase: fn(Vec<Diagnostic>) -> VerifyError,
+) -> Result<(), VerifyError> {
+    let warnings = context.end_phase_and_emit().map_err(phase)?;
+    all_diagnostics.extend(warnings);
+    Ok(())
+}
+
+/// Runs the upper and lower phases on the file in `context`, appending non fatal diagnostics to
+/// `all_diagnostics`.
+///
+/// Returns both representations of the config, or an error for the first phase that failed
+pub(crate) fn lower_phases(
+    context: &mut Context,
+    all_diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(upper::ConfigFile, index::ConfigFile), VerifyError> {
+    if let Some(path) = context.session().emit_path(EmitKind::LangDocs) {
+        let path = path.to_owned();
+        let docs = docs::language_docs(context.session().target());
+        write_artifact(context, &path, docs);
+    }
+    if let Some(path) = context.session().emit_path(EmitKind::JsonSchema) {
+        let path = path.to_owned();
+        let schema = schema::json_schema(context.session().target());
+        write_artifact(context, &path, schema);
+    }
+
+    let mut mid = upper::verify(context)?;
+    end_phase(context, all_diagnostics, VerifyError::Parse)?;
+
+    stages::expand(&mut mid, context);
+    deprecation::rewrite(&mut mid, context);
+    templates::expand(&mut mid, context);
+    lint::configure(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Parse)?;
+    trace!("Upper verify: {mid:#?}");
+
+    if let Some(path) = context.session().emit_path(EmitKind::UpperJson) {
+        let path = path.to_owned();
+        write_artifact(context, &path, upper::to_json(&mid));
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    //let s = toml::to_string(&mid).unwrap();
+    //trace!("What toml would be: {s}");
+
+    let lower = lower::verify(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lower)?;
+    let lower = lower.unwrap();
+    trace!("Lower verify: {lower:#?}");
+
+    let steps = self_test::lower(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lower)?;
+    let steps = steps.unwrap();
+    if let Some(path) = context.session().emit_path(EmitKind::SelfTest) {
+        let path = path.to_owned();
+        match self_test::encode(&steps) {
+            Ok(bytes) => write_artifact(context, &path, bytes),
+            Err(err) => context
+                .error(format!("failed to encode the self test: {err}"))
+                .code(ErrorCode::ArtifactEncodingFailed)
+                .emit(),
+        }
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    let schedule = mission_events::lower(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lower)?;
+    let schedule = schedule.unwrap();
+    if let Some(path) = context.session().emit_path(EmitKind::Schedule) {
+        let path = path.to_owned();
+        match mission_events::encode(&schedule) {
+            Ok(bytes) => write_artifact(context, &path, bytes),
+            Err(err) => context
+                .error(format!("failed to encode the mission events: {err}"))
+                .code(ErrorCode::ArtifactEncodingFailed)
+                .emit(),
+        }
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    let faults = fault_handling::lower(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lower)?;
+    let faults = faults.unwrap();
+    if let Some(path) = context.session().emit_path(EmitKind::FaultHandling) {
+        let path = path.to_owned();
+        match fault_handling::encode(&faults) {
+            Ok(bytes) => write_artifact(context, &path, bytes),
+            Err(err) => context
+                .error(format!("failed to encode the fault handling: {err}"))
+                .code(ErrorCode::ArtifactEncodingFailed)
+                .emit(),
+        }
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    let timing = tick::lower(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lower)?;
+    let timing = timing.unwrap();
+    if let Some(path) = context.session().emit_path(EmitKind::TickRate) {
+        let path = path.to_owned();
+        match timing.as_ref().map(tick::encode) {
+            Some(Ok(bytes)) => write_artifact(context, &path, bytes),
+            Some(Err(err)) => context
+                .error(format!("failed to encode the tick rate: {err}"))
+                .code(ErrorCode::ArtifactEncodingFailed)
+                .emit(),
+            None => {
+                context
+                    .error("cannot emit the tick rate, the config does not declare one")
+                    .code(ErrorCode::MissingTickRate)
+                    .emit();
+                context
+                    .help("add `tick_rate = ...` to the top of the file")
+                    .emit();
+            }
+        }
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    lint::check(&mid, context);
+    end_phase(context, all_diagnostics, VerifyError::Lint)?;
+
+    if let Some(path) = context.session().emit_path(EmitKind::AbortReasons) {
+        let path = path.to_owned();
+        write_artifact(context, &path, abort_reasons::to_json(&mid));
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    if let Some(path) = context.session().emit_path(EmitKind::Dot) {
+        let path = path.to_owned();
+        let names = decompile::Names::from_source(&mid, &lower)
+            .unwrap_or_else(|| decompile::Names::generic(&lower));
+        write_artifact(context, &path, export::dot(&lower, &names));
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    Ok((mid, lower))
+}
+
+/// Verifies the given toml file without encoding it, returning both the parsed and the lowered
+/// config. This is for tools that need to relate the lowered config back to names in the source.
+///
+/// Returns `Ok((upper, lower, diagnostics))` on success, or the phase that failed with its
+/// diagnostics.
+pub fn load_config(
+    session: &mut Session,
+    toml: String,
+    file_path: String,
+) -> Result<(upper::ConfigFile, index::ConfigFile, Vec<Diagnostic>), VerifyError> {
+    let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut context = session.add_file(toml, file_path);
+    let (mid, lower) = lower_phases(&mut context, &mut all_diagnostics)?;
+    Ok((mid, lower, all_diagnostics))
+}
 
 /// Verifies the given toml file and converts it to a postcard binary format sutiable for the
-/// rocket.
+/// rocket, inside a [`ncf`] container.
 ///
-/// Returns `Ok((bytes, diagnostics))` on success, or `Err(diagnostics)` on failure.
+/// Returns `Ok((bytes, diagnostics))` on success, or the phase that failed with its diagnostics.
 pub fn verify_inner(
     session: &mut Session,
     toml: String,
     file_path: String,
-) -> Result<(Vec<u8>, Vec<Diagnostic>), Vec<Diagnostic>> {
+) -> Result<(Vec<u8>, Vec<Diagnostic>), VerifyError> {
     let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
-    let mut context = session.add_file(toml, file_path).unwrap();
+    let mut context = session.add_file(toml, file_path);
+    let (mid, lower, bytes) = encode_phases(&mut context, &mut all_diagnostics)?;
+    session.set_summary(summary::Summary::new(&mid, &lower, bytes.len()));
+    Ok((bytes, all_diagnostics))
+}
 
-    let mid = upper::verify(&mut context);
-    let warnings = context.end_phase_and_emit()?;
-    let mid = mid.unwrap();
-    all_diagnostics.extend(warnings);
-    trace!("Upper verify: {mid:#?}");
+/// A config verified by [`verify_str`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifiedConfig {
+    /// The config encoded inside a [`ncf`] container, ready to upload
+    pub bytes: Vec<u8>,
 
-    //let s = toml::to_string(&mid).unwrap();
-    //trace!("What toml would be: {s}");
+    /// The lowered config that `bytes` encodes
+    pub config: index::ConfigFile,
 
-    let lower = lower::verify(mid, &mut context);
-    let warnings = context.end_phase_and_emit()?;
-    let lower = lower.unwrap();
-    all_diagnostics.extend(warnings);
-    trace!("Lower verify: {lower:#?}");
+    /// The warnings, notes, and helps emitted while verifying
+    pub diagnostics: Vec<LocatedDiagnostic>,
+}
+
+/// Why [`verify_str`] failed
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{phase}")]
+pub struct VerifyFailure {
+    /// The phase that failed, with the diagnostics it emitted
+    pub phase: VerifyError,
+
+    /// Every diagnostic emitted while verifying, including warnings from earlier phases
+    pub diagnostics: Vec<LocatedDiagnostic>,
+}
+
+/// Verifies the config `toml` and encodes it, without reading or writing any files or printing
+/// anything, for tools that embed the verifier such as a ground station. `name` is the file name
+/// that diagnostics point at, and selects the input format by its extension like a path would.
+///
+/// Diagnostics are returned with their spans resolved to lines and columns. Use [`verify_inner`]
+/// with a [`Session`] to set verification options
+pub fn verify_str(toml: &str, name: &str) -> Result<VerifiedConfig, VerifyFailure> {
+    let mut session = Session::new();
+    session.set_quiet(true);
+    let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut context = session.add_file(toml.to_owned(), name.to_owned());
+    let result = encode_phases(&mut context, &mut all_diagnostics);
+    if let Err(phase) = &result {
+        all_diagnostics.extend(phase.diagnostics().iter().cloned());
+    }
+    let diagnostics = all_diagnostics.iter().map(|d| session.locate(d)).collect();
+    match result {
+        Ok((_, config, bytes)) => Ok(VerifiedConfig {
+            bytes,
+            config,
+            diagnostics,
+        }),
+        Err(phase) => Err(VerifyFailure { phase, diagnostics }),
+    }
+}
+
+/// Verifies only the state called `state` in `toml`, without encoding anything, for editors and
+/// watch modes that re-check one state of a large config as it is edited.
+/// See [`lower::verify_state`] for what is left out
+pub fn verify_state(
+    session: &mut Session,
+    toml: String,
+    file_path: String,
+    state: &str,
+) -> Result<Vec<Diagnostic>, VerifyError> {
+    let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
+    let context = &mut session.add_file(toml, file_path);
+
+    let mut mid = upper::verify(context)?;
+    end_phase(context, &mut all_diagnostics, VerifyError::Parse)?;
+
+    stages::expand(&mut mid, context);
+    deprecation::rewrite(&mut mid, context);
+    templates::expand(&mut mid, context);
+    lint::configure(&mid, context);
+    end_phase(context, &mut all_diagnostics, VerifyError::Parse)?;
+
+    let _ = lower::verify_state(&mid, state, context);
+    end_phase(context, &mut all_diagnostics, VerifyError::Lower)?;
+    Ok(all_diagnostics)
+}
+
+/// Runs every phase on the file in `context` through encoding, appending non fatal diagnostics to
+/// `all_diagnostics`.
+///
+/// Returns both representations of the config and the encoded bytes, or an error for the first
+/// phase that failed
+pub(crate) fn encode_phases(
+    context: &mut Context,
+    all_diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(upper::ConfigFile, index::ConfigFile, Vec<u8>), VerifyError> {
+    let (mid, mut lower) = lower_phases(context, all_diagnostics)?;
 
-    let bytes = postcard::to_stdvec(&lower);
-    let warnings = context.end_phase_and_emit()?;
+    if context.session().ground_test() {
+        let replaced = ground_test::disarm(&mut lower);
+        context
+            .note(format!(
+                "ground test build: {replaced} pyro commands turn the beacon on instead. Do not fly this config"
+            ))
+            .emit();
+    }
+
+    let bytes = ncf::encode(&lower);
+    end_phase(context, all_diagnostics, VerifyError::Encode)?;
     let bytes = bytes.unwrap();
-    all_diagnostics.extend(warnings);
-    trace!("Postcard message is {} bytes", bytes.len());
+    trace!("Encoded config is {} bytes", bytes.len());
 
-    Ok((bytes, all_diagnostics))
+    internal::check_round_trip(&lower, &bytes, context);
+    end_phase(context, all_diagnostics, VerifyError::Encode)?;
+
+    let over_budget = context
+        .session()
+        .flash_budget()
+        .filter(|&budget| bytes.len() > budget);
+    if over_budget.is_some() || context.session().emit_stats() {
+        let report = size::measure(&mid, &lower, context);
+        if let Some(budget) = over_budget {
+            context
+                .error(format!(
+                    "encoded config is {} bytes, which exceeds the flash budget of {budget} bytes",
+                    bytes.len()
+                ))
+                .code(ErrorCode::FlashBudgetExceeded)
+                .emit();
+            report.emit_largest(5, context);
+        }
+        if context.session().emit_stats() {
+            report.emit_notes(context);
+            duration::emit_notes(&mid, context);
+            bandwidth::emit_notes(&mid, context);
+            timeline::emit_notes(&mid, context);
+            complexity::emit_notes(&mid, context);
+        }
+        end_phase(context, all_diagnostics, VerifyError::Encode)?;
+    }
+
+    if let Some(path) = context.session().emit_path(EmitKind::Patch) {
+        let path = path.to_owned();
+        patch::write_patch(context, &path, &bytes);
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    if let Some(path) = context.session().emit_path(EmitKind::CHeader) {
+        let path = path.to_owned();
+        let names = decompile::Names::from_source(&mid, &lower)
+            .unwrap_or_else(|| decompile::Names::generic(&lower));
+        // The bytes were just encoded, so their header is always valid
+        let (header, _) = ncf::split(&bytes).unwrap();
+        match codegen::c_header(&lower, &names, &header) {
+            Ok(text) => write_artifact(context, &path, text),
+            Err(err) => context
+                .error(format!("failed to write the C header: {err}"))
+                .code(ErrorCode::ArtifactEncodingFailed)
+                .emit(),
+        }
+        end_phase(context, all_diagnostics, VerifyError::Artifact)?;
+    }
+
+    Ok((mid, lower, bytes))
 }
 
 /// Loads a toml file at the given path, verifies it, and writes the encoded contents to
 /// `dst_path`, returning the diagnostics that the transformation produced.
+/// Verification options such as the flash budget and included files are taken from `session`.
 ///
 /// Returns `Err(...)` if any step fails without writing to `dst_path`. If Ok(...) is returned
 /// then the encoded config file has been written to `dst_path`, and all notes, warnings and helps
 /// encountered while converting will be placed in the returned Vector.
-pub fn verify_file(src_path: String, dst_path: String) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
-    let mut session = Session::new();
+pub fn verify_file(
+    session: &mut Session,
+    src_path: String,
+    dst_path: String,
+) -> Result<Vec<Diagnostic>, VerifyError> {
     let toml = match std::fs::read_to_string(&src_path) {
         Ok(t) => t,
         Err(err) => {
-            return Err(vec![Diagnostic {
+            return Err(VerifyError::Io(vec![Diagnostic {
                 level: Level::Error,
                 message: format!("failed to read file `{src_path}`: {err:?}"),
-                code: None,
+                code: Some(ErrorCode::ReadFailed.name().to_owned()),
                 spans: vec![],
-            }]);
+            }]));
         }
     };
 
-    let (bytes, mut diags) = verify_inner(&mut session, toml, src_path)?;
+    let (bytes, mut diags) = verify_inner(session, toml, src_path)?;
     if let Err(err) = std::fs::write(&dst_path, bytes) {
         diags.push(Diagnostic {
             level: Level::Error,
             message: format!("failed to write to file `{dst_path}`: {err:?}"),
-            code: None,
+            code: Some(ErrorCode::WriteFailed.name().to_owned()),
             spans: vec![],
         });
-        return Err(diags);
+        return Err(VerifyError::Io(diags));
     }
     Ok(diags)
 }
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    #[test]
+    fn verify_str() {
+        let config = "[[states]]\nname = \"Flight\"\n\n[[states.checks]]\nname = \"Apogee\"\ncheck = \"apogee\"\nflag = \"set\"\ntransition = \"Fligth\"\n";
+        let failure = super::verify_str(config, "gui.toml").unwrap_err();
+        assert!(matches!(failure.phase, super::VerifyError::Lower(_)));
+        let error = &failure.diagnostics[0];
+        assert_eq!(error.level, Level::Error);
+        assert_eq!(
+            (error.spans[0].file.as_str(), error.spans[0].line),
+            ("gui.toml", 8)
+        );
+        let fix = failure
+            .diagnostics
+            .iter()
+            .find_map(|d| d.replacement.as_deref());
+        assert_eq!(fix, Some("\"Flight\""));
+
+        let verified = super::verify_str(&config.replace("Fligth", "Flight"), "gui.toml").unwrap();
+        assert_eq!(verified.config.states.len(), 1);
+        assert_eq!(
+            crate::ncf::decode(&verified.bytes).unwrap().1,
+            verified.config
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! The limits that the verifier enforces on a config file, such as how many states it may have.
+//!
+//! Limits depend on the flight computer the config is built for, so they are selected by
+//! [`Target`], or by the board profile the session was given with [`crate::Session::set_board`].
+//! Library users can query them with [`Target::limits`] to show how much headroom a config has
+//! left.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nova_software_common as common;
+
+use common::index::State;
+use heapless::Vec;
+use serde::Deserialize;
+
+use crate::hardware::Board;
+
+/// A flight computer that configs can be built for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Target {
+    /// The Nova flight computer
+    #[default]
+    Nova,
+}
+
+impl Target {
+    pub const ALL: [Target; 1] = [Target::Nova];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Nova => "nova",
+        }
+    }
+
+    /// The board that configs for this target are verified against unless another is selected
+    pub fn board(self) -> Board {
+        match self {
+            Target::Nova => Board::default(),
+        }
+    }
+
+    pub fn limits(self) -> Limits {
+        self.board().profile().limits
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Target::ALL
+            .into_iter()
+            .find(|t| t.name() == s)
+            .ok_or_else(|| format!("unknown target `{s}`"))
+    }
+}
+
+/// Returns how many checks and commands a single state can hold in the encoded format
+pub(crate) fn format_capacity() -> (usize, usize) {
+    let state = State::new(Vec::new(), Vec::new(), None);
+    (state.checks.capacity(), state.commands.capacity())
+}
+
+fn format_states() -> usize {
+    common::MAX_STATES
+}
+
+fn format_checks() -> usize {
+    format_capacity().0
+}
+
+fn format_commands() -> usize {
+    format_capacity().1
+}
+
+/// The limits enforced when lowering a config for a particular [`Target`] or board. A board
+/// profile that leaves out the number of states, checks, or commands gets the capacity of the
+/// encoded format
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    /// The maximum number of states in a config
+    #[serde(default = "format_states")]
+    pub max_states: usize,
+
+    /// The maximum number of checks in a single state
+    #[serde(default = "format_checks")]
+    pub max_checks_per_state: usize,
+
+    /// The maximum number of commands in a single state
+    #[serde(default = "format_commands")]
+    pub max_commands_per_state: usize,
+
+    /// The rates the logger supports, which are the only values a `data_rate` command may set
+    pub data_rates: DataRates,
+
+    /// The most telemetry the radio link can carry, in bytes per second
+    pub link_budget: u32,
+
+    /// How many pyro channels the board has, numbered from 1
+    pub pyro_channels: u8,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Target::default().limits()
+    }
+}
+
+/// The rates that a board's logger supports. A profile gives either a range, such as
+/// `data_rates = { min = 1, max = 1000 }`, or a table, such as `data_rates = [1, 10, 100]`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum DataRates {
+    /// Every rate from `min` to `max`, inclusive
+    Range { min: u16, max: u16 },
+
+    /// Only the listed rates, sorted from slowest to fastest
+    Table(std::vec::Vec<u16>),
+}
+
+impl DataRates {
+    pub fn contains(&self, rate: u16) -> bool {
+        match self {
+            DataRates::Range { min, max } => (min..=max).contains(&&rate),
+            DataRates::Table(rates) => rates.contains(&rate),
+        }
+    }
+
+    /// The supported rate closest to `rate`, preferring the slower one on a tie since it is sure
+    /// to fit in the link budget
+    pub fn nearest(&self, rate: u16) -> Option<u16> {
+        match self {
+            DataRates::Range { min, max } if min <= max => Some(rate.clamp(*min, *max)),
+            DataRates::Range { .. } => None,
+            DataRates::Table(rates) => rates.iter().copied().min_by_key(|r| r.abs_diff(rate)),
+        }
+    }
+
+    /// Whether the rates are above 0, and a range is not empty or a table is sorted from slowest
+    /// to fastest
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            DataRates::Range { min, max } => *min > 0 && min <= max,
+            DataRates::Table(rates) => {
+                rates.first().is_some_and(|&r| r > 0) && rates.windows(2).all(|w| w[0] < w[1])
+            }
+        }
+    }
+}
+
+impl fmt::Display for DataRates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataRates::Range { min, max } => write!(f, "{min} to {max}"),
+            DataRates::Table(rates) => {
+                let listed: std::vec::Vec<String> = rates.iter().map(u16::to_string).collect();
+                f.write_str(&listed.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use crate::hardware::Board;
+    use nova_software_common as common;
+
+    #[test]
+    fn limits_fit_in_format() {
+        for target in Target::ALL {
+            assert_eq!(target.name().parse::<Target>().unwrap(), target);
+        }
+        for board in Board::ALL {
+            let limits = board.profile().limits;
+            assert!(limits.max_states <= common::MAX_STATES);
+            assert!(limits.pyro_channels <= crate::hardware::FORMAT_PYRO_CHANNELS);
+            assert!(limits.data_rates.is_valid());
+        }
+    }
+}
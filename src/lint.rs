@@ -0,0 +1,936 @@
+//! Lints that run on a config after it has been lowered successfully.
+//!
+//! These catch configs that are well formed but unsafe or suspicious to fly. They work on the
+//! upper representation so that diagnostics can point at names and values in the source.
+//!
+//! Missions have different risk tolerances, so the level of a warning lint can be changed with
+//! `--allow`, `--warn`, and `--deny` on the command line, or in the config's `[lints]` table:
+//!
+//! ```toml
+//! [lints]
+//! infinite_loop = "deny"
+//! abort_path = "allow"
+//! ```
+//!
+//! The command line takes precedence over the config. Lints that are errors by default cannot be
+//! lowered this way, since they can only be overridden with a justification, see
+//! [`crate::overrides`].
+
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use codemap_diagnostic::Level;
+
+use crate::{spanned::SpannedValue, upper, Context, ErrorCode, Span};
+
+/// A description of a lint that [`check`] runs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub name: &'static str,
+
+    /// The level of the diagnostics the lint emits
+    pub level: Level,
+
+    /// The message of the diagnostics the lint emits, with the parts that change written as
+    /// `{placeholder}`
+    pub message: &'static str,
+    pub description: &'static str,
+}
+
+/// Every lint that [`check`] runs
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "pre_launch_pyro",
+        level: Level::Error,
+        message: "`{channel}` is fired before launch is detected",
+        description: "A pyro channel is fired in the default state, or in a state that can be entered before launch is detected",
+    },
+    Lint {
+        name: "mission_duration",
+        level: Level::Warning,
+        message: "the flight can take {seconds} seconds after launch, longer than the mission duration of {limit} seconds",
+        description: "The flight can take longer than `--mission-duration` seconds after launch to reach a final state",
+    },
+    Lint {
+        name: "repeated_threshold",
+        level: Level::Warning,
+        message: "`{number}` is repeated in {count} checks",
+        description: "The same number is written as a threshold in three or more checks. The warning suggests moving it into a `[constants]` entry that each check uses",
+    },
+    Lint {
+        name: "telemetry_bandwidth",
+        level: Level::Error,
+        message: "state `{state}` needs {needed} bytes per second of telemetry, but the `{target}` radio link carries at most {budget}",
+        description: "A `data_rate` needs more bandwidth than the target's radio link carries, given `radio.packet_bytes`",
+    },
+    Lint {
+        name: "simultaneous_pyro",
+        level: Level::Warning,
+        message: "{channels} are fired at the same time in state `{state}`",
+        description: "Two pyro channels in a state are fired with the same `delay`",
+    },
+    Lint {
+        name: "simultaneous_command",
+        level: Level::Warning,
+        message: "`{object}` is set twice at the same time in state `{state}`",
+        description: "Two commands in a state set the same object with the same `delay`",
+    },
+    Lint {
+        name: "strict_floats",
+        level: Level::Warning,
+        message: "decimal value written as the integer `{literal}`",
+        description: "A decimal value is written as an integer, such as `delay = 1`. Only checked with `--strict-floats`",
+    },
+    Lint {
+        name: "tick_resolution",
+        level: Level::Warning,
+        message: "{duration} is not a whole number of ticks",
+        description: "A duration is shorter than one tick of `tick_rate`, or is not a whole number of ticks. Only checked when the config has `tick_rate`",
+    },
+    Lint {
+        name: "missing_fallback",
+        level: Level::Warning,
+        message: "sensor `{sensor}` has no fallback",
+        description: "A check reads a sensor that has no fallback in `[fault_handling]`. Only checked when the config has `[fault_handling]`",
+    },
+    Lint {
+        name: "deprecated",
+        level: Level::Warning,
+        message: "{item} `{name}` is deprecated",
+        description: "A check kind has been renamed. The old name is accepted until the next schema version, see the deprecated check kinds",
+    },
+    Lint {
+        name: "unreachable_state",
+        level: Level::Warning,
+        message: "state `{state}` is unreachable",
+        description: "No transition, abort, or timeout leads to a state from the default state, usually because of a typo in a transition",
+    },
+    Lint {
+        name: "complexity",
+        level: Level::Warning,
+        message: "complexity metric `{metric}` is {value}, above the limit of {max}",
+        description: "A complexity metric is above its ceiling. Only checked with `--complexity-limit`",
+    },
+    Lint {
+        name: "path_policy",
+        level: Level::Warning,
+        message: "transition from `{from}` to `{to}` skips {skipped}",
+        description: "A transition, abort, or timeout skips a group of states in a `[[path_policy]]`, such as going straight from boost to main without passing coast or drogue",
+    },
+    Lint {
+        name: "unused_check",
+        level: Level::Warning,
+        message: "check `{name}` is never used",
+        description: "A check in the `[checks]` table is not added to any state with `use_check`. The warning suggests removing it",
+    },
+    Lint {
+        name: "duplicate_check_name",
+        level: Level::Warning,
+        message: "check `{name}` is declared twice in state `{state}`",
+        description: "Two checks in a state have the same name, so diagnostics, summaries, and `requires` cannot tell them apart",
+    },
+    Lint {
+        name: "inert_check",
+        level: Level::Warning,
+        message: "check `{name}` has no effect",
+        description: "A check neither transitions nor aborts, and no other check `requires` it, so it does nothing when it trips",
+    },
+    Lint {
+        name: "abort_path",
+        level: Level::Warning,
+        message: "state `{state}` has no path to an abort safe state",
+        description: "A state that can be entered after launch has no path through checks and timeouts to an `abort_safe` state, so the flight can get stuck in it. Only checked when some state is `abort_safe`",
+    },
+    Lint {
+        name: "infinite_loop",
+        level: Level::Warning,
+        message: "{states} can loop forever without reaching an abort safe state",
+        description: "States transition between each other in a loop that passes through no `abort_safe` state, so the flight computer can cycle through them forever",
+    },
+    Lint {
+        name: "unused_constant",
+        level: Level::Warning,
+        message: "constant `{name}` is never used",
+        description: "A value in the `[constants]` table is not used by any expression. The warning suggests removing it",
+    },
+    Lint {
+        name: "refired_pyro",
+        level: Level::Warning,
+        message: "`{channel}` is fired again in state `{state}` without being disarmed",
+        description: "A pyro channel is fired in a state that can be entered after another state fired it, with no state in between setting it back to `false`",
+    },
+    Lint {
+        name: "unchecked_continuity",
+        level: Level::Warning,
+        message: "`{channel}` is fired in state `{state}` without checking its continuity first",
+        description: "A pyro channel is fired, but its continuity is not checked in `[self_test]` or in any state that leads to the state that fires it, so a disconnected charge would go unnoticed",
+    },
+    Lint {
+        name: "unused_pyro",
+        level: Level::Warning,
+        message: "`{channel}` is never used",
+        description: "A pyro channel of the board is never fired, disarmed, or checked for continuity, which usually means a charge is wired to the wrong channel",
+    },
+];
+
+/// The level of a lint, set with `--allow`, `--warn`, and `--deny` or in `[lints]`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LintLevel {
+    /// The lint's warnings are not shown
+    Allow,
+
+    /// The lint's diagnostics are warnings
+    Warn,
+
+    /// The lint's warnings are reported as errors
+    Deny,
+}
+
+impl LintLevel {
+    pub const ALL: [LintLevel; 3] = [LintLevel::Allow, LintLevel::Warn, LintLevel::Deny];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+}
+
+impl std::fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintLevel::ALL
+            .into_iter()
+            .find(|l| l.name() == s)
+            .ok_or_else(|| format!("unknown lint level `{s}`"))
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    /// Finds the lint in [`LINTS`] called `s`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(lint) = LINTS.iter().find(|l| l.name == s) {
+            return Ok(*lint);
+        }
+        match crate::spelling::closest(s, LINTS.iter().map(|l| l.name)) {
+            Some(closest) => Err(format!(
+                "unknown lint `{s}`, a lint with a similar name exists: `{closest}`"
+            )),
+            None => Err(format!("unknown lint `{s}`")),
+        }
+    }
+}
+
+/// Checks the `[lints]` table of `config` and the lint levels set on the command line, emitting
+/// errors to `context`, and sets the levels of the table in the session
+pub fn configure(config: &upper::ConfigFile, context: &mut Context) {
+    let mut levels = Vec::new();
+    for (name, level) in &config.lints {
+        let span = Span::from_spanned(context, level);
+        let lint = match name.parse::<Lint>() {
+            Ok(lint) => lint,
+            Err(message) => {
+                context
+                    .error(message)
+                    .code(ErrorCode::UnknownLint)
+                    .set_primary_span(span, "in `[lints]`")
+                    .emit();
+                continue;
+            }
+        };
+        let level = match level.get_ref().parse::<LintLevel>() {
+            Ok(level) => level,
+            Err(message) => {
+                context
+                    .error(message)
+                    .code(ErrorCode::InvalidLintLevel)
+                    .set_primary_span(span, "must be `allow`, `warn`, or `deny`")
+                    .emit();
+                continue;
+            }
+        };
+        if lint.level == Level::Error && level != LintLevel::Deny {
+            context
+                .error(format!(
+                    "`{name}` is deny by default, so it cannot be lowered to `{level}`"
+                ))
+                .code(ErrorCode::LoweredDenyLint)
+                .set_primary_span(span, "set here")
+                .emit();
+            override_help(context);
+            continue;
+        }
+        levels.push((lint.name, level));
+    }
+
+    let lowered: Vec<(&str, LintLevel)> = context
+        .session()
+        .lint_levels()
+        .iter()
+        .filter(|(name, level)| {
+            let lint = LINTS.iter().find(|l| l.name == *name);
+            *level != LintLevel::Deny && lint.is_some_and(|l| l.level == Level::Error)
+        })
+        .copied()
+        .collect();
+    for (name, level) in lowered {
+        context
+            .error(format!(
+                "`{name}` is deny by default, so it cannot be lowered to `{level}`"
+            ))
+            .code(ErrorCode::LoweredDenyLint)
+            .emit();
+        override_help(context);
+    }
+    context.set_config_lint_levels(levels);
+}
+
+fn override_help(context: &mut Context) {
+    context
+        .help("declare an `[[override]]` with a justification to report its errors as warnings")
+        .emit();
+}
+
+/// Runs every lint over `config`, emitting diagnostics to `context`
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    pre_launch_pyros(config, context);
+    crate::duration::check_mission_duration(config, context);
+    repeated_literals(config, context);
+    duplicate_check_names(config, context);
+    inert_checks(config, context);
+    crate::bandwidth::check(config, context);
+    crate::timeline::check(config, context);
+    crate::complexity::check(config, context);
+    crate::fault_handling::check_coverage(config, context);
+    crate::tick::check(config, context);
+    crate::path_policy::check(config, context);
+    crate::abort_paths::check(config, context);
+    crate::loops::check(config, context);
+    crate::pyro_usage::check(config, context);
+    if context.session().strict_floats() {
+        strict_floats(config, context);
+    }
+    crate::overrides::apply(config, context);
+}
+
+/// How many checks can use the same threshold before it is worth naming
+const REPEATED_LITERAL_CHECKS: usize = 3;
+
+/// Warns about checks in the same state that share a name
+fn duplicate_check_names(config: &upper::ConfigFile, context: &mut Context) {
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let checks = &state.checks;
+        for (i, check) in checks.iter().enumerate() {
+            let name = &check.get_ref().name;
+            let first = checks[..i]
+                .iter()
+                .find(|c| c.get_ref().name.get_ref() == name.get_ref());
+            if let Some(first) = first {
+                let first = Span::from_spanned(context, &first.get_ref().name);
+                let span = Span::from_spanned(context, name);
+                context
+                    .warn(format!(
+                        "check `{}` is declared twice in state `{}`",
+                        name.get_ref(),
+                        state.name.get_ref()
+                    ))
+                    .set_primary_span(span, "declared again here")
+                    .span_label(first, "first declared here")
+                    .lint("duplicate_check_name")
+                    .emit();
+            }
+        }
+    }
+}
+
+/// Warns about checks that do nothing when they trip: they neither transition nor abort, and no
+/// check `requires` them
+fn inert_checks(config: &upper::ConfigFile, context: &mut Context) {
+    let required = crate::requires::dependencies(config);
+    let states = config.states.get_ref().iter().map(|s| s.get_ref());
+    for check in states.flat_map(|s| &s.checks).map(|c| c.get_ref()) {
+        let aborts = match check.abort.as_ref().map(|a| a.get_ref()) {
+            Some(upper::AbortTarget::Global(false)) | None => false,
+            Some(_) => true,
+        };
+        let name = check.name.get_ref();
+        if check.transition.is_some()
+            || aborts
+            || required.iter().any(|d| d.requires.get_ref() == name)
+        {
+            continue;
+        }
+        let span = Span::from_spanned(context, &check.name);
+        context
+            .warn(format!("check `{name}` has no effect"))
+            .set_primary_span(span, "neither transitions nor aborts when it trips")
+            .lint("inert_check")
+            .emit();
+        context
+            .help("add a `transition` or `abort`, or remove the check")
+            .emit();
+    }
+}
+
+/// Returns true if `check` transitioning away is how the config detects a launch.
+/// Launch is detected by climbing above an altitude threshold
+pub(crate) fn is_launch_detection(check: &upper::Check) -> bool {
+    let kind = check.check.get_ref();
+    kind == "launch_detected" || kind == "altitude" && check.greater_than.is_some()
+}
+
+/// Every state that `check` can move the flight computer to
+pub(crate) fn check_targets<'a>(
+    config: &'a upper::ConfigFile,
+    check: &'a upper::Check,
+) -> Vec<&'a str> {
+    let mut targets = Vec::new();
+    if let Some(transition) = &check.transition {
+        targets.push(transition.get_ref().as_str());
+    }
+    match check.abort.as_ref().map(|a| a.get_ref()) {
+        Some(upper::AbortTarget::State(name)) => targets.push(name.as_str()),
+        Some(upper::AbortTarget::Detailed(upper::AbortDetails {
+            state: Some(name), ..
+        })) => targets.push(name.as_str()),
+        Some(upper::AbortTarget::Global(true) | upper::AbortTarget::Detailed(_)) => {
+            targets.extend(config.abort_state.as_ref().map(|s| s.get_ref().as_str()))
+        }
+        Some(upper::AbortTarget::Global(false)) | None => {}
+    }
+    targets
+}
+
+/// Finds every state that can be entered before launch is detected, mapped to the state it is
+/// first reached from. The default state maps to `None`
+pub(crate) fn pre_launch_states(config: &upper::ConfigFile) -> HashMap<&str, Option<&str>> {
+    let states: HashMap<&str, &upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| (s.get_ref().name.get_ref().as_str(), s.get_ref()))
+        .collect();
+    let default_state = match &config.default_state {
+        Some(name) => name.get_ref().as_str(),
+        None => config.states.get_ref()[0].get_ref().name.get_ref(),
+    };
+
+    let mut reached = HashMap::from([(default_state, None)]);
+    let mut queue = VecDeque::from([default_state]);
+    while let Some(name) = queue.pop_front() {
+        let state = states[name];
+        let mut next: Vec<&str> = state
+            .checks
+            .iter()
+            .map(|c| c.get_ref())
+            .filter(|c| !is_launch_detection(c))
+            .flat_map(|c| check_targets(config, c))
+            .collect();
+        let timeout = state.timeout.as_ref().map(|t| t.get_ref());
+        next.extend(
+            timeout
+                .and_then(|t| t.transition.as_ref())
+                .map(|t| t.get_ref().as_str()),
+        );
+        for target in next {
+            // Lowering already checked that every target exists
+            if states.contains_key(target) && !reached.contains_key(target) {
+                reached.insert(target, Some(name));
+                queue.push_back(target);
+            }
+        }
+    }
+    reached
+}
+
+/// Powering on the flight computer must never fire a pyro charge, so enabling a pyro channel in
+/// the default state, or in any state reachable from it before launch is detected, is an error
+fn pre_launch_pyros(config: &upper::ConfigFile, context: &mut Context) {
+    let reached = pre_launch_states(config);
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref().as_str();
+        if !reached.contains_key(name) {
+            continue;
+        }
+        for command in state.commands.iter().map(|c| c.get_ref()) {
+            let pyros = [
+                ("pyro1", &command.pyro1),
+                ("pyro2", &command.pyro2),
+                ("pyro3", &command.pyro3),
+            ];
+            for (channel, value) in pyros {
+                let value = match value {
+                    Some(value) if bool::from(*value.get_ref()) => value,
+                    _ => continue,
+                };
+                let span = Span::from_spanned(context, value);
+                let state_span = Span::from_spanned(context, &state.name);
+                context
+                    .error(format!("`{channel}` is fired before launch is detected"))
+                    .set_primary_span(span, "fires a pyro charge")
+                    .span_label(state_span, "in this state")
+                    .lint("pre_launch_pyro")
+                    .emit();
+
+                let mut path = vec![name];
+                while let Some(Some(previous)) = reached.get(path[path.len() - 1]) {
+                    path.push(previous);
+                }
+                let note = if path.len() == 1 {
+                    format!("`{name}` is the default state, which is entered as soon as the flight computer powers on")
+                } else {
+                    path.reverse();
+                    format!(
+                        "`{name}` can be entered from the default state without detecting launch: {}",
+                        path.join(" -> ")
+                    )
+                };
+                context.note(note).emit();
+                context
+                    .help("only fire pyro channels in states entered after an `altitude` check with `greater_than` detects launch")
+                    .emit();
+            }
+        }
+    }
+}
+
+/// The same threshold written out in several checks is easy to update in some places and miss in
+/// others, so warn when a number is repeated in [`REPEATED_LITERAL_CHECKS`] or more checks.
+/// Zero is a common bound rather than a tuned value, so it is never reported
+fn repeated_literals(config: &upper::ConfigFile, context: &mut Context) {
+    // Values in the order they first appear, with the spans of every check that uses them
+    let mut uses: Vec<(f32, Vec<&SpannedValue<f32>>)> = Vec::new();
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        for check in state.checks.iter().map(|c| c.get_ref()) {
+            let mut seen: Vec<f32> = Vec::new();
+            let values = [
+                &check.greater_than,
+                &check.less_than,
+                &check.upper_bound,
+                &check.lower_bound,
+            ];
+            for value in values.into_iter().flatten() {
+                let number = *value.get_ref();
+                // Values that come from a constant are already named
+                let literal = crate::lower::float_literal(value, context);
+                if number == 0.0
+                    || seen.contains(&number)
+                    || literal.is_none_or(|(_, l)| l.is_empty())
+                {
+                    continue;
+                }
+                seen.push(number);
+                match uses.iter_mut().find(|(n, _)| *n == number) {
+                    Some((_, spans)) => spans.push(value),
+                    None => uses.push((number, vec![value])),
+                }
+            }
+        }
+    }
+
+    for (number, values) in uses {
+        if values.len() < REPEATED_LITERAL_CHECKS {
+            continue;
+        }
+        let first = Span::from_spanned(context, values[0]);
+        let others: Vec<Span> = values[1..]
+            .iter()
+            .map(|v| Span::from_spanned(context, v))
+            .collect();
+        let fix = extract_constant(number, &values, context);
+        let mut warning = context
+            .warn(format!("`{number}` is repeated in {} checks", values.len()))
+            .lint("repeated_threshold")
+            .set_primary_span(first, "first used here");
+        for span in others {
+            warning = warning.span_label(span, "also used here");
+        }
+        match fix {
+            Some((name, mut edits)) => {
+                let (span, replacement) = edits.remove(0);
+                let message = format!("move the value into the constant `{name}`");
+                warning
+                    .suggest_edits(span, replacement, edits, message)
+                    .emit();
+            }
+            None => warning.emit(),
+        }
+        context
+            .help("if these checks share a setting, updating some of them but not the others is easy to miss when tuning")
+            .emit();
+    }
+}
+
+/// The edits that replace each of `values` with a new constant holding `number`, followed by the
+/// edit that declares it in the `[constants]` table, along with the name of the constant. Returns
+/// `None` if a value is not written as a literal in the config's own file
+fn extract_constant(
+    number: f32,
+    values: &[&SpannedValue<f32>],
+    context: &Context,
+) -> Option<(String, Vec<(Span, String)>)> {
+    let source = context.source();
+    let mut literals = Vec::new();
+    for value in values {
+        let (start, literal) = crate::lower::float_literal(value, context)?;
+        if start + literal.len() > source.len() || literal.replace('_', "").parse::<f64>().is_err()
+        {
+            return None;
+        }
+        literals.push((start, literal));
+    }
+
+    let base = format!("threshold_{number}")
+        .replace('.', "_")
+        .replace('-', "minus_");
+    let mut name = base.clone();
+    for i in 2.. {
+        if !source.contains(&name) {
+            break;
+        }
+        name = format!("{base}_{i}");
+    }
+    let mut edits: Vec<(Span, String)> = literals
+        .iter()
+        .map(|(start, literal)| {
+            let span = context.subspan(*start, start + literal.len());
+            (span, format!("\"{name}\""))
+        })
+        .collect();
+
+    // Add to an existing `[constants]` table, or start one before the first table so that it
+    // does not take in the top level keys
+    let declaration = format!("{name} = {}\n", literals[0].1);
+    let mut first_table = None;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed == "[constants]" {
+            let declaration = match line.ends_with('\n') {
+                true => declaration,
+                false => format!("\n{declaration}"),
+            };
+            edits.push((context.subspan(offset, offset), declaration));
+            return Some((name, edits));
+        }
+        if first_table.is_none() && trimmed.starts_with('[') {
+            first_table = Some(offset - line.len());
+        }
+    }
+    let (at, table) = match first_table {
+        Some(at) => (at, format!("[constants]\n{declaration}\n")),
+        None => (source.len(), format!("\n[constants]\n{declaration}")),
+    };
+    edits.push((context.subspan(at, at), table));
+    Some((name, edits))
+}
+
+/// Returns every decimal value in `config`, in source order
+fn float_values(config: &upper::ConfigFile) -> Vec<&SpannedValue<f32>> {
+    let mut values = Vec::new();
+    if let Some(recovery) = &config.recovery {
+        let recovery = recovery.get_ref();
+        values.extend([&recovery.launch_altitude, &recovery.main_altitude]);
+    }
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        for check in state.checks.iter().map(|c| c.get_ref()) {
+            values.extend([
+                &check.greater_than,
+                &check.less_than,
+                &check.upper_bound,
+                &check.lower_bound,
+            ]);
+        }
+        values.extend(state.commands.iter().map(|c| &c.get_ref().delay));
+        if let Some(timeout) = &state.timeout {
+            values.push(&timeout.get_ref().seconds);
+        }
+    }
+    values.into_iter().flatten().collect()
+}
+
+/// Writing some decimal values as `100` and others as `100.0` makes diffs noisy and hides which
+/// values were meant to be whole numbers, so when enabled, warn about every decimal value written
+/// as an integer
+fn strict_floats(config: &upper::ConfigFile, context: &mut Context) {
+    for value in float_values(config) {
+        let (start, literal) = match crate::lower::float_literal(value, context) {
+            Some(literal) => literal,
+            None => continue,
+        };
+        let digits = literal.trim_start_matches(['+', '-']);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+            continue;
+        }
+        let suggestion = format!("{literal}.0");
+        let span = context.subspan(start, start + literal.len());
+        context
+            .warn(format!("decimal value written as the integer `{literal}`"))
+            .set_primary_span(span, "not a float")
+            .suggest_replacement(span, suggestion, "write it as a float")
+            .lint("strict_floats")
+            .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Level;
+    use crate::{upper, Session};
+
+    fn lint_level(toml: &str, level: Level) -> usize {
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let config = upper::verify(&mut context).unwrap();
+        super::check(&config, &mut context);
+        let diagnostics = match context.end_phase_and_emit() {
+            Ok(d) | Err(d) => d,
+        };
+        diagnostics.iter().filter(|d| d.level == level).count()
+    }
+
+    #[test]
+    fn strict_floats() {
+        let toml = r#"
+[[states]]
+name = "Flight"
+abort_safe = true
+
+[[states.commands]]
+beacon = true
+delay = 1
+
+[[states.checks]]
+name = "Main"
+check = "altitude"
+upper_bound = 300.0
+lower_bound = -1_0
+transition = "Flight"
+"#;
+        let mut session = Session::new();
+        session.set_strict_floats(true);
+        let mut context = session.testing(toml);
+        let config = upper::verify(&mut context).unwrap();
+        super::check(&config, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let labels: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.message == "write it as a float")
+            .map(|d| d.spans[0].label.as_deref().unwrap())
+            .collect();
+        assert_eq!(labels, ["replace with `-1_0.0`", "replace with `1.0`"]);
+        assert_eq!(lint_level(toml, Level::Warning), 0);
+    }
+
+    fn lint(toml: &str) -> usize {
+        lint_level(toml, Level::Error)
+    }
+
+    #[test]
+    fn pre_launch_pyros() {
+        let base = r#"
+default_state = "Armed"
+abort_state = "Safe"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states.checks]]
+name = "Continuity"
+check = "pyro1_continuity"
+flag = "unset"
+abort = true
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+pyro1 = true
+"#;
+        let safe = "\n[[states]]\nname = \"Safe\"\nabort_safe = true\n";
+        assert_eq!(lint(&format!("{base}{safe}")), 0);
+
+        // Disabling a channel is always fine
+        let disable = "\n[[states.commands]]\npyro1 = false\n";
+        assert_eq!(lint(&format!("{base}{safe}{disable}")), 0);
+
+        // Reachable through the abort on the pad
+        let fire = "\n[[states.commands]]\npyro2 = true\n";
+        assert_eq!(lint(&format!("{base}{safe}{fire}")), 1);
+
+        // In the default state itself
+        let fire_on_boot = base.replace(
+            "abort = true\n",
+            "abort = true\n\n[[states.commands]]\npyro3 = \"enable\"\n",
+        );
+        assert_eq!(lint(&format!("{fire_on_boot}{safe}")), 1);
+    }
+
+    #[test]
+    fn repeated_literals() {
+        let check = |name: &str, value: &str| {
+            format!("\n[[states.checks]]\nname = \"{name}\"\ncheck = \"altitude\"\n{value}\ntransition = \"A\"\n")
+        };
+        let twice = format!(
+            "[[states]]\nname = \"A\"\nabort_safe = true\n{}{}",
+            check("One", "greater_than = 300.0"),
+            check("Two", "upper_bound = 300.0\nlower_bound = 0.0"),
+        );
+        assert_eq!(lint_level(&twice, Level::Warning), 0);
+
+        let three = format!("{twice}{}", check("Three", "greater_than = 3e2"));
+        assert_eq!(lint_level(&three, Level::Warning), 1);
+
+        // Zero is not a tuned value
+        let zeros = three.replace("300.0", "0.0").replace("3e2", "0.0");
+        assert_eq!(lint_level(&zeros, Level::Warning), 0);
+
+        // The fix declares a constant before the first table, and uses it in every check
+        let mut session = Session::new();
+        let mut context = session.testing(&three);
+        let config = upper::verify(&mut context).unwrap();
+        super::repeated_literals(&config, &mut context);
+        let literal = |at: usize, len: usize| context.subspan(at, at + len);
+        let uses = [
+            literal(three.find("300.0").unwrap(), 5),
+            literal(three.rfind("300.0").unwrap(), 5),
+            literal(three.find("3e2").unwrap(), 3),
+        ];
+        let declaration = context.subspan(0, 0);
+        context.end_phase_and_emit().unwrap();
+        let suggestion = &session.suggestions()[0];
+        let edits: Vec<_> = suggestion.all_edits().collect();
+        let name = "\"threshold_300\"";
+        let table = "[constants]\nthreshold_300 = 300.0\n\n";
+        assert_eq!(
+            edits,
+            [
+                (uses[0], name),
+                (uses[1], name),
+                (uses[2], name),
+                (declaration, table)
+            ]
+        );
+        let fixed = format!(
+            "{table}{}",
+            three.replace("300.0", name).replace("3e2", name)
+        );
+        assert_eq!(lint_level(&fixed, Level::Warning), 0);
+    }
+
+    #[test]
+    fn check_names() {
+        let check = |name: &str, action: &str| {
+            format!("\n[[states.checks]]\nname = \"{name}\"\ncheck = \"apogee\"\n{action}\n")
+        };
+        let config = format!(
+            "[[states]]\nname = \"A\"\nabort_safe = true\n{}{}{}",
+            check("Apogee", "transition = \"A\""),
+            check("Latch", ""),
+            check("Apogee", "abort = \"A\"\nrequires = \"Latch\""),
+        );
+        let mut session = Session::new();
+        let mut context = session.testing(&config);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["check `Apogee` is declared twice in state `A`"]);
+
+        let inert = config.replace("requires = \"Latch\"", "");
+        let mut context = session.testing(&inert);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        assert_eq!(diagnostics[1].message, "check `Latch` has no effect");
+    }
+
+    #[test]
+    fn levels() {
+        let config = r#"
+[lints]
+infinite_loop = "deny"
+unreachable_state = "allow"
+LINTS
+
+[[states]]
+name = "Hold"
+
+[states.timeout]
+seconds = 10.0
+transition = "Hold"
+
+[[states]]
+name = "Unused"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(&config.replace("LINTS", ""));
+        let mid = upper::verify(&mut context).unwrap();
+        super::configure(&mid, &mut context);
+        context.end_phase_and_emit().unwrap();
+        let _ = crate::lower::verify(&mid, &mut context);
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level != Level::Help)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "`Hold` can loop forever without reaching an abort safe state",
+                "`infinite_loop` is denied, so its warnings are reported as errors",
+            ]
+        );
+
+        // The command line takes precedence over the config
+        let mut session = Session::new();
+        session.set_lint_level(&"infinite_loop".parse().unwrap(), super::LintLevel::Warn);
+        let mut context = session.testing(&config.replace("LINTS", ""));
+        let mid = upper::verify(&mut context).unwrap();
+        super::configure(&mid, &mut context);
+        super::check(&mid, &mut context);
+        assert!(context.end_phase_and_emit().is_ok());
+
+        let invalid = config.replace(
+            "LINTS",
+            "pre_launch_pyro = \"allow\"\nabort_pth = \"deny\"\nabort_path = \"forbid\"",
+        );
+        let mut context = session.testing(&invalid);
+        let mid = upper::verify(&mut context).unwrap();
+        super::configure(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap_err();
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "unknown lint level `forbid`",
+                "unknown lint `abort_pth`, a lint with a similar name exists: `abort_path`",
+                "`pre_launch_pyro` is deny by default, so it cannot be lowered to `allow`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Loop detection, which finds states the flight computer can cycle through forever.
+//!
+//! Transitions that lead back to an earlier state are sometimes intended, such as re-arming after
+//! a disarmed hold. A loop that never passes through a state marked `abort_safe` is more likely a
+//! mistake, where two states hand the flight back and forth on a noisy condition until the battery
+//! runs out. Each such loop warns with the `infinite_loop` lint, which `--deny infinite_loop`
+//! turns into an error.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    duration::{reachable, successors},
+    upper, Context, Span,
+};
+
+/// Returns every loop in `config`, as the names of the states that can reach each other, in
+/// source order
+pub fn loops(config: &upper::ConfigFile) -> Vec<Vec<&str>> {
+    let order: Vec<&upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .collect();
+    let states: HashMap<&str, &upper::State> = order
+        .iter()
+        .map(|s| (s.name.get_ref().as_str(), *s))
+        .collect();
+    let reach: HashMap<&str, HashSet<&str>> = order
+        .iter()
+        .map(|s| {
+            let reach = reachable(config, &states, successors(config, s), |_| false);
+            (s.name.get_ref().as_str(), reach)
+        })
+        .collect();
+
+    let mut found: Vec<Vec<&str>> = Vec::new();
+    for state in &order {
+        let name = state.name.get_ref().as_str();
+        if !reach[name].contains(name) || found.iter().any(|l| l.contains(&name)) {
+            continue;
+        }
+        let members = order
+            .iter()
+            .map(|s| s.name.get_ref().as_str())
+            .filter(|other| reach[name].contains(other) && reach[other].contains(name))
+            .collect();
+        found.push(members);
+    }
+    found
+}
+
+/// Warns about every loop in `config` that does not pass through an abort safe state
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let states: HashMap<&str, &upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| (s.get_ref().name.get_ref().as_str(), s.get_ref()))
+        .collect();
+    for members in loops(config) {
+        if members.iter().any(|name| states[name].abort_safe) {
+            continue;
+        }
+        let spans: Vec<Span> = members
+            .iter()
+            .map(|name| Span::from_spanned(context, &states[name].name))
+            .collect();
+        let names: Vec<String> = members.iter().map(|name| format!("`{name}`")).collect();
+        let mut warning = context
+            .warn(format!(
+                "{} can loop forever without reaching an abort safe state",
+                names.join(" and ")
+            ))
+            .set_primary_span(spans[0], "part of a loop with no abort safe state")
+            .lint("infinite_loop");
+        for span in &spans[1..] {
+            warning = warning.span_label(*span, "part of the loop");
+        }
+        warning.emit();
+        context
+            .help("loops are expected when they pass through a state with `abort_safe = true`, such as one that disarms the rocket")
+            .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn infinite_loops() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Settled"
+check = "altitude"
+less_than = 20.0
+transition = "Armed"
+
+[[states]]
+name = "Hold"
+SAFE
+
+[states.timeout]
+seconds = 10.0
+transition = "Hold"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(&config.replace("SAFE", ""));
+        let mid = upper::verify(&mut context).unwrap();
+        assert_eq!(super::loops(&mid), [vec!["Armed", "Flight"], vec!["Hold"]]);
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages[0],
+            "`Armed` and `Flight` can loop forever without reaching an abort safe state"
+        );
+        assert_eq!(
+            messages[2],
+            "`Hold` can loop forever without reaching an abort safe state"
+        );
+
+        let mut context = session.testing(&config.replace("SAFE", "abort_safe = true"));
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        assert_eq!(context.end_phase_and_emit().unwrap().len(), 2);
+    }
+}
@@ -1,71 +1,282 @@
 use std::borrow::Borrow;
-use std::{collections::HashMap, convert::TryInto};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 
+use crate::spanned::SpannedValue;
 use common::index::{self, StateTransition};
 use common::index::{Check, Command, ConfigFile, State, StateIndex};
 use heapless::Vec;
-use toml::Spanned;
 
-use crate::{upper, Context, Span};
+use crate::{limits::Limits, upper, Context, ErrorCode, Span};
 use nova_software_common as common;
 
-pub(crate) struct Temp<'s>(HashMap<&'s str, StateIndex>);
+/// The maximum length of a state or check name.
+/// Names end up in generated code and telemetry dictionaries, so they are kept short
+pub const MAX_NAME_LEN: usize = 32;
+
+pub(crate) struct Temp<'s> {
+    states: HashMap<&'s str, StateIndex>,
+
+    /// The index of the top level `abort_state`, if one was declared
+    abort_state: Option<StateIndex>,
+
+    /// Values that high level check kinds expand to
+    recovery: Option<&'s upper::Recovery>,
+}
+
+/// Converts the position of a state in the config into its index in the encoded config, or
+/// returns `None` if the encoded format cannot refer to a state at that position
+pub(crate) fn state_index(position: usize) -> Option<StateIndex> {
+    if position >= common::MAX_STATES {
+        return None;
+    }
+    let position: u8 = position.try_into().ok()?;
+    // SAFETY: `position` was checked to be less than `MAX_STATES` above
+    Some(unsafe { StateIndex::new_unchecked(position) })
+}
 
 impl<'s> Temp<'s> {
-    fn new(states: &'s [Spanned<upper::State>]) -> Self {
-        Self(
-            states
-                .iter()
-                .enumerate()
-                .map(|(i, state)| {
-                    let i: u8 = i.try_into().unwrap();
-
-                    // SAFETY: `i` comes from enumerate, which only yields indices in range
-                    let index = unsafe { StateIndex::new_unchecked(i) };
-                    (state.get_ref().name.borrow(), index)
-                })
-                .collect(),
-        )
+    /// Assigns an index to each of `states`, emitting an error that lists the states that do not
+    /// fit if there are more than `limits` allows
+    fn new(
+        states: &'s SpannedValue<std::vec::Vec<SpannedValue<upper::State>>>,
+        limits: &Limits,
+        context: &mut Context,
+    ) -> Result<Self, ()> {
+        let mut indices = HashMap::new();
+        let mut overflowing = std::vec::Vec::new();
+        for (position, state) in states.get_ref().iter().enumerate() {
+            let name = &state.get_ref().name;
+            match state_index(position).filter(|_| position < limits.max_states) {
+                Some(index) => {
+                    indices.insert(name.get_ref().as_str(), index);
+                }
+                None => overflowing.push(Span::from_spanned(context, name)),
+            }
+        }
+        if !overflowing.is_empty() {
+            let span = Span::from_spanned(context, states);
+            let mut err = context
+                .error("too many states")
+                .code(ErrorCode::TooManyStates)
+                .set_primary_span(
+                    span,
+                    format!(
+                        "the maximum number of states is {}, but this config has {}",
+                        limits.max_states,
+                        states.get_ref().len()
+                    ),
+                );
+            for span in overflowing {
+                err = err.span_label(span, "does not fit");
+            }
+            err.emit();
+            return Err(());
+        }
+        Ok(Self {
+            states: indices,
+            abort_state: None,
+            recovery: None,
+        })
     }
 
-    fn get_index(&self, name: &Spanned<String>, context: &mut Context) -> Result<StateIndex, ()> {
-        match self.0.get(name.get_ref().as_str()) {
-            Some(v) => Ok(*v),
-            None => {
-                let span = Span::from_spanned(context, name);
+    fn get_index(
+        &self,
+        name: &SpannedValue<String>,
+        context: &mut Context,
+    ) -> Result<StateIndex, ()> {
+        let span = Span::from_spanned(context, name);
+        self.lookup(name.get_ref(), span, true, context)
+    }
+
+    /// Finds the state called `name`, which was written at `span`. If `literal` is set, `span` is
+    /// the string holding the name, and a misspelled name can be replaced there
+    fn lookup(
+        &self,
+        name: &str,
+        span: Span,
+        literal: bool,
+        context: &mut Context,
+    ) -> Result<StateIndex, ()> {
+        if let Some(index) = self.states.get(name) {
+            return Ok(*index);
+        }
+        let err = context
+            .error(format!("state not found `{name}`"))
+            .code(ErrorCode::UnknownState)
+            .set_primary_span(span, "not found");
+        match crate::spelling::closest(name, self.states.keys().copied()) {
+            Some(closest) if literal => err
+                .suggest_replacement(
+                    span,
+                    format!("\"{closest}\""),
+                    "a state with a similar name exists",
+                )
+                .emit(),
+            Some(closest) => {
+                err.emit();
                 context
-                    .error(format!("state not found `{}`", name.get_ref()))
-                    .set_primary_span(span, "not found")
+                    .help(format!("a state with a similar name exists: `{closest}`"))
                     .emit();
-                Err(())
             }
+            None => err.emit(),
+        }
+        Err(())
+    }
+
+    /// Resolves the `abort` key of a check. Returns `Ok(None)` for `abort = false`
+    fn get_abort_index(
+        &self,
+        target: &SpannedValue<upper::AbortTarget>,
+        context: &mut Context,
+    ) -> Result<Option<StateIndex>, ()> {
+        let span = Span::from_spanned(context, target);
+        let details = match target.get_ref() {
+            upper::AbortTarget::Detailed(details) => Some(details),
+            _ => None,
+        };
+        if let Some(reason) = details.and_then(|d| d.reason.as_deref()) {
+            crate::abort_reasons::lookup(reason, span, context)?;
+        }
+        let named = details.and_then(|d| d.state.as_ref());
+        match target.get_ref() {
+            upper::AbortTarget::State(name) => self.lookup(name, span, true, context).map(Some),
+            upper::AbortTarget::Detailed(_) if named.is_some() => {
+                self.lookup(named.unwrap(), span, false, context).map(Some)
+            }
+            upper::AbortTarget::Global(false) => Ok(None),
+            upper::AbortTarget::Global(true) | upper::AbortTarget::Detailed(_) => match self
+                .abort_state
+            {
+                Some(index) => Ok(Some(index)),
+                None => {
+                    context
+                        .error("abort to the top level `abort_state` used without declaring one")
+                        .code(ErrorCode::MissingAbortState)
+                        .set_primary_span(span, "no abort state to abort to")
+                        .emit();
+                    context
+                        .help("declare `abort_state = \"...\"` at the top of the file, or name the state to abort to")
+                        .emit();
+                    Err(())
+                }
+            },
         }
     }
 }
 
-// When we go to a low level file, the default state must be first
-pub fn verify(mid: upper::ConfigFile, context: &mut crate::Context) -> Result<ConfigFile, ()> {
+/// Assigns state indices and resolves the top level keys that the checks of every state refer to
+fn prepare<'s>(mid: &'s upper::ConfigFile, context: &mut Context) -> Result<Temp<'s>, ()> {
     let span = Span::from_spanned(context, &mid.states);
     if mid.states.get_ref().is_empty() {
         context
             .error("states missing")
+            .code(ErrorCode::NoStates)
             .set_primary_span(span, "you need to have at least one state")
             .emit();
 
         return Err(());
     }
-    if mid.states.get_ref().len() > common::MAX_STATES as usize {
+    verify_unique_states(mid, context)?;
+    let limits = context.session().limits();
+    let mut temp = Temp::new(&mid.states, &limits, context)?;
+    if let Some(name) = &mid.abort_state {
+        let index = temp.get_index(name, context)?;
+        let state = mid
+            .states
+            .get_ref()
+            .iter()
+            .map(|s| s.get_ref())
+            .find(|s| s.name.get_ref() == name.get_ref())
+            // `get_index` succeeded, so a state with this name exists
+            .unwrap();
+        if !state.abort_safe {
+            let span = Span::from_spanned(context, name);
+            let state_span = Span::from_spanned(context, &state.name);
+            context
+                .error(format!(
+                    "abort state `{}` is not marked abort safe",
+                    name.get_ref()
+                ))
+                .code(ErrorCode::AbortStateNotSafe)
+                .set_primary_span(span, "declared as the abort state here")
+                .span_label(state_span, "add `abort_safe = true` to this state")
+                .emit();
+            return Err(());
+        }
+        temp.abort_state = Some(index);
+    }
+    if let Some(recovery) = &mid.recovery {
+        verify_recovery(recovery.get_ref(), context)?;
+        temp.recovery = Some(recovery.get_ref());
+    }
+    crate::requires::verify(mid, context)?;
+    Ok(temp)
+}
+
+/// Checks that no two states share a name, including the states that `[[stages]]` adds with their
+/// stage's name in front
+fn verify_unique_states(mid: &upper::ConfigFile, context: &mut Context) -> Result<(), ()> {
+    let mut first: HashMap<&str, Span> = HashMap::new();
+    let mut result = Ok(());
+    for state in mid.states.get_ref() {
+        let name = &state.get_ref().name;
+        let span = Span::from_spanned(context, name);
+        let first_span = match first.get(name.get_ref().as_str()) {
+            Some(first_span) => *first_span,
+            None => {
+                first.insert(name.get_ref(), span);
+                continue;
+            }
+        };
         context
-            .error("too many states")
-            .set_primary_span(
-                span,
-                format!("the maxinum number of states is {}", common::MAX_STATES),
-            )
+            .error(format!("state `{}` is declared twice", name.get_ref()))
+            .code(ErrorCode::DuplicateState)
+            .set_primary_span(span, "declared again here")
+            .span_label(first_span, "first declared here")
+            .emit();
+        if name.get_ref().contains('_') {
+            context
+                .note("the states of a `[[stages]]` entry are named with the stage's name in front, such as `Booster_Boost`")
+                .emit();
+        }
+        result = Err(());
+    }
+    result
+}
+
+/// Reports that `mid` has no `default_state`, so the flight computer starts in the first state.
+/// This is a note, or an error with [`crate::Session::set_require_default_state`]
+fn infer_default_state(mid: &upper::ConfigFile, context: &mut Context) -> Result<(), ()> {
+    let first = &mid.states.get_ref()[0].get_ref().name;
+    let span = Span::from_spanned(context, first);
+    let name = first.get_ref();
+    if context.session().require_default_state() {
+        context
+            .error("no `default_state` declared")
+            .code(ErrorCode::MissingDefaultState)
+            .set_primary_span(span, "the flight computer would start in this state")
+            .emit();
+        context
+            .help(format!(
+                "add `default_state = \"{name}\"` to the top of the file"
+            ))
             .emit();
         return Err(());
     }
+    context
+        .note(format!(
+            "no `default_state` declared, so the flight computer starts in `{name}`"
+        ))
+        .set_primary_span(span, "the first state is the default")
+        .emit();
+    Ok(())
+}
 
-    let temp = Temp::new(mid.states.get_ref().as_slice());
+// When we go to a low level file, the default state must be first
+pub fn verify(mid: &upper::ConfigFile, context: &mut crate::Context) -> Result<ConfigFile, ()> {
+    let temp = prepare(mid, context)?;
+    let limits = context.session().limits();
 
     let mut states: Vec<State, { common::MAX_STATES }> = mid
         .states
@@ -75,14 +286,17 @@ pub fn verify(mid: upper::ConfigFile, context: &mut crate::Context) -> Result<Co
         .map(|_| State::new(Vec::new(), Vec::new(), None))
         .collect();
 
-    let default_state = mid.default_state.map_or_else(
+    let default_state = mid.default_state.as_ref().map_or_else(
         // SAFETY: We have checked that there is at least one state above, so index 0 is in bounds
         || Ok(unsafe { StateIndex::new_unchecked(0) }),
-        |name| temp.get_index(&name, context),
+        |name| temp.get_index(name, context),
     )?;
 
     for (src_state, dst_state) in mid.states.get_ref().iter().zip(states.iter_mut()) {
+        validate_name("state", &src_state.get_ref().name, context);
+        check_state_capacity(src_state.get_ref(), &limits, context)?;
         for src_check in &src_state.get_ref().checks {
+            validate_name("check", &src_check.get_ref().name, context);
             let check: index::Check = convert_check(src_check, &temp, context)?;
             dst_state.checks.push(check).unwrap();
         }
@@ -91,16 +305,291 @@ pub fn verify(mid: upper::ConfigFile, context: &mut crate::Context) -> Result<Co
             let command: index::Command = convert_command(src_command, context)?;
             dst_state.commands.push(command).unwrap();
         }
+        dst_state.timeout = convert_timeout(src_state.get_ref(), &temp, context)?;
     }
 
+    warn_unreachable(mid, context);
+    if mid.default_state.is_none() {
+        infer_default_state(mid, context)?;
+    }
     Ok(ConfigFile {
         default_state,
         states,
     })
 }
 
+/// Verifies only the state called `name`, for quickly re-checking one state of a large config.
+///
+/// The states that `name` transitions to and the states that transition to it are unknown
+/// externals: their names are resolved, but their checks and commands are not verified, and
+/// neither is anything that depends on the whole state graph, such as reachability
+pub fn verify_state(
+    mid: &upper::ConfigFile,
+    name: &str,
+    context: &mut crate::Context,
+) -> Result<State, ()> {
+    let temp = prepare(mid, context)?;
+    let limits = context.session().limits();
+    let Some(src_state) = mid
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .find(|s| s.name.get_ref() == name)
+    else {
+        context
+            .error(format!("state not found `{name}`"))
+            .code(ErrorCode::UnknownState)
+            .emit();
+        let names = mid
+            .states
+            .get_ref()
+            .iter()
+            .map(|s| s.get_ref().name.get_ref());
+        if let Some(closest) = crate::spelling::closest(name, names.map(String::as_str)) {
+            context
+                .help(format!("a state with a similar name exists: `{closest}`"))
+                .emit();
+        }
+        return Err(());
+    };
+
+    let mut dst_state = State::new(Vec::new(), Vec::new(), None);
+    validate_name("state", &src_state.name, context);
+    check_state_capacity(src_state, &limits, context)?;
+    for src_check in &src_state.checks {
+        validate_name("check", &src_check.get_ref().name, context);
+        let check: index::Check = convert_check(src_check, &temp, context)?;
+        dst_state.checks.push(check).unwrap();
+    }
+    for src_command in &src_state.commands {
+        let command: index::Command = convert_command(src_command, context)?;
+        dst_state.commands.push(command).unwrap();
+    }
+    dst_state.timeout = convert_timeout(src_state, &temp, context)?;
+
+    let externals = Neighbourhood::of(mid, name).externals();
+    if !externals.is_empty() {
+        let list: std::vec::Vec<String> = externals.iter().map(|s| format!("`{s}`")).collect();
+        context
+            .note(format!(
+                "only `{name}` was verified, {} {} assumed to be valid",
+                list.join(", "),
+                if list.len() == 1 { "is" } else { "are" }
+            ))
+            .emit();
+    }
+    Ok(dst_state)
+}
+
+/// The part of the state graph around one state, where every other state is an unknown external
+/// that is only known by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbourhood<'a> {
+    /// The states that the state can transition to
+    pub successors: std::vec::Vec<&'a str>,
+
+    /// The states that can transition to the state
+    pub predecessors: std::vec::Vec<&'a str>,
+}
+
+impl<'a> Neighbourhood<'a> {
+    /// Collects the transitions into and out of the state called `name` in `config`
+    pub fn of(config: &'a upper::ConfigFile, name: &str) -> Self {
+        let mut successors = std::vec::Vec::new();
+        let mut predecessors = std::vec::Vec::new();
+        for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+            let state_name = state.name.get_ref().as_str();
+            let next = crate::duration::successors(config, state);
+            if state_name == name {
+                successors.extend(next.into_iter().filter(|s| *s != name));
+            } else if next.contains(&name) {
+                predecessors.push(state_name);
+            }
+        }
+        successors.sort_unstable();
+        successors.dedup();
+        Self {
+            successors,
+            predecessors,
+        }
+    }
+
+    /// Every state around the state, each listed once
+    pub fn externals(&self) -> std::vec::Vec<&'a str> {
+        let mut all: std::vec::Vec<&str> = self
+            .successors
+            .iter()
+            .chain(&self.predecessors)
+            .copied()
+            .collect();
+        all.sort_unstable();
+        all.dedup();
+        all
+    }
+}
+
+/// Every state that the flight computer can enter, following the checks, aborts, and timeouts of
+/// each state from the default state. States that a `[fault_handling]` fallback aborts to can be
+/// entered whenever a sensor fails
+pub fn reachable_states(config: &upper::ConfigFile) -> HashSet<&str> {
+    let states: HashMap<&str, &upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| (s.get_ref().name.get_ref().as_str(), s.get_ref()))
+        .collect();
+    let mut queue: std::vec::Vec<&str> = match &config.default_state {
+        Some(name) => vec![name.get_ref().as_str()],
+        None => config
+            .states
+            .get_ref()
+            .iter()
+            .take(1)
+            .map(|s| s.get_ref().name.get_ref().as_str())
+            .collect(),
+    };
+    let fallbacks = config
+        .fault_handling
+        .iter()
+        .flat_map(|f| f.get_ref().values());
+    for fallback in fallbacks.map(|f| f.get_ref()) {
+        if fallback.action.get_ref() == "abort" {
+            let target = fallback.state.as_ref().or(config.abort_state.as_ref());
+            queue.extend(target.map(|s| s.get_ref().as_str()));
+        }
+    }
+
+    let mut reached = HashSet::new();
+    while let Some(name) = queue.pop() {
+        if !reached.insert(name) {
+            continue;
+        }
+        if let Some(state) = states.get(name) {
+            queue.extend(crate::duration::successors(config, state));
+        }
+    }
+    reached
+}
+
+/// Warns about states that can never be entered, which are almost always caused by a typo in the
+/// name of a transition
+fn warn_unreachable(config: &upper::ConfigFile, context: &mut Context) {
+    let reached = reachable_states(config);
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref();
+        if reached.contains(name.as_str()) {
+            continue;
+        }
+        let span = Span::from_spanned(context, &state.name);
+        context
+            .warn(format!("state `{name}` is unreachable"))
+            .set_primary_span(span, "no transition leads here from the default state")
+            .lint("unreachable_state")
+            .emit();
+        context
+            .help(format!(
+                "check the spelling of the transitions that should lead to `{name}`, or remove it"
+            ))
+            .emit();
+    }
+}
+
+/// Checks that `state` has no more checks and commands than `limits` allows
+fn check_state_capacity(
+    state: &upper::State,
+    limits: &Limits,
+    context: &mut Context,
+) -> Result<(), ()> {
+    let name_span = Span::from_spanned(context, &state.name);
+    let mut result = Ok(());
+    if state.checks.len() > limits.max_checks_per_state {
+        let overflowing: std::vec::Vec<Span> = state.checks[limits.max_checks_per_state..]
+            .iter()
+            .map(|check| Span::from_spanned(context, &check.get_ref().name))
+            .collect();
+        let mut err = context
+            .error(format!(
+                "too many checks in state `{}`",
+                state.name.get_ref()
+            ))
+            .code(ErrorCode::TooManyChecks)
+            .set_primary_span(
+                name_span,
+                format!(
+                    "the maximum number of checks per state is {}, but this state has {}",
+                    limits.max_checks_per_state,
+                    state.checks.len()
+                ),
+            );
+        for span in overflowing {
+            err = err.span_label(span, "does not fit");
+        }
+        err.emit();
+        result = Err(());
+    }
+    if state.commands.len() > limits.max_commands_per_state {
+        context
+            .error(format!(
+                "too many commands in state `{}`",
+                state.name.get_ref()
+            ))
+            .code(ErrorCode::TooManyCommands)
+            .set_primary_span(
+                name_span,
+                format!(
+                    "the maximum number of commands per state is {}, but this state has {}",
+                    limits.max_commands_per_state,
+                    state.commands.len()
+                ),
+            )
+            .emit();
+        result = Err(());
+    }
+    result
+}
+
+/// Checks that `name` can be used as an identifier by downstream tooling, emitting errors if it
+/// cannot. `kind` is the type of item being named, used in messages
+pub(crate) fn validate_name(kind: &str, name: &SpannedValue<String>, context: &mut Context) {
+    let span = Span::from_spanned(context, name);
+    let value = name.get_ref();
+    if value.is_empty() {
+        context
+            .error(format!("{kind} name cannot be empty"))
+            .code(ErrorCode::EmptyName)
+            .set_primary_span(span, "empty name")
+            .emit();
+        return;
+    }
+    if value.len() > MAX_NAME_LEN {
+        context
+            .error(format!("{kind} name `{value}` is too long"))
+            .code(ErrorCode::NameTooLong)
+            .set_primary_span(
+                span,
+                format!(
+                    "name is {} characters, the maximum is {MAX_NAME_LEN}",
+                    value.len()
+                ),
+            )
+            .emit();
+    }
+    let starts_with_letter = value.starts_with(|c: char| c.is_ascii_alphabetic());
+    if !starts_with_letter || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        context
+            .error(format!("{kind} name `{value}` contains invalid characters"))
+            .code(ErrorCode::InvalidNameCharacters)
+            .set_primary_span(
+                span,
+                "names must start with a letter and only contain letters, digits, and `_`",
+            )
+            .emit();
+    }
+}
+
 pub(crate) fn convert_command(
-    command: &Spanned<upper::Command>,
+    command: &SpannedValue<upper::Command>,
     context: &mut Context,
 ) -> Result<Command, ()> {
     let span = Span::from_spanned(context, command);
@@ -127,6 +616,7 @@ pub(crate) fn convert_command(
         // Zero assignments fond, expected one
         context
             .error("command action missing")
+            .code(ErrorCode::MissingCommandAction)
             .set_primary_span(span, "you must specify one command action")
             .emit();
         return Err(());
@@ -149,19 +639,33 @@ pub(crate) fn convert_command(
             values.push(Span::from_spanned(context, s));
         }
 
-        let mut err = context.error("too many command actions").set_primary_span(
-            span,
-            format!(
-                "you must specify exactly one command action, not {}",
-                values.len()
-            ),
-        );
+        let mut err = context
+            .error("too many command actions")
+            .code(ErrorCode::TooManyCommandActions)
+            .set_primary_span(
+                span,
+                format!(
+                    "you must specify exactly one command action, not {}",
+                    values.len()
+                ),
+            );
 
         for span in values {
             err = err.span_label(span, "declared here");
         }
         err.emit();
     }
+    let pyros = [
+        (1, &command.pyro1),
+        (2, &command.pyro2),
+        (3, &command.pyro3),
+    ];
+    for (channel, pyro) in pyros {
+        if let Some(pyro) = pyro {
+            let span = Span::from_spanned(context, pyro);
+            crate::hardware::check_pyro_channel(channel, span, context)?;
+        }
+    }
     use common::CommandObject;
     //The user only set one option, now map that to an object and state
     let object = {
@@ -180,6 +684,27 @@ pub(crate) fn convert_command(
             unreachable!("{command:?}")
         }
     };
+    if let Some(data_rate) = &command.data_rate {
+        let rates = context.session().limits().data_rates;
+        let rate = *data_rate.get_ref();
+        if !rates.contains(rate) {
+            let span = Span::from_spanned(context, data_rate);
+            let err = context
+                .error(format!("unsupported data rate {rate}"))
+                .code(ErrorCode::UnsupportedDataRate)
+                .set_primary_span(span, format!("the logger supports data rates of {rates}"));
+            match rates.nearest(rate) {
+                Some(nearest) => err
+                    .suggest_replacement(span, nearest.to_string(), "the nearest supported rate")
+                    .emit(),
+                None => err.emit(),
+            }
+            return Err(());
+        }
+    }
+    if let Some(delay) = &command.delay {
+        note_float_precision(delay, context);
+    }
     Ok(index::Command {
         object,
         delay: common::Seconds(
@@ -192,24 +717,273 @@ pub(crate) fn convert_command(
     })
 }
 
+/// Converts the timeout of `state`, which must have both `seconds` and a `transition` to a state
+/// that exists
+fn convert_timeout(
+    state: &upper::State,
+    temp: &Temp<'_>,
+    context: &mut Context,
+) -> Result<Option<index::Timeout>, ()> {
+    let timeout = match &state.timeout {
+        Some(timeout) => timeout.get_ref(),
+        None => return Ok(None),
+    };
+    // Tables inside arrays have no span of their own, so point at the state's name when a key is
+    // missing
+    let name_span = Span::from_spanned(context, &state.name);
+    let name = state.name.get_ref();
+    let seconds = match &timeout.seconds {
+        Some(seconds) => seconds,
+        None => {
+            context
+                .error(format!("the timeout of `{name}` has no `seconds`"))
+                .code(ErrorCode::IncompleteTimeout)
+                .set_primary_span(name_span, "in this state")
+                .emit();
+            context
+                .help("add `seconds = ...` to `[states.timeout]`")
+                .emit();
+            return Err(());
+        }
+    };
+    let value = *seconds.get_ref();
+    if !value.is_finite() || value <= 0.0 {
+        let span = Span::from_spanned(context, seconds);
+        context
+            .error(format!("invalid timeout `{value}`"))
+            .code(ErrorCode::InvalidTimeout)
+            .set_primary_span(span, "timeouts must be a positive number of seconds")
+            .emit();
+        return Err(());
+    }
+    note_float_precision(seconds, context);
+    let transition = match &timeout.transition {
+        Some(transition) => temp.get_index(transition, context)?,
+        None => {
+            context
+                .error(format!("the timeout of `{name}` has no `transition`"))
+                .code(ErrorCode::IncompleteTimeout)
+                .set_primary_span(name_span, "in this state")
+                .emit();
+            context
+                .help("add `transition = \"...\"` with the state to enter when the timeout expires")
+                .emit();
+            return Err(());
+        }
+    };
+    Ok(Some(index::Timeout::new(
+        value,
+        StateTransition::Transition(transition),
+    )))
+}
+
 pub(crate) fn convert_check(
-    check: &Spanned<upper::Check>,
+    check: &SpannedValue<upper::Check>,
     temp: &Temp<'_>,
     context: &mut Context,
 ) -> Result<Check, ()> {
     let full_span = Span::from_spanned(context, check);
     let check = check.get_ref();
-    if check.upper_bound.is_some() && check.lower_bound.is_none()
-        || check.upper_bound.is_none() && check.lower_bound.is_some()
-    {
-        panic!(
-            "Unmatched bound! if one of `lower_bound` or `higher_bound` is used, both must be set"
-        );
+    let data = match expand_sugar(check, temp, context)? {
+        Some(data) => data,
+        None => convert_condition(check, full_span, context)?,
+    };
+
+    let transition = match &check.transition {
+        Some(state) => Some((
+            temp.get_index(state, context)?,
+            state.start(),
+            Span::from_spanned(context, state),
+        )),
+        None => None,
+    };
+
+    let abort = match &check.abort {
+        Some(target) => temp
+            .get_abort_index(target, context)?
+            .map(|index| (index, target.start(), Span::from_spanned(context, target))),
+        None => None,
+    };
+
+    let transition = match (transition, abort) {
+        (Some(t), None) => Some(StateTransition::Transition(t.0)),
+        (None, Some(a)) => Some(StateTransition::Abort(a.0)),
+        (None, None) => None,
+        (Some(mut t), Some(mut a)) => {
+            if t.1 > a.1 {
+                // swap a and t so that t is always first
+                std::mem::swap(&mut t, &mut a);
+            }
+            let s1 = t.2;
+            let s2 = a.2;
+            context
+                .error("abort and transition cannot be active in the same check")
+                .code(ErrorCode::AbortAndTransition)
+                .set_primary_span_no_msg(s1)
+                .span_label(s2, "second action declared here")
+                .emit();
+
+            context
+                .help("remove either `abort` or `transition`")
+                .add_span(s1)
+                .add_span(s2)
+                .emit();
+            return Err(());
+        }
+    };
+
+    Ok(index::Check::new(data, transition))
+}
+
+/// Checks that the values in the `[recovery]` section make sense
+fn verify_recovery(recovery: &upper::Recovery, context: &mut Context) -> Result<(), ()> {
+    let mut result = Ok(());
+    for (field, value) in [
+        ("launch_altitude", &recovery.launch_altitude),
+        ("main_altitude", &recovery.main_altitude),
+    ] {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        note_float_precision(value, context);
+        if value.get_ref().is_nan() || *value.get_ref() <= 0.0 {
+            let span = Span::from_spanned(context, value);
+            context
+                .error(format!("`{field}` must be above the pad"))
+                .code(ErrorCode::RecoveryAltitudeBelowPad)
+                .set_primary_span(span, "must be greater than 0")
+                .emit();
+            result = Err(());
+        }
+    }
+    result
+}
+
+/// Looks up a value from the `[recovery]` section that the high level check `check` needs,
+/// emitting an error if it is missing
+fn recovery_value(
+    check: &upper::Check,
+    field: &str,
+    value: fn(&upper::Recovery) -> &Option<SpannedValue<f32>>,
+    temp: &Temp<'_>,
+    context: &mut Context,
+) -> Result<f32, ()> {
+    match temp.recovery.and_then(|r| value(r).as_ref()) {
+        Some(value) => Ok(*value.get_ref()),
+        None => {
+            let span = Span::from_spanned(context, &check.check);
+            context
+                .error(format!(
+                    "`{}` checks require `{field}` in the `[recovery]` section",
+                    check.check.get_ref()
+                ))
+                .code(ErrorCode::MissingRecoveryAltitude)
+                .set_primary_span(span, format!("needs `recovery.{field}`"))
+                .emit();
+            context
+                .help(format!(
+                    "add `[recovery]` with `{field} = ...` to the top of the file"
+                ))
+                .emit();
+            Err(())
+        }
+    }
+}
+
+/// Expands high level check kinds, which state the intent of a check and take their thresholds
+/// from the `[recovery]` section:
+/// - `launch_detected`: altitude is above `recovery.launch_altitude`
+/// - `apogee_passed`: the apogee flag is set
+/// - `below_main_altitude`: altitude is below `recovery.main_altitude`
+///
+/// Returns `Ok(None)` if `check` is not a high level check
+fn expand_sugar(
+    check: &upper::Check,
+    temp: &Temp<'_>,
+    context: &mut Context,
+) -> Result<Option<common::CheckData>, ()> {
+    use common::{CheckData, FloatCondition, NativeFlagCondition};
+
+    let kind = check.check.get_ref().as_str();
+    if !upper::HIGH_LEVEL_CHECKS.contains(&kind) {
+        return Ok(None);
+    }
+
+    let mut conditions: std::vec::Vec<Span> = [
+        &check.greater_than,
+        &check.less_than,
+        &check.upper_bound,
+        &check.lower_bound,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|value| Span::from_spanned(context, value))
+    .collect();
+    conditions.extend(check.flag.as_ref().map(|f| Span::from_spanned(context, f)));
+    if !conditions.is_empty() {
+        let span = Span::from_spanned(context, &check.check);
+        let mut err = context
+            .error(format!("`{kind}` checks do not take a condition"))
+            .code(ErrorCode::UnexpectedCheckCondition)
+            .set_primary_span(span, "the condition is implied by this check kind");
+        for condition in conditions {
+            err = err.span_label(condition, "remove this");
+        }
+        err.emit();
+        return Err(());
+    }
+
+    let data = match kind {
+        "launch_detected" => {
+            let altitude = recovery_value(
+                check,
+                "launch_altitude",
+                |r| &r.launch_altitude,
+                temp,
+                context,
+            )?;
+            CheckData::Altitude(FloatCondition::GreaterThan(altitude))
+        }
+        "apogee_passed" => CheckData::ApogeeFlag(NativeFlagCondition(true)),
+        "below_main_altitude" => {
+            let altitude =
+                recovery_value(check, "main_altitude", |r| &r.main_altitude, temp, context)?;
+            CheckData::Altitude(FloatCondition::LessThan(altitude))
+        }
+        _ => unreachable!("`HIGH_LEVEL_CHECKS` and `expand_sugar` disagree on `{kind}`"),
+    };
+    Ok(Some(data))
+}
+
+/// Converts the check kind and condition of a low level check, such as
+/// `check = "altitude"` with `greater_than = 100.0`
+pub(crate) fn convert_condition(
+    check: &upper::Check,
+    full_span: Span,
+    context: &mut Context,
+) -> Result<common::CheckData, ()> {
+    let lone_bound = match (&check.upper_bound, &check.lower_bound) {
+        (Some(bound), None) => Some((bound, "upper_bound", "lower_bound")),
+        (None, Some(bound)) => Some((bound, "lower_bound", "upper_bound")),
+        _ => None,
+    };
+    if let Some((bound, key, missing)) = lone_bound {
+        let span = Span::from_spanned(context, bound);
+        context
+            .error(format!("`{key}` is set without `{missing}`"))
+            .code(ErrorCode::UnmatchedBound)
+            .set_primary_span(span, format!("a range also needs `{missing}`"))
+            .emit();
+        return Err(());
     }
     let mut count = 0;
     if check.greater_than.is_some() {
         count += 1;
     }
+    if check.less_than.is_some() {
+        count += 1;
+    }
     if check.upper_bound.is_some() && check.lower_bound.is_some() {
         count += 1;
     }
@@ -217,17 +991,27 @@ pub(crate) fn convert_check(
         count += 1;
     }
     if count == 0 {
+        // Checks declared with `[[states.checks]]` have an empty span, so point at the name
+        let span = Span::from_spanned(context, &check.name);
         context
-            .error("too many check conditions")
-            .set_primary_span(full_span, "you must specify one check condition per check")
+            .error("check condition missing")
+            .code(ErrorCode::MissingCheckCondition)
+            .set_primary_span(span, "you must specify one check condition per check")
+            .emit();
+        context
+            .help("add one of `greater_than`, `less_than`, `upper_bound` with `lower_bound`, or `flag`")
             .emit();
+        return Err(());
     }
     if count > 1 {
         let mut spans: std::vec::Vec<Span> = std::vec::Vec::new();
         if let Some(gt) = &check.greater_than {
             spans.push(Span::from_spanned(context, gt));
         }
-        if let (Some(u), Some(l)) = (&check.greater_than, &check.lower_bound) {
+        if let Some(lt) = &check.less_than {
+            spans.push(Span::from_spanned(context, lt));
+        }
+        if let (Some(u), Some(l)) = (&check.upper_bound, &check.lower_bound) {
             spans.push(Span::from_spanned(context, u));
             spans.push(Span::from_spanned(context, l));
         }
@@ -235,13 +1019,16 @@ pub(crate) fn convert_check(
             spans.push(Span::from_spanned(context, flag));
         }
 
-        let mut err = context.error("too many command actions").set_primary_span(
-            full_span,
-            format!(
-                "you must specify exactly one check condition, not {}",
-                spans.len()
-            ),
-        );
+        let mut err = context
+            .error("too many check conditions")
+            .code(ErrorCode::TooManyCheckConditions)
+            .set_primary_span(
+                full_span,
+                format!(
+                    "you must specify exactly one check condition, not {}",
+                    spans.len()
+                ),
+            );
 
         for span in spans {
             err = err.span_label(span, "declared here");
@@ -265,19 +1052,47 @@ pub(crate) fn convert_check(
         "pyro2_continuity" => CheckKind::Pyro2Continuity,
         "pyro3_continuity" => CheckKind::Pyro3Continuity,
         _ => {
-            context
+            let target = context.session().target();
+            let names = crate::registry::check_kinds(target).map(|k| k.name);
+            let span = Span::from_spanned(context, &check.check);
+            let err = context
                 .error(format!("no check with name `{check_name}` exists"))
-                .set_primary_span(full_span, format!("unknown check `{check_name}`"))
-                .emit();
+                .code(ErrorCode::UnknownCheck)
+                .set_primary_span(full_span, format!("unknown check `{check_name}`"));
+            match crate::spelling::closest(check_name, names) {
+                Some(closest) => err
+                    .suggest_replacement(
+                        span,
+                        format!("\"{closest}\""),
+                        "a check with a similar name exists",
+                    )
+                    .emit(),
+                None => err.emit(),
+            }
 
+            let kinds: String = crate::registry::check_kinds(target)
+                .map(|k| format!("\n  `{}`: {}", k.name, k.description))
+                .collect();
             context
-                .help("try `apogee`, or `altitude`, or `pyroX_continuity`")
+                .help(format!(
+                    "the `{target}` target supports these checks:{kinds}"
+                ))
                 .set_primary_span_no_msg(full_span)
                 .emit();
 
             return Err(());
         }
     };
+    let pyro = match check_kind {
+        CheckKind::Pyro1Continuity => Some(1),
+        CheckKind::Pyro2Continuity => Some(2),
+        CheckKind::Pyro3Continuity => Some(3),
+        _ => None,
+    };
+    if let Some(channel) = pyro {
+        let span = Span::from_spanned(context, &check.check);
+        crate::hardware::check_pyro_channel(channel, span, context)?;
+    }
 
     #[allow(dead_code)]
     pub enum CheckCondition {
@@ -288,10 +1103,24 @@ pub(crate) fn convert_check(
         Between { upper_bound: f32, lower_bound: f32 },
     }
 
+    for value in [
+        &check.greater_than,
+        &check.less_than,
+        &check.upper_bound,
+        &check.lower_bound,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        note_float_precision(value, context);
+    }
+
     //The user only set one option, now map that to an object and state
     let condition = {
         if let Some(gt) = &check.greater_than {
             CheckCondition::GreaterThan(*gt.get_ref())
+        } else if let Some(lt) = &check.less_than {
+            CheckCondition::LessThan(*lt.get_ref())
         } else if let (Some(u), Some(l)) = (&check.upper_bound, &check.lower_bound) {
             CheckCondition::Between {
                 upper_bound: *u.get_ref(),
@@ -305,6 +1134,7 @@ pub(crate) fn convert_check(
                     let span = Span::from_spanned(context, flag);
                     context
                         .error("flag values must be `set` or `unset`")
+                        .code(ErrorCode::UnknownFlag)
                         .set_primary_span(span, format!("unknown flag value `{check_name}`"))
                         .emit();
                     return Err(());
@@ -315,112 +1145,167 @@ pub(crate) fn convert_check(
         }
     };
 
-    let mismatch_err = |context: &mut Context, span, span_msg| -> Result<!, ()> {
-        let span = Span::from_spanned(context, span);
+    let mismatch_err = |context: &mut Context, span: Span, span_msg: &str| -> Result<!, ()> {
         context
             .error("mismatched check type")
+            .code(ErrorCode::MismatchedCheckType)
             .set_primary_span(span, span_msg)
             .emit();
         Err(())
     };
 
     use common::{CheckData, FloatCondition, NativeFlagCondition, PyroContinuityCondition};
+    let float_condition = |condition| match condition {
+        CheckCondition::GreaterThan(val) => Some(FloatCondition::GreaterThan(val)),
+        CheckCondition::LessThan(val) => Some(FloatCondition::LessThan(val)),
+        CheckCondition::Between {
+            upper_bound,
+            lower_bound,
+        } => Some(FloatCondition::Between {
+            upper_bound,
+            lower_bound,
+        }),
+        CheckCondition::FlagEq(_) => None,
+    };
+    // Where the condition was written, for kinds that were given the wrong type of condition
+    let condition_span = match (
+        &check.flag,
+        [&check.greater_than, &check.less_than, &check.upper_bound],
+    ) {
+        (Some(flag), _) => Span::from_spanned(context, flag),
+        (None, values) => match values.into_iter().flatten().next() {
+            Some(value) => Span::from_spanned(context, value),
+            None => Span::from_spanned(context, &check.check),
+        },
+    };
+    let flag_only = |kind: &str| format!("`{kind}` checks take `flag`");
+    let float_only = |kind: &str| {
+        format!(
+            "`{kind}` checks take `greater_than`, `less_than`, or `upper_bound` and `lower_bound`"
+        )
+    };
+
     // Perform type checking on kind and condition
     let data = match check_kind {
         CheckKind::Apogee => match condition {
             CheckCondition::FlagEq(val) => CheckData::ApogeeFlag(NativeFlagCondition(val)),
-            _ => mismatch_err(context, &check.check, "")?,
+            _ => mismatch_err(context, condition_span, &flag_only(check_name))?,
         },
-        CheckKind::Altitude => match condition {
-            CheckCondition::Between {
-                upper_bound,
-                lower_bound,
-            } => CheckData::Altitude(FloatCondition::Between {
-                upper_bound,
-                lower_bound,
-            }),
-            CheckCondition::GreaterThan(val) => {
-                CheckData::Altitude(FloatCondition::GreaterThan(val))
-            }
-            CheckCondition::LessThan(val) => CheckData::Altitude(FloatCondition::LessThan(val)),
-            _ => panic!(),
+        CheckKind::Altitude => match float_condition(condition) {
+            Some(condition) => CheckData::Altitude(condition),
+            None => mismatch_err(context, condition_span, &float_only(check_name))?,
         },
         CheckKind::Pyro1Continuity => match condition {
             CheckCondition::FlagEq(val) => CheckData::Pyro1Continuity(PyroContinuityCondition(val)),
-            _ => panic!(),
+            _ => mismatch_err(context, condition_span, &flag_only(check_name))?,
         },
         CheckKind::Pyro2Continuity => match condition {
             CheckCondition::FlagEq(val) => CheckData::Pyro2Continuity(PyroContinuityCondition(val)),
-            _ => panic!(),
+            _ => mismatch_err(context, condition_span, &flag_only(check_name))?,
         },
         CheckKind::Pyro3Continuity => match condition {
             CheckCondition::FlagEq(val) => CheckData::Pyro3Continuity(PyroContinuityCondition(val)),
-            _ => panic!(),
+            _ => mismatch_err(context, condition_span, &flag_only(check_name))?,
         },
     };
 
-    let transition = match &check.transition {
-        Some(state) => Some((temp.get_index(state, context)?, state)),
-        None => None,
-    };
-
-    let abort = match &check.abort {
-        Some(state) => Some((temp.get_index(state, context)?, state)),
-        None => None,
-    };
+    Ok(data)
+}
 
-    let transition = match (transition, abort) {
-        (Some(t), None) => Some(StateTransition::Transition(t.0)),
-        (None, Some(a)) => Some(StateTransition::Abort(a.0)),
-        (None, None) => None,
-        (Some(mut t), Some(mut a)) => {
-            if t.1.start() > a.1.start() {
-                // swap a and t so that t is always first
-                std::mem::swap(&mut t, &mut a);
-            }
-            let s1 = Span::from_spanned(context, t.1);
-            let s2 = Span::from_spanned(context, a.1);
-            context
-                .error("abort and transition cannot be active in the same check")
-                .set_primary_span_no_msg(s1)
-                .span_label(s2, "second action declared here")
-                .emit();
+/// Returns the literal that `value` was written as, along with the offset it starts at
+pub(crate) fn float_literal<'c>(
+    value: &SpannedValue<f32>,
+    context: &'c Context,
+) -> Option<(usize, &'c str)> {
+    // toml only reports the last character of a float as its span, so walk backwards to find
+    // the start of the literal
+    let end = value.end();
+    let before = context.source_before(end)?;
+    let start = before
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || "._+-".contains(c)))
+        .map_or(0, |i| i + 1);
+    Some((end - (before.len() - start), &before[start..]))
+}
 
-            context
-                .help("remove either `abort` or `transition`")
-                .add_span(s1)
-                .add_span(s2)
-                .emit();
-            return Err(());
-        }
+/// Emits a note if the literal that `value` was parsed from cannot be stored exactly in an `f32`,
+/// showing the value the rocket will actually use.
+/// Only active when enabled with [`crate::Session::set_float_precision_notes`]
+fn note_float_precision(value: &SpannedValue<f32>, context: &mut Context) {
+    if !context.session().float_precision_notes() {
+        return;
+    }
+    let (start, literal) = match float_literal(value, context) {
+        Some(literal) => literal,
+        None => return,
+    };
+    let end = value.end();
+    let literal = literal.replace('_', "");
+    let written: f64 = match literal.parse() {
+        Ok(written) => written,
+        Err(_) => return,
     };
+    let stored = f64::from(*value.get_ref());
+    if stored.is_nan() || written == stored {
+        return;
+    }
 
-    Ok(index::Check::new(data, transition))
+    let span = context.subspan(start, end);
+    context
+        .note(format!(
+            "`{literal}` cannot be represented exactly as a 32 bit float"
+        ))
+        .set_primary_span(span, format!("stored as `{stored}`"))
+        .emit();
 }
 
 #[cfg(test)]
 mod tests {
-    use common::{index::StateIndex, CheckData, FloatCondition, PyroContinuityCondition};
+    use common::{
+        index::StateIndex, CheckData, FloatCondition, NativeFlagCondition, PyroContinuityCondition,
+    };
 
     use super::{common, index};
-    use crate::{upper, upper::cs, Session};
+    use crate::{
+        limits::{DataRates, Target},
+        upper,
+        upper::cs,
+        Session,
+    };
 
     #[test]
     fn basic1() {
         let upper = upper::ConfigFile {
             default_state: Some(cs("PowerOn".to_owned())),
+            abort_state: None,
+            recovery: None,
+            crosscheck: None,
+            radio: None,
+            self_test: None,
+            mission_events: vec![],
+            fault_handling: None,
+            tick_rate: None,
+            overrides: vec![],
+            path_policies: vec![],
+            lints: Default::default(),
+            checks: Default::default(),
+            stages: vec![],
             states: cs(vec![cs(upper::State {
                 name: cs("PowerOn".to_owned()),
                 timeout: None,
+                abort_safe: false,
+                use_check: None,
                 checks: vec![cs(upper::Check {
                     name: cs("Takeoff".to_owned()),
                     check: cs("altitude".to_owned()),
                     greater_than: Some(cs(100.0)),
+                    less_than: None,
                     transition: None,
                     upper_bound: None,
                     flag: None,
+                    requires: None,
                     lower_bound: None,
                     abort: None,
+                    separate: None,
                 })],
                 commands: vec![],
             })]),
@@ -450,34 +1335,57 @@ mod tests {
     fn basic2() {
         let upper = upper::ConfigFile {
             default_state: None,
+            abort_state: None,
+            recovery: None,
+            crosscheck: None,
+            radio: None,
+            self_test: None,
+            mission_events: vec![],
+            fault_handling: None,
+            tick_rate: None,
+            overrides: vec![],
+            path_policies: vec![],
+            lints: Default::default(),
+            checks: Default::default(),
+            stages: vec![],
             states: cs(vec![
                 cs(upper::State {
                     name: cs("Ground".to_owned()),
                     timeout: None,
+                    abort_safe: false,
+                    use_check: None,
                     checks: vec![cs(upper::Check {
                         name: cs("Takeoff".to_owned()),
                         check: cs("altitude".to_owned()),
                         greater_than: Some(cs(100.0)),
+                        less_than: None,
                         transition: None,
                         upper_bound: None,
                         flag: None,
+                        requires: None,
                         lower_bound: None,
                         abort: None,
+                        separate: None,
                     })],
                     commands: vec![],
                 }),
                 cs(upper::State {
                     name: cs("Launch".to_owned()),
                     timeout: None,
+                    abort_safe: false,
+                    use_check: None,
                     checks: vec![cs(upper::Check {
                         name: cs("Pyro1Cont".to_owned()),
                         check: cs("pyro1_continuity".to_owned()),
                         greater_than: None,
+                        less_than: None,
                         transition: None,
                         upper_bound: None,
                         flag: Some(cs("set".to_owned())),
+                        requires: None,
                         lower_bound: None,
                         abort: None,
+                        separate: None,
                     })],
                     commands: vec![],
                 }),
@@ -521,18 +1429,36 @@ mod tests {
         let bad_name = "I do not exist!".to_owned();
         let upper = upper::ConfigFile {
             default_state: Some(cs(bad_name.clone())),
+            abort_state: None,
+            recovery: None,
+            crosscheck: None,
+            radio: None,
+            self_test: None,
+            mission_events: vec![],
+            fault_handling: None,
+            tick_rate: None,
+            overrides: vec![],
+            path_policies: vec![],
+            lints: Default::default(),
+            checks: Default::default(),
+            stages: vec![],
             states: cs(vec![cs(upper::State {
                 name: cs("PowerOn".to_owned()),
                 timeout: None,
+                abort_safe: false,
+                use_check: None,
                 checks: vec![cs(upper::Check {
                     name: cs("Takeoff".to_owned()),
                     check: cs("altitude".to_owned()),
                     greater_than: Some(cs(100.0)),
+                    less_than: None,
                     transition: None,
                     upper_bound: None,
                     flag: None,
+                    requires: None,
                     lower_bound: None,
                     abort: None,
+                    separate: None,
                 })],
                 commands: vec![],
             })]),
@@ -544,18 +1470,36 @@ mod tests {
     fn error_mutiple_subchecks() {
         let upper = upper::ConfigFile {
             default_state: None,
+            abort_state: None,
+            recovery: None,
+            crosscheck: None,
+            radio: None,
+            self_test: None,
+            mission_events: vec![],
+            fault_handling: None,
+            tick_rate: None,
+            overrides: vec![],
+            path_policies: vec![],
+            lints: Default::default(),
+            checks: Default::default(),
+            stages: vec![],
             states: cs(vec![cs(upper::State {
                 name: cs("PowerOn".to_owned()),
                 timeout: None,
+                abort_safe: false,
+                use_check: None,
                 checks: vec![cs(upper::Check {
                     name: cs("Check".to_owned()),
                     check: cs("pyro1_continuity".to_owned()),
                     greater_than: Some(cs(100.0)),
+                    less_than: None,
                     transition: None,
                     upper_bound: Some(cs(0.0)),
                     flag: Some(cs("set".to_owned())),
+                    requires: None,
                     lower_bound: Some(cs(0.5)),
                     abort: None,
+                    separate: None,
                 })],
                 commands: vec![],
             })]),
@@ -563,10 +1507,595 @@ mod tests {
         check_error(upper);
     }
 
+    #[test]
+    fn abort_state_shorthand() {
+        let config = r#"
+abort_state = "Safe"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Pyro1Check"
+check = "pyro1_continuity"
+flag = "unset"
+abort = true
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        let lowered = super::verify(&mid, &mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        let safe = unsafe { StateIndex::new_unchecked(1) };
+        assert_eq!(
+            lowered.states[0].checks[0].transition,
+            Some(index::StateTransition::Abort(safe))
+        );
+    }
+
+    #[test]
+    fn error_abort_state_not_abort_safe() {
+        let config = r#"
+abort_state = "Safe"
+
+[[states]]
+name = "Safe"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(super::verify(&mid, &mut context).is_err());
+        assert!(context.end_phase_and_emit().is_err());
+    }
+
+    #[test]
+    fn error_abort_shorthand_without_abort_state() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Pyro1Check"
+check = "pyro1_continuity"
+flag = "unset"
+abort = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(super::verify(&mid, &mut context).is_err());
+        assert!(context.end_phase_and_emit().is_err());
+    }
+
+    #[test]
+    fn incomplete_conditions() {
+        let config = |condition: &str| {
+            format!("[[states]]\nname = \"A\"\n\n[[states.checks]]\nname = \"High\"\ncheck = \"altitude\"\n{condition}\ntransition = \"A\"\n")
+        };
+        let errors = |condition: &str| {
+            let failure = crate::verify_str(&config(condition), "test.toml").unwrap_err();
+            failure.diagnostics
+        };
+
+        let lone = errors("upper_bound = 500.0");
+        assert_eq!(
+            lone[0].message,
+            "`upper_bound` is set without `lower_bound`"
+        );
+        assert_eq!(lone[0].code.as_deref(), Some("E0074"));
+        assert_eq!(lone[0].spans[0].line, 7);
+        let lone = errors("lower_bound = 100.0");
+        assert_eq!(
+            lone[0].message,
+            "`lower_bound` is set without `upper_bound`"
+        );
+
+        // A misspelled condition leaves the check without one
+        let missing = errors("greater_thn = 5.0");
+        let error = missing
+            .iter()
+            .find(|d| d.level == codemap_diagnostic::Level::Error)
+            .unwrap();
+        assert_eq!(error.message, "check condition missing");
+        assert_eq!(error.spans[0].line, 5);
+        assert!(missing
+            .iter()
+            .any(|d| d.message.contains("`greater_than`, `less_than`")));
+    }
+
+    #[test]
+    fn unsupported_data_rate() {
+        let config = "[[states]]\nname = \"PowerOn\"\n\n[[states.commands]]\ndata_rate = 30\n";
+        assert!(crate::verify_str(config, "test.toml").is_ok());
+        let failure = crate::verify_str(&config.replace("30", "2000"), "test.toml").unwrap_err();
+        let error = &failure.diagnostics[0];
+        assert_eq!(error.message, "unsupported data rate 2000");
+        let fix = failure
+            .diagnostics
+            .iter()
+            .find_map(|d| d.replacement.as_deref());
+        assert_eq!(fix, Some("1000"));
+
+        // A board whose logger only supports some rates
+        let mut board = crate::hardware::Profile::default();
+        board.limits.data_rates = DataRates::Table(vec![1, 10, 20, 50]);
+        let mut session = Session::new();
+        session.set_board(board);
+        let err = crate::verify_inner(&mut session, config.to_owned(), "test.toml".to_owned())
+            .unwrap_err();
+        let label = err.diagnostics()[0].spans[0].label.as_deref();
+        assert_eq!(
+            label,
+            Some("the logger supports data rates of 1, 10, 20, 50")
+        );
+        assert_eq!(DataRates::Table(vec![1, 10, 20, 50]).nearest(30), Some(20));
+    }
+
+    #[test]
+    fn error_over_limits() {
+        let too_many_checks = (0..=Target::Nova.limits().max_checks_per_state)
+            .map(|i| {
+                format!(
+                    r#"
+[[states.checks]]
+name = "Check{i}"
+check = "apogee"
+flag = "set"
+"#
+                )
+            })
+            .collect::<String>();
+        let data_rate = r#"
+[[states.commands]]
+data_rate = 0
+"#;
+        for body in [too_many_checks.as_str(), data_rate] {
+            let config = format!("[[states]]\nname = \"PowerOn\"\n{body}");
+            let mut session = Session::new();
+            let mut context = session.testing(&config);
+            let mid = upper::verify(&mut context).unwrap();
+            context.end_phase_and_emit().unwrap();
+            assert!(super::verify(&mid, &mut context).is_err());
+            assert!(context.end_phase_and_emit().is_err());
+        }
+    }
+
+    #[test]
+    fn error_too_many_states() {
+        // More states than a `u8` index can refer to
+        let states: String = (0..300)
+            .map(|i| format!("[[states]]\nname = \"State{i}\"\n"))
+            .collect();
+        let mut session = Session::new();
+        let mut context = session.testing(&states);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(super::verify(&mid, &mut context).is_err());
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let overflowing = errors[0].spans.len() - 1;
+        assert_eq!(overflowing, 300 - Target::Nova.limits().max_states);
+    }
+
+    #[test]
+    fn unreachable_states() {
+        let config = r#"
+abort_state = "Safe"
+
+[fault_handling]
+barometer = { action = "abort" }
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Fligth"
+
+[[states]]
+name = "Fligth"
+
+[[states]]
+name = "Flight"
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        let mut reached: std::vec::Vec<_> = super::reachable_states(&mid).into_iter().collect();
+        reached.sort();
+        assert_eq!(reached, ["Armed", "Fligth", "Safe"]);
+
+        super::verify(&mid, &mut context).unwrap();
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        assert_eq!(diagnostics[0].message, "state `Flight` is unreachable");
+    }
+
+    #[test]
+    fn timeouts() {
+        let config = |seconds: &str, transition: &str| {
+            format!(
+                r#"
+[[states]]
+name = "Drogue"
+
+[states.timeout]
+seconds = {seconds}
+transition = "{transition}"
+
+[[states]]
+name = "Main"
+"#
+            )
+        };
+        let mut session = Session::new();
+        let mut context = session.testing(&config("120.0", "Main"));
+        let mid = upper::verify(&mut context).unwrap();
+        let lower = super::verify(&mid, &mut context).unwrap();
+        let timeout = lower.states[0].timeout.unwrap();
+        assert_eq!(timeout.time, 120.0);
+        assert_eq!(
+            timeout.transition,
+            index::StateTransition::Transition(unsafe { StateIndex::new_unchecked(1) })
+        );
+
+        for (seconds, transition, message) in [
+            ("-1.0", "Main", "invalid timeout `-1`"),
+            ("nan", "Main", "invalid timeout `NaN`"),
+            ("10.0", "Mian", "state not found `Mian`"),
+        ] {
+            let mut session = Session::new();
+            let mut context = session.testing(&config(seconds, transition));
+            let mid = upper::verify(&mut context).unwrap();
+            assert!(super::verify(&mid, &mut context).is_err());
+            let diagnostics = context.end_phase_and_emit().unwrap_err();
+            assert_eq!(diagnostics[0].message, message);
+            if transition == "Mian" {
+                assert_eq!(session.suggestions()[0].replacement, "\"Main\"");
+            }
+        }
+    }
+
+    #[test]
+    fn only_state() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "altitude"
+less_than = 20.0
+transition = "Landed"
+
+[[states]]
+name = "Landed"
+
+[[states.checks]]
+name = "Again"
+check = "altitude"
+greater_than = 30.0
+transition = "Missing"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        let neighbourhood = super::Neighbourhood::of(&mid, "Flight");
+        assert_eq!(neighbourhood.successors, ["Landed"]);
+        assert_eq!(neighbourhood.predecessors, ["Armed"]);
+
+        let state = super::verify_state(&mid, "Flight", &mut context).unwrap();
+        assert_eq!(state.checks.len(), 1);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        assert_eq!(
+            diagnostics[0].message,
+            "only `Flight` was verified, `Armed`, `Landed` are assumed to be valid"
+        );
+
+        assert!(super::verify_state(&mid, "Landed", &mut context).is_err());
+        assert!(context.end_phase_and_emit().is_err());
+        assert!(super::verify_state(&mid, "Descent", &mut context).is_err());
+    }
+
+    #[test]
+    fn error_bad_names() {
+        for (state_name, check_name) in [
+            ("Power On", "Takeoff"),
+            ("PowerOn", ""),
+            ("1stStage", "Takeoff"),
+            ("PowerOn", "ThisCheckNameIsFarTooLongToBeUseful"),
+        ] {
+            let upper = upper::ConfigFile {
+                default_state: None,
+                abort_state: None,
+                recovery: None,
+                crosscheck: None,
+                radio: None,
+                self_test: None,
+                mission_events: vec![],
+                fault_handling: None,
+                tick_rate: None,
+                overrides: vec![],
+                path_policies: vec![],
+                lints: Default::default(),
+                checks: Default::default(),
+                stages: vec![],
+                states: cs(vec![cs(upper::State {
+                    name: cs(state_name.to_owned()),
+                    timeout: None,
+                    abort_safe: false,
+                    use_check: None,
+                    checks: vec![cs(upper::Check {
+                        name: cs(check_name.to_owned()),
+                        check: cs("altitude".to_owned()),
+                        greater_than: Some(cs(100.0)),
+                        less_than: None,
+                        transition: None,
+                        upper_bound: None,
+                        flag: None,
+                        requires: None,
+                        lower_bound: None,
+                        abort: None,
+                        separate: None,
+                    })],
+                    commands: vec![],
+                })]),
+            };
+            check_error(upper);
+        }
+    }
+
+    #[test]
+    fn inferred_default_state() {
+        let config = "[[states]]\nname = \"PowerOn\"\n\n[[states]]\nname = \"Flight\"\n";
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        super::verify(&mid, &mut context).unwrap();
+        let notes = context.end_phase_and_emit().unwrap();
+        assert_eq!(
+            notes.last().unwrap().message,
+            "no `default_state` declared, so the flight computer starts in `PowerOn`"
+        );
+
+        let mut session = Session::new();
+        session.set_require_default_state(true);
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(super::verify(&mid, &mut context).is_err());
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(
+            errors[errors.len() - 2].message,
+            "no `default_state` declared"
+        );
+    }
+
+    #[test]
+    fn float_precision_notes() {
+        let config = r#"
+default_state = "PowerOn"
+
+[[states]]
+name = "PowerOn"
+
+[[states.checks]]
+name = "Takeoff"
+check = "altitude"
+greater_than = 100.0
+
+[[states.commands]]
+pyro1 = true
+delay = 0.1
+"#;
+        let mut session = Session::new();
+        session.set_float_precision_notes(true);
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        super::verify(&mid, &mut context).unwrap();
+        let notes = context.end_phase_and_emit().unwrap();
+        // `100.0` is exact, `0.1` is not
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].message.contains("`0.1`"));
+    }
+
+    #[test]
+    fn high_level_checks() {
+        let config = r#"
+[recovery]
+launch_altitude = 30.0
+main_altitude = 150.0
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "launch_detected"
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee_passed"
+transition = "Drogue"
+
+[[states]]
+name = "Drogue"
+
+[[states.checks]]
+name = "Main"
+check = "below_main_altitude"
+transition = "Flight"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        let lower = super::verify(&mid, &mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        let checks: std::vec::Vec<_> = lower.states.iter().map(|state| state.checks[0]).collect();
+        let (flight, drogue) =
+            unsafe { (StateIndex::new_unchecked(1), StateIndex::new_unchecked(2)) };
+        let transition = |index| Some(index::StateTransition::Transition(index));
+        assert_eq!(
+            checks,
+            [
+                index::Check::new(
+                    CheckData::Altitude(FloatCondition::GreaterThan(30.0)),
+                    transition(flight)
+                ),
+                index::Check::new(
+                    CheckData::ApogeeFlag(NativeFlagCondition(true)),
+                    transition(drogue)
+                ),
+                index::Check::new(
+                    CheckData::Altitude(FloatCondition::LessThan(150.0)),
+                    transition(flight)
+                ),
+            ]
+        );
+
+        // Missing recovery values and explicit conditions are errors
+        for bad in [
+            config.replace("main_altitude = 150.0", ""),
+            config.replace(
+                "check = \"apogee_passed\"",
+                "check = \"apogee_passed\"\nflag = \"set\"",
+            ),
+            config.replace("launch_altitude = 30.0", "launch_altitude = -1.0"),
+        ] {
+            let mut session = Session::new();
+            let mut context = session.testing(&bad);
+            let mid = upper::verify(&mut context).unwrap();
+            assert!(super::verify(&mid, &mut context).is_err());
+        }
+    }
+
+    #[test]
+    fn altitude_conditions() {
+        let config = r#"
+[[states]]
+name = "Drogue"
+
+[[states.checks]]
+name = "BelowMain"
+check = "altitude"
+CONDITION
+transition = "Main"
+
+[[states]]
+name = "Main"
+"#;
+        let main = Some(index::StateTransition::Transition(unsafe {
+            StateIndex::new_unchecked(1)
+        }));
+        for (condition, expected) in [
+            ("less_than = 300.0", FloatCondition::LessThan(300.0)),
+            ("greater_than = 5.0", FloatCondition::GreaterThan(5.0)),
+            (
+                "upper_bound = 300.0\nlower_bound = 250.0",
+                FloatCondition::Between {
+                    upper_bound: 300.0,
+                    lower_bound: 250.0,
+                },
+            ),
+        ] {
+            let mut session = Session::new();
+            let mut context = session.testing(&config.replace("CONDITION", condition));
+            let mid = upper::verify(&mut context).unwrap();
+            let lower = super::verify(&mid, &mut context).unwrap();
+            let check = index::Check::new(CheckData::Altitude(expected), main);
+            assert_eq!(lower.states[0].checks[0], check);
+        }
+
+        for bad in ["flag = \"set\"", "less_than = 300.0\ngreater_than = 5.0"] {
+            let mut session = Session::new();
+            let mut context = session.testing(&config.replace("CONDITION", bad));
+            let mid = upper::verify(&mut context).unwrap();
+            assert!(super::verify(&mid, &mut context).is_err());
+        }
+    }
+
+    #[test]
+    fn duplicate_states() {
+        let config = "[[states]]\nname = \"Pad\"\n\n[[states]]\nname = \"Pad\"\n";
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        assert!(super::verify(&mid, &mut context).is_err());
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(errors[0].message, "state `Pad` is declared twice");
+
+        // A stage's state collides with a top level state of the same qualified name
+        let config = r#"
+[[states]]
+name = "Booster_Boost"
+
+[[stages]]
+name = "Booster"
+
+[[stages.states]]
+name = "Boost"
+"#;
+        let err =
+            crate::load_config(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap_err();
+        assert_eq!(
+            err.diagnostics()[0].message,
+            "state `Booster_Boost` is declared twice"
+        );
+    }
+
+    #[test]
+    fn mismatched_conditions() {
+        for (condition, label) in [
+            (
+                "check = \"altitude\"\nflag = \"set\"",
+                "`altitude` checks take `greater_than`, `less_than`, or `upper_bound` and `lower_bound`",
+            ),
+            (
+                "check = \"pyro1_continuity\"\ngreater_than = 1.0",
+                "`pyro1_continuity` checks take `flag`",
+            ),
+        ] {
+            let config = format!(
+                "[[states]]\nname = \"Armed\"\n\n[[states.checks]]\nname = \"Bad\"\n{condition}\ntransition = \"Armed\"\n"
+            );
+            let mut session = Session::new();
+            let mut context = session.testing(&config);
+            let mid = upper::verify(&mut context).unwrap();
+            assert!(super::verify(&mid, &mut context).is_err());
+            let errors = context.end_phase_and_emit().unwrap_err();
+            assert_eq!(errors[0].message, "mismatched check type");
+            assert_eq!(errors[0].spans[0].label.as_deref(), Some(label));
+        }
+    }
+
     fn check_ok(input: upper::ConfigFile, expected: index::ConfigFile) {
         let mut session = Session::new();
         let mut context = session.testing("");
-        let cfg_file = super::verify(input, &mut context);
+        let cfg_file = super::verify(&input, &mut context);
         let errors = context.end_phase();
 
         match errors {
@@ -584,7 +2113,7 @@ mod tests {
     fn check_error(input: upper::ConfigFile) {
         let mut session = Session::new();
         let mut context = session.testing("");
-        let cfg_file = super::verify(input, &mut context);
+        let cfg_file = super::verify(&input, &mut context);
         let errors = context.end_phase();
 
         match errors {
@@ -1,47 +1,1208 @@
 //! Does this show up on clap?
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
 use anyhow::Result;
-use clap::Parser;
-use codemap_diagnostic::Diagnostic;
+use clap::{Parser, Subcommand};
 use log::*;
+use nova_verifier::{
+    complexity::Ceiling,
+    limits::Target,
+    lint::{Lint, LintLevel},
+    ncf::VersionRange,
+    prelude::index,
+    presets::Preset,
+    upper::InputFormat,
+    EmitKind, ErrorFormat, SarifFormatter, Tally,
+};
 
 /// Command line utility for converting toml config files to .ncf files for the Nova Flight Computer
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    global: GlobalOptions,
+
+    /// Without a subcommand, the arguments of `build`. Deprecated, use `build` or `check`
+    #[clap(flatten)]
+    build: BuildArgs,
+
+    /// Only verify the input, the same as the `check` subcommand
+    #[clap(long, hide = true, conflicts_with = "emit")]
+    check: bool,
+
+    /// Only verify one state, the same as `check --only-state`
+    #[clap(long, hide = true, value_name = "NAME", conflicts_with_all = &["emit", "stats"])]
+    only_state: Option<String>,
+}
+
+/// Options that every subcommand accepts
+#[derive(clap::Args, Debug)]
+struct GlobalOptions {
+    /// Never talk to a flight computer or the network. Also enabled by setting `NOVA_OFFLINE=1`
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// How to print diagnostics: `human`, `short` for one `file:line:col: level: message` line
+    /// per diagnostic, or `json` for one JSON object per line
+    #[clap(
+        long,
+        global = true,
+        alias = "diagnostics-format",
+        default_value_t = ErrorFormat::default()
+    )]
+    error_format: ErrorFormat,
+}
+
+/// Options shared by every subcommand that verifies a config
+#[derive(clap::Args, Debug)]
+struct VerifyOptions {
     /// The path to the input configuration toml file
     #[clap(default_value_t = String::from("rocket.toml"))]
     input: String,
 
+    /// The syntax of the input file, `toml`, `json`, `yaml`, or `ron`.
+    /// Guessed from the input's extension if not given
+    #[clap(long)]
+    input_format: Option<InputFormat>,
+
+    /// Ignore the local override file next to the input, such as `rocket.local.toml` for
+    /// `rocket.toml`. When it exists, `check`, `watch`, and `graph` otherwise merge it over the
+    /// input, and `build` does with `--local`
+    #[clap(long)]
+    no_local: bool,
+
+    /// Add the states and sections of another config file, such as `abort.toml`, to the input.
+    /// May be repeated. A state or value declared in more than one file is an error
+    #[clap(long, value_name = "PATH")]
+    include: Vec<String>,
+
+    /// Warn if the flight can take longer than this many seconds from launch until it reaches a
+    /// final state
+    #[clap(long, value_name = "SECONDS")]
+    mission_duration: Option<f32>,
+
+    /// Fail if the encoded config is larger than this many bytes
+    #[clap(long)]
+    flash_budget: Option<usize>,
+
+    /// Note every float literal that cannot be stored exactly as a 32 bit float
+    #[clap(long)]
+    float_precision_notes: bool,
+
+    /// Warn about decimal values written as integers, such as `delay = 1` rather than `delay = 1.0`
+    #[clap(long)]
+    strict_floats: bool,
+
+    /// Reject keys that the config language does not know, such as a misspelled `delay`, rather
+    /// than ignoring them
+    #[clap(long)]
+    strict_keys: bool,
+
+    /// Fail if the config does not declare `default_state`, rather than starting in the first state
+    #[clap(long)]
+    require_default_state: bool,
+
+    /// Fail if the config produces any warning, for flight ready builds
+    #[clap(long)]
+    deny_warnings: bool,
+
+    /// Hide the warnings of a lint, such as `--allow abort_path`. Takes precedence over the
+    /// config's `[lints]` table
+    #[clap(long, value_name = "LINT")]
+    allow: Vec<Lint>,
+
+    /// Report a lint as a warning, even if the config's `[lints]` table allows or denies it
+    #[clap(long, value_name = "LINT")]
+    warn: Vec<Lint>,
+
+    /// Report the warnings of a lint as errors, such as `--deny infinite_loop`. Takes precedence
+    /// over the config's `[lints]` table
+    #[clap(long, value_name = "LINT")]
+    deny: Vec<Lint>,
+
+    /// Warn if a complexity metric of the config is above MAX. Metrics: `states`, `transitions`,
+    /// `cyclomatic`, `checks-per-state`
+    #[clap(long, value_name = "METRIC=MAX")]
+    complexity_limit: Vec<Ceiling>,
+
+    /// Build a config for a lab dry run, where every pyro command turns the beacon on instead.
+    /// The config's metadata sidecar is marked as a ground test, so it is not mistaken for a
+    /// flight config
+    #[clap(long)]
+    ground_test: bool,
+
+    /// The flight computer to build the config for
+    #[clap(long, default_value_t = Target::default())]
+    target: Target,
+
+    /// The board to verify the config against, either a built-in board (`nova-fc-v1` or
+    /// `nova-fc-v2`) or the path of a TOML board profile. Defaults to the target's board
+    #[clap(long, value_name = "BOARD")]
+    board: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Sign a compiled config as a reviewer, adding the approval to the config's metadata sidecar
+    Approve(ApproveArgs),
+
+    /// Compare a post-flight state transition log against the config that flew
+    Analyze(AnalyzeArgs),
+
+    /// Create a new config file from a built in preset
+    Init(InitArgs),
+
+    /// Check that a compiled .ncf file is intact and can be read by the firmware it will be
+    /// uploaded to
+    ValidateNcf(ValidateNcfArgs),
+
+    /// Re-encode a compiled .ncf file for firmware that reads a different format version
+    Transcode(TranscodeArgs),
+
+    /// Check that the configs of two redundant flight computers agree on deployment altitudes,
+    /// pyro commands, and abort logic
+    Crosscheck(CrosscheckArgs),
+
+    /// Verify a config and write the encoded .ncf file, or build every config listed in a
+    /// `nova.toml` workspace manifest with `--workspace`
+    Build(BuildArgs),
+
+    /// Verify a config without writing anything. Exits with a nonzero code if verification fails
+    Check(CheckArgs),
+
+    /// Write the state machine of a config as a Graphviz digraph
+    Graph(GraphArgs),
+
+    /// Remove the `out` directory that `build --workspace` writes artifacts to
+    Clean(CleanArgs),
+
+    /// Reconstruct a config file from a compiled .ncf file
+    Decompile(DecompileArgs),
+
+    /// Print what changed between two configs, each either a config file or a compiled .ncf file
+    Diff(DiffArgs),
+
+    /// Print a table of the states, checks, commands, and transitions in a compiled .ncf file,
+    /// along with how many bytes each takes up
+    Inspect(InspectArgs),
+
+    /// Run a compiled .ncf file against a CSV profile of sensor readings, such as a recording of a
+    /// past flight, and print every transition and command
+    Sim(SimArgs),
+
+    /// Explain the diagnostic with a code, such as `E0001` or `pre_launch_pyro`
+    Explain(ExplainArgs),
+
+    /// Verify a config every time it is saved, until interrupted. Nothing is written
+    Watch(WatchArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct WatchArgs {
+    #[clap(flatten)]
+    options: VerifyOptions,
+
+    /// Only verify the state called NAME, see `check --only-state`
+    #[clap(long, value_name = "NAME")]
+    only_state: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    #[clap(flatten)]
+    options: VerifyOptions,
+
+    /// Only verify the state called NAME, assuming the states it transitions to and from are
+    /// valid. Faster than verifying the whole config while editing one state
+    #[clap(long, value_name = "NAME", conflicts_with_all = &["stats", "verbose"])]
+    only_state: Option<String>,
+
+    /// Print a summary table of the states, checks, and commands in the config after verifying it
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Print how many encoded bytes each state, check, and command takes up, and how long each
+    /// state can last
+    #[clap(long)]
+    stats: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct GraphArgs {
+    #[clap(flatten)]
+    options: VerifyOptions,
+
+    /// Where to write the digraph. Defaults to the input with a `.dot` extension
+    #[clap(short, long, value_name = "PATH")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExplainArgs {
+    /// The code shown in brackets after the level of a diagnostic. Without it, every code is
+    /// listed
+    code: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// The previous config, such as the last one accepted for flight
+    old: String,
+
+    /// The config to compare against it
+    new: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct DecompileArgs {
+    /// The compiled .ncf file
+    config: String,
+
+    /// The config file the .ncf file was built from, used to name states and checks. Without it,
+    /// names are made up from their position
+    #[clap(long, value_name = "PATH")]
+    source: Option<String>,
+
+    /// Where to write the config file. Printed to stdout if not given
+    #[clap(short, long, value_name = "PATH")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct InspectArgs {
+    /// The compiled .ncf file
+    config: String,
+
+    /// The config file the .ncf file was built from, used to name states and checks. Without it,
+    /// names are made up from their position
+    #[clap(long, value_name = "PATH")]
+    source: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SimArgs {
+    /// The compiled .ncf file
+    config: String,
+
+    /// The sensor readings, as CSV with a `time` column and columns named after the check kinds
+    /// that read them, such as `altitude` and `apogee`
+    #[clap(long, value_name = "PATH")]
+    profile: String,
+
+    /// The config file the .ncf file was built from, used to name states and checks. Without it,
+    /// names are made up from their position
+    #[clap(long, value_name = "PATH")]
+    source: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildArgs {
+    #[clap(flatten)]
+    options: VerifyOptions,
+
     /// The name of the output
     #[clap(default_value_t = String::from("config.ncf"))]
     output: String,
+
+    /// Print a summary table of the states, checks, and commands in the config after verifying it
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Print how many encoded bytes each state, check, and command takes up, and how long each
+    /// state can last
+    #[clap(long)]
+    stats: bool,
+
+    /// Write an intermediate artifact, optionally to a specific path.
+    /// Supported kinds: `upper-json`, `patch`, `lang-docs`, `abort-reasons`, `self-test`,
+    /// `schedule`, `fault-handling`, `tick-rate`, `dot`, `json-schema`, `c-header`.
+    /// With `--workspace`, artifacts are written next to each member's config and take no path
+    #[clap(long, value_name = "KIND[=PATH]")]
+    emit: Vec<EmitArg>,
+
+    /// The previously uploaded .ncf file that `--emit patch` diffs against
+    #[clap(long, value_name = "PATH")]
+    patch_base: Option<String>,
+
+    /// Merge the local override file next to the input over it, such as `rocket.local.toml` for
+    /// `rocket.toml`. The override file is recorded in the output's metadata sidecar
+    #[clap(long, conflicts_with_all = &["no-local", "workspace"])]
+    local: bool,
+
+    /// Build every member of the workspace instead of the input
+    #[clap(long)]
+    workspace: bool,
+
+    /// The workspace manifest. Defaults to the `nova.toml` in the current directory or the
+    /// closest one above it
+    #[clap(long, value_name = "PATH", requires = "workspace")]
+    manifest: Option<String>,
 }
 
-fn main() {
-    match run() {
-        Ok(d) => {
-            info!("Verify finished with {} diagnostics", d.len());
+#[derive(clap::Args, Debug)]
+struct CleanArgs {
+    /// The workspace manifest. Defaults to the `nova.toml` in the current directory or the
+    /// closest one above it
+    #[clap(long, value_name = "PATH")]
+    manifest: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateNcfArgs {
+    /// The compiled .ncf file to check
+    config: String,
+
+    /// The format versions that the firmware reports it can read, as `N` or `MIN..=MAX`
+    #[clap(long, value_name = "RANGE")]
+    firmware_versions: Option<VersionRange>,
+}
+
+#[derive(clap::Args, Debug)]
+struct TranscodeArgs {
+    /// The compiled .ncf file to convert
+    input: String,
+
+    /// The path to write the converted .ncf file to
+    output: String,
+
+    /// The format version to convert to
+    #[clap(long, value_name = "N")]
+    to_version: u16,
+}
+
+#[derive(clap::Args, Debug)]
+struct CrosscheckArgs {
+    /// The first flight computer's configuration file
+    first: String,
+
+    /// The second flight computer's configuration file
+    second: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct InitArgs {
+    /// The recovery scheme to generate a config for.
+    /// Supported presets: `dual-deploy`, `single-deploy`, `two-stage`
+    #[clap(long)]
+    preset: Preset,
+
+    /// The path to write the new configuration toml file to
+    #[clap(default_value_t = String::from("rocket.toml"))]
+    output: String,
+
+    /// Overwrite the output file if it already exists
+    #[clap(long)]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalyzeArgs {
+    /// The configuration toml file that was flown
+    config: String,
+
+    /// The flight log, with one `time,state` record per state entry
+    #[clap(long)]
+    log: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct ApproveArgs {
+    /// The compiled .ncf file to approve
+    config: String,
+
+    /// A file containing the reviewer's hex encoded ed25519 secret key
+    #[clap(long)]
+    key: String,
+
+    /// The reviewer's name, recorded alongside the signature
+    #[clap(long)]
+    reviewer: String,
+}
+
+#[derive(Debug)]
+struct EmitArg {
+    kind: EmitKind,
+    path: Option<String>,
+}
+
+impl FromStr for EmitArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = match s.split_once('=') {
+            Some((kind, path)) => (kind, Some(path.to_owned())),
+            None => (s, None),
+        };
+        let kind = match kind {
+            "upper-json" => EmitKind::UpperJson,
+            "patch" => EmitKind::Patch,
+            "lang-docs" => EmitKind::LangDocs,
+            "abort-reasons" => EmitKind::AbortReasons,
+            "self-test" => EmitKind::SelfTest,
+            "schedule" => EmitKind::Schedule,
+            "fault-handling" => EmitKind::FaultHandling,
+            "tick-rate" => EmitKind::TickRate,
+            "dot" => EmitKind::Dot,
+            "json-schema" => EmitKind::JsonSchema,
+            "c-header" => EmitKind::CHeader,
+            _ => {
+                return Err(format!(
+                    "unknown emit kind `{kind}`, expected `upper-json`, `patch`, `lang-docs`, `abort-reasons`, `self-test`, `schedule`, `fault-handling`, `tick-rate`, `dot`, `json-schema`, or `c-header`"
+                ))
+            }
+        };
+        Ok(Self { kind, path })
+    }
+}
+
+/// The exit codes of the verifier. Scripts and build tools rely on these, so a code must never
+/// change meaning. Success without warnings is 0
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Exit {
+    /// The config or file being checked has a problem, which was reported as a diagnostic
+    Diagnostics = 1,
+
+    /// The command line was invalid. clap also exits with this code when parsing fails
+    Usage = 2,
+
+    /// A file could not be read or written
+    Io = 3,
+
+    /// The verifier itself misbehaved, such as by panicking
+    Internal = 4,
+
+    /// The command succeeded, but printed warnings
+    Warnings = 5,
+}
+
+/// Why a command failed, and the message to print if it was not already reported as a diagnostic
+#[derive(Debug)]
+struct Failure {
+    exit: Exit,
+    message: Option<String>,
+}
+
+impl Failure {
+    fn new(exit: Exit, message: impl Into<String>) -> Self {
+        Self {
+            exit,
+            message: Some(message.into()),
         }
-        Err(d) => {
-            info!("Verify failed with {} diagnostics!", d.len());
+    }
+
+    /// The problem was already printed as a diagnostic
+    fn reported() -> Self {
+        Self {
+            exit: Exit::Diagnostics,
+            message: None,
         }
     }
+
+    fn read(path: &str, err: std::io::Error) -> Self {
+        Self::new(Exit::Io, format!("failed to read `{path}`: {err}"))
+    }
+
+    fn write(path: &str, err: std::io::Error) -> Self {
+        Self::new(Exit::Io, format!("failed to write to file `{path}`: {err}"))
+    }
 }
 
-fn run() -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+fn main() {
     pretty_env_logger::init();
-    let args = Args::parse();
+    let mut args = Args::parse();
+    args.global.offline |= nova_verifier::offline::enabled_by_env();
 
-    let src_path = args.input;
+    let global = &args.global;
+    let session = new_session(global);
+    let result = nova_verifier::internal::catch(|| match args.command {
+        Some(Command::Approve(approve_args)) => approve(approve_args),
+        Some(Command::Analyze(analyze_args)) => analyze(analyze_args, global),
+        Some(Command::ValidateNcf(validate_args)) => validate_ncf(validate_args),
+        Some(Command::Transcode(transcode_args)) => transcode(transcode_args),
+        Some(Command::Crosscheck(crosscheck_args)) => crosscheck(crosscheck_args, global),
+        Some(Command::Init(init_args)) => init(init_args),
+        Some(Command::Build(build_args)) => build(build_args, global),
+        Some(Command::Check(check_args)) => check(check_args, global),
+        Some(Command::Graph(graph_args)) => graph(graph_args, global),
+        Some(Command::Clean(clean_args)) => clean(clean_args),
+        Some(Command::Decompile(decompile_args)) => decompile(decompile_args, global),
+        Some(Command::Diff(diff_args)) => diff(diff_args, global),
+        Some(Command::Inspect(inspect_args)) => inspect(inspect_args, global),
+        Some(Command::Sim(sim_args)) => sim(sim_args, global),
+        Some(Command::Explain(explain_args)) => explain(explain_args),
+        Some(Command::Watch(watch_args)) => watch(watch_args, global),
+        None => {
+            eprintln!("warning: running without a subcommand is deprecated, use `nova-verifier build` or `nova-verifier check`");
+            if args.check || args.only_state.is_some() {
+                let check_args = CheckArgs {
+                    options: args.build.options,
+                    only_state: args.only_state,
+                    verbose: args.build.verbose,
+                    stats: args.build.stats,
+                };
+                check(check_args, global)
+            } else {
+                build(args.build, global)
+            }
+        }
+    });
+    let result = result.unwrap_or_else(|report| {
+        session.print(&report.diagnostics());
+        Err(Failure {
+            exit: Exit::Internal,
+            message: None,
+        })
+    });
+    if let Err(failure) = &result {
+        if let Some(message) = &failure.message {
+            eprintln!("error: {message}");
+        }
+    }
+
+    let printed = *PRINTED.lock().unwrap();
+    match global.error_format {
+        ErrorFormat::Json => {}
+        // The log is only complete once every diagnostic has been printed
+        ErrorFormat::Sarif => eprintln!("{}", SarifFormatter::finish()),
+        _ => {
+            if let Some(summary) = printed.summary() {
+                session.print(&[summary]);
+            }
+        }
+    }
+    match result {
+        Err(failure) => std::process::exit(failure.exit as i32),
+        Ok(()) if printed.warnings > 0 => std::process::exit(Exit::Warnings as i32),
+        Ok(()) => {}
+    }
+}
+
+/// The errors and warnings printed by every session so far, which pick the exit code
+static PRINTED: Mutex<Tally> = Mutex::new(Tally {
+    errors: 0,
+    warnings: 0,
+});
+
+/// A session that adds the diagnostics it printed to [`PRINTED`] when dropped
+struct Tracked(nova_verifier::Session);
+
+impl Deref for Tracked {
+    type Target = nova_verifier::Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Tracked {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        *PRINTED.lock().unwrap() += self.0.printed();
+    }
+}
+
+/// Creates a session with the global options in `args`. The session must not touch devices or
+/// the network if `args.offline` is set
+fn new_session(args: &GlobalOptions) -> Tracked {
+    let mut session = nova_verifier::Session::new();
+    session.set_offline(args.offline);
+    session.set_error_format(args.error_format);
+    Tracked(session)
+}
+
+fn approve(args: ApproveArgs) -> Result<(), Failure> {
+    use nova_verifier::approval::ApprovalError;
+
+    approve_inner(args).map_err(|err| {
+        let exit = match err {
+            ApprovalError::Io { .. } => Exit::Io,
+            _ => Exit::Diagnostics,
+        };
+        Failure::new(exit, err.to_string())
+    })
+}
+
+fn approve_inner(args: ApproveArgs) -> Result<(), nova_verifier::approval::ApprovalError> {
+    use nova_verifier::approval::{self, ApprovalError, Metadata};
+
+    let config = std::fs::read(&args.config).map_err(|source| ApprovalError::Io {
+        path: args.config.clone(),
+        source,
+    })?;
+    let key = approval::load_signing_key(&args.key)?;
+    let sidecar = Metadata::sidecar_path(&args.config);
+    let mut metadata = Metadata::load(&sidecar)?;
+    approval::approve(&mut metadata, &config, args.reviewer, &key)?;
+    metadata.save(&sidecar)?;
+
+    println!(
+        "approved `{}`, which now has {} approvals",
+        args.config,
+        metadata.approvals.len()
+    );
+    Ok(())
+}
+
+fn analyze(args: AnalyzeArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    use nova_verifier::analyze;
+
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|err| Failure::read(path, err));
+    let toml = read(&args.config)?;
+    let log = read(&args.log)?;
+
+    let mut session = new_session(global);
+    let (config, _, _) = nova_verifier::load_config(&mut session, toml, args.config)
+        .map_err(|_| Failure::reported())?;
+    let log = analyze::parse_log(&log, &config)
+        .map_err(|err| Failure::new(Exit::Diagnostics, format!("`{}` {err}", args.log)))?;
+
+    let analysis = analyze::analyze(&config, &log);
+    print!("{analysis}");
+    Ok(())
+}
+
+fn validate_ncf(args: ValidateNcfArgs) -> Result<(), Failure> {
+    use nova_verifier::ncf;
+
+    let bytes = std::fs::read(&args.config).map_err(|err| Failure::read(&args.config, err))?;
+    let describe =
+        |err: ncf::NcfError| Failure::new(Exit::Diagnostics, format!("`{}`: {err}", args.config));
+    let (header, _) = ncf::split(&bytes).map_err(describe)?;
+    if let Some(accepted) = args.firmware_versions {
+        ncf::check_version(&header, accepted).map_err(describe)?;
+    }
+    let (_, config) = ncf::decode(&bytes).map_err(describe)?;
+
+    println!(
+        "`{}` is valid, with {} states encoded with format version {} by verifier {}",
+        args.config,
+        config.states.len(),
+        header.format_version,
+        header.compiler_version()
+    );
+    let sidecar = nova_verifier::approval::Metadata::sidecar_path(&args.config);
+    if let Ok(metadata) = nova_verifier::approval::Metadata::load(&sidecar) {
+        if metadata.ground_test {
+            println!("it is a ground test build, and must not be flown");
+        }
+        if let Some(local) = metadata.local_override {
+            println!("it was built with the local overrides in `{local}`");
+        }
+    }
+    Ok(())
+}
+
+fn watch(args: WatchArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    use nova_verifier::watch::Watcher;
+
+    let input = &args.options.input;
+    let mut paths = vec![input.into()];
+    paths.extend(args.options.include.iter().map(Into::into));
+    if !args.options.no_local {
+        paths.push(nova_verifier::overlay::local_path(input).into());
+    }
+    let mut watcher = Watcher::new(paths);
+    loop {
+        let mut session = verify_session(&args.options, global)?;
+        match std::fs::read_to_string(input) {
+            Ok(toml) => {
+                let path = input.clone();
+                let result = match &args.only_state {
+                    Some(state) => nova_verifier::verify_state(&mut session, toml, path, state),
+                    None => nova_verifier::verify_inner(&mut session, toml, path).map(|(_, d)| d),
+                };
+                match result {
+                    Ok(_) => eprintln!("`{input}` verified"),
+                    Err(err) => eprintln!("`{input}`: {err}"),
+                }
+            }
+            Err(err) => eprintln!("error: failed to read `{input}`: {err}"),
+        }
+        eprintln!("watching for changes...");
+        watcher.wait();
+        eprintln!();
+    }
+}
+
+fn explain(args: ExplainArgs) -> Result<(), Failure> {
+    use nova_verifier::diagnostics::{self, level_name};
+
+    let code = match args.code {
+        Some(code) => code,
+        None => {
+            for diagnostic in diagnostics::all() {
+                let level = level_name(diagnostic.level);
+                println!(
+                    "{:<24} {level:<8} {}",
+                    diagnostic.code, diagnostic.description
+                );
+            }
+            return Ok(());
+        }
+    };
+    let diagnostic = diagnostics::find(&code).ok_or_else(|| {
+        Failure::new(
+            Exit::Usage,
+            format!("no diagnostic has the code `{code}`, run `explain` to list every code"),
+        )
+    })?;
+    println!(
+        "{}[{}]: {}",
+        level_name(diagnostic.level),
+        diagnostic.code,
+        diagnostic.message
+    );
+    println!();
+    println!("{}.", diagnostic.description);
+    println!();
+    println!(
+        "See {} in the language reference, written by `--emit lang-docs`",
+        diagnostic.docs_link()
+    );
+    Ok(())
+}
+
+/// Reads the compiled .ncf file at `path`
+fn read_ncf(path: &str) -> Result<(nova_verifier::ncf::Header, index::ConfigFile), Failure> {
+    let bytes = std::fs::read(path).map_err(|err| Failure::read(path, err))?;
+    nova_verifier::ncf::decode(&bytes)
+        .map_err(|err| Failure::new(Exit::Diagnostics, format!("`{path}`: {err}")))
+}
+
+/// Returns the names of the states and checks of `config`, which was read from `path`. They are
+/// taken from `source` if it builds into `config`, and are otherwise made up
+fn names(
+    config: &index::ConfigFile,
+    path: &str,
+    source: Option<String>,
+    global: &GlobalOptions,
+) -> Result<nova_verifier::decompile::Names, Failure> {
+    use nova_verifier::decompile::Names;
+
+    let mut names = None;
+    if let Some(source_path) = source {
+        let source = std::fs::read_to_string(&source_path)
+            .map_err(|err| Failure::read(&source_path, err))?;
+        let mut session = new_session(global);
+        let (upper, lower, _) =
+            nova_verifier::load_config(&mut session, source, source_path.clone()).map_err(
+                |err| {
+                    info!("Loading the source failed: {err}");
+                    Failure::reported()
+                },
+            )?;
+        if lower == *config {
+            names = Names::from_source(&upper, config);
+        } else {
+            warn!("`{source_path}` does not build into `{path}`");
+            eprintln!(
+                "warning: `{source_path}` does not build into `{path}`, so its names are not used"
+            );
+        }
+    }
+    Ok(names.unwrap_or_else(|| Names::generic(config)))
+}
+
+fn decompile(args: DecompileArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    let (_, config) = read_ncf(&args.config)?;
+    let names = names(&config, &args.config, args.source, global)?;
+    let decompiled = nova_verifier::decompile::decompile(&config, &names);
+    for lossy in &decompiled.lossy {
+        eprintln!("warning: cannot be expressed in a config file: {lossy}");
+    }
+    match args.output {
+        Some(output) => {
+            std::fs::write(&output, decompiled.toml).map_err(|err| Failure::write(&output, err))
+        }
+        None => {
+            print!("{}", decompiled.toml);
+            Ok(())
+        }
+    }
+}
+
+/// Reads the config at `path`, which is either a config file or a compiled .ncf file. Returns the
+/// lowered config and its names, and whether the names came from a config file
+fn read_any(
+    path: &str,
+    global: &GlobalOptions,
+) -> Result<(index::ConfigFile, nova_verifier::decompile::Names, bool), Failure> {
+    use nova_verifier::{decompile::Names, ncf};
+
+    let bytes = std::fs::read(path).map_err(|err| Failure::read(path, err))?;
+    if bytes.starts_with(&ncf::MAGIC) || bytes.starts_with(&ncf::LEGACY_MAGIC) {
+        let (_, config) = read_ncf(path)?;
+        let names = Names::generic(&config);
+        return Ok((config, names, false));
+    }
+    let source = String::from_utf8(bytes).map_err(|_| {
+        Failure::new(
+            Exit::Usage,
+            format!("`{path}` is neither a config file nor a compiled .ncf file"),
+        )
+    })?;
+    let mut session = new_session(global);
+    let (upper, lower, _) = nova_verifier::load_config(&mut session, source, path.to_owned())
+        .map_err(|err| {
+            info!("Loading `{path}` failed: {err}");
+            Failure::reported()
+        })?;
+    let names = Names::from_source(&upper, &lower).unwrap_or_else(|| Names::generic(&lower));
+    Ok((lower, names, true))
+}
+
+fn diff(args: DiffArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    use nova_verifier::diff::{self, Side};
+
+    let (old, old_names, old_named) = read_any(&args.old, global)?;
+    let (new, new_names, new_named) = read_any(&args.new, global)?;
+    let old = Side {
+        config: &old,
+        names: &old_names,
+    };
+    let new = Side {
+        config: &new,
+        names: &new_names,
+    };
+    match diff::diff(old, new, old_named && new_named).as_str() {
+        "" => println!("`{}` and `{}` are the same", args.old, args.new),
+        changes => print!("{changes}"),
+    }
+    Ok(())
+}
+
+fn inspect(args: InspectArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    let (header, config) = read_ncf(&args.config)?;
+    let names = names(&config, &args.config, args.source, global)?;
+    print!(
+        "{}",
+        nova_verifier::inspect::inspect(&header, &config, &names)
+    );
+    Ok(())
+}
+
+fn sim(args: SimArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    use nova_verifier::sim;
+
+    let (_, config) = read_ncf(&args.config)?;
+    let profile =
+        std::fs::read_to_string(&args.profile).map_err(|err| Failure::read(&args.profile, err))?;
+    let profile = sim::parse_profile(&profile)
+        .map_err(|err| Failure::new(Exit::Diagnostics, format!("`{}` {err}", args.profile)))?;
+    let names = names(&config, &args.config, args.source, global)?;
+    print!(
+        "{}",
+        sim::simulate(&config, &profile).render(&config, &names)
+    );
+    Ok(())
+}
+
+fn transcode(args: TranscodeArgs) -> Result<(), Failure> {
+    use nova_verifier::ncf::NcfError;
+
+    let bytes = std::fs::read(&args.input).map_err(|err| Failure::read(&args.input, err))?;
+    let converted = nova_verifier::ncf::transcode(&bytes, args.to_version).map_err(|err| {
+        let exit = match err {
+            NcfError::UnknownTarget(_) => Exit::Usage,
+            _ => Exit::Diagnostics,
+        };
+        Failure::new(exit, format!("`{}`: {err}", args.input))
+    })?;
+    std::fs::write(&args.output, converted).map_err(|err| Failure::write(&args.output, err))?;
+
+    println!(
+        "wrote `{}` for format version {}",
+        args.output, args.to_version
+    );
+    Ok(())
+}
+
+fn crosscheck(args: CrosscheckArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    let mut session = new_session(global);
+    let result = nova_verifier::crosscheck::crosscheck_files(
+        &mut session,
+        args.first.clone(),
+        args.second.clone(),
+    );
+    match result {
+        Ok(_) => {
+            println!(
+                "`{}` and `{}` agree on every critical setting",
+                args.first, args.second
+            );
+            Ok(())
+        }
+        Err(d) => {
+            info!("Crosscheck failed with {} diagnostics!", d.len());
+            Err(Failure::reported())
+        }
+    }
+}
+
+fn build(args: BuildArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    if args.workspace {
+        return build_workspace(args, global);
+    }
+    let mut session = verify_session(&args.options, global)?;
+    if !args.local {
+        // A build only differs from the reviewed config when it asks to
+        session.set_override_file(None);
+    } else if session.override_file().is_none() {
+        let local = nova_verifier::overlay::local_path(&args.options.input);
+        return Err(Failure::new(
+            Exit::Usage,
+            format!("`--local` was given, but `{local}` does not exist"),
+        ));
+    }
+    session.set_emit_stats(args.stats);
+    session.set_patch_base(args.patch_base);
     let dst_path = args.output;
+    for emit in &args.emit {
+        let path = emit.path.clone().unwrap_or_else(|| {
+            let path = Path::new(&dst_path).with_extension(emit.kind.default_extension());
+            path.to_string_lossy().into_owned()
+        });
+        session.add_emit(emit.kind, path);
+    }
+
+    let bytes = verify(&mut session, &args.options.input)?;
+    if let (true, Some(summary)) = (args.verbose, session.summary()) {
+        print!("{summary}");
+    }
+    let overrides = session.summary().map_or(&[][..], |s| &s.overrides);
+    let local_override = session.override_file();
+    mark_build(
+        &dst_path,
+        &bytes,
+        args.options.ground_test,
+        overrides,
+        local_override,
+    )?;
+    std::fs::write(&dst_path, bytes).map_err(|err| Failure::write(&dst_path, err))
+}
 
-    let r = nova_verifier::verify_file(src_path, dst_path.clone());
+fn build_workspace(args: BuildArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    use nova_verifier::workspace;
+
+    let mut emits = Vec::new();
+    for emit in args.emit {
+        if emit.kind == EmitKind::Patch {
+            return Err(Failure::new(
+                Exit::Usage,
+                "`build --workspace` cannot emit patches, members have no previous config to diff",
+            ));
+        }
+        if emit.path.is_some() {
+            return Err(Failure::new(
+                Exit::Usage,
+                "`build --workspace` writes artifacts next to each member's config, so `--emit` takes no path",
+            ));
+        }
+        emits.push(emit.kind);
+    }
+    let manifest = find_manifest(args.manifest)?;
 
-    let bytes = std::fs::read(dst_path).unwrap();
-    let obj: nova_software_common::index::ConfigFile = postcard::from_bytes(&bytes).unwrap();
-    if r.is_ok() {
-        trace!("Encoded obj is: {obj:#?}");
+    let mut session = new_session(global);
+    session.set_ground_test(args.options.ground_test);
+    session.set_warnings_as_errors(args.options.deny_warnings);
+    let (built, _) = workspace::build(&mut session, &manifest, &emits).map_err(|d| {
+        info!("Workspace build failed with {} diagnostics!", d.len());
+        Failure::reported()
+    })?;
+    for member in &built {
+        println!(
+            "built `{}` from `{}` into `{}`, {} bytes",
+            member.name,
+            member.config.display(),
+            member.dir.display(),
+            member.bytes.len()
+        );
     }
+    Ok(())
+}
+
+fn clean(args: CleanArgs) -> Result<(), Failure> {
+    let manifest = find_manifest(args.manifest)?;
+    match nova_verifier::workspace::clean(&manifest) {
+        Ok(Some(out)) => println!("removed `{}`", out.display()),
+        Ok(None) => println!("nothing to clean"),
+        Err(err) => {
+            return Err(Failure::new(
+                Exit::Io,
+                format!("cannot remove the workspace's artifacts: {err}"),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Returns `manifest`, or the closest workspace manifest if it was not given
+fn find_manifest(manifest: Option<String>) -> Result<std::path::PathBuf, Failure> {
+    use nova_verifier::workspace;
+
+    match manifest {
+        Some(path) => Ok(path.into()),
+        None => {
+            let cwd = std::env::current_dir().map_err(|err| Failure::read(".", err))?;
+            workspace::find(&cwd).ok_or_else(|| {
+                Failure::new(
+                    Exit::Usage,
+                    format!(
+                        "no `{}` found in the current directory or above it",
+                        workspace::MANIFEST_NAME
+                    ),
+                )
+            })
+        }
+    }
+}
+
+fn init(args: InitArgs) -> Result<(), Failure> {
+    if !args.force && Path::new(&args.output).exists() {
+        return Err(Failure::new(
+            Exit::Usage,
+            format!(
+                "`{}` already exists, pass `--force` to overwrite it",
+                args.output
+            ),
+        ));
+    }
+    std::fs::write(&args.output, args.preset.source())
+        .map_err(|err| Failure::write(&args.output, err))?;
+
+    println!(
+        "wrote a {} config to `{}`, search it for `TODO` to find the values to tune",
+        args.preset, args.output
+    );
+    Ok(())
+}
+
+/// Creates a session with the global options in `global`, configured with the verification
+/// options in `options`
+fn verify_session(options: &VerifyOptions, global: &GlobalOptions) -> Result<Tracked, Failure> {
+    let mut session = new_session(global);
+    session.set_flash_budget(options.flash_budget);
+    session.set_float_precision_notes(options.float_precision_notes);
+    session.set_strict_floats(options.strict_floats);
+    session.set_strict_keys(options.strict_keys);
+    session.set_require_default_state(options.require_default_state);
+    session.set_warnings_as_errors(options.deny_warnings);
+    let levels = [
+        (&options.allow, LintLevel::Allow),
+        (&options.warn, LintLevel::Warn),
+        (&options.deny, LintLevel::Deny),
+    ];
+    for (lints, level) in levels {
+        for lint in lints {
+            session.set_lint_level(lint, level);
+        }
+    }
+    session.set_ground_test(options.ground_test);
+    session.set_complexity_limits(options.complexity_limit.clone());
+    session.set_target(options.target);
+    if let Some(board) = &options.board {
+        use nova_verifier::hardware::{Profile, ProfileError};
+
+        let profile = Profile::load(board).map_err(|err| {
+            let exit = match err {
+                ProfileError::Read(..) => Exit::Io,
+                _ => Exit::Usage,
+            };
+            Failure::new(exit, err.to_string())
+        })?;
+        session.set_board(profile);
+    }
+    session.set_input_format(options.input_format);
+    session.set_mission_duration(options.mission_duration);
+    for include in &options.include {
+        session.add_include(include.clone());
+    }
+    let local = nova_verifier::overlay::local_path(&options.input);
+    if !options.no_local && Path::new(&local).exists() {
+        session.set_override_file(Some(local));
+    }
+    Ok(session)
+}
+
+/// Verifies the input of `session`, returning the encoded config
+fn verify(session: &mut nova_verifier::Session, input: &str) -> Result<Vec<u8>, Failure> {
+    let toml = std::fs::read_to_string(input).map_err(|err| Failure::read(input, err))?;
+    match nova_verifier::verify_inner(session, toml, input.to_owned()) {
+        Ok((bytes, diagnostics)) => {
+            info!("Verify finished with {} diagnostics", diagnostics.len());
+            Ok(bytes)
+        }
+        Err(err) => {
+            info!(
+                "Verify failed: {err}, with {} diagnostics",
+                err.diagnostics().len()
+            );
+            Err(Failure::reported())
+        }
+    }
+}
+
+fn check(args: CheckArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    let mut session = verify_session(&args.options, global)?;
+    session.set_emit_stats(args.stats);
+    let input = &args.options.input;
+    if let Some(state) = &args.only_state {
+        let toml = std::fs::read_to_string(input).map_err(|err| Failure::read(input, err))?;
+        return match nova_verifier::verify_state(&mut session, toml, input.clone(), state) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Failure::reported()),
+        };
+    }
+    verify(&mut session, input)?;
+    if let (true, Some(summary)) = (args.verbose, session.summary()) {
+        print!("{summary}");
+    }
+    Ok(())
+}
+
+fn graph(args: GraphArgs, global: &GlobalOptions) -> Result<(), Failure> {
+    let mut session = verify_session(&args.options, global)?;
+    let output = args.output.unwrap_or_else(|| {
+        let path = Path::new(&args.options.input).with_extension(EmitKind::Dot.default_extension());
+        path.to_string_lossy().into_owned()
+    });
+    session.add_emit(EmitKind::Dot, output.clone());
+    verify(&mut session, &args.options.input)?;
+    println!(
+        "wrote the state machine of `{}` to `{output}`",
+        args.options.input
+    );
+    Ok(())
+}
+
+/// Records in the metadata sidecar of the config at `dst_path` whether it is a ground test build,
+/// the lints it overrides, and the local override file merged over it. Approvals of an earlier build
+/// of different bytes are dropped
+fn mark_build(
+    dst_path: &str,
+    bytes: &[u8],
+    ground_test: bool,
+    overrides: &[nova_verifier::overrides::Record],
+    local_override: Option<&str>,
+) -> Result<(), Failure> {
+    use nova_verifier::approval::Metadata;
 
-    r
+    Metadata::mark_build(dst_path, bytes, ground_test, overrides, local_override).map_err(|err| {
+        Failure::new(
+            Exit::Io,
+            format!("cannot update the metadata of `{dst_path}`: {err}"),
+        )
+    })
 }
@@ -0,0 +1,149 @@
+//! Commands scheduled at a fixed time after launch.
+//!
+//! Some actions belong to the mission timeline rather than to a state, such as dropping to a low
+//! telemetry rate two minutes into the flight to save power:
+//!
+//! ```toml
+//! [[mission_events]]
+//! name = "Low rate telemetry"
+//! time = 120.0
+//! command = { data_rate = 2 }
+//! ```
+//!
+//! The events are lowered into a [`Schedule`] that runs alongside the state machine. The `.ncf`
+//! format has no room for it, so it is encoded separately with `--emit schedule`.
+
+use nova_software_common::{CommandObject, Seconds};
+use serde::Serialize;
+
+use crate::{lower, ncf, spanned::SpannedValue, upper, Context, ErrorCode, Span};
+
+/// The bytes every encoded schedule starts with
+pub const MAGIC: [u8; 4] = *b"NSC\0";
+
+/// A command that runs at a fixed time after launch
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct ScheduledCommand {
+    pub time: Seconds,
+    pub object: CommandObject,
+}
+
+/// Every mission event of a config, in the order they run
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Schedule {
+    pub commands: Vec<ScheduledCommand>,
+}
+
+/// Verifies and lowers the `[[mission_events]]` of `config`, emitting errors to `context`.
+/// Events must be listed in the order they run
+pub fn lower(config: &upper::ConfigFile, context: &mut Context) -> Result<Schedule, ()> {
+    let mut result = Ok(Schedule::default());
+    let mut previous: Option<&SpannedValue<f32>> = None;
+    for event in &config.mission_events {
+        let event = event.get_ref();
+        let time = *event.time.get_ref();
+        let span = Span::from_spanned(context, &event.time);
+        if !time.is_finite() || time < 0.0 {
+            context
+                .error(format!("mission event time {time} is not after launch"))
+                .code(ErrorCode::EventBeforeLaunch)
+                .set_primary_span(span, "must be at least 0 seconds")
+                .emit();
+            result = Err(());
+        } else if let Some(previous) = previous.filter(|p| *p.get_ref() > time) {
+            let previous_span = Span::from_spanned(context, previous);
+            context
+                .error("mission events are not in time order")
+                .code(ErrorCode::EventsOutOfOrder)
+                .set_primary_span(span, format!("runs at {time} seconds"))
+                .span_label(
+                    previous_span,
+                    format!(
+                        "but is listed after this event at {} seconds",
+                        previous.get_ref()
+                    ),
+                )
+                .emit();
+            context
+                .help("list `[[mission_events]]` in the order they run")
+                .emit();
+            result = Err(());
+        }
+        previous = Some(&event.time);
+
+        if let Some(delay) = &event.command.get_ref().delay {
+            let span = Span::from_spanned(context, delay);
+            context
+                .error("mission event commands cannot have a `delay`")
+                .code(ErrorCode::DelayedEventCommand)
+                .set_primary_span(span, "use the event's `time` instead")
+                .emit();
+            result = Err(());
+            continue;
+        }
+        match lower::convert_command(&event.command, context) {
+            Ok(command) => {
+                if let Ok(schedule) = &mut result {
+                    schedule.commands.push(ScheduledCommand {
+                        time: Seconds(time),
+                        object: command.object,
+                    });
+                }
+            }
+            Err(()) => result = Err(()),
+        }
+    }
+    result
+}
+
+/// Encodes `schedule` for the flight computer
+pub fn encode(schedule: &Schedule) -> Result<Vec<u8>, postcard::Error> {
+    ncf::encode_tagged(MAGIC, schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use nova_software_common::CommandObject;
+
+    use super::lower;
+    use crate::{upper, Session};
+
+    #[test]
+    fn schedule() {
+        let config = r#"
+[[mission_events]]
+name = "Low rate telemetry"
+time = 120.0
+command = { data_rate = 2 }
+
+[[mission_events]]
+time = TIME
+command = { beacon = true }
+
+[[states]]
+name = "Flight"
+"#;
+        let mut session = Session::new();
+        let ordered = config.replace("TIME", "300.0");
+        let mut context = session.testing(&ordered);
+        let ordered = upper::verify(&mut context).unwrap();
+        let schedule = lower(&ordered, &mut context).unwrap();
+        let commands: Vec<_> = schedule
+            .commands
+            .iter()
+            .map(|c| (c.time.0, c.object))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                (120.0, CommandObject::DataRate(2)),
+                (300.0, CommandObject::Beacon(true)),
+            ]
+        );
+
+        let unordered = config.replace("TIME", "60.0");
+        let mut context = session.testing(&unordered);
+        let unordered = upper::verify(&mut context).unwrap();
+        assert!(lower(&unordered, &mut context).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! An in-memory flight computer for testing uploads without hardware.
+//!
+//! [`MockFlightComputer`] is a [`Transport`] that answers frames with a [`Receiver`] directly, so
+//! configs can be uploaded, read back, and checked end to end in CI. Faults can be scripted with
+//! [`MockFlightComputer::inject`] to exercise the retry and resume paths of the protocol.
+//!
+//! Only built for tests, or with the `mock-fc` feature for tools outside this crate.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::protocol::{Receiver, Transport, TransportError};
+
+/// What happens to a frame sent to a [`MockFlightComputer`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The frame arrives and is answered normally
+    Pass,
+
+    /// The frame is lost, so no reply arrives
+    Drop,
+
+    /// The frame arrives with a flipped byte, so its CRC does not match
+    Corrupt,
+
+    /// The link disconnects instead of sending the frame
+    Disconnect,
+}
+
+/// A flight computer that stores its config in memory
+#[derive(Debug, Default)]
+pub struct MockFlightComputer {
+    receiver: Receiver,
+    faults: VecDeque<Fault>,
+    reply: Option<Vec<u8>>,
+    disconnected: bool,
+
+    /// The number of frames sent to this flight computer, including lost ones
+    pub frames: usize,
+}
+
+impl MockFlightComputer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A flight computer that already stores `config`
+    pub fn with_config(config: Vec<u8>) -> Self {
+        Self {
+            receiver: Receiver::with_config(config),
+            ..Self::default()
+        }
+    }
+
+    /// Applies `faults` to the next frames sent, in order. Frames after them pass
+    pub fn inject(&mut self, faults: impl IntoIterator<Item = Fault>) {
+        self.faults.extend(faults);
+    }
+
+    /// The config the flight computer would fly with
+    pub fn config(&self) -> Option<&[u8]> {
+        self.receiver.config()
+    }
+}
+
+impl Transport for MockFlightComputer {
+    fn send(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+        if self.disconnected {
+            return Err(TransportError::Disconnected);
+        }
+        self.frames += 1;
+        let mut frame = frame.to_vec();
+        match self.faults.pop_front().unwrap_or(Fault::Pass) {
+            Fault::Pass => {}
+            Fault::Drop => return Ok(()),
+            Fault::Corrupt => frame[0] ^= 0xFF,
+            Fault::Disconnect => {
+                self.disconnected = true;
+                return Err(TransportError::Disconnected);
+            }
+        }
+        self.reply = self.receiver.handle(&frame);
+        Ok(())
+    }
+
+    fn recv(&mut self, _timeout: Duration) -> Result<Vec<u8>, TransportError> {
+        self.reply.take().ok_or(TransportError::Timeout)
+    }
+
+    fn reconnect(&mut self) -> Result<(), TransportError> {
+        self.disconnected = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fault, MockFlightComputer};
+    use crate::{approval, ncf, presets::Preset, protocol, Session};
+
+    #[test]
+    fn end_to_end() {
+        let mut session = Session::new();
+        let source = Preset::DualDeploy.source().to_owned();
+        let (bytes, _) =
+            crate::verify_inner(&mut session, source.clone(), "rocket.toml".to_owned()).unwrap();
+        let (_, expected, _) =
+            crate::load_config(&mut session, source, "rocket.toml".to_owned()).unwrap();
+
+        let options = protocol::Options {
+            chunk_len: 16,
+            ..protocol::Options::default()
+        };
+        let metadata = approval::approve_for_testing(&mut session, &bytes);
+        let mut fc = MockFlightComputer::new();
+        fc.inject([Fault::Pass, Fault::Corrupt, Fault::Disconnect, Fault::Drop]);
+        protocol::upload(&session, &metadata, &mut fc, &bytes, &options).unwrap();
+
+        // Verify what the device holds, as a pre-flight check would
+        let read = protocol::read_back(&session, &mut fc, &options).unwrap();
+        assert_eq!(read, bytes);
+        let (header, config) = ncf::decode(&read).unwrap();
+        assert!(ncf::check_version(&header, "1".parse().unwrap()).is_ok());
+        assert_eq!(config, expected);
+
+        let transcoded = ncf::transcode(&read, ncf::FORMAT_VERSION).unwrap();
+        let mut fc = MockFlightComputer::with_config(b"old".to_vec());
+        let metadata = approval::approve_for_testing(&mut session, &transcoded);
+        protocol::upload(&session, &metadata, &mut fc, &transcoded, &options).unwrap();
+        assert_eq!(fc.config(), Some(transcoded.as_slice()));
+    }
+}
@@ -0,0 +1,309 @@
+//! The `.ncf` container that encoded configs are written in.
+//!
+//! A config is encoded with postcard using the types from `nova_software_common`, so the bytes
+//! only make sense to firmware built against the same version of that crate. Firmware built
+//! against a different version would misread the config without noticing, so every `.ncf` file
+//! starts with a small header recording the format version it was encoded with:
+//!
+//! | Bytes | Contents                                                       |
+//! |-------|----------------------------------------------------------------|
+//! | 0..4  | [`MAGIC`]                                                      |
+//! | 4..6  | The format version, as a little endian `u16`                   |
+//! | 6..9  | The major, minor, and patch version of the verifier that wrote it |
+//! | 9..13 | The CRC-32 of the payload, as a little endian `u32`            |
+//! | 13..  | The postcard encoded `index::ConfigFile`                       |
+//!
+//! The checksum catches a config that was corrupted on its way to the flight computer, such as by
+//! a truncated upload. Files written before the header had a checksum start with [`LEGACY_MAGIC`]
+//! and are rejected, so that they are rebuilt rather than flown unchecked.
+//!
+//! The verifier only lowers configs to encodings that the pinned `nova_software_common` has. A
+//! feature that needs a new variant, such as a new kind of check or command, is held back until
+//! that crate is updated with it, and the update bumps [`FORMAT_VERSION`] in the same change.
+//! Until then the config language does not accept the feature, rather than accepting it and
+//! failing while lowering.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nova_software_common::index;
+use serde::Serialize;
+use thiserror::Error;
+
+/// The bytes every `.ncf` file starts with
+pub const MAGIC: [u8; 4] = *b"NCF\x01";
+
+/// The bytes that `.ncf` files written before the header recorded the verifier version and a
+/// checksum start with
+pub const LEGACY_MAGIC: [u8; 4] = *b"NCF\0";
+
+/// The version of `nova_software_common`'s encoding that this verifier writes.
+/// Bump this whenever updating `nova_software_common` changes how a config is encoded, such as
+/// adding a variant to `CheckData` or reordering fields
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Every format version that this verifier can read and write, oldest first
+pub const KNOWN_VERSIONS: [u16; 1] = [FORMAT_VERSION];
+
+/// The length of the header in bytes
+pub const HEADER_LEN: usize = MAGIC.len() + 2 + 3 + 4;
+
+/// The header at the start of every `.ncf` file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The version of `nova_software_common`'s encoding used for the payload
+    pub format_version: u16,
+
+    /// The major, minor, and patch version of the verifier that wrote the file
+    pub compiler_version: [u8; 3],
+
+    /// The CRC-32 of the payload, see [`crate::patch::crc32`]
+    pub crc: u32,
+}
+
+impl Header {
+    /// The version of the verifier that wrote the file, such as `0.1.0`
+    pub fn compiler_version(&self) -> String {
+        let [major, minor, patch] = self.compiler_version;
+        format!("{major}.{minor}.{patch}")
+    }
+}
+
+/// The version of this verifier, as written to the header. Components above 255 are saturated
+fn compiler_version() -> [u8; 3] {
+    let component = |v: &str| v.parse::<u64>().map_or(u8::MAX, |v| v.min(255) as u8);
+    [
+        component(env!("CARGO_PKG_VERSION_MAJOR")),
+        component(env!("CARGO_PKG_VERSION_MINOR")),
+        component(env!("CARGO_PKG_VERSION_PATCH")),
+    ]
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NcfError {
+    #[error("not a .ncf file, it does not start with the expected magic bytes")]
+    BadMagic,
+
+    #[error("the file is too short to contain a .ncf header")]
+    Truncated,
+
+    #[error("the file was written by an older verifier whose header has no checksum, rebuild it")]
+    LegacyHeader,
+
+    #[error("the config is corrupted, its checksum is {found:#010x} but the header expects {expected:#010x}")]
+    ChecksumMismatch { expected: u32, found: u32 },
+
+    #[error("the config was encoded with format version {found}, but this verifier only understands version {FORMAT_VERSION}")]
+    UnknownVersion { found: u16 },
+
+    #[error(
+        "the config was encoded with format version {found}, but the firmware accepts {accepted}"
+    )]
+    Unsupported { found: u16, accepted: VersionRange },
+
+    #[error(
+        "cannot transcode to format version {0}, this verifier only knows version {FORMAT_VERSION}"
+    )]
+    UnknownTarget(u16),
+
+    #[error("failed to decode the config: {0}")]
+    Decode(String),
+
+    #[error("failed to encode the config: {0}")]
+    Encode(String),
+}
+
+/// An inclusive range of format versions, such as the versions a firmware build can read.
+/// Parsed from `N` or `MIN..=MAX`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: u16) -> bool {
+        (self.min..=self.max).contains(&version)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "version {}", self.min)
+        } else {
+            write!(f, "versions {} to {}", self.min, self.max)
+        }
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("invalid format version `{v}`"))
+        };
+        let (min, max) = match s.split_once("..=") {
+            Some((min, max)) => (parse(min)?, parse(max)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if min > max {
+            return Err(format!("version range `{s}` is empty"));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+/// Encodes `config` with a header for the current [`FORMAT_VERSION`]
+pub fn encode(config: &index::ConfigFile) -> Result<Vec<u8>, postcard::Error> {
+    let payload = postcard::to_stdvec(config)?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.extend(compiler_version());
+    bytes.extend(crate::patch::crc32(&payload).to_le_bytes());
+    bytes.extend(payload);
+    Ok(bytes)
+}
+
+/// Encodes `payload` after a header with `magic` and the current [`FORMAT_VERSION`]. Used for data
+/// that is loaded alongside a `.ncf` file, such as [`crate::self_test`]
+pub fn encode_tagged(magic: [u8; 4], payload: &impl Serialize) -> Result<Vec<u8>, postcard::Error> {
+    let mut bytes = magic.to_vec();
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.extend(postcard::to_stdvec(payload)?);
+    Ok(bytes)
+}
+
+/// Reads the header of a `.ncf` file and checks the payload against its checksum, returning it
+/// along with the payload that follows it
+pub fn split(bytes: &[u8]) -> Result<(Header, &[u8]), NcfError> {
+    if bytes.starts_with(&LEGACY_MAGIC) {
+        return Err(NcfError::LegacyHeader);
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(if MAGIC.starts_with(bytes) || bytes.starts_with(&MAGIC) {
+            NcfError::Truncated
+        } else {
+            NcfError::BadMagic
+        });
+    }
+    let (header, payload) = bytes.split_at(HEADER_LEN);
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(NcfError::BadMagic);
+    }
+    let header = Header {
+        format_version: u16::from_le_bytes([header[4], header[5]]),
+        compiler_version: [header[6], header[7], header[8]],
+        crc: u32::from_le_bytes([header[9], header[10], header[11], header[12]]),
+    };
+    let found = crate::patch::crc32(payload);
+    if found != header.crc {
+        return Err(NcfError::ChecksumMismatch {
+            expected: header.crc,
+            found,
+        });
+    }
+    Ok((header, payload))
+}
+
+/// Decodes a `.ncf` file written by this verifier
+pub fn decode(bytes: &[u8]) -> Result<(Header, index::ConfigFile), NcfError> {
+    let (header, payload) = split(bytes)?;
+    if !KNOWN_VERSIONS.contains(&header.format_version) {
+        return Err(NcfError::UnknownVersion {
+            found: header.format_version,
+        });
+    }
+    let config = postcard::from_bytes(payload).map_err(|e| NcfError::Decode(e.to_string()))?;
+    Ok((header, config))
+}
+
+/// Re-encodes the `.ncf` file `bytes` for firmware that reads format version `to_version`, so that
+/// an airframe flying older firmware can use a config built by this verifier.
+///
+/// Every version in [`KNOWN_VERSIONS`] can be read and written. When a new version is added, the
+/// conversions between it and older versions go here, and a config that uses a feature the target
+/// version lacks is an error explaining which feature could not be converted
+pub fn transcode(bytes: &[u8], to_version: u16) -> Result<Vec<u8>, NcfError> {
+    if !KNOWN_VERSIONS.contains(&to_version) {
+        return Err(NcfError::UnknownTarget(to_version));
+    }
+    let (_, config) = decode(bytes)?;
+    encode(&config).map_err(|e| NcfError::Encode(e.to_string()))
+}
+
+/// Checks that firmware accepting the format versions in `accepted` can read the file with
+/// `header`
+pub fn check_version(header: &Header, accepted: VersionRange) -> Result<(), NcfError> {
+    if accepted.contains(header.format_version) {
+        Ok(())
+    } else {
+        Err(NcfError::Unsupported {
+            found: header.format_version,
+            accepted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let config = index::ConfigFile {
+            default_state: unsafe { index::StateIndex::new_unchecked(0) },
+            states: heapless::Vec::new(),
+        };
+        let bytes = encode(&config).unwrap();
+        assert_eq!(&bytes[..4], b"NCF\x01");
+        let (header, decoded) = decode(&bytes).unwrap();
+        assert_eq!(header.format_version, FORMAT_VERSION);
+        assert_eq!(header.compiler_version(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(decoded, config);
+
+        assert_eq!(decode(&bytes[HEADER_LEN..]), Err(NcfError::BadMagic));
+        assert_eq!(decode(&bytes[..3]), Err(NcfError::Truncated));
+        assert_eq!(decode(&bytes[..8]), Err(NcfError::Truncated));
+
+        let mut legacy = b"NCF\0\x01\x00".to_vec();
+        legacy.extend(&bytes[HEADER_LEN..]);
+        assert_eq!(decode(&legacy), Err(NcfError::LegacyHeader));
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            decode(&corrupted),
+            Err(NcfError::ChecksumMismatch { expected, .. }) if expected == header.crc
+        ));
+
+        let mut future = bytes.clone();
+        future[4] = 99;
+        assert!(matches!(
+            decode(&future),
+            Err(NcfError::UnknownVersion { found: 99 })
+        ));
+
+        let range: VersionRange = "2..=3".parse().unwrap();
+        assert!(check_version(&header, range).is_err());
+        assert!(check_version(&header, "1".parse().unwrap()).is_ok());
+        assert!("3..=2".parse::<VersionRange>().is_err());
+    }
+
+    #[test]
+    fn transcode_versions() {
+        let config = index::ConfigFile {
+            default_state: unsafe { index::StateIndex::new_unchecked(0) },
+            states: heapless::Vec::new(),
+        };
+        let bytes = encode(&config).unwrap();
+        assert_eq!(transcode(&bytes, FORMAT_VERSION).unwrap(), bytes);
+        assert_eq!(
+            transcode(&bytes, FORMAT_VERSION + 1),
+            Err(NcfError::UnknownTarget(FORMAT_VERSION + 1))
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Offline mode, which guarantees that nothing talks to a flight computer or the network.
+//!
+//! CI and review machines sometimes have hardware plugged in, and a stray command there should
+//! never be able to reach it. Offline mode is enabled with `--offline` or by setting [`ENV_VAR`],
+//! and every subcommand that touches a device or the network must call
+//! [`crate::Session::require_online`] before doing so.
+
+use thiserror::Error;
+
+/// The environment variable that enables offline mode when set to anything other than an empty
+/// string, `0`, or `false`
+pub const ENV_VAR: &str = "NOVA_OFFLINE";
+
+/// Returns true if `value`, the value of [`ENV_VAR`], enables offline mode
+pub fn enabled_by(value: Option<&str>) -> bool {
+    !matches!(value.map(str::trim), None | Some("" | "0" | "false"))
+}
+
+/// Returns true if the environment enables offline mode
+pub fn enabled_by_env() -> bool {
+    enabled_by(std::env::var(ENV_VAR).ok().as_deref())
+}
+
+/// Something was about to talk to a device or the network in offline mode
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("cannot {action} in offline mode, which is enabled by `--offline` or the `{ENV_VAR}` environment variable")]
+pub struct OfflineError {
+    /// What was refused, such as `upload to the flight computer`
+    pub action: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::enabled_by;
+    use crate::Session;
+
+    #[test]
+    fn offline() {
+        assert!(!enabled_by(None));
+        assert!(!enabled_by(Some("0")));
+        assert!(!enabled_by(Some("")));
+        assert!(enabled_by(Some("1")));
+        assert!(enabled_by(Some("yes")));
+
+        let mut session = Session::new();
+        assert!(session.require_online("upload").is_ok());
+        session.set_offline(true);
+        let err = session.require_online("upload").unwrap_err();
+        assert!(err.to_string().starts_with("cannot upload in offline mode"));
+    }
+}
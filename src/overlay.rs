@@ -0,0 +1,272 @@
+//! Local override files, layered over a config like cargo layers its config files.
+//!
+//! A reviewed `rocket.toml` can be paired with a `rocket.local.toml` that changes a few values for
+//! testing, without touching the reviewed file. The override is merged over the config before it
+//! is deserialized:
+//! - Tables are merged key by key, so an override only needs the values it changes
+//! - Arrays of tables with a `name`, such as `states` and `checks`, are merged by name. Entries
+//!   with a new name are appended
+//! - Anything else, including `commands`, is replaced as a whole
+//!
+//! Every value keeps the span of the file it came from, so diagnostics point into the override
+//! file when an overridden value is wrong. A note is emitted for every value that is overridden.
+//!
+//! `check`, `watch`, and `graph` merge the override file whenever it exists. `build` only merges
+//! it with `--local`, so a built config is the reviewed one unless asked otherwise, and records
+//! the override file in the config's metadata sidecar.
+
+use std::path::Path;
+
+use crate::tree::{self, Node, Value};
+use crate::{upper, Context, ErrorCode};
+
+/// Returns the path of the local override file for the config at `path`, which has `.local`
+/// before the extension, e.g. `rocket.toml` becomes `rocket.local.toml`
+pub fn local_path(path: &str) -> String {
+    let path = Path::new(path);
+    let local = match path.extension() {
+        Some(extension) => path.with_extension(format!("local.{}", extension.to_string_lossy())),
+        None => path.with_extension("local"),
+    };
+    local.to_string_lossy().into_owned()
+}
+
+/// A value in the base config that was replaced by the override file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Override {
+    /// The dotted name of the value, such as `states.Drogue.timeout.seconds`. Entries in named
+    /// arrays are named by their `name`
+    pub key: String,
+
+    /// The span of the replaced value
+    pub base: (usize, usize),
+
+    /// The span of the value that replaced it
+    pub overlay: (usize, usize),
+}
+
+/// Returns the `name` of an array entry, if it is a table with one
+pub(crate) fn entry_name_node(node: &Node) -> Option<&Node> {
+    match &node.value {
+        Value::Table(entries) => entries
+            .iter()
+            .find_map(|(key, value)| (key == "name").then_some(value)),
+        _ => None,
+    }
+}
+
+/// Returns the name of an array entry that is merged by name
+fn entry_name(node: &Node) -> Option<&str> {
+    match entry_name_node(node).map(|n| &n.value) {
+        Some(Value::String(name)) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+fn child_key(key: &str, child: &str) -> String {
+    if key.is_empty() {
+        child.to_owned()
+    } else {
+        format!("{key}.{child}")
+    }
+}
+
+fn merge_into(base: &mut Node, overlay: Node, key: &str, overrides: &mut Vec<Override>) {
+    let Node { span, value } = overlay;
+    match (&mut base.value, value) {
+        (Value::Table(entries), Value::Table(overlay)) => {
+            for (name, value) in overlay {
+                match entries.iter_mut().find(|(k, _)| *k == name) {
+                    Some((_, existing)) => {
+                        merge_into(existing, value, &child_key(key, &name), overrides)
+                    }
+                    None => entries.push((name, value)),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(overlay))
+            if !overlay.is_empty()
+                && items
+                    .iter()
+                    .chain(&overlay)
+                    .all(|n| entry_name(n).is_some()) =>
+        {
+            for value in overlay {
+                let name = entry_name(&value).unwrap().to_owned();
+                match items.iter_mut().find(|n| entry_name(n) == Some(&name)) {
+                    Some(existing) => {
+                        merge_into(existing, value, &child_key(key, &name), overrides)
+                    }
+                    None => items.push(value),
+                }
+            }
+        }
+        // Values that are repeated, such as the `name` that entries are merged by, stay in the base
+        (existing, value) if *existing == value => {}
+        (_, value) => {
+            overrides.push(Override {
+                key: key.to_owned(),
+                base: base.span,
+                overlay: span,
+            });
+            *base = Node::new(span, value);
+        }
+    }
+}
+
+/// Merges `overlay` over `base`, returning every value in `base` that was replaced
+pub fn merge(base: &mut Node, overlay: Node) -> Vec<Override> {
+    let mut overrides = Vec::new();
+    merge_into(base, overlay, "", &mut overrides);
+    overrides
+}
+
+/// Moves every span in `node` forward by `offset` bytes
+pub(crate) fn shift(node: &mut Node, offset: usize) {
+    node.span = (node.span.0 + offset, node.span.1 + offset);
+    match &mut node.value {
+        Value::Array(items) => items.iter_mut().for_each(|n| shift(n, offset)),
+        Value::Table(entries) => entries.iter_mut().for_each(|(_, n)| shift(n, offset)),
+        _ => {}
+    }
+}
+
+fn emit_parse_error(context: &mut Context, error: tree::Error, offset: usize) {
+    let (start, end) = error.span.unwrap_or((0, 0));
+    let span = context.subspan(start + offset, end + offset);
+    context
+        .error("failed to parse config file")
+        .code(ErrorCode::InvalidConfig)
+        .set_primary_span(span, error.message)
+        .emit();
+}
+
+/// Parses the file in `context` with `parse`
+pub(crate) fn parse_base(context: &mut Context, parse: tree::Parser) -> Result<Node, ()> {
+    parse(context.source()).map_err(|e| emit_parse_error(context, e, 0))
+}
+
+/// Parses `source` with `parse` and adds it to `context` as a layer, returning the parsed file
+/// with its spans pointing into the layer
+pub(crate) fn parse_layer(
+    context: &mut Context,
+    source: String,
+    path: String,
+    parse: tree::Parser,
+) -> Result<Node, ()> {
+    let node = parse(&source);
+    let offset = context.add_layer(source, path);
+    let mut node = node.map_err(|e| emit_parse_error(context, e, offset))?;
+    shift(&mut node, offset);
+    Ok(node)
+}
+
+/// Merges the override file `overlay` read from `overlay_path` over `base`, emitting a note for
+/// every overridden value
+pub(crate) fn apply(context: &mut Context, base: &mut Node, overlay: Node, overlay_path: &str) {
+    for o in merge(base, overlay) {
+        let base = context.subspan(o.base.0, o.base.1);
+        let overlay = context.subspan(o.overlay.0, o.overlay.1);
+        let line = context.line_number(overlay);
+        context
+            .note(format!("`{}` is overridden by `{overlay_path}`", o.key))
+            .set_primary_span(base, format!("value overridden in {overlay_path}:{line}"))
+            .span_label(overlay, "overriding value")
+            .emit();
+    }
+}
+
+/// Deserializes a config that was parsed into a tree
+pub(crate) fn finish(context: &mut Context, config: &Node) -> Result<upper::ConfigFile, ()> {
+    tree::from_node(config).map_err(|e| emit_parse_error(context, e, 0))
+}
+
+/// Parses the file in `context` with `parse`, merges the override file `(source, path)` over it
+/// after parsing it with `parse_overlay`, and deserializes the result
+pub fn verify(
+    context: &mut Context,
+    parse: tree::Parser,
+    (overlay_source, overlay_path): (String, String),
+    parse_overlay: tree::Parser,
+) -> Result<upper::ConfigFile, ()> {
+    let base = parse_base(context, parse);
+    let overlay = parse_layer(context, overlay_source, overlay_path.clone(), parse_overlay);
+    let (mut base, overlay) = (base?, overlay?);
+    apply(context, &mut base, overlay, &overlay_path);
+    finish(context, &base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{local_path, merge, verify};
+    use crate::tree::parse_toml;
+    use crate::Session;
+
+    const BASE: &str = r#"
+[recovery]
+main_altitude = 150.0
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "launch_detected"
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[states.timeout]
+seconds = 30.0
+transition = "Armed"
+"#;
+
+    const OVERLAY: &str = r#"
+recovery = { main_altitude = 100.0 }
+
+[[states]]
+name = "Flight"
+timeout = { seconds = 5.0 }
+
+[[states]]
+name = "Test"
+"#;
+
+    #[test]
+    fn merges_by_name() {
+        let mut base = parse_toml(BASE).unwrap();
+        let overlay = parse_toml(OVERLAY).unwrap();
+        let keys: Vec<_> = merge(&mut base, overlay)
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["recovery.main_altitude", "states.Flight.timeout.seconds"]
+        );
+
+        let mut session = Session::new();
+        let mut context = session.testing(BASE);
+        let overlay = (OVERLAY.to_owned(), "rocket.local.toml".to_owned());
+        let config = verify(&mut context, parse_toml, overlay, parse_toml).unwrap();
+        let states = config.states.get_ref();
+        assert_eq!(states.len(), 3);
+        let timeout = states[1].get_ref().timeout.as_ref().unwrap().get_ref();
+        assert_eq!(*timeout.seconds.as_ref().unwrap().get_ref(), 5.0);
+        assert_eq!(timeout.transition.as_ref().unwrap().get_ref(), "Armed");
+
+        // Spans of overridden values point into the override file
+        let main = config.recovery.as_ref().unwrap().get_ref();
+        let main = main.main_altitude.as_ref().unwrap();
+        assert!(main.start() > BASE.len());
+        assert_eq!(context.end_phase_and_emit().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn local_paths() {
+        assert_eq!(local_path("rocket.toml"), "rocket.local.toml");
+        assert_eq!(local_path("cfg/rocket.json"), "cfg/rocket.local.json");
+        assert_eq!(local_path("rocket"), "rocket.local");
+    }
+}
@@ -0,0 +1,198 @@
+//! Justified overrides of deny by default lints.
+//!
+//! Some lints find configs that are unsafe to fly unless something outside the config makes them
+//! safe, such as firing a pyro channel before launch is detected on a static test stand. These
+//! lints are errors, and there is no way to quietly allow them. Instead the config declares an
+//! override with the reason it is safe:
+//!
+//! ```toml
+//! [[override]]
+//! lint = "pre_launch_pyro"
+//! justification = "Static fire on the test stand, the charge is the ignition squib"
+//! ```
+//!
+//! The lint's errors are reported as warnings followed by the justification. Every override is
+//! listed in the summary and recorded in the config's metadata sidecar, so reviewers see it next
+//! to the approvals, see [`crate::approval::Metadata::overrides`].
+
+use codemap_diagnostic::Level;
+use serde::{Deserialize, Serialize};
+
+use crate::{lint::LINTS, upper, Context, ErrorCode, Span};
+
+/// An override as recorded in a config's metadata
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub lint: String,
+    pub justification: String,
+}
+
+/// Every override declared in `config`
+pub fn records(config: &upper::ConfigFile) -> Vec<Record> {
+    config
+        .overrides
+        .iter()
+        .map(|o| Record {
+            lint: o.get_ref().lint.get_ref().clone(),
+            justification: o.get_ref().justification.get_ref().clone(),
+        })
+        .collect()
+}
+
+/// Checks that every override in `config` names a deny by default lint once, with a
+/// justification, emitting errors to `context`. Returns the overrides that are valid
+fn verify<'a>(config: &'a upper::ConfigFile, context: &mut Context) -> Vec<&'a upper::Override> {
+    let mut valid: Vec<&upper::Override> = Vec::new();
+    for item in config.overrides.iter().map(|o| o.get_ref()) {
+        let name = item.lint.get_ref();
+        let span = Span::from_spanned(context, &item.lint);
+        match LINTS.iter().find(|l| l.name == name) {
+            None => {
+                context
+                    .error(format!("unknown lint `{name}`"))
+                    .code(ErrorCode::UnknownLint)
+                    .set_primary_span(span, "cannot be overridden")
+                    .emit();
+                continue;
+            }
+            Some(lint) if lint.level != Level::Error => {
+                context
+                    .error(format!("`{name}` is not a deny by default lint"))
+                    .code(ErrorCode::InvalidOverride)
+                    .set_primary_span(span, "only lints that are errors can be overridden")
+                    .emit();
+                continue;
+            }
+            Some(_) => {}
+        }
+        if item.justification.get_ref().trim().is_empty() {
+            let justification = Span::from_spanned(context, &item.justification);
+            context
+                .error(format!("the override of `{name}` has no justification"))
+                .code(ErrorCode::MissingJustification)
+                .set_primary_span(justification, "explain why the config is safe to fly")
+                .emit();
+            continue;
+        }
+        if let Some(first) = valid.iter().find(|v| v.lint.get_ref() == name) {
+            let first = Span::from_spanned(context, &first.lint);
+            context
+                .error(format!("`{name}` is overridden twice"))
+                .code(ErrorCode::DuplicateOverride)
+                .set_primary_span(span, "overridden again here")
+                .span_label(first, "first overridden here")
+                .emit();
+            continue;
+        }
+        valid.push(item);
+    }
+    valid
+}
+
+/// Applies the overrides of `config` to the lint errors emitted so far in this phase. Each error
+/// from an overridden lint becomes a warning, followed by a note with the justification.
+/// Overrides that are invalid, or that no error needed, are reported
+pub fn apply(config: &upper::ConfigFile, context: &mut Context) {
+    let emitted = std::mem::take(context.phase_diagnostics_mut());
+    let overrides = verify(config, context);
+    let mut used = vec![false; overrides.len()];
+    let errors = std::mem::take(context.phase_diagnostics_mut());
+
+    // The justification follows the notes and helps that belong to the overridden diagnostic
+    let justify = |context: &mut Context, item: &upper::Override| {
+        let span = Span::from_spanned(context, &item.justification);
+        context
+            .note(format!("`{}` is overridden", item.lint.get_ref()))
+            .set_primary_span(span, "justified here")
+            .emit();
+    };
+    let mut pending = None;
+    for mut diagnostic in emitted {
+        if matches!(diagnostic.level, Level::Error | Level::Warning) {
+            if let Some(item) = pending.take() {
+                justify(context, item);
+            }
+        }
+        let index = overrides
+            .iter()
+            .position(|o| diagnostic.code.as_deref() == Some(o.lint.get_ref()));
+        if let (Some(index), Level::Error) = (index, diagnostic.level) {
+            used[index] = true;
+            diagnostic.level = Level::Warning;
+            pending = Some(overrides[index]);
+        }
+        context.phase_diagnostics_mut().push(diagnostic);
+    }
+    if let Some(item) = pending {
+        justify(context, item);
+    }
+    context.phase_diagnostics_mut().extend(errors);
+
+    for (item, used) in overrides.iter().zip(used) {
+        if !used {
+            let span = Span::from_spanned(context, &item.lint);
+            context
+                .warn(format!("override of `{}` is unused", item.lint.get_ref()))
+                .set_primary_span(span, "the lint found nothing to report")
+                .emit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use crate::{lint, upper, Session};
+
+    const CONFIG: &str = r#"
+OVERRIDE
+
+[[states]]
+name = "Stand"
+
+[[states.commands]]
+pyro1 = true
+"#;
+
+    fn levels(overrides: &str) -> Vec<(Level, String)> {
+        let mut session = Session::new();
+        let mut context = session.testing(&CONFIG.replace("OVERRIDE", overrides));
+        let config = upper::verify(&mut context).unwrap();
+        lint::check(&config, &mut context);
+        let diagnostics = match context.end_phase_and_emit() {
+            Ok(d) | Err(d) => d,
+        };
+        diagnostics
+            .into_iter()
+            .map(|d| (d.level, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn overrides() {
+        let fired = "`pyro1` is fired before launch is detected".to_owned();
+        assert_eq!(levels("")[0], (Level::Error, fired.clone()));
+
+        let justified = r#"
+[[override]]
+lint = "pre_launch_pyro"
+justification = "Static fire, the charge is the motor igniter"
+"#;
+        let overridden = levels(justified);
+        assert_eq!(overridden[0], (Level::Warning, fired));
+        assert!(overridden.contains(&(Level::Note, "`pre_launch_pyro` is overridden".to_owned())));
+
+        for invalid in [
+            justified.replace("Static fire, the charge is the motor igniter", " "),
+            justified.replace("pre_launch_pyro", "repeated_threshold"),
+            justified.repeat(2),
+        ] {
+            assert!(levels(&invalid).iter().any(|(l, _)| *l == Level::Error));
+        }
+        let unused = justified.replace("pre_launch_pyro", "telemetry_bandwidth");
+        assert!(levels(&unused)
+            .iter()
+            .any(|(l, m)| *l == Level::Warning && m.contains("unused")));
+    }
+}
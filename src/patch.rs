@@ -0,0 +1,233 @@
+//! Delta patches between two encoded config files.
+//!
+//! Uploading a full config over the telemetry link takes minutes, so when a config is tuned in the
+//! field we send only the bytes that changed. A [`Patch`] is a list of chunks to overwrite in the
+//! previous config, plus lengths and checksums of both the previous and new configs so that the
+//! flight computer can refuse a patch built against a different base.
+//!
+//! Patches are encoded with postcard, like the config files themselves.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{Context, ErrorCode};
+
+/// Two chunks separated by a gap this small or smaller are merged into one, since the gap costs
+/// fewer bytes to resend than the offset and length of another chunk
+const MERGE_GAP: usize = 4;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// The length of the config this patch applies to
+    pub base_len: u32,
+
+    /// The CRC32 of the config this patch applies to
+    pub base_crc: u32,
+
+    /// The length of the config after the patch is applied
+    pub result_len: u32,
+
+    /// The CRC32 of the config after the patch is applied
+    pub result_crc: u32,
+
+    /// Byte ranges to overwrite, in increasing order of offset
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub offset: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    #[error("patch was built for a different base config")]
+    BaseMismatch,
+
+    #[error("chunk at offset {0} is out of bounds")]
+    OutOfBounds(u32),
+
+    #[error("patched config does not match the expected result")]
+    ResultMismatch,
+}
+
+/// Computes the standard CRC-32 (IEEE) checksum of `bytes`
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Builds a patch that turns `base` into `new`
+pub fn diff(base: &[u8], new: &[u8]) -> Patch {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        if base.get(i) == Some(&new[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < new.len() && base.get(i) != Some(&new[i]) {
+            i += 1;
+        }
+
+        match chunks.last_mut() {
+            Some(last) if start - (last.offset as usize + last.data.len()) <= MERGE_GAP => {
+                let last_end = last.offset as usize + last.data.len();
+                last.data.extend_from_slice(&new[last_end..i]);
+            }
+            _ => chunks.push(Chunk {
+                offset: start as u32,
+                data: new[start..i].to_vec(),
+            }),
+        }
+    }
+
+    Patch {
+        base_len: base.len() as u32,
+        base_crc: crc32(base),
+        result_len: new.len() as u32,
+        result_crc: crc32(new),
+        chunks,
+    }
+}
+
+impl Patch {
+    /// Applies this patch to `base` the same way the flight computer does, checking both the base
+    /// and the result against the checksums in the patch
+    pub fn apply(&self, base: &[u8]) -> Result<Vec<u8>, PatchError> {
+        if base.len() != self.base_len as usize || crc32(base) != self.base_crc {
+            return Err(PatchError::BaseMismatch);
+        }
+        let mut result = base.to_vec();
+        result.resize(self.result_len as usize, 0);
+        for chunk in &self.chunks {
+            let start = chunk.offset as usize;
+            let dst = result
+                .get_mut(start..start + chunk.data.len())
+                .ok_or(PatchError::OutOfBounds(chunk.offset))?;
+            dst.copy_from_slice(&chunk.data);
+        }
+        if crc32(&result) != self.result_crc {
+            return Err(PatchError::ResultMismatch);
+        }
+        Ok(result)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Serializing plain integers and byte vectors cannot fail
+        postcard::to_stdvec(self).unwrap()
+    }
+}
+
+/// Builds a patch from the session's patch base to `new`, checks that it applies cleanly, and
+/// writes it to `path`. Problems are emitted as errors to `context`
+pub(crate) fn write_patch(context: &mut Context, path: &str, new: &[u8]) {
+    let base_path = match context.session().patch_base() {
+        Some(base_path) => base_path.to_owned(),
+        None => {
+            context
+                .error("emitting a patch requires the previous config to diff against")
+                .code(ErrorCode::MissingPatchBase)
+                .emit();
+            return;
+        }
+    };
+    let base = match std::fs::read(&base_path) {
+        Ok(base) => base,
+        Err(err) => {
+            context
+                .error(format!("failed to read file `{base_path}`: {err:?}"))
+                .code(ErrorCode::ReadFailed)
+                .emit();
+            return;
+        }
+    };
+
+    let patch = diff(&base, new);
+    match patch.apply(&base) {
+        Ok(result) if result == new => {}
+        Ok(_) => {
+            context
+                .error("internal error: generated patch does not reproduce the new config")
+                .code(ErrorCode::InternalError)
+                .emit();
+            return;
+        }
+        Err(err) => {
+            context
+                .error(format!(
+                    "internal error: generated patch does not apply: {err}"
+                ))
+                .code(ErrorCode::InternalError)
+                .emit();
+            return;
+        }
+    }
+
+    let bytes = patch.to_bytes();
+    context
+        .note(format!(
+            "patch is {} bytes, replacing {} of {} bytes",
+            bytes.len(),
+            patch.chunks.iter().map(|c| c.data.len()).sum::<usize>(),
+            new.len()
+        ))
+        .emit();
+    crate::write_artifact(context, path, bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, diff, PatchError};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let base = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let cases: [&[u8]; 5] = [
+            &base,
+            &[1, 2, 0, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0],
+            &[1, 2, 3],
+            &[
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+            ],
+            &[],
+        ];
+        for new in cases {
+            let patch = diff(&base, new);
+            assert_eq!(patch.apply(&base).unwrap(), new);
+        }
+        assert!(diff(&base, &base).chunks.is_empty());
+    }
+
+    #[test]
+    fn nearby_changes_are_merged() {
+        let base = [0; 16];
+        let mut new = base;
+        new[2] = 1;
+        new[5] = 1;
+        new[15] = 1;
+        let patch = diff(&base, &new);
+        assert_eq!(patch.chunks.len(), 2);
+        assert_eq!(patch.chunks[0].data, [1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn wrong_base_is_rejected() {
+        let patch = diff(&[1, 2, 3], &[1, 5, 3]);
+        assert_eq!(patch.apply(&[1, 2, 4]), Err(PatchError::BaseMismatch));
+    }
+}
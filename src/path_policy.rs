@@ -0,0 +1,177 @@
+//! Path policies, which encode the recovery sequence that every flight has to follow.
+//!
+//! A `[[path_policy]]` lists groups of states in the order the flight passes through them, such as
+//! boost, then coast or drogue, then main. A transition, abort, or timeout may stay in its group,
+//! move to the next group, or go back to an earlier one, but one that skips a group, such as going
+//! straight from boost to main, is almost always a mistake and warns with the `path_policy` lint.
+//! States that are not in any group are not constrained.
+
+use std::collections::HashMap;
+
+use crate::{lint, upper, Context, ErrorCode, Span};
+
+/// Every transition out of `state`, as the name of the state it leads to and the span of the key
+/// that causes it
+fn edges<'a>(
+    config: &'a upper::ConfigFile,
+    state: &'a upper::State,
+    context: &Context,
+) -> Vec<(&'a str, Span)> {
+    let mut edges = Vec::new();
+    for check in state.checks.iter().map(|c| c.get_ref()) {
+        if let Some(transition) = &check.transition {
+            let span = Span::from_spanned(context, transition);
+            edges.push((transition.get_ref().as_str(), span));
+        }
+        if let Some(abort) = &check.abort {
+            let span = Span::from_spanned(context, abort);
+            let targets = lint::check_targets(config, check);
+            let skip = usize::from(check.transition.is_some());
+            edges.extend(targets.into_iter().skip(skip).map(|t| (t, span)));
+        }
+    }
+    let timeout = state.timeout.as_ref().map(|t| t.get_ref());
+    if let Some(transition) = timeout.and_then(|t| t.transition.as_ref()) {
+        let span = Span::from_spanned(context, transition);
+        edges.push((transition.get_ref().as_str(), span));
+    }
+    edges
+}
+
+/// Maps every state in `policy` to the position of its group, emitting an error for states that
+/// do not exist or are in more than one group
+fn groups<'a>(
+    config: &upper::ConfigFile,
+    policy: &'a upper::PathPolicy,
+    context: &mut Context,
+) -> Result<HashMap<&'a str, usize>, ()> {
+    let mut positions: HashMap<&str, (usize, Span)> = HashMap::new();
+    let mut result = Ok(());
+    for (position, group) in policy.order.get_ref().iter().enumerate() {
+        for name in group {
+            let span = Span::from_spanned(context, name);
+            let exists = config
+                .states
+                .get_ref()
+                .iter()
+                .any(|s| s.get_ref().name.get_ref() == name.get_ref());
+            if !exists {
+                context
+                    .error(format!("state not found `{}`", name.get_ref()))
+                    .code(ErrorCode::UnknownState)
+                    .set_primary_span(span, "not found")
+                    .emit();
+                result = Err(());
+            } else if let Some((_, first)) = positions.get(name.get_ref().as_str()) {
+                context
+                    .error(format!(
+                        "state `{}` is listed twice in path policy `{}`",
+                        name.get_ref(),
+                        policy.name.get_ref()
+                    ))
+                    .code(ErrorCode::DuplicatePathPolicyState)
+                    .set_primary_span(span, "listed again here")
+                    .span_label(*first, "first listed here")
+                    .emit();
+                result = Err(());
+            } else {
+                positions.insert(name.get_ref(), (position, span));
+            }
+        }
+    }
+    result.map(|_| positions.into_iter().map(|(k, (v, _))| (k, v)).collect())
+}
+
+/// Warns about every transition that skips a group of states in a `[[path_policy]]`
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    for policy in config.path_policies.iter().map(|p| p.get_ref()) {
+        let Ok(positions) = groups(config, policy, context) else {
+            continue;
+        };
+        let order = policy.order.get_ref();
+        for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+            let from = state.name.get_ref().as_str();
+            let Some(&position) = positions.get(from) else {
+                continue;
+            };
+            for (to, span) in edges(config, state, context) {
+                if !positions.get(to).is_some_and(|&p| p > position + 1) {
+                    continue;
+                }
+                let skipped: Vec<_> = order[position + 1]
+                    .iter()
+                    .map(|s| format!("`{}`", s.get_ref()))
+                    .collect();
+                let skipped = skipped.join(" or ");
+                let policy_span = Span::from_spanned(context, &policy.name);
+                context
+                    .warn(format!(
+                        "transition from `{from}` to `{to}` skips {skipped}"
+                    ))
+                    .set_primary_span(span, format!("goes straight to `{to}`"))
+                    .span_label(
+                        policy_span,
+                        "the flight must pass through the groups of this policy in order",
+                    )
+                    .lint("path_policy")
+                    .emit();
+                context
+                    .help(format!("transition to {skipped} first"))
+                    .emit();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn skipped_group() {
+        let config = r#"
+[[path_policy]]
+name = "recovery"
+order = [["Boost"], ["Coast", "Drogue"], ["Main"]]
+
+[[states]]
+name = "Boost"
+
+[[states.checks]]
+name = "Burnout"
+check = "altitude"
+greater_than = 1000.0
+transition = "Coast"
+
+[[states.checks]]
+name = "Early"
+check = "altitude"
+less_than = 300.0
+transition = "Main"
+
+[[states]]
+name = "Coast"
+timeout = { seconds = 10.0, transition = "Main" }
+
+[[states]]
+name = "Drogue"
+
+[[states]]
+name = "Main"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.code.as_deref() == Some("path_policy"))
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message,
+            "transition from `Boost` to `Main` skips `Coast` or `Drogue`"
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Everything needed to verify a config from another crate, such as a ground station or an
+//! editor.
+//!
+//! ```ignore
+//! use nova_verifier::prelude::*;
+//!
+//! let mut session = Session::new();
+//! match verify_inner(&mut session, source, "rocket.toml".to_owned()) {
+//!     Ok((bytes, warnings)) => upload(bytes),
+//!     Err(err) => show(err.diagnostics()),
+//! }
+//! ```
+//!
+//! The items here, and the public items of the modules declared at the top of the crate without
+//! `#[doc(hidden)]`, only change in a breaking way with a major version. The snapshot in
+//! `tests/public-api.txt` records them, so that a change to the stable API is never accidental.
+//! The structs of [`upper`] gain fields as the config language grows, so build them by parsing a
+//! config rather than by hand.
+
+pub use crate::diagnostics::{self, DiagnosticInfo};
+pub use crate::limits::{Limits, Target};
+pub use crate::ncf::{self, NcfError};
+pub use crate::upper;
+pub use crate::{
+    load_config, verify_file, verify_inner, verify_state, verify_str, Context, DiagnosticFormatter,
+    EmitKind, ErrorFormat, HumanFormatter, JsonFormatter, LocatedDiagnostic, LocatedSpan,
+    SarifFormatter, Session, ShortFormatter, Tally, VerifiedConfig, VerifyError, VerifyFailure,
+};
+pub use codemap_diagnostic::{Diagnostic, Level};
+pub use nova_software_common::index;
@@ -0,0 +1,81 @@
+//! Built in starting points for new config files.
+//!
+//! Each preset is a complete config for a common recovery scheme that verifies without any
+//! diagnostics. Thresholds and delays that depend on the rocket are marked with `TODO` comments.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A recovery scheme that `init` can generate a config for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// Drogue at apogee, main parachute close to the ground
+    DualDeploy,
+
+    /// One parachute at apogee with a backup charge
+    SingleDeploy,
+
+    /// Sustainer ignition after booster burnout, then dual deploy
+    TwoStage,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::DualDeploy, Preset::SingleDeploy, Preset::TwoStage];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::DualDeploy => "dual-deploy",
+            Preset::SingleDeploy => "single-deploy",
+            Preset::TwoStage => "two-stage",
+        }
+    }
+
+    /// The toml source of this preset
+    pub fn source(self) -> &'static str {
+        match self {
+            Preset::DualDeploy => include_str!("presets/dual_deploy.toml"),
+            Preset::SingleDeploy => include_str!("presets/single_deploy.toml"),
+            Preset::TwoStage => include_str!("presets/two_stage.toml"),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Preset::ALL.iter().map(|p| p.name()).collect();
+                format!("unknown preset `{s}`, expected one of {}", names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Preset;
+    use crate::Session;
+
+    #[test]
+    fn presets_are_clean() {
+        for preset in Preset::ALL {
+            let mut session = Session::new();
+            let result = crate::verify_inner(
+                &mut session,
+                preset.source().to_owned(),
+                format!("{preset}.toml"),
+            );
+            let (_, diagnostics) = result.unwrap_or_else(|d| panic!("{preset} failed: {d:#?}"));
+            assert!(diagnostics.is_empty(), "{preset}: {diagnostics:#?}");
+        }
+    }
+}
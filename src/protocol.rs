@@ -0,0 +1,538 @@
+//! The framed protocol used to upload configs to a flight computer and read them back.
+//!
+//! Pad side links are flaky, and a half written config is worse than none, so uploads are never
+//! a plain stream of bytes:
+//! - Nothing is sent unless the session is online and the config's metadata sidecar has the
+//!   approvals the session requires, see [`crate::approval`].
+//! - Every frame ends with a CRC-32 of its contents. Corrupt frames are dropped, and the sender
+//!   retries once it times out waiting for a reply.
+//! - The flight computer acknowledges every chunk with the offset it expects next, or refuses it
+//!   with the offset it actually has, so chunks are never duplicated or skipped.
+//! - An upload starts with [`Frame::Begin`], which carries the length and CRC-32 of the whole
+//!   config. Beginning the same upload again after a disconnect resumes where it stopped.
+//! - Nothing replaces the stored config until [`Frame::Commit`], which the flight computer only
+//!   accepts once every byte has arrived and the CRC-32 of the whole config matches.
+//!
+//! The bytes are carried by a [`Transport`], which delivers whole frames. [`Receiver`] is the
+//! flight computer's side of the protocol. [`read_back`] also refuses to send anything in offline
+//! mode, see [`crate::offline`].
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    approval::{self, ApprovalError, Metadata},
+    offline::OfflineError,
+    Session,
+};
+
+/// The most config bytes sent in one frame
+pub const MAX_CHUNK_LEN: usize = 256;
+
+/// A message between the verifier and a flight computer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// Starts an upload of `len` bytes whose CRC-32 is `crc`, or resumes it if the flight computer
+    /// already has part of the same upload. Answered with the offset to continue from
+    Begin { len: u32, crc: u32 },
+
+    /// Config bytes starting at `offset`
+    Chunk { offset: u32, data: Vec<u8> },
+
+    /// Replaces the stored config with the finished upload
+    Commit,
+
+    /// Asks for the stored config, starting at `offset`
+    Read { offset: u32 },
+
+    /// Sent by the flight computer when a request succeeds. `offset` is where the next chunk
+    /// should start
+    Ack { offset: u32 },
+
+    /// Sent by the flight computer when a request is refused. `offset` is where the next chunk
+    /// should start
+    Nack { offset: u32 },
+
+    /// Part of the stored config, which is `len` bytes long in total
+    Data {
+        offset: u32,
+        len: u32,
+        data: Vec<u8>,
+    },
+}
+
+/// Returns the CRC-32 (IEEE) of `bytes`
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Encodes `frame` followed by the CRC-32 of its encoding
+pub fn encode_frame(frame: &Frame) -> Vec<u8> {
+    // Frames only contain integers and byte arrays, so this cannot fail
+    let mut bytes = postcard::to_stdvec(frame).unwrap();
+    let crc = crc32(&bytes);
+    bytes.extend(crc.to_le_bytes());
+    bytes
+}
+
+/// Decodes a frame written by [`encode_frame`], returning `None` if it is corrupt
+pub fn decode_frame(bytes: &[u8]) -> Option<Frame> {
+    let split = bytes.len().checked_sub(4)?;
+    let (body, crc) = bytes.split_at(split);
+    if crc32(body).to_le_bytes() != crc {
+        return None;
+    }
+    postcard::from_bytes(body).ok()
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    #[error("timed out waiting for a reply")]
+    Timeout,
+
+    #[error("the link disconnected")]
+    Disconnected,
+
+    #[error("{0}")]
+    Io(String),
+}
+
+/// A link to a flight computer that carries whole frames, such as a serial port
+pub trait Transport {
+    fn send(&mut self, frame: &[u8]) -> Result<(), TransportError>;
+
+    /// Waits up to `timeout` for the next frame
+    fn recv(&mut self, timeout: Duration) -> Result<Vec<u8>, TransportError>;
+
+    /// Opens the link again after it disconnected
+    fn reconnect(&mut self) -> Result<(), TransportError>;
+}
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error(transparent)]
+    Offline(#[from] OfflineError),
+
+    #[error("the config is not approved for upload: {0}")]
+    NotApproved(#[from] ApprovalError),
+
+    #[error("the flight computer did not respond after {0} attempts")]
+    NoResponse(u32),
+
+    #[error("failed to reconnect to the flight computer: {0}")]
+    Transport(TransportError),
+
+    #[error(
+        "the flight computer rejected the config, its CRC did not match once every byte was sent"
+    )]
+    Rejected,
+
+    #[error("the flight computer has no config to read")]
+    NoConfig,
+
+    #[error("unexpected reply from the flight computer: {0:?}")]
+    Unexpected(Frame),
+
+    #[error("the config is {0} bytes, which is too large to upload")]
+    TooLarge(usize),
+}
+
+/// How patient the sending side of the protocol is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The most config bytes sent in one chunk, at most [`MAX_CHUNK_LEN`]
+    pub chunk_len: usize,
+
+    /// How long to wait for each reply
+    pub timeout: Duration,
+
+    /// How many times a request is retried without progress before giving up
+    pub retries: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            chunk_len: MAX_CHUNK_LEN,
+            timeout: Duration::from_millis(500),
+            retries: 5,
+        }
+    }
+}
+
+/// Why an exchange did not produce a reply
+enum Interrupted {
+    /// The link was reopened, so the flight computer may have forgotten the current request
+    Reconnected,
+    Failed(ProtocolError),
+}
+
+/// Sends `frame` until a valid reply arrives, counting failed attempts in `failures`
+fn exchange(
+    transport: &mut impl Transport,
+    frame: &Frame,
+    options: &Options,
+    failures: &mut u32,
+) -> Result<Frame, Interrupted> {
+    let bytes = encode_frame(frame);
+    loop {
+        if *failures > options.retries {
+            return Err(Interrupted::Failed(ProtocolError::NoResponse(*failures)));
+        }
+        let reply = transport
+            .send(&bytes)
+            .and_then(|_| transport.recv(options.timeout));
+        match reply {
+            Ok(reply) => match decode_frame(&reply) {
+                Some(reply) => return Ok(reply),
+                None => *failures += 1,
+            },
+            Err(TransportError::Disconnected) => {
+                *failures += 1;
+                transport
+                    .reconnect()
+                    .map_err(|e| Interrupted::Failed(ProtocolError::Transport(e)))?;
+                return Err(Interrupted::Reconnected);
+            }
+            Err(_) => *failures += 1,
+        }
+    }
+}
+
+/// Uploads `config` over `transport`, resuming after disconnects. When this returns `Ok`, the
+/// flight computer has stored the whole config and checked its CRC-32. When it returns `Err`, the
+/// flight computer still has its previous config, or the whole new one if the link failed while
+/// committing, but never part of one.
+///
+/// `metadata` is the sidecar of `config`. Before the first frame is sent, the session must be
+/// online and the approvals in `metadata` must meet the session's [`approval::Policy`]
+pub fn upload(
+    session: &Session,
+    metadata: &Metadata,
+    transport: &mut impl Transport,
+    config: &[u8],
+    options: &Options,
+) -> Result<(), ProtocolError> {
+    session.require_online("upload a config to the flight computer")?;
+    let policy = session.approval_policy();
+    approval::require_approvals(metadata, config, &policy.trusted, policy.required)?;
+    let len: u32 = config
+        .len()
+        .try_into()
+        .map_err(|_| ProtocolError::TooLarge(config.len()))?;
+    let crc = crc32(config);
+    let chunk_len = options.chunk_len.clamp(1, MAX_CHUNK_LEN);
+    let mut failures = 0;
+
+    'session: loop {
+        let begin = Frame::Begin { len, crc };
+        let mut offset = match exchange(transport, &begin, options, &mut failures) {
+            Ok(Frame::Ack { offset }) if offset <= len => offset,
+            Ok(reply) => return Err(ProtocolError::Unexpected(reply)),
+            Err(Interrupted::Reconnected) => continue 'session,
+            Err(Interrupted::Failed(err)) => return Err(err),
+        };
+        while offset < len {
+            let start = offset as usize;
+            let end = (start + chunk_len).min(config.len());
+            let chunk = Frame::Chunk {
+                offset,
+                data: config[start..end].to_vec(),
+            };
+            match exchange(transport, &chunk, options, &mut failures) {
+                Ok(Frame::Ack { offset: next }) if next > offset && next <= len => {
+                    offset = next;
+                    failures = 0;
+                }
+                Ok(Frame::Nack { offset: expected }) if expected <= len => {
+                    offset = expected;
+                    failures += 1;
+                }
+                Ok(reply) => return Err(ProtocolError::Unexpected(reply)),
+                Err(Interrupted::Reconnected) => continue 'session,
+                Err(Interrupted::Failed(err)) => return Err(err),
+            }
+        }
+        return match exchange(transport, &Frame::Commit, options, &mut failures) {
+            Ok(Frame::Ack { .. }) => Ok(()),
+            Ok(Frame::Nack { .. }) => Err(ProtocolError::Rejected),
+            Ok(reply) => Err(ProtocolError::Unexpected(reply)),
+            Err(Interrupted::Reconnected) => continue 'session,
+            Err(Interrupted::Failed(err)) => Err(err),
+        };
+    }
+}
+
+/// Reads the config stored on the flight computer over `transport`
+pub fn read_back(
+    session: &Session,
+    transport: &mut impl Transport,
+    options: &Options,
+) -> Result<Vec<u8>, ProtocolError> {
+    session.require_online("read the config from the flight computer")?;
+    let mut config = Vec::new();
+    let mut failures = 0;
+    loop {
+        let offset = config.len() as u32;
+        match exchange(transport, &Frame::Read { offset }, options, &mut failures) {
+            // Data that does not move the read forward is a failed attempt, so a flight computer
+            // that keeps sending it cannot stall the read forever
+            Ok(Frame::Data {
+                offset: at, data, ..
+            }) if at == offset && data.is_empty() => failures += 1,
+            Ok(Frame::Data {
+                offset: at,
+                len,
+                data,
+            }) if at == offset
+                && u32::try_from(data.len())
+                    .ok()
+                    .and_then(|n| offset.checked_add(n))
+                    .is_some_and(|end| end <= len) =>
+            {
+                config.extend(data);
+                failures = 0;
+                if config.len() as u32 == len {
+                    return Ok(config);
+                }
+            }
+            Ok(Frame::Nack { .. }) => return Err(ProtocolError::NoConfig),
+            Ok(reply) => return Err(ProtocolError::Unexpected(reply)),
+            // Reads do not change anything, so they can simply be sent again
+            Err(Interrupted::Reconnected) => {}
+            Err(Interrupted::Failed(err)) => return Err(err),
+        }
+    }
+}
+
+/// The flight computer's side of the protocol, which stores the uploaded config in memory
+#[derive(Debug, Default, Clone)]
+pub struct Receiver {
+    /// The length and CRC-32 of the upload in progress
+    upload: Option<(u32, u32)>,
+    staged: Vec<u8>,
+    config: Option<Vec<u8>>,
+}
+
+impl Receiver {
+    /// A receiver that already stores `config`
+    pub fn with_config(config: Vec<u8>) -> Self {
+        Self {
+            config: Some(config),
+            ..Self::default()
+        }
+    }
+
+    /// The last config that was committed
+    pub fn config(&self) -> Option<&[u8]> {
+        self.config.as_deref()
+    }
+
+    /// Handles the frame `bytes`, returning the encoded reply. Corrupt frames are dropped without
+    /// a reply, so that the sender times out and retries
+    pub fn handle(&mut self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let reply = match decode_frame(bytes)? {
+            Frame::Begin { len, crc } => {
+                if self.upload != Some((len, crc)) {
+                    self.upload = Some((len, crc));
+                    self.staged.clear();
+                }
+                self.ack()
+            }
+            Frame::Chunk { offset, data } => match self.upload {
+                Some((len, _))
+                    if offset as usize == self.staged.len()
+                        && self.staged.len() + data.len() <= len as usize =>
+                {
+                    self.staged.extend(data);
+                    self.ack()
+                }
+                Some(_) => Frame::Nack {
+                    offset: self.staged.len() as u32,
+                },
+                None => Frame::Nack { offset: 0 },
+            },
+            Frame::Commit => match self.upload.take() {
+                Some((len, crc))
+                    if self.staged.len() == len as usize && crc32(&self.staged) == crc =>
+                {
+                    self.config = Some(std::mem::take(&mut self.staged));
+                    Frame::Ack { offset: len }
+                }
+                _ => {
+                    self.staged.clear();
+                    Frame::Nack { offset: 0 }
+                }
+            },
+            Frame::Read { offset } => match &self.config {
+                Some(config) if offset as usize <= config.len() => {
+                    let start = offset as usize;
+                    let end = (start + MAX_CHUNK_LEN).min(config.len());
+                    Frame::Data {
+                        offset,
+                        len: config.len() as u32,
+                        data: config[start..end].to_vec(),
+                    }
+                }
+                _ => Frame::Nack { offset: 0 },
+            },
+            Frame::Ack { .. } | Frame::Nack { .. } | Frame::Data { .. } => return None,
+        };
+        Some(encode_frame(&reply))
+    }
+
+    fn ack(&self) -> Frame {
+        Frame::Ack {
+            offset: self.staged.len() as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_fc::{Fault, MockFlightComputer};
+
+    #[test]
+    fn recovers_from_faults() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let config: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let options = Options {
+            chunk_len: 100,
+            ..Options::default()
+        };
+        use Fault::*;
+        let mut session = Session::new();
+        let metadata = approval::approve_for_testing(&mut session, &config);
+        let mut fc = MockFlightComputer::new();
+        fc.inject([
+            Pass, Pass, Drop, Pass, Corrupt, Pass, Disconnect, Pass, Drop,
+        ]);
+        upload(&session, &metadata, &mut fc, &config, &options).unwrap();
+        assert_eq!(fc.config(), Some(config.as_slice()));
+
+        fc.inject([Corrupt, Pass, Disconnect]);
+        assert_eq!(read_back(&session, &mut fc, &options).unwrap(), config);
+    }
+
+    /// A flight computer that answers every read with no data
+    struct EmptyReads(Option<Vec<u8>>);
+
+    impl Transport for EmptyReads {
+        fn send(&mut self, frame: &[u8]) -> Result<(), TransportError> {
+            let offset = match decode_frame(frame) {
+                Some(Frame::Read { offset }) => offset,
+                frame => panic!("unexpected frame {frame:?}"),
+            };
+            self.0 = Some(encode_frame(&Frame::Data {
+                offset,
+                len: 10,
+                data: Vec::new(),
+            }));
+            Ok(())
+        }
+
+        fn recv(&mut self, _timeout: Duration) -> Result<Vec<u8>, TransportError> {
+            self.0.take().ok_or(TransportError::Timeout)
+        }
+
+        fn reconnect(&mut self) -> Result<(), TransportError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn empty_reads_fail() {
+        let options = Options::default();
+        let session = Session::new();
+        assert!(matches!(
+            read_back(&session, &mut EmptyReads(None), &options),
+            Err(ProtocolError::NoResponse(6))
+        ));
+    }
+
+    #[test]
+    fn keeps_old_config_on_failure() {
+        let options = Options::default();
+        let mut session = Session::new();
+        let mut fc = MockFlightComputer::new();
+        assert!(matches!(
+            read_back(&session, &mut fc, &options),
+            Err(ProtocolError::NoConfig)
+        ));
+        let metadata = approval::approve_for_testing(&mut session, b"old");
+        upload(&session, &metadata, &mut fc, b"old", &options).unwrap();
+
+        fc.inject([Fault::Pass]);
+        fc.inject([Fault::Drop; 10]);
+        let metadata = approval::approve_for_testing(&mut session, b"new config");
+        assert!(matches!(
+            upload(&session, &metadata, &mut fc, b"new config", &options),
+            Err(ProtocolError::NoResponse(6))
+        ));
+        assert_eq!(fc.config(), Some(&b"old"[..]));
+    }
+
+    #[test]
+    fn refuses_offline() {
+        let options = Options::default();
+        let mut session = Session::new();
+        let metadata = approval::approve_for_testing(&mut session, b"new config");
+        session.set_offline(true);
+        let mut fc = MockFlightComputer::with_config(b"old".to_vec());
+        assert!(matches!(
+            upload(&session, &metadata, &mut fc, b"new config", &options),
+            Err(ProtocolError::Offline(_))
+        ));
+        assert!(matches!(
+            read_back(&session, &mut fc, &options),
+            Err(ProtocolError::Offline(_))
+        ));
+        assert_eq!(fc.frames, 0);
+        assert_eq!(fc.config(), Some(&b"old"[..]));
+    }
+
+    #[test]
+    fn refuses_unapproved() {
+        let options = Options::default();
+        let mut session = Session::new();
+        let mut fc = MockFlightComputer::with_config(b"old".to_vec());
+        assert!(matches!(
+            upload(
+                &session,
+                &Metadata::default(),
+                &mut fc,
+                b"new config",
+                &options
+            ),
+            Err(ProtocolError::NotApproved(
+                ApprovalError::NotEnoughApprovals {
+                    found: 0,
+                    required: 2
+                }
+            ))
+        ));
+
+        // Approvals of another config do not count
+        let metadata = approval::approve_for_testing(&mut session, b"other config");
+        assert!(matches!(
+            upload(&session, &metadata, &mut fc, b"new config", &options),
+            Err(ProtocolError::NotApproved(
+                ApprovalError::WrongConfig { .. }
+            ))
+        ));
+        assert_eq!(fc.frames, 0);
+        assert_eq!(fc.config(), Some(&b"old"[..]));
+    }
+}
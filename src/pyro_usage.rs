@@ -0,0 +1,301 @@
+//! Analysis of how each pyro channel is used.
+//!
+//! The mistakes that destroy recovery systems are rarely malformed configs. They are charges that
+//! fire twice, charges that fire without anyone checking they are connected, and charges wired to
+//! a channel the config never fires. Each warns with its own lint:
+//!
+//! - `refired_pyro`: a channel is fired in a state that can be reached from another state that
+//!   fired it, without passing a state that disarms it with `pyroN = false`
+//! - `unchecked_continuity`: a channel is fired in a state that no `pyroN_continuity` check runs
+//!   before, either in `[self_test]` or in a state that leads to it
+//! - `unused_pyro`: a pyro channel of the board is never fired, disarmed, or checked, in a config
+//!   that uses some other pyro channel. Configs without any pyro channels, such as telemetry
+//!   flights, are not warned about
+//!
+//! A state that both fires and disarms a channel is taken to disarm it after firing.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    duration::{reachable, successors},
+    spanned::SpannedValue,
+    upper::{self, TomlBool},
+    Context, Span,
+};
+
+/// The pyro channels that the encoded format can address, named as in commands
+const CHANNELS: [&str; 3] = ["pyro1", "pyro2", "pyro3"];
+
+/// The value that `command` sets pyro channel `channel` to, counting from 0
+fn setting(command: &upper::Command, channel: usize) -> Option<&SpannedValue<TomlBool>> {
+    [&command.pyro1, &command.pyro2, &command.pyro3][channel].as_ref()
+}
+
+/// The first command of `state` that fires `channel`
+fn fired(state: &upper::State, channel: usize) -> Option<&SpannedValue<TomlBool>> {
+    state
+        .commands
+        .iter()
+        .filter_map(|c| setting(c.get_ref(), channel))
+        .find(|value| bool::from(*value.get_ref()))
+}
+
+fn disarms(state: &upper::State, channel: usize) -> bool {
+    state
+        .commands
+        .iter()
+        .filter_map(|c| setting(c.get_ref(), channel))
+        .any(|value| !bool::from(*value.get_ref()))
+}
+
+/// Whether `check` reads the continuity of `channel`
+fn reads_continuity(check: &upper::Check, channel: usize) -> bool {
+    *check.check.get_ref() == format!("{}_continuity", CHANNELS[channel])
+}
+
+/// Runs every pyro channel analysis over `config`, emitting warnings to `context`
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let order: Vec<&upper::State> = config
+        .states
+        .get_ref()
+        .iter()
+        .map(|s| s.get_ref())
+        .collect();
+    let states: HashMap<&str, &upper::State> = order
+        .iter()
+        .map(|s| (s.name.get_ref().as_str(), *s))
+        .collect();
+    for channel in 0..CHANNELS.len() {
+        refired(config, &order, &states, channel, context);
+        unchecked_continuity(config, &order, &states, channel, context);
+    }
+    unused(config, &order, context);
+}
+
+/// Warns about states that fire `channel` after another state fired it, without disarming it in
+/// between
+fn refired(
+    config: &upper::ConfigFile,
+    order: &[&upper::State],
+    states: &HashMap<&str, &upper::State>,
+    channel: usize,
+    context: &mut Context,
+) {
+    let name = CHANNELS[channel];
+    // States that leave the channel fired
+    let armed: Vec<&upper::State> = order
+        .iter()
+        .copied()
+        .filter(|s| fired(s, channel).is_some() && !disarms(s, channel))
+        .collect();
+    for state in &armed {
+        let state_name = state.name.get_ref().as_str();
+        let first = armed.iter().find(|earlier| {
+            let next = successors(config, earlier);
+            reachable(config, states, next, |s| disarms(s, channel)).contains(state_name)
+        });
+        let first = match first {
+            Some(first) => first,
+            None => continue,
+        };
+        // `armed` only has states that fire the channel
+        let span = Span::from_spanned(context, fired(state, channel).unwrap());
+        let first_span = Span::from_spanned(context, fired(first, channel).unwrap());
+        let warning = context
+            .warn(format!(
+                "`{name}` is fired again in state `{state_name}` without being disarmed"
+            ))
+            .lint("refired_pyro");
+        let warning = if std::ptr::eq(*first, *state) {
+            warning.set_primary_span(span, "fired every time this state is entered")
+        } else {
+            warning
+                .set_primary_span(span, "fired again here")
+                .span_label(first_span, "first fired here")
+        };
+        warning.emit();
+        context
+            .help(format!(
+                "disarm the channel with `{name} = false` once its charge has fired"
+            ))
+            .emit();
+    }
+}
+
+/// Warns about states that fire `channel` when no continuity check for it can have run first
+fn unchecked_continuity(
+    config: &upper::ConfigFile,
+    order: &[&upper::State],
+    states: &HashMap<&str, &upper::State>,
+    channel: usize,
+    context: &mut Context,
+) {
+    let self_tested = config.self_test.as_ref().is_some_and(|t| {
+        let steps = &t.get_ref().steps;
+        steps.iter().any(|s| reads_continuity(s.get_ref(), channel))
+    });
+    if self_tested {
+        return;
+    }
+    let checked: HashSet<&str> = order
+        .iter()
+        .filter(|s| {
+            s.checks
+                .iter()
+                .any(|c| reads_continuity(c.get_ref(), channel))
+        })
+        .flat_map(|s| reachable(config, states, successors(config, s), |_| false))
+        .collect();
+    let name = CHANNELS[channel];
+    for state in order {
+        let value = match fired(state, channel) {
+            Some(value) => value,
+            None => continue,
+        };
+        let state_name = state.name.get_ref();
+        if checked.contains(state_name.as_str()) {
+            continue;
+        }
+        let span = Span::from_spanned(context, value);
+        context
+            .warn(format!(
+                "`{name}` is fired in state `{state_name}` without checking its continuity first"
+            ))
+            .set_primary_span(span, "a disconnected charge would not be noticed")
+            .lint("unchecked_continuity")
+            .emit();
+        context
+            .help(format!(
+                "add a `{name}_continuity` check to `[self_test]`, or to a state before `{state_name}`"
+            ))
+            .emit();
+    }
+}
+
+/// Warns about pyro channels of the session's board that `config` never uses
+fn unused(config: &upper::ConfigFile, order: &[&upper::State], context: &mut Context) {
+    let board = context.session().board();
+    let (board_name, count) = (board.name.clone(), board.limits.pyro_channels);
+    let events = config.mission_events.iter().map(|e| &e.get_ref().command);
+    let commands: Vec<&upper::Command> = order
+        .iter()
+        .flat_map(|s| &s.commands)
+        .chain(events)
+        .map(|c| c.get_ref())
+        .collect();
+    let steps = config.self_test.iter().flat_map(|t| &t.get_ref().steps);
+    let checks: Vec<&upper::Check> = order
+        .iter()
+        .flat_map(|s| &s.checks)
+        .chain(steps)
+        .map(|c| c.get_ref())
+        .collect();
+    let used: Vec<bool> = (0..CHANNELS.len())
+        .map(|channel| {
+            commands.iter().any(|c| setting(c, channel).is_some())
+                || checks.iter().any(|c| reads_continuity(c, channel))
+        })
+        .collect();
+    if !used.contains(&true) {
+        return;
+    }
+    for (name, used) in CHANNELS.iter().zip(used).take(count.into()) {
+        if used {
+            continue;
+        }
+        context
+            .warn(format!("`{name}` is never used"))
+            .lint("unused_pyro")
+            .emit();
+        context
+            .note(format!(
+                "the `{board_name}` board has {count} pyro channels, but nothing fires, disarms, or checks the continuity of `{name}`"
+            ))
+            .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn pyro_usage() {
+        let config = r#"
+[self_test]
+steps = [{ name = "Drogue", check = "pyro1_continuity", flag = "set" }]
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "MainContinuity"
+check = "pyro2_continuity"
+flag = "set"
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+pyro1 = true
+
+[[states.checks]]
+name = "Descending"
+check = "apogee"
+flag = "set"
+transition = "Drogue"
+
+[[states]]
+name = "Drogue"
+
+[[states.commands]]
+pyro1 = true
+
+[[states.commands]]
+pyro2 = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == codemap_diagnostic::Level::Warning)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "`pyro1` is fired again in state `Drogue` without being disarmed",
+                "`pyro3` is never used",
+            ]
+        );
+
+        // Disarming in between is fine, but dropping the self test leaves `pyro1` unchecked
+        let disarmed = config
+            .replace("[self_test]\nsteps = [{ name = \"Drogue\", check = \"pyro1_continuity\", flag = \"set\" }]\n", "")
+            .replace(
+                "transition = \"Drogue\"",
+                "transition = \"Drogue\"\n\n[[states.commands]]\npyro1 = false\ndelay = 1.0",
+            );
+        let mut context = session.testing(&disarmed);
+        let mid = upper::verify(&mut context).unwrap();
+        super::check(&mid, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == codemap_diagnostic::Level::Warning)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "`pyro1` is fired in state `Flight` without checking its continuity first",
+                "`pyro1` is fired in state `Drogue` without checking its continuity first",
+                "`pyro3` is never used",
+            ]
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Descriptions of everything the config language accepts: sections and their keys, check kinds,
+//! and command actions.
+//!
+//! These are used to generate documentation and help messages, so that both stay in sync with
+//! what the verifier actually accepts. Lints are described separately in [`crate::lint::LINTS`].
+
+use crate::limits::Target;
+
+/// A table in the config, along with the keys it accepts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// How the section is written in toml, such as `[[states.checks]]`
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// Every key the section accepts, with a description of each
+    pub keys: &'static [(&'static str, &'static str)],
+}
+
+/// A value that `check = "..."` accepts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CheckKind {
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// The conditions this kind can be combined with. High level kinds take none, see
+    /// [`crate::upper::HIGH_LEVEL_CHECKS`]
+    pub conditions: &'static [&'static str],
+
+    /// The flight computers that support this kind
+    pub targets: &'static [Target],
+}
+
+/// An action that a command can perform. Each command performs exactly one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CommandAction {
+    pub name: &'static str,
+
+    /// The values the action accepts
+    pub value: &'static str,
+    pub description: &'static str,
+}
+
+/// The version of the config language. Deprecated names keep working until this is bumped, see
+/// [`DEPRECATIONS`]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A check kind that was renamed. Configs using the old name are still accepted, with a warning
+/// from the `deprecated` lint, see [`crate::deprecation`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The old name of the check kind
+    pub name: &'static str,
+
+    /// The check kind to use instead, which must be in [`CHECK_KINDS`]
+    pub replacement: &'static str,
+
+    /// The [`SCHEMA_VERSION`] that the old name was deprecated in. The entry is removed, and the
+    /// old name rejected, when the schema version is bumped past it
+    pub since: u32,
+}
+
+/// Every deprecated check kind. Nothing has been renamed yet
+pub const DEPRECATIONS: &[Deprecation] = &[];
+
+pub const SECTIONS: &[Section] = &[
+    Section {
+        name: "top level",
+        description: "Keys at the top of the file, before any table",
+        keys: &[
+            ("default_state", "The state the flight computer starts in when it powers on. Defaults to the first state"),
+            ("abort_state", "The state that checks abort to when they use `abort = true`. It must be marked `abort_safe`"),
+            ("tick_rate", "How many times a second the flight computer's control loop runs. Durations are checked against it"),
+        ],
+    },
+    Section {
+        name: "[[override]]",
+        description: "Allows a deny by default lint for this config. The justification is recorded in the config's metadata and shown in the summary",
+        keys: &[
+            ("lint", "The name of the lint, which must have the error level"),
+            ("justification", "Why the config is safe to fly despite the lint"),
+        ],
+    },
+    Section {
+        name: "[[path_policy]]",
+        description: "The order that the flight must pass through groups of states. A transition or abort from a state in one group to a state more than one group later warns with the `path_policy` lint",
+        keys: &[
+            ("name", "What the policy is for, shown in diagnostics"),
+            ("order", "Groups of state names in the order they are passed through, such as `[[\"Boost\"], [\"Coast\", \"Drogue\"], [\"Main\"]]`"),
+        ],
+    },
+    Section {
+        name: "[lints]",
+        description: "The levels of warning lints for this config, which `--allow`, `--warn`, and `--deny` on the command line take precedence over. Lints that are errors by default can only be overridden with `[[override]]`",
+        keys: &[("<name>", "The level of the lint called `<name>`: `allow`, `warn`, or `deny`")],
+    },
+    Section {
+        name: "[constants]",
+        description: "Named numbers that float values can use, such as `less_than = \"main_deploy_alt + 50\"`. Expressions support `+`, `-`, `*`, `/`, and parentheses",
+        keys: &[("<name>", "A number, used by writing its name in an expression")],
+    },
+    Section {
+        name: "[checks]",
+        description: "Checks that are defined once and added to several states with `use_check`, such as a continuity check that every state in flight runs",
+        keys: &[(
+            "<name>",
+            "A check with the same keys as `[[states.checks]]`, used with `use_check = \"<name>\"`",
+        )],
+    },
+    Section {
+        name: "[recovery]",
+        description: "Thresholds used by the high level check kinds",
+        keys: &[
+            ("launch_altitude", "Altitude in meters above the pad that `launch_detected` checks trip at"),
+            ("main_altitude", "Altitude in meters above the pad that `below_main_altitude` checks trip under"),
+        ],
+    },
+    Section {
+        name: "[crosscheck]",
+        description: "Settings used when comparing this config with a redundant flight computer's config using `crosscheck`",
+        keys: &[(
+            "allow",
+            "Names of settings that may differ between the configs, such as `\"recovery.main_altitude\"`",
+        )],
+    },
+    Section {
+        name: "[radio]",
+        description: "The telemetry radio, used to check that every `data_rate` fits in the target's link budget",
+        keys: &[(
+            "packet_bytes",
+            "The size in bytes of each telemetry packet, including framing",
+        )],
+    },
+    Section {
+        name: "[self_test]",
+        description: "Checks the flight computer runs in order on the pad before launch, reporting the result of each",
+        keys: &[(
+            "steps",
+            "The checks to run, see `[[self_test.steps]]`",
+        )],
+    },
+    Section {
+        name: "[[self_test.steps]]",
+        description: "A check in the self test. Takes the same keys as `[[states.checks]]`, except `transition`, `abort`, and `requires`",
+        keys: &[
+            ("name", "The name reported with the result of the step"),
+            ("check", "What is checked, one of the low level check kinds"),
+        ],
+    },
+    Section {
+        name: "[[mission_events]]",
+        description: "A command that runs at a fixed time after launch, regardless of the current state. Events must be listed in the order they run",
+        keys: &[
+            ("name", "What the event is for"),
+            ("time", "Seconds after launch that the command runs"),
+            ("command", "The command to run, as an inline table with the keys of `[[states.commands]]` except `delay`"),
+        ],
+    },
+    Section {
+        name: "[fault_handling]",
+        description: "What to do when a sensor is declared failed, keyed by the name of the sensor. Each value is an inline table with `action` and its options",
+        keys: &[
+            ("barometer", "The fallback for the barometer, see the sensors"),
+            ("backup_barometer", "The fallback for the backup barometer"),
+            ("continuity", "The fallback for pyro continuity sensing"),
+        ],
+    },
+    Section {
+        name: "fallbacks in [fault_handling]",
+        description: "What to do when one sensor fails",
+        keys: &[
+            ("action", "`\"abort\"` to abort, `\"backup\"` to switch its checks to another sensor that measures the same thing, or `\"hold\"` to stop its checks from tripping"),
+            ("state", "The state to abort to. Defaults to the top level `abort_state`. Only for `\"abort\"`"),
+            ("backup", "The sensor to switch to. Only for `\"backup\"`"),
+        ],
+    },
+    Section {
+        name: "[[stages]]",
+        description: "A stage of a multi-stage rocket, with its own states. Its states are added to the top level `states` as `Stage_State`",
+        keys: &[
+            ("name", "The name of the stage, used by `separate`"),
+            ("states", "The states of this stage, see `[[states]]`. Transitions within the stage may use the short names of its states"),
+        ],
+    },
+    Section {
+        name: "[[states]]",
+        description: "A state the flight computer can be in. Only the checks and commands of the current state run",
+        keys: &[
+            ("name", "The name of the state, used by transitions"),
+            ("abort_safe", "Marks the state as safe to abort to"),
+            ("checks", "The checks that run while in this state, see `[[states.checks]]`"),
+            ("commands", "The commands that run when this state is entered, see `[[states.commands]]`"),
+            ("timeout", "Leaves the state after a fixed time, see `[states.timeout]`"),
+            ("use_check", "The name of a check in `[checks]` to add to this state, or a list of names"),
+        ],
+    },
+    Section {
+        name: "[[states.checks]]",
+        description: "Something the flight computer checks while in a state, which transitions or aborts to another state when it trips",
+        keys: &[
+            ("name", "The name of the check"),
+            ("check", "What is checked, one of the check kinds"),
+            ("transition", "The state to transition to when the check trips"),
+            ("abort", "The state to abort to when the check trips, or `true` for the top level `abort_state`. A table `{ state = \"...\", reason = \"...\" }` also records one of the abort reasons. Cannot be combined with `transition`"),
+            ("separate", "The stage to separate into when the check trips, transitioning to its first state. Cannot be combined with `transition` or `abort`"),
+            ("greater_than", "Trips when the value is greater than this"),
+            ("less_than", "Trips when the value is less than this"),
+            ("upper_bound", "Trips when the value is between `lower_bound` and this"),
+            ("lower_bound", "Trips when the value is between this and `upper_bound`"),
+            ("flag", "Trips when a flag is `\"set\"` or `\"unset\"`"),
+            ("requires", "The name of another check that must have tripped before this one can. Not supported by any target yet"),
+        ],
+    },
+    Section {
+        name: "[[states.commands]]",
+        description: "An action performed when a state is entered",
+        keys: &[("delay", "Seconds to wait after entering the state before performing the action. Defaults to 0")],
+    },
+    Section {
+        name: "[states.timeout]",
+        description: "Leaves a state once it has been active for a fixed time",
+        keys: &[
+            ("seconds", "How long the state can be active before the timeout trips"),
+            ("transition", "The state to transition to when the timeout trips"),
+        ],
+    },
+];
+
+/// A sensor that check kinds depend on, which the flight computer can declare failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sensor {
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// What the sensor measures. A sensor can only back up another that measures the same thing
+    pub measures: &'static str,
+
+    /// The check kinds that read this sensor
+    pub checks: &'static [&'static str],
+}
+
+pub const SENSORS: &[Sensor] = &[
+    Sensor {
+        name: "barometer",
+        description: "The pressure sensor that altitude and apogee are derived from",
+        measures: "altitude",
+        checks: &[
+            "altitude",
+            "apogee",
+            "launch_detected",
+            "apogee_passed",
+            "below_main_altitude",
+        ],
+    },
+    Sensor {
+        name: "backup_barometer",
+        description: "A second pressure sensor, only read when a fallback switches to it",
+        measures: "altitude",
+        checks: &[],
+    },
+    Sensor {
+        name: "continuity",
+        description: "Continuity sensing on the pyro channels",
+        measures: "continuity",
+        checks: &["pyro1_continuity", "pyro2_continuity", "pyro3_continuity"],
+    },
+];
+
+/// Returns the sensor that checks of `kind` read, if any
+pub fn sensor_for(kind: &str) -> Option<&'static Sensor> {
+    SENSORS.iter().find(|s| s.checks.contains(&kind))
+}
+
+pub const CHECK_KINDS: &[CheckKind] = &[
+    CheckKind {
+        name: "apogee",
+        description: "Whether the flight computer has detected apogee",
+        conditions: &["flag"],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "altitude",
+        description: "Altitude in meters above the pad",
+        conditions: &["greater_than", "less_than", "upper_bound and lower_bound"],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "pyro1_continuity",
+        description: "Whether a charge is connected to pyro channel 1",
+        conditions: &["flag"],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "pyro2_continuity",
+        description: "Whether a charge is connected to pyro channel 2",
+        conditions: &["flag"],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "pyro3_continuity",
+        description: "Whether a charge is connected to pyro channel 3",
+        conditions: &["flag"],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "launch_detected",
+        description: "Altitude is above `recovery.launch_altitude`",
+        conditions: &[],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "apogee_passed",
+        description: "The flight computer has detected apogee",
+        conditions: &[],
+        targets: &Target::ALL,
+    },
+    CheckKind {
+        name: "below_main_altitude",
+        description: "Altitude is below `recovery.main_altitude`",
+        conditions: &[],
+        targets: &Target::ALL,
+    },
+];
+
+pub const COMMAND_ACTIONS: &[CommandAction] = &[
+    CommandAction {
+        name: "pyro1",
+        value: "`true`, `false`, `\"enable\"`, or `\"disable\"`",
+        description: "Fires or disarms pyro channel 1",
+    },
+    CommandAction {
+        name: "pyro2",
+        value: "`true`, `false`, `\"enable\"`, or `\"disable\"`",
+        description: "Fires or disarms pyro channel 2",
+    },
+    CommandAction {
+        name: "pyro3",
+        value: "`true`, `false`, `\"enable\"`, or `\"disable\"`",
+        description: "Fires or disarms pyro channel 3",
+    },
+    CommandAction {
+        name: "data_rate",
+        value: "an integer within the target's limits",
+        description: "Sets how many telemetry packets are sent each second. Stays in effect after the state is left",
+    },
+    CommandAction {
+        name: "beacon",
+        value: "`true`, `false`, `\"enable\"`, or `\"disable\"`",
+        description: "Turns the radio beacon on or off",
+    },
+];
+
+/// A reason that an abort can record, see [`crate::abort_reasons`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AbortReason {
+    pub name: &'static str,
+
+    /// The number the reason is encoded as. Codes are never reused or renumbered, since ground
+    /// stations and flight logs refer to them
+    pub code: u8,
+    pub description: &'static str,
+}
+
+pub const ABORT_REASONS: &[AbortReason] = &[
+    AbortReason {
+        name: "UNSPECIFIED",
+        code: 0,
+        description: "No reason was given",
+    },
+    AbortReason {
+        name: "NO_CONTINUITY",
+        code: 1,
+        description: "A pyro channel lost continuity",
+    },
+    AbortReason {
+        name: "UNEXPECTED_ALTITUDE",
+        code: 2,
+        description: "Altitude is outside the range expected for the current state",
+    },
+    AbortReason {
+        name: "SENSOR_FAILURE",
+        code: 3,
+        description: "A sensor stopped reporting or reported impossible values",
+    },
+    AbortReason {
+        name: "TIMEOUT",
+        code: 4,
+        description: "A phase of flight took longer than expected",
+    },
+    AbortReason {
+        name: "LOW_BATTERY",
+        code: 5,
+        description: "The battery voltage is too low to continue safely",
+    },
+];
+
+/// Returns every check kind that `target` supports, in registry order
+pub fn check_kinds(target: Target) -> impl Iterator<Item = &'static CheckKind> {
+    CHECK_KINDS
+        .iter()
+        .filter(move |k| k.targets.contains(&target))
+}
+
+/// Returns the registered check kind called `name`
+pub fn check_kind(name: &str) -> Option<&'static CheckKind> {
+    CHECK_KINDS.iter().find(|k| k.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_kind, check_kinds, ABORT_REASONS, CHECK_KINDS};
+    use crate::{limits::Target, upper};
+
+    #[test]
+    fn high_level_checks_are_registered() {
+        for name in upper::HIGH_LEVEL_CHECKS {
+            assert!(check_kind(name).unwrap().conditions.is_empty(), "{name}");
+        }
+        let low_level = CHECK_KINDS
+            .iter()
+            .filter(|k| !upper::HIGH_LEVEL_CHECKS.contains(&k.name));
+        for kind in low_level {
+            assert!(!kind.conditions.is_empty(), "{}", kind.name);
+        }
+    }
+
+    #[test]
+    fn abort_reason_codes_are_unique() {
+        for (i, reason) in ABORT_REASONS.iter().enumerate() {
+            for other in &ABORT_REASONS[i + 1..] {
+                assert_ne!(reason.code, other.code, "{}", reason.name);
+                assert_ne!(reason.name, other.name);
+            }
+        }
+    }
+
+    #[test]
+    fn every_kind_has_a_target() {
+        for kind in CHECK_KINDS {
+            let supported = Target::ALL
+                .into_iter()
+                .any(|t| check_kinds(t).any(|k| k == kind));
+            assert!(supported, "{}", kind.name);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Checks that depend on the latched result of other checks.
+//!
+//! `requires = "Burnout"` on a check means it can only trip once the check named `Burnout` has
+//! tripped, which expresses "only look for apogee after burnout" without repeating the burnout
+//! condition. The requirements form a graph between checks, which must not have cycles, since a
+//! check that transitively requires itself can never trip.
+//!
+//! The encoded format has no flag for the latched result of a check yet, so configs that use
+//! `requires` are verified but cannot be lowered.
+
+use std::collections::HashMap;
+
+use crate::spanned::SpannedValue;
+
+use crate::{upper, Context, ErrorCode, Span};
+
+/// A check that requires another, with the names of both as written in the config
+#[derive(Copy, Clone, Debug)]
+pub struct Dependency<'a> {
+    pub check: &'a SpannedValue<String>,
+    pub requires: &'a SpannedValue<String>,
+}
+
+/// Every `requires` in `config`, in source order
+pub fn dependencies(config: &upper::ConfigFile) -> Vec<Dependency<'_>> {
+    config
+        .states
+        .get_ref()
+        .iter()
+        .flat_map(|s| &s.get_ref().checks)
+        .filter_map(|c| {
+            let c = c.get_ref();
+            c.requires.as_ref().map(|requires| Dependency {
+                check: &c.name,
+                requires,
+            })
+        })
+        .collect()
+}
+
+/// Returns a cycle of check names starting and ending at the same check, if the requirements have
+/// one
+pub fn find_cycle<'a>(dependencies: &[Dependency<'a>]) -> Option<Vec<&'a str>> {
+    let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+    for dep in dependencies {
+        graph
+            .entry(dep.check.get_ref())
+            .or_default()
+            .push(dep.requires.get_ref());
+    }
+
+    #[derive(Copy, Clone, PartialEq)]
+    enum Mark {
+        InProgress,
+        Done,
+    }
+    fn visit<'a>(
+        name: &'a str,
+        graph: &HashMap<&'a str, Vec<&'a str>>,
+        marks: &mut HashMap<&'a str, Mark>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<&'a str>> {
+        match marks.get(name) {
+            Some(Mark::Done) => return None,
+            Some(Mark::InProgress) => {
+                let start = path.iter().position(|n| *n == name).unwrap();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(name);
+                return Some(cycle);
+            }
+            None => {}
+        }
+        marks.insert(name, Mark::InProgress);
+        path.push(name);
+        for next in graph.get(name).into_iter().flatten() {
+            if let Some(cycle) = visit(next, graph, marks, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        marks.insert(name, Mark::Done);
+        None
+    }
+
+    let mut marks = HashMap::new();
+    dependencies
+        .iter()
+        .find_map(|dep| visit(dep.check.get_ref(), &graph, &mut marks, &mut Vec::new()))
+}
+
+/// Checks that every `requires` names an existing check and that no check requires itself,
+/// emitting errors to `context`.
+///
+/// Because the encoded format cannot express requirements yet, this also fails for any config
+/// that uses `requires`
+pub fn verify(config: &upper::ConfigFile, context: &mut Context) -> Result<(), ()> {
+    let dependencies = dependencies(config);
+    if dependencies.is_empty() {
+        return Ok(());
+    }
+
+    let checks: Vec<&str> = config
+        .states
+        .get_ref()
+        .iter()
+        .flat_map(|s| &s.get_ref().checks)
+        .map(|c| c.get_ref().name.get_ref().as_str())
+        .collect();
+    let mut result = Ok(());
+    for dep in &dependencies {
+        let name = dep.requires.get_ref();
+        let span = Span::from_spanned(context, dep.requires);
+        if !checks.contains(&name.as_str()) {
+            context
+                .error(format!("no check named `{name}` exists"))
+                .code(ErrorCode::UnknownRequiredCheck)
+                .set_primary_span(span, "required here")
+                .emit();
+            result = Err(());
+        }
+    }
+    result?;
+
+    if let Some(cycle) = find_cycle(&dependencies) {
+        let first = dependencies
+            .iter()
+            .find(|d| d.check.get_ref() == cycle[0])
+            .unwrap();
+        let span = Span::from_spanned(context, first.requires);
+        context
+            .error(format!("check `{}` requires itself", cycle[0]))
+            .code(ErrorCode::RequirementCycle)
+            .set_primary_span(span, "this check can never trip")
+            .emit();
+        context
+            .note(format!(
+                "the requirements form a cycle: {}",
+                cycle.join(" -> ")
+            ))
+            .emit();
+        return Err(());
+    }
+
+    let target = context.session().target();
+    for dep in &dependencies {
+        let span = Span::from_spanned(context, dep.requires);
+        context
+            .error(format!(
+                "check `{}` cannot require another check on the `{target}` target",
+                dep.check.get_ref()
+            ))
+            .code(ErrorCode::UnsupportedRequirement)
+            .set_primary_span(
+                span,
+                "the encoded config has no flag for latched check results",
+            )
+            .emit();
+    }
+    context
+        .help("move the check into a state that is only entered after the required check trips")
+        .emit();
+    Err(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dependencies, find_cycle};
+    use crate::{upper, Session};
+
+    #[test]
+    fn cycles() {
+        let config = r#"
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Burnout"
+check = "altitude"
+greater_than = 100.0
+requires = "Apogee"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+requires = "Coast"
+
+[[states.checks]]
+name = "Coast"
+check = "altitude"
+greater_than = 200.0
+REQUIRES
+"#;
+        let mut session = Session::new();
+        let acyclic = config.replace("REQUIRES", "");
+        let acyclic = upper::verify(&mut session.testing(&acyclic)).unwrap();
+        assert_eq!(find_cycle(&dependencies(&acyclic)), None);
+
+        let cyclic = config.replace("REQUIRES", "requires = \"Burnout\"");
+        let cyclic = upper::verify(&mut session.testing(&cyclic)).unwrap();
+        assert_eq!(
+            find_cycle(&dependencies(&cyclic)).unwrap(),
+            ["Burnout", "Apogee", "Coast", "Burnout"]
+        );
+    }
+}
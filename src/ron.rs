@@ -0,0 +1,422 @@
+//! RON front end.
+//!
+//! [RON](https://github.com/ron-rs/ron) reads like Rust struct literals, which some find easier
+//! to follow than toml's arrays of tables once states have several checks and commands:
+//!
+//! ```text
+//! (
+//!     default_state: "Armed",
+//!     states: [
+//!         (
+//!             name: "Armed",
+//!             checks: [
+//!                 (name: "Launch", check: "altitude", greater_than: 30.0, transition: "Flight"),
+//!             ],
+//!         ),
+//!         (name: "Flight", commands: [(beacon: true)]),
+//!     ],
+//! )
+//! ```
+//!
+//! Structs and maps become tables, lists and tuples become arrays, `Some(x)` is `x` and `None` is a
+//! missing value. A bare identifier such as `transition: Flight` is read as a string, the same way
+//! serde reads a unit enum variant. Like the other front ends, this parser records the span of
+//! every value.
+
+use crate::tree::{Error, Node, Value};
+
+/// Parses `source` as a single RON value
+pub fn parse(source: &str) -> Result<Node, Error> {
+    let mut parser = Parser { source, pos: 0 };
+    parser.skip_trivia()?;
+    while parser.rest().starts_with("#!") {
+        parser.attribute()?;
+        parser.skip_trivia()?;
+    }
+    let node = parser.value()?;
+    parser.skip_trivia()?;
+    if parser.pos != source.len() {
+        return Err(parser.error("trailing characters after the end of the document"));
+    }
+    Ok(node)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// An error spanning the character at the current position
+    fn error(&self, message: impl Into<String>) -> Error {
+        let end = self.peek().map_or(self.pos, |c| self.pos + c.len_utf8());
+        Error::new(message, (self.pos, end))
+    }
+
+    /// Skips whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), Error> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                let end = trimmed
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated block comment"))?;
+                self.pos += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    /// Skips an inner attribute such as `#![enable(implicit_some)]`
+    fn attribute(&mut self) -> Result<(), Error> {
+        self.pos += 2;
+        self.expect('[')?;
+        let end = self
+            .rest()
+            .find(']')
+            .ok_or_else(|| self.error("unterminated attribute"))?;
+        self.pos += end + 1;
+        Ok(())
+    }
+
+    fn ident(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn value(&mut self) -> Result<Node, Error> {
+        let start = self.pos;
+        let value = match self.peek() {
+            Some('(') => self.parens()?,
+            Some('[') => self.list()?,
+            Some('{') => self.map()?,
+            Some('"') => Value::String(self.string()?),
+            Some('r') if self.rest()[1..].starts_with(['"', '#']) => {
+                Value::String(self.raw_string()?)
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.number()?,
+            Some(c) if is_ident_start(c) => {
+                let ident = self.ident();
+                match ident {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "None" => Value::Null,
+                    "Some" => {
+                        self.skip_trivia()?;
+                        self.expect('(')?;
+                        self.skip_trivia()?;
+                        let inner = self.value()?;
+                        self.skip_trivia()?;
+                        self.expect(')')?;
+                        // `Some(x)` is spanned as `x`, which is what diagnostics should point at
+                        return Ok(inner);
+                    }
+                    _ => {
+                        let after_ident = self.pos;
+                        self.skip_trivia()?;
+                        if self.peek() == Some('(') {
+                            // A named struct or tuple, the name is only for readability
+                            self.parens()?
+                        } else {
+                            self.pos = after_ident;
+                            Value::String(ident.to_owned())
+                        }
+                    }
+                }
+            }
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of file")),
+        };
+        Ok(Node::new((start, self.pos), value))
+    }
+
+    /// Parses a comma separated sequence up to `close`, calling `item` for each element
+    fn comma_separated(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        loop {
+            self.skip_trivia()?;
+            if self.eat(close) {
+                return Ok(());
+            }
+            item(self)?;
+            self.skip_trivia()?;
+            if !self.eat(',') {
+                self.skip_trivia()?;
+                return self.expect(close);
+            }
+        }
+    }
+
+    /// Parses `(field: value, ...)` as a table, `(value, ...)` as an array, and `()` as null
+    fn parens(&mut self) -> Result<Value, Error> {
+        self.expect('(')?;
+        self.skip_trivia()?;
+        if self.eat(')') {
+            return Ok(Value::Null);
+        }
+
+        let save = self.pos;
+        let is_struct = self.peek().is_some_and(is_ident_start) && {
+            self.ident();
+            self.skip_trivia()?;
+            self.peek() == Some(':')
+        };
+        self.pos = save;
+
+        if is_struct {
+            let mut entries: Vec<(String, Node)> = Vec::new();
+            self.comma_separated(')', |p| {
+                let key_start = p.pos;
+                if !p.peek().is_some_and(is_ident_start) {
+                    return Err(p.error("expected a field name"));
+                }
+                let key = p.ident().to_owned();
+                let key_span = (key_start, p.pos);
+                p.skip_trivia()?;
+                p.expect(':')?;
+                p.skip_trivia()?;
+                let value = p.value()?;
+                if entries.iter().any(|(k, _)| *k == key) {
+                    return Err(Error::new(format!("duplicate field `{key}`"), key_span));
+                }
+                entries.push((key, value));
+                Ok(())
+            })?;
+            Ok(Value::Table(entries))
+        } else {
+            let mut items = Vec::new();
+            self.comma_separated(')', |p| {
+                items.push(p.value()?);
+                Ok(())
+            })?;
+            Ok(Value::Array(items))
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, Error> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.comma_separated(']', |p| {
+            items.push(p.value()?);
+            Ok(())
+        })?;
+        Ok(Value::Array(items))
+    }
+
+    /// Parses `{ "key": value, ... }`. Keys must be strings or identifiers
+    fn map(&mut self) -> Result<Value, Error> {
+        self.expect('{')?;
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        self.comma_separated('}', |p| {
+            let key_node = p.value()?;
+            let key = match key_node.value {
+                Value::String(key) => key,
+                _ => return Err(Error::new("map keys must be strings", key_node.span)),
+            };
+            p.skip_trivia()?;
+            p.expect(':')?;
+            p.skip_trivia()?;
+            let value = p.value()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(Error::new(format!("duplicate key `{key}`"), key_node.span));
+            }
+            entries.push((key, value));
+            Ok(())
+        })?;
+        Ok(Value::Table(entries))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => s.push(self.escape()?),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, Error> {
+        let start = self.pos - 1;
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += c.len_utf8();
+        let hex = |p: &mut Self, digits: &str| {
+            u32::from_str_radix(digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| Error::new("invalid escape", (start, p.pos)))
+        };
+        Ok(match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'x' => {
+                let digits = self.rest().get(..2).unwrap_or_default();
+                self.pos += digits.len();
+                hex(self, digits)?
+            }
+            'u' => {
+                self.expect('{')?;
+                let end = self
+                    .rest()
+                    .find('}')
+                    .ok_or_else(|| self.error("unterminated unicode escape"))?;
+                let digits = &self.rest()[..end];
+                self.pos += end + 1;
+                hex(self, digits)?
+            }
+            _ => return Err(Error::new("invalid escape", (start, self.pos))),
+        })
+    }
+
+    /// Parses `r"..."` or `r#"..."#` with any number of `#`s
+    fn raw_string(&mut self) -> Result<String, Error> {
+        self.expect('r')?;
+        let hashes = self.rest().len() - self.rest().trim_start_matches('#').len();
+        self.pos += hashes;
+        self.expect('"')?;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let end = self
+            .rest()
+            .find(&terminator)
+            .ok_or_else(|| self.error("unterminated raw string"))?;
+        let s = self.rest()[..end].to_owned();
+        self.pos += end + terminator.len();
+        Ok(s)
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| {
+                let sign_ok = (c == '-' || c == '+') && (i == 0 || rest[..i].ends_with(['e', 'E']));
+                !(c.is_ascii_digit() || "._eE".contains(c) || sign_ok)
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        self.pos += len;
+        let text = rest[..len].replace('_', "");
+        let invalid = || {
+            Error::new(
+                format!("invalid number `{}`", &rest[..len]),
+                (start, start + len),
+            )
+        };
+        let is_float = text.contains(['.', 'e', 'E']);
+        if !is_float {
+            if let Ok(i) = text.parse::<i64>() {
+                return Ok(Value::Integer(i));
+            }
+        }
+        text.parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Value::Float)
+            .ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::tree::Value;
+
+    #[test]
+    fn parse_with_spans() {
+        let source = r##"#![enable(implicit_some)]
+// A comment
+Config(
+    a: Some(1_000),
+    b: [1.5, -2e3, None, ], /* block */
+    c: { "k": r#"raw"# },
+    d: Flight,
+    e: (1, "two"),
+)"##;
+        let node = parse(source).unwrap();
+        let entries = match &node.value {
+            Value::Table(entries) => entries,
+            _ => panic!(),
+        };
+        assert_eq!(
+            &source[node.span.0..node.span.1],
+            &source[source.find("Config").unwrap()..]
+        );
+        let a = &entries[0].1;
+        assert_eq!(a.value, Value::Integer(1000));
+        assert_eq!(&source[a.span.0..a.span.1], "1_000");
+        match &entries[1].1.value {
+            Value::Array(items) => {
+                assert_eq!(items[1].value, Value::Float(-2000.0));
+                assert_eq!(items[2].value, Value::Null);
+            }
+            _ => panic!(),
+        }
+        assert_eq!(entries[3].1.value, Value::String("Flight".to_owned()));
+        assert!(matches!(&entries[4].1.value, Value::Array(items) if items.len() == 2));
+
+        for (bad, at) in [
+            ("(a: 1 b: 2)", 6),
+            ("(a: 1, a: 2)", 7),
+            ("[1, 2", 5),
+            ("\"x", 2),
+        ] {
+            assert_eq!(parse(bad).unwrap_err().span.unwrap().0, at, "{bad}");
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Generates a JSON Schema of the config language, so that editors such as VS Code with the Even
+//! Better TOML extension can validate and complete `rocket.toml` while it is written.
+//!
+//! The shape of the schema mirrors [`crate::upper::ConfigFile`], and like [`crate::docs`] every
+//! description and list of accepted names is taken from the [`crate::registry`], so the schema
+//! stays in sync with what the verifier accepts. Tables only reject unknown keys where the
+//! verifier does.
+
+use serde_json::{json, Map, Value};
+
+use crate::{limits::Target, lint::LINTS, registry};
+
+/// The registry section called `name`
+fn section(name: &str) -> &'static registry::Section {
+    registry::SECTIONS
+        .iter()
+        .find(|s| s.name == name)
+        .unwrap_or_else(|| panic!("no registry section `{name}`"))
+}
+
+/// The description of `key` in the registry section called `name`
+fn describe(name: &str, key: &str) -> &'static str {
+    section(name)
+        .keys
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map_or("", |(_, description)| description)
+}
+
+/// A table whose keys are described by the registry section called `name`. `closed` tables
+/// reject keys that are not in `properties`
+fn table(name: &str, properties: Vec<(&str, Value)>, required: &[&str], closed: bool) -> Value {
+    let mut map = Map::new();
+    for (key, mut schema) in properties {
+        schema["description"] = describe(name, key).into();
+        map.insert(key.to_owned(), schema);
+    }
+    let mut table = json!({
+        "type": "object",
+        "description": section(name).description,
+        "properties": map,
+    });
+    if !required.is_empty() {
+        table["required"] = json!(required);
+    }
+    if closed {
+        table["additionalProperties"] = false.into();
+    }
+    table
+}
+
+/// A float, or a string holding an expression of `[constants]`, see [`crate::constants`]
+fn float() -> Value {
+    json!({ "type": ["number", "string"] })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn integer(max: u64) -> Value {
+    json!({ "type": "integer", "minimum": 0, "maximum": max })
+}
+
+/// A string that must be one of `names`
+fn one_of<'a>(names: impl IntoIterator<Item = &'a str>) -> Value {
+    json!({ "type": "string", "enum": names.into_iter().collect::<Vec<_>>() })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn described(mut schema: Value, description: &str) -> Value {
+    schema["description"] = description.into();
+    schema
+}
+
+fn reference(definition: &str) -> Value {
+    json!({ "$ref": format!("#/definitions/{definition}") })
+}
+
+/// A `[[states.checks]]` entry, also used by `[checks]` and `[[self_test.steps]]`
+fn check(target: Target) -> Value {
+    let kinds = registry::check_kinds(target).map(|k| k.name);
+    let deprecated = registry::DEPRECATIONS.iter().map(|d| d.name);
+    let reasons = registry::ABORT_REASONS.iter().map(|r| r.name);
+    let abort = json!({
+        "anyOf": [
+            string(),
+            { "type": "boolean" },
+            {
+                "type": "object",
+                "properties": {
+                    "state": described(string(), "The state to abort to. Defaults to the top level `abort_state`"),
+                    "reason": described(one_of(reasons), "Why the flight was aborted, one of the abort reasons"),
+                },
+                "additionalProperties": false,
+            },
+        ],
+    });
+    table(
+        "[[states.checks]]",
+        vec![
+            ("name", string()),
+            ("check", one_of(kinds.chain(deprecated))),
+            ("transition", string()),
+            ("abort", abort),
+            ("separate", string()),
+            ("greater_than", float()),
+            ("less_than", float()),
+            ("upper_bound", float()),
+            ("lower_bound", float()),
+            ("flag", one_of(["set", "unset"])),
+            ("requires", string()),
+        ],
+        &["name", "check"],
+        false,
+    )
+}
+
+/// A `[[states.commands]]` entry, whose actions are described by [`registry::COMMAND_ACTIONS`]
+fn command() -> Value {
+    let toggle = json!({
+        "anyOf": [{ "type": "boolean" }, one_of(["true", "false", "enable", "disable"])],
+    });
+    let mut command = table("[[states.commands]]", vec![("delay", float())], &[], false);
+    for action in registry::COMMAND_ACTIONS {
+        let mut schema = match action.name {
+            "data_rate" => integer(u16::MAX.into()),
+            _ => toggle.clone(),
+        };
+        schema["description"] = action.description.into();
+        command["properties"][action.name] = schema;
+    }
+    command
+}
+
+/// Renders the JSON Schema of configs built for `target`
+pub fn json_schema(target: Target) -> String {
+    let sensors = || registry::SENSORS.iter().map(|s| s.name);
+    let lints = LINTS
+        .iter()
+        .filter(|l| l.level == codemap_diagnostic::Level::Error)
+        .map(|l| l.name);
+
+    let fallback = table(
+        "fallbacks in [fault_handling]",
+        vec![
+            ("action", one_of(["abort", "backup", "hold"])),
+            ("state", string()),
+            ("backup", one_of(sensors())),
+        ],
+        &["action"],
+        true,
+    );
+    let fault_handling = table(
+        "[fault_handling]",
+        sensors().map(|s| (s, fallback.clone())).collect(),
+        &[],
+        true,
+    );
+    let state = table(
+        "[[states]]",
+        vec![
+            ("name", string()),
+            ("abort_safe", json!({ "type": "boolean" })),
+            ("checks", array(reference("check"))),
+            ("commands", array(reference("command"))),
+            (
+                "timeout",
+                table(
+                    "[states.timeout]",
+                    vec![("seconds", float()), ("transition", string())],
+                    &[],
+                    false,
+                ),
+            ),
+            ("use_check", json!({ "anyOf": [string(), array(string())] })),
+        ],
+        &["name"],
+        false,
+    );
+
+    let mut config = table(
+        "top level",
+        vec![
+            ("default_state", string()),
+            ("abort_state", string()),
+            ("tick_rate", integer(u16::MAX.into())),
+        ],
+        &[],
+        false,
+    );
+    let sections = [
+        (
+            "recovery",
+            table(
+                "[recovery]",
+                vec![("launch_altitude", float()), ("main_altitude", float())],
+                &[],
+                false,
+            ),
+        ),
+        (
+            "crosscheck",
+            table("[crosscheck]", vec![("allow", array(string()))], &[], false),
+        ),
+        (
+            "radio",
+            table(
+                "[radio]",
+                vec![("packet_bytes", integer(u32::MAX.into()))],
+                &[],
+                false,
+            ),
+        ),
+        (
+            "self_test",
+            table(
+                "[self_test]",
+                vec![("steps", array(reference("check")))],
+                &[],
+                false,
+            ),
+        ),
+        (
+            "mission_events",
+            array(table(
+                "[[mission_events]]",
+                vec![
+                    ("name", string()),
+                    ("time", float()),
+                    ("command", reference("command")),
+                ],
+                &["time", "command"],
+                false,
+            )),
+        ),
+        ("fault_handling", fault_handling),
+        (
+            "override",
+            array(table(
+                "[[override]]",
+                vec![("lint", one_of(lints)), ("justification", string())],
+                &["lint", "justification"],
+                true,
+            )),
+        ),
+        (
+            "path_policy",
+            array(table(
+                "[[path_policy]]",
+                vec![("name", string()), ("order", array(array(string())))],
+                &["name", "order"],
+                true,
+            )),
+        ),
+        (
+            "lints",
+            json!({
+                "type": "object",
+                "description": section("[lints]").description,
+                "properties": LINTS
+                    .iter()
+                    .filter(|l| l.level == codemap_diagnostic::Level::Warning)
+                    .map(|l| (l.name.to_owned(), described(one_of(["allow", "warn", "deny"]), l.description)))
+                    .collect::<Map<_, _>>(),
+                "additionalProperties": false,
+            }),
+        ),
+        (
+            "constants",
+            json!({
+                "type": "object",
+                "description": section("[constants]").description,
+                "additionalProperties": { "type": "number" },
+            }),
+        ),
+        (
+            "checks",
+            json!({
+                "type": "object",
+                "description": section("[checks]").description,
+                "additionalProperties": reference("check"),
+            }),
+        ),
+        (
+            "stages",
+            array(table(
+                "[[stages]]",
+                vec![("name", string()), ("states", array(state.clone()))],
+                &["name", "states"],
+                true,
+            )),
+        ),
+        ("states", array(state)),
+    ];
+    for (key, mut schema) in sections {
+        // An array of tables is described by its entries
+        if schema["type"] == "array" {
+            schema["description"] = schema["items"]["description"].clone();
+        }
+        config["properties"][key] = schema;
+    }
+
+    let mut schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": format!("nova-verifier config for the `{target}` target"),
+        "definitions": { "check": check(target), "command": command() },
+    });
+    // The schema's own keys go first, followed by the keys of the config's top level table
+    if let (Value::Object(schema), Value::Object(config)) = (&mut schema, config) {
+        schema.extend(config);
+    }
+    // Only strings, numbers, and booleans are serialized, so this cannot fail
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::limits::Target;
+
+    /// Calls `f` with every table's properties in `schema`
+    fn properties(schema: &Value, f: &mut impl FnMut(&serde_json::Map<String, Value>)) {
+        match schema {
+            Value::Object(map) => {
+                if let Some(Value::Object(properties)) = map.get("properties") {
+                    f(properties);
+                }
+                map.values().for_each(|v| properties(v, f));
+            }
+            Value::Array(items) => items.iter().for_each(|v| properties(v, f)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn json_schema() {
+        let schema: Value = serde_json::from_str(&super::json_schema(Target::Nova)).unwrap();
+        properties(&schema, &mut |properties| {
+            for (key, value) in properties {
+                let description = value["description"].as_str().unwrap_or_default();
+                assert!(!description.is_empty(), "`{key}` has no description");
+            }
+        });
+
+        let check = &schema["definitions"]["check"]["properties"];
+        let kinds = check["check"]["enum"].as_array().unwrap();
+        assert!(kinds.contains(&"pyro1_continuity".into()));
+        assert_eq!(check["flag"]["enum"], serde_json::json!(["set", "unset"]));
+        assert_eq!(
+            schema["properties"]["states"]["items"]["required"][0],
+            "name"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! The pre-launch self test.
+//!
+//! The `[self_test]` section lists checks that the flight computer runs in order on the pad, such
+//! as pyro continuity, and reports the result of each before it can be armed:
+//!
+//! ```toml
+//! [[self_test.steps]]
+//! name = "Drogue"
+//! check = "pyro1_continuity"
+//! flag = "set"
+//! ```
+//!
+//! Steps use the same check kinds and conditions as `[[states.checks]]`, so they are verified the
+//! same way. The `.ncf` format has no room for the sequence, so it is encoded separately with
+//! `--emit self-test` and loaded alongside the config.
+//!
+//! | Bytes | Contents                                                   |
+//! |-------|------------------------------------------------------------|
+//! | 0..4  | [`MAGIC`]                                                  |
+//! | 4..6  | [`ncf::FORMAT_VERSION`], as a little endian `u16`          |
+//! | 6..   | The postcard encoded steps, as a sequence of `CheckData`   |
+
+use nova_software_common::CheckData;
+
+use crate::{lower, ncf, upper, Context, ErrorCode, Span};
+
+/// The bytes every encoded self test starts with
+pub const MAGIC: [u8; 4] = *b"NST\0";
+
+/// A step of the self test, lowered to the check the flight computer runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The name reported with the result of this step
+    pub name: String,
+    pub data: CheckData,
+}
+
+/// Verifies and lowers the `[self_test]` section of `config`, emitting errors to `context`.
+/// Returns no steps if there is no self test
+pub fn lower(config: &upper::ConfigFile, context: &mut Context) -> Result<Vec<Step>, ()> {
+    let self_test = match &config.self_test {
+        Some(self_test) => self_test,
+        None => return Ok(Vec::new()),
+    };
+    let steps = &self_test.get_ref().steps;
+    let limits = context.session().limits();
+    if steps.len() > limits.max_checks_per_state {
+        let span = Span::from_spanned(context, self_test);
+        context
+            .error("too many self test steps")
+            .code(ErrorCode::TooManySelfTestSteps)
+            .set_primary_span(
+                span,
+                format!(
+                    "the self test can have at most {} steps, but this has {}",
+                    limits.max_checks_per_state,
+                    steps.len()
+                ),
+            )
+            .emit();
+        return Err(());
+    }
+
+    let mut result = Ok(Vec::new());
+    for step in steps {
+        let span = Span::from_spanned(context, step);
+        let check = step.get_ref();
+        lower::validate_name("self test step", &check.name, context);
+        let mut actions = Vec::new();
+        if let Some(transition) = &check.transition {
+            actions.push(("transition", Span::from_spanned(context, transition)));
+        }
+        if let Some(abort) = &check.abort {
+            actions.push(("abort", Span::from_spanned(context, abort)));
+        }
+        if let Some(requires) = &check.requires {
+            actions.push(("requires", Span::from_spanned(context, requires)));
+        }
+        for (key, action) in actions {
+            context
+                .error(format!("self test steps cannot use `{key}`"))
+                .code(ErrorCode::InvalidSelfTestStep)
+                .set_primary_span(action, "the self test only reports results")
+                .emit();
+            result = Err(());
+        }
+        let kind = check.check.get_ref();
+        if upper::HIGH_LEVEL_CHECKS.contains(&kind.as_str()) {
+            let kind_span = Span::from_spanned(context, &check.check);
+            context
+                .error(format!("`{kind}` cannot be used in the self test"))
+                .code(ErrorCode::InvalidSelfTestCheck)
+                .set_primary_span(kind_span, "this check kind only makes sense in flight")
+                .emit();
+            result = Err(());
+            continue;
+        }
+        match lower::convert_condition(check, span, context) {
+            Ok(data) => {
+                if let Ok(steps) = &mut result {
+                    steps.push(Step {
+                        name: check.name.get_ref().clone(),
+                        data,
+                    });
+                }
+            }
+            Err(()) => result = Err(()),
+        }
+    }
+    result
+}
+
+/// Encodes `steps` for the flight computer
+pub fn encode(steps: &[Step]) -> Result<Vec<u8>, postcard::Error> {
+    let data: Vec<&CheckData> = steps.iter().map(|s| &s.data).collect();
+    ncf::encode_tagged(MAGIC, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use nova_software_common::{CheckData, PyroContinuityCondition};
+
+    use super::{encode, lower, MAGIC};
+    use crate::{upper, Session};
+
+    #[test]
+    fn steps() {
+        let config = r#"
+[[self_test.steps]]
+name = "Drogue"
+check = "pyro1_continuity"
+flag = "set"
+
+[[self_test.steps]]
+name = "Main"
+check = "pyro2_continuity"
+flag = "set"
+ACTION
+
+[[states]]
+name = "Pad"
+"#;
+        let mut session = Session::new();
+        let valid = config.replace("ACTION", "");
+        let mut context = session.testing(&valid);
+        let valid = upper::verify(&mut context).unwrap();
+        let steps = lower(&valid, &mut context).unwrap();
+        let names: Vec<_> = steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Drogue", "Main"]);
+        assert_eq!(
+            steps[0].data,
+            CheckData::Pyro1Continuity(PyroContinuityCondition(true))
+        );
+        let bytes = encode(&steps).unwrap();
+        assert_eq!(bytes[..4], MAGIC);
+
+        let aborting = config.replace("ACTION", "abort = \"Pad\"");
+        let mut context = session.testing(&aborting);
+        let aborting = upper::verify(&mut context).unwrap();
+        assert!(lower(&aborting, &mut context).is_err());
+    }
+}
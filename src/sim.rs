@@ -0,0 +1,433 @@
+//! Simulation of a compiled config against a sensor profile, for `sim`.
+//!
+//! Interprets the lowered state machine the way the flight computer would, feeding it a time
+//! series of sensor readings such as a recording from a past flight, and reports every transition
+//! and command along the way. This checks that a config does what was intended with real data
+//! before it is uploaded.
+//!
+//! A profile is a CSV file whose first line names its columns. `time` in seconds is required, and
+//! the others are readings named after the check kinds that read them:
+//!
+//! ```text
+//! time,altitude,apogee
+//! 0.0,0.0,0
+//! 1.5,42.0,0
+//! 14.2,1830.0,1
+//! ```
+//!
+//! Flags are written as `1`, `0`, `true`, or `false`. An empty value keeps the previous reading,
+//! and a check whose sensor has no reading yet never trips.
+
+use std::fmt::Write;
+
+use nova_software_common::index::{self, StateTransition};
+use nova_software_common::{CheckData, FloatCondition};
+use thiserror::Error;
+
+use crate::decompile::Names;
+use crate::export::{describe_check, describe_command};
+
+/// The columns a profile may have besides `time`
+pub const COLUMNS: [&str; 5] = [
+    "altitude",
+    "apogee",
+    "pyro1_continuity",
+    "pyro2_continuity",
+    "pyro3_continuity",
+];
+
+/// The sensor readings at a point in time. `None` until the sensor has a reading
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Sample {
+    /// Seconds since the flight computer powered on
+    pub time: f32,
+    pub altitude: Option<f32>,
+    pub apogee: Option<bool>,
+    pub pyro_continuity: [Option<bool>; 3],
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct ProfileError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parses a profile, see the [module documentation](self) for its format.
+/// Blank lines and lines starting with `#` are ignored
+pub fn parse_profile(text: &str) -> Result<Vec<Sample>, ProfileError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let (header_line, header) = lines.next().ok_or(ProfileError {
+        line: 1,
+        message: "the profile is empty".to_owned(),
+    })?;
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    for column in &columns {
+        if *column != "time" && !COLUMNS.contains(column) {
+            return Err(ProfileError {
+                line: header_line,
+                message: format!(
+                    "unknown column `{column}`, expected `time` or one of {}",
+                    COLUMNS.join(", ")
+                ),
+            });
+        }
+    }
+    if !columns.contains(&"time") {
+        return Err(ProfileError {
+            line: header_line,
+            message: "the profile has no `time` column".to_owned(),
+        });
+    }
+
+    let mut samples: Vec<Sample> = Vec::new();
+    let mut sample = Sample::default();
+    for (line, text) in lines {
+        let err = |message: String| ProfileError { line, message };
+        let values: Vec<&str> = text.split(',').map(str::trim).collect();
+        if values.len() != columns.len() {
+            return Err(err(format!(
+                "expected {} values, found {}",
+                columns.len(),
+                values.len()
+            )));
+        }
+        let mut time = None;
+        for (column, value) in columns.iter().zip(values) {
+            if value.is_empty() {
+                continue;
+            }
+            let float = || {
+                value
+                    .parse::<f32>()
+                    .map_err(|_| err(format!("invalid `{column}` value `{value}`")))
+            };
+            let flag = || match value {
+                "1" | "true" => Ok(true),
+                "0" | "false" => Ok(false),
+                _ => Err(err(format!("invalid `{column}` flag `{value}`"))),
+            };
+            match *column {
+                "time" => time = Some(float()?),
+                "altitude" => sample.altitude = Some(float()?),
+                "apogee" => sample.apogee = Some(flag()?),
+                "pyro1_continuity" => sample.pyro_continuity[0] = Some(flag()?),
+                "pyro2_continuity" => sample.pyro_continuity[1] = Some(flag()?),
+                _ => sample.pyro_continuity[2] = Some(flag()?),
+            }
+        }
+        sample.time = time.ok_or_else(|| err("missing `time`".to_owned()))?;
+        if let Some(last) = samples.last() {
+            if sample.time < last.time {
+                return Err(err(format!(
+                    "time {} is before the previous sample",
+                    sample.time
+                )));
+            }
+        }
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+/// Why the state machine moved to another state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cause {
+    /// The check with this index in the state tripped
+    Check(usize),
+
+    /// The state's timeout expired
+    Timeout,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    Transition {
+        from: usize,
+        transition: StateTransition,
+        cause: Cause,
+    },
+    Command {
+        state: usize,
+        command: index::Command,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+    /// Seconds since the flight computer powered on
+    pub time: f32,
+    pub event: Event,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    /// Every transition and command, in the order they happened
+    pub steps: Vec<Step>,
+
+    /// The state the flight computer was in at the end of the profile
+    pub final_state: usize,
+
+    /// The time of the last sample
+    pub end: f32,
+}
+
+fn compare(condition: FloatCondition, value: Option<f32>) -> bool {
+    let value = match value {
+        Some(value) => value,
+        None => return false,
+    };
+    match condition {
+        FloatCondition::GreaterThan(threshold) => value > threshold,
+        FloatCondition::LessThan(threshold) => value < threshold,
+        FloatCondition::Between {
+            upper_bound,
+            lower_bound,
+        } => lower_bound < value && value < upper_bound,
+    }
+}
+
+/// Returns true if a check reading `data` trips with the readings in `sample`
+fn trips(data: CheckData, sample: &Sample) -> bool {
+    match data {
+        CheckData::Altitude(condition) => compare(condition, sample.altitude),
+        CheckData::ApogeeFlag(flag) => sample.apogee == Some(flag.0),
+        CheckData::Pyro1Continuity(flag) => sample.pyro_continuity[0] == Some(flag.0),
+        CheckData::Pyro2Continuity(flag) => sample.pyro_continuity[1] == Some(flag.0),
+        CheckData::Pyro3Continuity(flag) => sample.pyro_continuity[2] == Some(flag.0),
+    }
+}
+
+fn target(transition: StateTransition) -> usize {
+    match transition {
+        StateTransition::Transition(to) | StateTransition::Abort(to) => usize::from(to),
+    }
+}
+
+/// Runs `config` against `profile`, starting in the default state at the time of the first
+/// sample.
+///
+/// At every sample, the commands of the current state whose delay has passed run first, then the
+/// state's timeout is checked, and then its checks in order. The first check that trips and has a
+/// transition moves the state machine on, and the new state's checks are first evaluated at the
+/// next sample
+pub fn simulate(config: &index::ConfigFile, profile: &[Sample]) -> Simulation {
+    let start = profile.first().map_or(0.0, |s| s.time);
+    let mut steps = Vec::new();
+    let mut state = usize::from(config.default_state);
+    let mut entered = start;
+    // The indices of the commands of the current state that have run
+    let mut fired: Vec<usize> = Vec::new();
+
+    for sample in profile {
+        // Every timeout moves to another state, so bounding the number of timeouts that expire at
+        // once keeps a loop of zero second timeouts from running forever
+        for _ in 0..=config.states.len() {
+            let current = &config.states[state];
+            // Commands still waiting when the timeout expires never run
+            let deadline = current
+                .timeout
+                .map_or(sample.time, |t| sample.time.min(entered + t.time));
+            let mut due: Vec<(usize, &index::Command)> = current
+                .commands
+                .iter()
+                .enumerate()
+                .filter(|(i, c)| !fired.contains(i) && entered + c.delay.0 <= deadline)
+                .collect();
+            due.sort_by(|a, b| a.1.delay.0.total_cmp(&b.1.delay.0));
+            for (i, command) in &due {
+                fired.push(*i);
+                steps.push(Step {
+                    time: entered + command.delay.0,
+                    event: Event::Command {
+                        state,
+                        command: **command,
+                    },
+                });
+            }
+
+            let (time, transition, cause) = match current.timeout {
+                Some(timeout) if entered + timeout.time <= sample.time => {
+                    (entered + timeout.time, timeout.transition, Cause::Timeout)
+                }
+                _ => {
+                    let check = current.checks.iter().enumerate().find_map(|(i, check)| {
+                        check
+                            .transition
+                            .filter(|_| trips(check.data, sample))
+                            .map(|t| (i, t))
+                    });
+                    match check {
+                        Some((i, transition)) => (sample.time, transition, Cause::Check(i)),
+                        None => break,
+                    }
+                }
+            };
+            steps.push(Step {
+                time,
+                event: Event::Transition {
+                    from: state,
+                    transition,
+                    cause,
+                },
+            });
+            state = target(transition);
+            entered = time;
+            fired.clear();
+            if let Cause::Check(_) = cause {
+                break;
+            }
+        }
+    }
+
+    Simulation {
+        steps,
+        final_state: state,
+        end: profile.last().map_or(start, |s| s.time),
+    }
+}
+
+impl Simulation {
+    /// Renders the steps of the simulation, naming states and checks with `names`
+    pub fn render(&self, config: &index::ConfigFile, names: &Names) -> String {
+        // Writing to a `String` cannot fail
+        let mut out = String::new();
+        for step in &self.steps {
+            let _ = write!(out, "{:>9.3}s  ", step.time);
+            let _ = match step.event {
+                Event::Transition {
+                    from,
+                    transition,
+                    cause,
+                } => {
+                    let arrow = match transition {
+                        StateTransition::Transition(_) => "->",
+                        StateTransition::Abort(_) => "abort ->",
+                    };
+                    let cause = match cause {
+                        Cause::Check(i) => format!(
+                            "check `{}`: {}",
+                            names.checks[from][i],
+                            describe_check(config.states[from].checks[i].data)
+                        ),
+                        Cause::Timeout => "timeout".to_owned(),
+                    };
+                    writeln!(
+                        out,
+                        "{} {arrow} {}  ({cause})",
+                        names.states[from],
+                        names.states[target(transition)]
+                    )
+                }
+                Event::Command { state, command } => {
+                    let mut command = command;
+                    // The time is already shown, so the delay is not repeated
+                    command.delay.0 = 0.0;
+                    writeln!(
+                        out,
+                        "{}: {}",
+                        names.states[state],
+                        describe_command(&command)
+                    )
+                }
+            };
+        }
+        let _ = writeln!(
+            out,
+            "ended in {} at {:.3}s",
+            names.states[self.final_state], self.end
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_profile, simulate, Cause, Event};
+    use crate::{decompile::Names, Session};
+
+    #[test]
+    fn simulate_flight() {
+        let config = r#"
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Descent"
+
+[[states]]
+name = "Descent"
+
+[[states.commands]]
+pyro1 = true
+delay = 1.0
+
+[states.timeout]
+seconds = 10.0
+transition = "Armed"
+"#;
+        let mut session = Session::new();
+        let (_, lower, _) =
+            crate::load_config(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap();
+        let profile = parse_profile(
+            "time,altitude,apogee\n0.0,0.0,0\n1.0,50.0,\n\n# coast\n5.0,900.0,1\n5.5,,\n20.0,0.0,0\n",
+        )
+        .unwrap();
+        assert_eq!(profile[3].altitude, Some(900.0));
+
+        let simulation = simulate(&lower, &profile);
+        let causes: Vec<_> = simulation
+            .steps
+            .iter()
+            .map(|s| match s.event {
+                Event::Transition { cause, .. } => (s.time, Some(cause)),
+                Event::Command { .. } => (s.time, None),
+            })
+            .collect();
+        assert_eq!(
+            causes,
+            [
+                (1.0, Some(Cause::Check(0))),
+                (5.0, Some(Cause::Check(0))),
+                (6.0, None),
+                (15.0, Some(Cause::Timeout)),
+            ]
+        );
+        assert_eq!(simulation.final_state, 0);
+
+        let rendered = simulation.render(&lower, &Names::generic(&lower));
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(
+            lines[0],
+            "    1.000s  State0 -> State1  (check `State0Check0`: altitude > 30)"
+        );
+        assert_eq!(lines[2], "    6.000s  State2: pyro1 = true");
+        assert_eq!(lines[4], "ended in State0 at 20.000s");
+    }
+
+    #[test]
+    fn bad_profiles() {
+        assert_eq!(parse_profile("").unwrap_err().line, 1);
+        assert_eq!(parse_profile("time,altitdue").unwrap_err().line, 1);
+        assert_eq!(parse_profile("altitude\n1.0").unwrap_err().line, 1);
+        assert_eq!(parse_profile("time,apogee\n0.0,2").unwrap_err().line, 2);
+        assert_eq!(parse_profile("time\n1.0\n0.5").unwrap_err().line, 3);
+        assert_eq!(parse_profile("time,altitude\n1.0").unwrap_err().line, 2);
+    }
+}
@@ -0,0 +1,220 @@
+//! Attributes the bytes of the encoded postcard message back to the states, checks, and commands
+//! in the toml file that produced them.
+//!
+//! The flight computer stores the config in a small flash region, so when a config is too large
+//! (or when the user asks for `--stats`) we want to say "this check costs 14 bytes" and point at
+//! the check, rather than only reporting the length of the final buffer.
+
+use std::fmt;
+
+use nova_software_common::index;
+use serde::Serialize;
+
+use crate::{spanned::SpannedValue, upper, Context, Span};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    State,
+    Check,
+    Command,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ItemKind::State => "state",
+            ItemKind::Check => "check",
+            ItemKind::Command => "command",
+        })
+    }
+}
+
+/// The encoded size of a single item from the source file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemSize {
+    pub kind: ItemKind,
+
+    /// The user facing name of this item, if it has one
+    pub name: Option<String>,
+
+    pub span: Span,
+
+    /// The number of bytes this item occupies in the encoded message.
+    /// For states this only counts the state's own overhead, not the checks and commands inside
+    /// it, which are reported as separate items
+    pub bytes: usize,
+}
+
+/// Per item breakdown of an encoded config file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeReport {
+    /// The length of the complete encoded message
+    pub total: usize,
+
+    /// Every state, check, and command in source order
+    pub items: Vec<ItemSize>,
+}
+
+pub(crate) fn encoded_len<T: Serialize>(value: &T) -> usize {
+    postcard::to_stdvec(value).map_or(0, |bytes| bytes.len())
+}
+
+/// Measures how many bytes each item in `lower` takes up when encoded.
+///
+/// `lower` must be the result of lowering `upper`. Lowering preserves the order of states, checks,
+/// and commands, which is how each encoded item is matched up with its source span.
+pub fn measure(
+    upper: &upper::ConfigFile,
+    lower: &index::ConfigFile,
+    context: &Context,
+) -> SizeReport {
+    let mut items = Vec::new();
+    for (src_state, dst_state) in upper.states.get_ref().iter().zip(lower.states.iter()) {
+        let src_state = src_state.get_ref();
+        let mut children = Vec::new();
+
+        for (src_check, dst_check) in src_state.checks.iter().zip(dst_state.checks.iter()) {
+            children.push(ItemSize {
+                kind: ItemKind::Check,
+                name: Some(src_check.get_ref().name.get_ref().clone()),
+                // toml does not record spans for tables inside arrays, so point at the name
+                span: Span::from_spanned(context, &src_check.get_ref().name),
+                bytes: encoded_len(dst_check),
+            });
+        }
+
+        for (src_command, dst_command) in src_state.commands.iter().zip(dst_state.commands.iter()) {
+            children.push(ItemSize {
+                kind: ItemKind::Command,
+                name: None,
+                span: command_span(src_command.get_ref(), context),
+                bytes: encoded_len(dst_command),
+            });
+        }
+
+        let children_bytes: usize = children.iter().map(|item| item.bytes).sum();
+        items.push(ItemSize {
+            kind: ItemKind::State,
+            name: Some(src_state.name.get_ref().clone()),
+            span: Span::from_spanned(context, &src_state.name),
+            bytes: encoded_len(dst_state).saturating_sub(children_bytes),
+        });
+        items.extend(children);
+    }
+
+    SizeReport {
+        total: encoded_len(lower),
+        items,
+    }
+}
+
+/// Returns the span of the action a command performs.
+/// toml does not record spans for tables inside arrays, so the command's own span is unusable
+fn command_span(command: &upper::Command, context: &Context) -> Span {
+    let span = |value: &Option<SpannedValue<upper::TomlBool>>| {
+        value.as_ref().map(|v| Span::from_spanned(context, v))
+    };
+    span(&command.pyro1)
+        .or_else(|| span(&command.pyro2))
+        .or_else(|| span(&command.pyro3))
+        .or_else(|| span(&command.beacon))
+        .or_else(|| {
+            command
+                .data_rate
+                .as_ref()
+                .map(|v| Span::from_spanned(context, v))
+        })
+        .unwrap_or_else(|| context.span().into())
+}
+
+impl SizeReport {
+    /// Returns the `n` items that take up the most space, largest first
+    pub fn largest(&self, n: usize) -> Vec<&ItemSize> {
+        let mut items: Vec<&ItemSize> = self.items.iter().collect();
+        // Stable sort so that items of equal size stay in source order
+        items.sort_by_key(|item| std::cmp::Reverse(item.bytes));
+        items.truncate(n);
+        items
+    }
+
+    /// Emits a note for every item in this report
+    pub fn emit_notes(&self, context: &mut Context) {
+        for item in &self.items {
+            emit_item_note(item, context);
+        }
+        context
+            .note(format!("encoded config is {} bytes", self.total))
+            .emit();
+    }
+
+    /// Emits a note for the `n` largest items in this report
+    pub fn emit_largest(&self, n: usize, context: &mut Context) {
+        for item in self.largest(n) {
+            emit_item_note(item, context);
+        }
+    }
+}
+
+fn emit_item_note(item: &ItemSize, context: &mut Context) {
+    let line = context.line_number(item.span);
+    let message = match &item.name {
+        Some(name) => format!(
+            "{} `{name}` costs {} bytes (line {line})",
+            item.kind, item.bytes
+        ),
+        None => format!("{} costs {} bytes (line {line})", item.kind, item.bytes),
+    };
+    context
+        .note(message)
+        .set_primary_span(item.span, format!("{} bytes", item.bytes))
+        .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ItemKind;
+    use crate::{lower, upper, Session};
+
+    #[test]
+    fn attributes_every_item() {
+        let config = r#"
+[[states]]
+name = "Ground"
+
+[[states.checks]]
+name = "Takeoff"
+check = "altitude"
+greater_than = 100.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+pyro1 = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let upper = upper::verify(&mut context).unwrap();
+        let lower = lower::verify(&upper, &mut context).unwrap();
+        context.end_phase_and_emit().unwrap();
+
+        let report = super::measure(&upper, &lower, &context);
+        let kinds: Vec<ItemKind> = report.items.iter().map(|item| item.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ItemKind::State,
+                ItemKind::Check,
+                ItemKind::State,
+                ItemKind::Command
+            ]
+        );
+        assert_eq!(report.total, postcard::to_stdvec(&lower).unwrap().len());
+
+        let sum: usize = report.items.iter().map(|item| item.bytes).sum();
+        // The remaining bytes are the default state and the length prefixes of the top level vec
+        assert!(sum < report.total);
+        assert!(report.items.iter().all(|item| item.bytes > 0));
+    }
+}
@@ -0,0 +1,136 @@
+//! Values that remember where in the source they came from.
+//!
+//! [`toml::Spanned`] records the byte range of a value while it is deserialized, but has no public
+//! constructor, so configs could only be built by parsing source. [`SpannedValue`] is read through
+//! `toml::Spanned` and converted at parse time, and can also be built directly, such as by tests
+//! and tools that construct a config in code. It compares, hashes, orders, and serializes as its
+//! value alone, like `toml::Spanned`.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value along with the byte range of the source it was read from
+#[derive(Clone, Debug, Default)]
+pub struct SpannedValue<T> {
+    start: usize,
+    end: usize,
+    value: T,
+}
+
+impl<T> SpannedValue<T> {
+    /// A value that was read from the bytes `span` of the source
+    pub fn new(value: T, span: Range<usize>) -> Self {
+        Self {
+            start: span.start,
+            end: span.end,
+            value,
+        }
+    }
+
+    /// The byte offset of the start of the value
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The byte offset just past the end of the value
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The byte offsets of the start and end of the value
+    pub fn span(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> From<toml::Spanned<T>> for SpannedValue<T> {
+    fn from(spanned: toml::Spanned<T>) -> Self {
+        let (start, end) = spanned.span();
+        Self::new(spanned.into_inner(), start..end)
+    }
+}
+
+impl Borrow<str> for SpannedValue<String> {
+    fn borrow(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<T: PartialEq> PartialEq for SpannedValue<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for SpannedValue<T> {}
+
+impl<T: Hash> Hash for SpannedValue<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for SpannedValue<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Ord> Ord for SpannedValue<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SpannedValue<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        toml::Spanned::<T>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl<T: Serialize> Serialize for SpannedValue<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::SpannedValue;
+
+    #[test]
+    fn spans() {
+        #[derive(Deserialize)]
+        struct Config {
+            name: SpannedValue<String>,
+        }
+        let source = "name = \"Flight\"";
+        let config: Config = toml::from_str(source).unwrap();
+        assert_eq!(
+            &source[config.name.start()..config.name.end()],
+            "\"Flight\""
+        );
+
+        let built = SpannedValue::new("Flight".to_owned(), 0..0);
+        assert_eq!(built, config.name);
+        assert_eq!(serde_json::to_string(&built).unwrap(), "\"Flight\"");
+    }
+}
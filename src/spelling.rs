@@ -0,0 +1,61 @@
+//! "Did you mean" suggestions for misspelled names.
+//!
+//! A typo in a transition target is the most common mistake in a config, so unknown state and
+//! check names suggest the closest name that does exist, if one is close enough to be a typo.
+
+/// The number of single character insertions, deletions, substitutions, and swaps of adjacent
+/// characters that turn `a` into `b`. This is the Levenshtein distance, with swaps added since
+/// they are a common typo
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // `rows[i][j]` is the distance between the first `i` characters of `a` and `j` of `b`
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i];
+        for j in 1..=b.len() {
+            let substitute = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = substitute.min(row[j - 1] + 1).min(rows[i - 1][j] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            row.push(best);
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a typo of it. Names that
+/// only differ in case are always close enough, and ties go to the candidate that sorts first
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| match c.eq_ignore_ascii_case(name) {
+            true => (0, c),
+            false => (distance(name, c), c),
+        })
+        .filter(|(d, _)| *d <= limit)
+        .min()
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, distance};
+
+    #[test]
+    fn closest_name() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("Fligth", "Flight"), 1);
+
+        let states = ["Flight", "Apogee", "Descent", "Main"];
+        assert_eq!(closest("Fligth", states), Some("Flight"));
+        assert_eq!(closest("descent", states), Some("Descent"));
+        assert_eq!(closest("Mian", states), Some("Main"));
+        assert_eq!(closest("Recovery", states), None);
+        assert_eq!(closest("Flight", states), None);
+    }
+}
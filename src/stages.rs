@@ -0,0 +1,222 @@
+//! The state machines of multi-stage rockets.
+//!
+//! Each stage of the rocket has its own states, written under a `[[stages]]` entry. A check with
+//! `separate = "Sustainer"` separates into another stage, transitioning to its first state:
+//!
+//! ```toml
+//! [[stages]]
+//! name = "Booster"
+//!
+//! [[stages.states]]
+//! name = "Boost"
+//!
+//! [[stages.states.checks]]
+//! name = "Staging"
+//! check = "altitude"
+//! greater_than = 1500.0
+//! separate = "Sustainer"
+//!
+//! [[stages]]
+//! name = "Sustainer"
+//!
+//! [[stages.states]]
+//! name = "Ignition"
+//! ```
+//!
+//! The encoded format has a single state machine, so [`expand`] adds the states of every stage to
+//! the top level `states` with the stage's name in front, such as `Booster_Boost`. Transitions,
+//! aborts, and timeouts inside a stage may use the short names of the stage's own states, and names
+//! that are not in the stage refer to top level states, such as a shared abort state.
+
+use crate::{
+    spanned::SpannedValue,
+    upper::{self, AbortTarget},
+    Context, ErrorCode, Span,
+};
+
+/// The name a state of `stage` has after expanding
+fn qualified(stage: &str, state: &str) -> String {
+    format!("{stage}_{state}")
+}
+
+/// Rewrites `name` to the qualified name if it is one of `local`, the states of `stage`
+fn resolve(name: &mut String, stage: &str, local: &[String]) {
+    if local.contains(name) {
+        *name = qualified(stage, name);
+    }
+}
+
+/// Rewrites the names of the states of `stage` that `state` refers to
+fn qualify(state: &mut upper::State, stage: &str, local: &[String]) {
+    for check in &mut state.checks {
+        let check = check.get_mut();
+        if let Some(transition) = &mut check.transition {
+            resolve(transition.get_mut(), stage, local);
+        }
+        match check.abort.as_mut().map(|a| a.get_mut()) {
+            Some(AbortTarget::State(name)) => resolve(name, stage, local),
+            Some(AbortTarget::Detailed(details)) => {
+                if let Some(name) = &mut details.state {
+                    resolve(name, stage, local);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(transition) = state
+        .timeout
+        .as_mut()
+        .and_then(|t| t.get_mut().transition.as_mut())
+    {
+        resolve(transition.get_mut(), stage, local);
+    }
+}
+
+/// Adds the states of every stage in `config` to its top level states, and replaces each
+/// `separate` with a transition to the first state of the stage
+pub fn expand(config: &mut upper::ConfigFile, context: &mut Context) {
+    let stages = std::mem::take(&mut config.stages);
+    let mut entries = Vec::new();
+    for stage in stages {
+        let stage_span = stage.span();
+        let stage = stage.into_inner();
+        let name = stage.name.get_ref().clone();
+        if entries.iter().any(|(n, _)| *n == name) {
+            let span = Span::from_spanned(context, &stage.name);
+            context
+                .error(format!("stage `{name}` is declared twice"))
+                .code(ErrorCode::DuplicateStage)
+                .set_primary_span(span, "stage names must be unique")
+                .emit();
+            continue;
+        }
+        let local: Vec<String> = stage
+            .states
+            .iter()
+            .map(|s| s.get_ref().name.get_ref().clone())
+            .collect();
+        match local.first() {
+            Some(first) => entries.push((name.clone(), qualified(&name, first))),
+            None => {
+                let span = Span::from_spanned(context, &stage.name);
+                context
+                    .error(format!("stage `{name}` has no states"))
+                    .code(ErrorCode::EmptyStage)
+                    .set_primary_span(span, "add a `[[stages.states]]` entry to this stage")
+                    .emit();
+                continue;
+            }
+        }
+        for mut state in stage.states {
+            let inner = state.get_mut();
+            let range = inner.name.start()..inner.name.end();
+            inner.name = SpannedValue::new(qualified(&name, inner.name.get_ref()), range);
+            qualify(inner, &name, &local);
+            config.states.get_mut().push(state);
+        }
+        if config.states.span() == (0, 0) {
+            config.states = SpannedValue::new(
+                std::mem::take(config.states.get_mut()),
+                stage_span.0..stage_span.1,
+            );
+        }
+    }
+
+    for state in config.states.get_mut() {
+        for check in &mut state.get_mut().checks {
+            let check = check.get_mut();
+            let separate = match check.separate.take() {
+                Some(separate) => separate,
+                None => continue,
+            };
+            let span = Span::from_spanned(context, &separate);
+            if check.transition.is_some() || check.abort.is_some() {
+                context
+                    .error("`separate` cannot be combined with `transition` or `abort`")
+                    .code(ErrorCode::SeparateAndTransition)
+                    .set_primary_span(span, "separating already transitions to the next stage")
+                    .emit();
+                continue;
+            }
+            match entries.iter().find(|(n, _)| n == separate.get_ref()) {
+                Some((_, entry)) => {
+                    let range = separate.start()..separate.end();
+                    check.transition = Some(SpannedValue::new(entry.clone(), range));
+                }
+                None => {
+                    context
+                        .error(format!("unknown stage `{}`", separate.get_ref()))
+                        .code(ErrorCode::UnknownStage)
+                        .set_primary_span(span, "not a `[[stages]]` entry")
+                        .emit();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn two_stages() {
+        let config = r#"
+[[stages]]
+name = "Booster"
+
+[[stages.states]]
+name = "Boost"
+
+[[stages.states.checks]]
+name = "Staging"
+check = "altitude"
+greater_than = 1500.0
+separate = "Sustainer"
+
+[[stages]]
+name = "Sustainer"
+
+[[stages.states]]
+name = "Ignition"
+
+[stages.states.timeout]
+seconds = 5.0
+transition = "Coast"
+
+[[stages.states]]
+name = "Coast"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let mut mid = upper::verify(&mut context).unwrap();
+        super::expand(&mut mid, &mut context);
+        assert!(context.end_phase_and_emit().unwrap().is_empty());
+        let states = mid.states.get_ref();
+        let names: Vec<_> = states.iter().map(|s| s.get_ref().name.get_ref()).collect();
+        assert_eq!(
+            names,
+            ["Booster_Boost", "Sustainer_Ignition", "Sustainer_Coast"]
+        );
+        let burnout = states[0].get_ref().checks[0].get_ref();
+        assert_eq!(
+            burnout.transition.as_ref().unwrap().get_ref(),
+            "Sustainer_Ignition"
+        );
+        let timeout = states[1].get_ref().timeout.as_ref().unwrap().get_ref();
+        assert_eq!(
+            timeout.transition.as_ref().unwrap().get_ref(),
+            "Sustainer_Coast"
+        );
+        assert!(crate::lower::verify(&mid, &mut context).is_ok());
+        context.end_phase_and_emit().unwrap();
+
+        let mut context =
+            session.testing(&config.replace("separate = \"Sustainer\"", "separate = \"Sustainr\""));
+        let mut mid = upper::verify(&mut context).unwrap();
+        super::expand(&mut mid, &mut context);
+        let errors = context.end_phase_and_emit().unwrap_err();
+        assert_eq!(errors[0].message, "unknown stage `Sustainr`");
+    }
+}
@@ -0,0 +1,166 @@
+//! Rejects keys that the config language does not know, with `--strict-keys`.
+//!
+//! Most tables are deserialized without `deny_unknown_fields`, so a misspelled key such as
+//! `delay_secs = 3` in a command is silently ignored and the command runs without a delay. With
+//! strict keys, [`check`] walks the parsed document alongside the sections in
+//! [`registry::SECTIONS`] and reports every key that is not one of them, pointing at the key.
+//!
+//! Only the file being verified is checked, not files layered over it with `--include` or
+//! `--override`.
+
+use crate::{
+    registry::{self, SECTIONS},
+    tree::{Node, Value},
+    Context, ErrorCode,
+};
+
+/// The section that the value of `key` in `section` is described by, if it is a table or an array
+/// of tables
+fn child_section(section: &str, key: &str) -> Option<&'static str> {
+    let special = match (section, key) {
+        ("[checks]", _) | ("[self_test]", "steps") => Some("[[states.checks]]"),
+        ("[[stages]]", "states") => Some("[[states]]"),
+        ("[fault_handling]", _) => Some("fallbacks in [fault_handling]"),
+        ("[[mission_events]]", "command") => Some("[[states.commands]]"),
+        _ => None,
+    };
+    if special.is_some() {
+        return special;
+    }
+    let path = match section {
+        "top level" => key.to_owned(),
+        _ => format!("{}.{key}", section.trim_matches(['[', ']'])),
+    };
+    SECTIONS
+        .iter()
+        .map(|s| s.name)
+        .find(|name| name.trim_matches(['[', ']']) == path && name.starts_with('['))
+}
+
+/// Every key allowed in `section`, or `None` if any key is
+fn known_keys(section: &str) -> Option<Vec<&'static str>> {
+    let keys = SECTIONS.iter().find(|s| s.name == section)?.keys;
+    if keys.iter().any(|(key, _)| *key == "<name>") {
+        return None;
+    }
+    let mut known: Vec<&str> = keys.iter().map(|(key, _)| *key).collect();
+    match section {
+        "top level" => known.extend(
+            SECTIONS
+                .iter()
+                .filter(|s| s.name.starts_with('[') && !s.name.contains('.'))
+                .map(|s| s.name.trim_matches(['[', ']'])),
+        ),
+        "[[states.commands]]" => known.extend(registry::COMMAND_ACTIONS.iter().map(|a| a.name)),
+        _ => {}
+    }
+    Some(known)
+}
+
+/// Reports the unknown keys of the table `entries`, which is described by `section`, returning how
+/// many there were
+fn walk(source: &str, section: &str, entries: &[(String, Node)], context: &mut Context) -> usize {
+    let known = known_keys(section);
+    let mut unknown = 0;
+    for (key, node) in entries {
+        if let Some(known) = &known {
+            if !known.contains(&key.as_str()) {
+                report(source, section, key, node, known, context);
+                unknown += 1;
+                continue;
+            }
+        }
+        let child = match child_section(section, key) {
+            Some(child) => child,
+            None => continue,
+        };
+        match &node.value {
+            Value::Table(entries) => unknown += walk(source, child, entries, context),
+            Value::Array(items) => {
+                for item in items {
+                    if let Value::Table(entries) = &item.value {
+                        unknown += walk(source, child, entries, context);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    unknown
+}
+
+fn report(
+    source: &str,
+    section: &str,
+    key: &str,
+    node: &Node,
+    known: &[&str],
+    context: &mut Context,
+) {
+    // The document only records where values are, so look for the key just before its value
+    let (start, end) = match source[..node.span.0].rfind(key) {
+        Some(start) => (start, start + key.len()),
+        None => node.span,
+    };
+    let span = context.subspan(start, end);
+    let err = context
+        .error(format!("unknown key `{key}`"))
+        .code(ErrorCode::UnknownKey)
+        .set_primary_span(span, format!("not a key of {section}"));
+    match crate::spelling::closest(key, known.iter().copied()) {
+        Some(closest) => err
+            .suggest_replacement(span, closest, "a key with a similar name exists")
+            .emit(),
+        None => err.emit(),
+    }
+}
+
+/// Reports every key in `document`, the file in `context`, that the config language does not know
+pub fn check(document: &Node, context: &mut Context) -> Result<(), ()> {
+    let source = context.source().to_owned();
+    match &document.value {
+        Value::Table(entries) if walk(&source, "top level", entries, context) > 0 => Err(()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn unknown_keys() {
+        let config = r#"
+tick_rate = 100
+
+[recovery]
+main_altitude = 300.0
+
+[[states]]
+name = "Descent"
+
+[[states.checks]]
+name = "Main"
+check = "altitude"
+less_than = 300.0
+transition = "Descent"
+
+[[states.commands]]
+pyro2 = true
+delay_secs = 3
+"#;
+        let mut session = Session::new();
+        session.set_strict_keys(true);
+        let mut context = session.testing(config);
+        let err = upper::verify(&mut context).unwrap_err();
+        let diagnostics = err.into_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "unknown key `delay_secs`");
+
+        let mut session = Session::new();
+        session.set_strict_keys(true);
+        let mut context = session.testing(&config.replace("delay_secs", "delay"));
+        assert!(upper::verify(&mut context).is_ok());
+        assert!(context.end_phase_and_emit().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! An at a glance overview of a verified config, for reviewers who want a quick sanity check
+//! without reading the full `--stats` report.
+
+use std::fmt;
+
+use nova_software_common::index;
+
+use crate::upper;
+
+/// Counts for a single state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateSummary {
+    pub name: String,
+    pub checks: usize,
+    pub commands: usize,
+
+    /// Checks and timeouts that transition to another state
+    pub transitions: usize,
+
+    /// Checks and timeouts that abort to another state
+    pub aborts: usize,
+}
+
+/// Counts for every state in a config, along with the size of the encoded config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// Every state in source order
+    pub states: Vec<StateSummary>,
+
+    /// The length of the encoded config in bytes
+    pub encoded_bytes: usize,
+
+    /// Every deny by default lint the config overrides, see [`crate::overrides`]
+    pub overrides: Vec<crate::overrides::Record>,
+}
+
+impl Summary {
+    /// Summarizes `lower`, which must be the result of lowering `upper`, and encodes to
+    /// `encoded_bytes` bytes
+    pub fn new(upper: &upper::ConfigFile, lower: &index::ConfigFile, encoded_bytes: usize) -> Self {
+        let states = upper
+            .states
+            .get_ref()
+            .iter()
+            .zip(lower.states.iter())
+            .map(|(src, dst)| {
+                let outcomes = dst
+                    .checks
+                    .iter()
+                    .filter_map(|c| c.transition)
+                    .chain(dst.timeout.map(|t| t.transition));
+                let (mut transitions, mut aborts) = (0, 0);
+                for outcome in outcomes {
+                    match outcome {
+                        index::StateTransition::Transition(_) => transitions += 1,
+                        index::StateTransition::Abort(_) => aborts += 1,
+                    }
+                }
+                StateSummary {
+                    name: src.get_ref().name.get_ref().clone(),
+                    checks: dst.checks.len(),
+                    commands: dst.commands.len(),
+                    transitions,
+                    aborts,
+                }
+            })
+            .collect();
+        Self {
+            states,
+            encoded_bytes,
+            overrides: crate::overrides::records(upper),
+        }
+    }
+
+    /// Returns the sum of each column over every state
+    pub fn totals(&self) -> StateSummary {
+        let sum = |f: fn(&StateSummary) -> usize| self.states.iter().map(f).sum();
+        StateSummary {
+            name: "total".to_owned(),
+            checks: sum(|s| s.checks),
+            commands: sum(|s| s.commands),
+            transitions: sum(|s| s.transitions),
+            aborts: sum(|s| s.aborts),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let totals = self.totals();
+        let width = self
+            .states
+            .iter()
+            .chain(std::iter::once(&totals))
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("state".len());
+        let row = |f: &mut fmt::Formatter<'_>, s: &StateSummary| {
+            writeln!(
+                f,
+                "{:<width$}  {:>6}  {:>8}  {:>11}  {:>6}",
+                s.name, s.checks, s.commands, s.transitions, s.aborts
+            )
+        };
+
+        writeln!(
+            f,
+            "{:<width$}  {:>6}  {:>8}  {:>11}  {:>6}",
+            "state", "checks", "commands", "transitions", "aborts"
+        )?;
+        for state in &self.states {
+            row(f, state)?;
+        }
+        writeln!(f, "{}", "-".repeat(width + 39))?;
+        row(f, &totals)?;
+        writeln!(
+            f,
+            "{} states, encoded in {} bytes",
+            self.states.len(),
+            self.encoded_bytes
+        )?;
+        for record in &self.overrides {
+            writeln!(f, "overrides `{}`: {}", record.lint, record.justification)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Summary;
+    use crate::Session;
+
+    #[test]
+    fn counts() {
+        let config = r#"
+default_state = "Armed"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states.checks]]
+name = "Continuity"
+check = "pyro1_continuity"
+flag = "unset"
+abort = "Safe"
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+pyro1 = true
+
+[[states.checks]]
+name = "Apogee"
+check = "apogee"
+flag = "set"
+transition = "Safe"
+
+[[states]]
+name = "Safe"
+abort_safe = true
+"#;
+        let mut session = Session::new();
+        let (upper, lower, _) =
+            crate::load_config(&mut session, config.to_owned(), "a.toml".to_owned()).unwrap();
+        let summary = Summary::new(&upper, &lower, 42);
+        let counts: Vec<_> = summary
+            .states
+            .iter()
+            .map(|s| (s.checks, s.commands, s.transitions, s.aborts))
+            .collect();
+        assert_eq!(counts, [(2, 0, 1, 1), (1, 1, 1, 0), (0, 0, 0, 0)]);
+        assert_eq!(summary.totals().transitions, 2);
+        assert!(summary
+            .to_string()
+            .ends_with("3 states, encoded in 42 bytes\n"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Checks that are defined once and used by several states.
+//!
+//! A check that guards many states, such as a continuity check that aborts the flight, is written
+//! in the top level `[checks]` table under a name, and added to each state with `use_check`:
+//!
+//! ```toml
+//! [checks.continuity]
+//! name = "Continuity"
+//! check = "pyro1"
+//! flag = "unset"
+//! abort = true
+//!
+//! [[states]]
+//! name = "Coast"
+//! use_check = "continuity"
+//! ```
+//!
+//! [`expand`] copies every used check into the states that use it, after the checks the state
+//! declares itself, so that the rest of the verifier only sees ordinary checks. Spans of a copied
+//! check still point at the `[checks]` table, so diagnostics are reported once per definition.
+
+use crate::{upper, Context, ErrorCode, Span};
+
+/// Adds the checks named by the `use_check` of each state in `config` to that state
+pub fn expand(config: &mut upper::ConfigFile, context: &mut Context) {
+    let mut used = Vec::new();
+    for state in config.states.get_mut() {
+        let state = state.get_mut();
+        let names = match &state.use_check {
+            Some(names) => names,
+            None => continue,
+        };
+        let span = Span::from_spanned(context, names);
+        for name in &names.get_ref().0 {
+            match config.checks.get(name) {
+                Some(check) => {
+                    state.checks.push(check.clone());
+                    used.push(name.clone());
+                }
+                None => {
+                    context
+                        .error(format!("unknown check `{name}`"))
+                        .code(ErrorCode::UnknownCheckTemplate)
+                        .set_primary_span(span, "not in the `[checks]` table")
+                        .emit();
+                    let known: Vec<_> = config.checks.keys().map(|k| format!("`{k}`")).collect();
+                    let help = if known.is_empty() {
+                        format!("define it in a `[checks.{name}]` table")
+                    } else {
+                        format!("the checks that can be used are {}", known.join(", "))
+                    };
+                    context.help(help).emit();
+                }
+            }
+        }
+    }
+
+    for (name, check) in &config.checks {
+        if used.contains(name) {
+            continue;
+        }
+        // A check declared under its own `[checks.name]` header has an empty span, so it is found
+        // by its header instead
+        let (span, declaration) = match table_lines(context.source(), name) {
+            Some((start, end)) => {
+                let header = context.lines(start, start).1.trim_end().len();
+                let span = context.subspan(start, start + header);
+                (span, Some(context.subspan(start, end)))
+            }
+            None => {
+                let (lines, text) = context.lines(check.start(), check.end());
+                let inline =
+                    check.start() < check.end() && text.trim_start().starts_with(name.as_str());
+                (Span::from_spanned(context, check), inline.then_some(lines))
+            }
+        };
+        let warning = context
+            .warn(format!("check `{name}` is never used"))
+            .set_primary_span(span, "no state has `use_check` with this check")
+            .lint("unused_check");
+        match declaration {
+            Some(lines) => warning
+                .suggest_replacement(lines, "", "remove the unused check")
+                .emit(),
+            None => warning.emit(),
+        }
+    }
+}
+
+/// The byte range of the `[checks.name]` table in `source`, from its header up to the next
+/// header
+fn table_lines(source: &str, name: &str) -> Option<(usize, usize)> {
+    let header = format!("[checks.{name}]");
+    let mut start = None;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match start {
+            None if trimmed == header => start = Some(offset),
+            Some(start) if trimmed.starts_with('[') => return Some((start, offset)),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    start.map(|start| (start, source.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{upper, Session};
+
+    #[test]
+    fn expand() {
+        let toml = r#"
+[checks.continuity]
+name = "Continuity"
+check = "pyro1"
+flag = "unset"
+abort = "Safe"
+
+[checks.spare]
+name = "Spare"
+check = "pyro2"
+flag = "set"
+transition = "Safe"
+
+[[states]]
+name = "Armed"
+use_check = "continuity"
+
+[[states.checks]]
+name = "Launch"
+check = "launch_detected"
+transition = "Coast"
+
+[[states]]
+name = "Coast"
+use_check = ["continuity", "missing"]
+
+[[states]]
+name = "Safe"
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let mut config = upper::verify(&mut context).unwrap();
+        super::expand(&mut config, &mut context);
+        let start = toml.find("[checks.spare]").unwrap();
+        let table = context.subspan(start, toml.find("[[states]]").unwrap());
+        let states = config.states.get_ref();
+        let checks = states[0].get_ref().checks.iter();
+        let names: Vec<_> = checks
+            .map(|c| c.get_ref().name.get_ref().as_str())
+            .collect();
+        assert_eq!(names, ["Launch", "Continuity"]);
+        assert_eq!(states[1].get_ref().checks.len(), 1);
+
+        let errors = context.end_phase_and_emit().unwrap_err();
+        let messages: Vec<_> = errors.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown check `missing`",
+                "the checks that can be used are `continuity`, `spare`",
+                "check `spare` is never used",
+                "remove the unused check",
+            ]
+        );
+        assert_eq!(session.suggestions()[0].span, table);
+    }
+}
@@ -0,0 +1,165 @@
+//! The rate of the flight computer's control loop.
+//!
+//! The flight computer evaluates checks and runs delayed commands once per tick, so no duration
+//! can be shorter than a tick, and every duration is rounded to a whole number of ticks. A config
+//! can declare the rate it was written for with `tick_rate = 100`, in ticks per second. The
+//! `tick_resolution` lint then warns about every duration that the flight computer cannot
+//! represent exactly, and the rate is encoded with `--emit tick-rate` so that the firmware can
+//! refuse a config written for a different rate.
+
+use crate::spanned::SpannedValue;
+use serde::Serialize;
+
+use crate::{ncf, upper, Context, ErrorCode, Span};
+
+/// The bytes every encoded tick rate starts with
+pub const MAGIC: [u8; 4] = *b"NTK\0";
+
+/// How far a duration can be from a whole number of ticks before it is reported, in ticks.
+/// Durations are written in decimal, which 32 bit floats cannot represent exactly
+const TOLERANCE: f32 = 1e-3;
+
+/// The timing a config was written for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Timing {
+    /// Ticks per second
+    pub tick_rate: u16,
+}
+
+/// Verifies the `tick_rate` of `config`, emitting errors to `context`.
+/// Returns `Ok(None)` if the config does not declare one
+pub fn lower(config: &upper::ConfigFile, context: &mut Context) -> Result<Option<Timing>, ()> {
+    let tick_rate = match &config.tick_rate {
+        Some(tick_rate) => tick_rate,
+        None => return Ok(None),
+    };
+    if *tick_rate.get_ref() == 0 {
+        let span = Span::from_spanned(context, tick_rate);
+        context
+            .error("the tick rate must be at least 1")
+            .code(ErrorCode::InvalidTickRate)
+            .set_primary_span(span, "the control loop would never run")
+            .emit();
+        return Err(());
+    }
+    Ok(Some(Timing {
+        tick_rate: *tick_rate.get_ref(),
+    }))
+}
+
+/// Every duration in `config` with a description of what it is
+fn durations(config: &upper::ConfigFile) -> Vec<(String, &SpannedValue<f32>)> {
+    let mut durations = Vec::new();
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let name = state.name.get_ref();
+        let delays = state
+            .commands
+            .iter()
+            .filter_map(|c| c.get_ref().delay.as_ref());
+        durations.extend(delays.map(|d| (format!("command delay in `{name}`"), d)));
+        let timeout = state.timeout.as_ref().map(|t| t.get_ref());
+        if let Some(seconds) = timeout.and_then(|t| t.seconds.as_ref()) {
+            durations.push((format!("timeout of `{name}`"), seconds));
+        }
+    }
+    for event in config.mission_events.iter().map(|e| e.get_ref()) {
+        durations.push(("mission event time".to_owned(), &event.time));
+    }
+    durations
+}
+
+/// Warns about every duration that is not a whole number of ticks, once `config` declares a
+/// tick rate
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    let rate = match config.tick_rate.as_ref().map(|r| *r.get_ref()) {
+        Some(rate) if rate > 0 => rate,
+        _ => return,
+    };
+    let tick = 1.0 / f32::from(rate);
+    for (what, duration) in durations(config) {
+        let seconds = *duration.get_ref();
+        let ticks = seconds * f32::from(rate);
+        if !ticks.is_finite() || (ticks - ticks.round()).abs() <= TOLERANCE {
+            continue;
+        }
+        let span = Span::from_spanned(context, duration);
+        if ticks < 1.0 && seconds > 0.0 {
+            context
+                .warn(format!("{what} is shorter than one tick"))
+                .set_primary_span(
+                    span,
+                    format!("{seconds} seconds, but a tick is {tick} seconds at {rate} Hz"),
+                )
+                .lint("tick_resolution")
+                .emit();
+        } else {
+            let rounded = ticks.round() / f32::from(rate);
+            context
+                .warn(format!("{what} is not a whole number of ticks"))
+                .set_primary_span(
+                    span,
+                    format!("{ticks} ticks at {rate} Hz, which runs after {rounded} seconds"),
+                )
+                .lint("tick_resolution")
+                .emit();
+        }
+    }
+}
+
+/// Encodes `timing` for the flight computer
+pub fn encode(timing: &Timing) -> Result<Vec<u8>, postcard::Error> {
+    ncf::encode_tagged(MAGIC, timing)
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use crate::{upper, Session};
+
+    #[test]
+    fn resolution() {
+        let toml = r#"
+tick_rate = 50
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+beacon = true
+delay = 0.01
+
+[[states.commands]]
+pyro1 = true
+delay = 0.5
+
+[[states.commands]]
+pyro2 = true
+delay = 0.55
+
+[[mission_events]]
+time = 0.03
+command = { data_rate = 2 }
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(toml);
+        let config = upper::verify(&mut context).unwrap();
+        let timing = super::lower(&config, &mut context).unwrap();
+        assert_eq!(timing.unwrap().tick_rate, 50);
+        super::check(&config, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Warning)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "command delay in `Flight` is shorter than one tick",
+                "command delay in `Flight` is not a whole number of ticks",
+                "mission event time is not a whole number of ticks",
+            ]
+        );
+    }
+}
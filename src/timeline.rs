@@ -0,0 +1,209 @@
+//! When the commands of each state run, and lints for commands that collide.
+//!
+//! Every command in a state runs `delay` seconds after the state is entered. Two pyro charges fired
+//! at the same instant draw their current from the battery at once, and two commands that set the
+//! same object at the same instant leave it to the firmware to decide which one wins, so both are
+//! worth a second look.
+
+use crate::{spanned::SpannedValue, upper, Context, Span};
+
+/// A command action scheduled in a state
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Event<'a> {
+    /// Seconds after entering the state
+    pub time: f32,
+
+    /// The object the command acts on, such as `pyro1` or `data_rate`
+    pub object: &'static str,
+
+    /// Whether the command fires a pyro charge
+    pub fires_pyro: bool,
+
+    pub command: &'a SpannedValue<upper::Command>,
+}
+
+/// Returns every action that the commands of `state` perform, sorted by the time they run.
+/// Commands that run at the same time keep their source order
+pub fn timeline(state: &upper::State) -> Vec<Event<'_>> {
+    let mut events = Vec::new();
+    for command in &state.commands {
+        let c = command.get_ref();
+        let time = c.delay.as_ref().map_or(0.0, |d| *d.get_ref());
+        let pyros = [
+            ("pyro1", &c.pyro1),
+            ("pyro2", &c.pyro2),
+            ("pyro3", &c.pyro3),
+        ];
+        for (object, value) in pyros {
+            if let Some(value) = value {
+                let fires_pyro = bool::from(*value.get_ref());
+                events.push(Event {
+                    time,
+                    object,
+                    fires_pyro,
+                    command,
+                });
+            }
+        }
+        let others = [
+            ("data_rate", c.data_rate.is_some()),
+            ("beacon", c.beacon.is_some()),
+        ];
+        for (object, _) in others.into_iter().filter(|(_, set)| *set) {
+            events.push(Event {
+                time,
+                object,
+                fires_pyro: false,
+                command,
+            });
+        }
+    }
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    events
+}
+
+/// Warns about pyro charges fired at the same time, and objects set more than once at the same
+/// time
+pub fn check(config: &upper::ConfigFile, context: &mut Context) {
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let events = timeline(state);
+        for group in events.chunk_by(|a, b| a.time == b.time) {
+            let time = group[0].time;
+            let fired: Vec<_> = group.iter().filter(|e| e.fires_pyro).collect();
+            if fired.len() > 1 {
+                let spans: Vec<_> = fired
+                    .iter()
+                    .map(|e| Span::from_spanned(context, e.command))
+                    .collect();
+                let names: Vec<_> = fired.iter().map(|e| format!("`{}`", e.object)).collect();
+                let mut warning = context
+                    .warn(format!(
+                        "{} are fired at the same time in state `{}`",
+                        names.join(" and "),
+                        state.name.get_ref()
+                    ))
+                    .set_primary_span(spans[0], format!("fires {time} seconds after entering"))
+                    .lint("simultaneous_pyro");
+                for span in &spans[1..] {
+                    warning = warning.span_label(*span, "fires at the same time");
+                }
+                warning.emit();
+                context
+                    .help("stagger the commands with `delay` so the charges do not draw current at once")
+                    .emit();
+            }
+
+            let mut seen: Vec<&Event> = Vec::new();
+            for event in group {
+                let first = match seen.iter().find(|e| e.object == event.object) {
+                    Some(first) => first,
+                    None => {
+                        seen.push(event);
+                        continue;
+                    }
+                };
+                let span = Span::from_spanned(context, event.command);
+                let first_span = Span::from_spanned(context, first.command);
+                context
+                    .warn(format!(
+                        "`{}` is set twice at the same time in state `{}`",
+                        event.object,
+                        state.name.get_ref()
+                    ))
+                    .set_primary_span(span, "this command")
+                    .span_label(
+                        first_span,
+                        format!("runs at the same time as this one, {time} seconds after entering"),
+                    )
+                    .lint("simultaneous_command")
+                    .emit();
+            }
+        }
+    }
+}
+
+/// Emits a note with the timeline of each state that has commands
+pub fn emit_notes(config: &upper::ConfigFile, context: &mut Context) {
+    for state in config.states.get_ref().iter().map(|s| s.get_ref()) {
+        let events = timeline(state);
+        if events.is_empty() {
+            continue;
+        }
+        let entries: Vec<_> = events
+            .iter()
+            .map(|e| format!("{}s {}", e.time, e.object))
+            .collect();
+        let span = Span::from_spanned(context, &state.name);
+        context
+            .note(format!(
+                "commands in state `{}` run at: {}",
+                state.name.get_ref(),
+                entries.join(", ")
+            ))
+            .set_primary_span(span, "entered at 0s")
+            .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codemap_diagnostic::Level;
+
+    use super::timeline;
+    use crate::{upper, Session};
+
+    #[test]
+    fn collisions() {
+        let config = r#"
+[[states]]
+name = "Deploy"
+
+[[states.commands]]
+pyro2 = true
+delay = 1.0
+
+[[states.commands]]
+pyro1 = true
+
+[[states.commands]]
+pyro3 = true
+delay = 1.0
+
+[[states.commands]]
+data_rate = 10
+
+[[states.commands]]
+data_rate = 20
+"#;
+        let mut session = Session::new();
+        let mut context = session.testing(config);
+        let config = upper::verify(&mut context).unwrap();
+        let state = config.states.get_ref()[0].get_ref();
+        let order: Vec<_> = timeline(state).iter().map(|e| (e.time, e.object)).collect();
+        assert_eq!(
+            order,
+            [
+                (0.0, "pyro1"),
+                (0.0, "data_rate"),
+                (0.0, "data_rate"),
+                (1.0, "pyro2"),
+                (1.0, "pyro3"),
+            ]
+        );
+
+        super::check(&config, &mut context);
+        let diagnostics = context.end_phase_and_emit().unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == Level::Warning)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "`data_rate` is set twice at the same time in state `Deploy`",
+                "`pyro2` and `pyro3` are fired at the same time in state `Deploy`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! A format independent document tree with byte spans, used by the non toml front ends.
+//!
+//! Front ends such as [`crate::json`] parse their input into a [`Node`], which is then deserialized
+//! into [`crate::upper::ConfigFile`] like any other serde data. The deserializer understands the
+//! private protocol that [`toml::Spanned`] uses, so every [`crate::upper::SpannedValue`], which is
+//! read through it, gets the span of the node it was read from, just like when parsing toml.
+
+use std::fmt;
+
+use serde::de::{self, value::BorrowedStrDeserializer, IntoDeserializer, Visitor};
+
+// The names `toml::Spanned` expects when it is deserialized. These are private to the toml crate,
+// but are part of how `Spanned` interacts with serde so they cannot change without breaking every
+// toml deserializer
+const SPANNED_NAME: &str = "$__toml_private_Spanned";
+const SPANNED_START: &str = "$__toml_private_start";
+const SPANNED_END: &str = "$__toml_private_end";
+const SPANNED_VALUE: &str = "$__toml_private_value";
+
+/// A value in a document, along with the byte range of the source that it was parsed from
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub span: (usize, usize),
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Node>),
+
+    /// Key value pairs in source order
+    Table(Vec<(String, Node)>),
+}
+
+impl Node {
+    pub fn new(span: (usize, usize), value: Value) -> Self {
+        Self { span, value }
+    }
+}
+
+/// An error from parsing or deserializing a document, with the byte range it occurred at if known
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    pub span: Option<(usize, usize)>,
+}
+
+impl Error {
+    pub fn new(message: impl Into<String>, span: (usize, usize)) -> Self {
+        Self {
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    /// Attaches `span` to this error, unless a more specific span is already known
+    fn or_span(mut self, span: (usize, usize)) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            message: msg.to_string(),
+            span: None,
+        }
+    }
+}
+
+/// A front end that parses a source file into a [`Node`]
+pub type Parser = fn(&str) -> Result<Node, Error>;
+
+/// Parses `source` as toml, keeping the span of every value.
+///
+/// toml only reports the last character of a float as its span, and tables inside arrays get an
+/// empty span at the start of the file
+pub fn parse_toml(source: &str) -> Result<Node, Error> {
+    toml::from_str(source).map_err(|e| {
+        let (line, col) = e.line_col().unwrap_or((0, 0));
+        let start: usize = source
+            .split_inclusive('\n')
+            .take(line)
+            .map(str::len)
+            .sum::<usize>()
+            + col;
+        let start = start.min(source.len());
+        Error::new(e.to_string(), (start, (start + 1).min(source.len())))
+    })
+}
+
+/// Deserializes a `T` from `node`
+pub fn from_node<'de, T: de::Deserialize<'de>>(node: &'de Node) -> Result<T, Error> {
+    T::deserialize(node)
+}
+
+impl<'de> de::Deserializer<'de> for &'de Node {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Values that fail to deserialize are reported at the innermost node. Errors that a
+        // `Deserialize` impl returns after the visitor finishes are spanned by the caller instead
+        let result = match &self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Array(items) => visitor.visit_seq(SeqAccess(items.iter())),
+            Value::Table(entries) => visitor.visit_map(MapAccess {
+                entries: entries.iter(),
+                value: None,
+            }),
+        };
+        result.map_err(|e| e.or_span(self.span))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name == SPANNED_NAME && fields == [SPANNED_START, SPANNED_END, SPANNED_VALUE] {
+            return visitor.visit_map(SpannedAccess {
+                node: self,
+                field: 0,
+            });
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match &self.value {
+            Value::String(s) => visitor
+                .visit_enum(s.as_str().into_deserializer())
+                .map_err(|e: Error| e.or_span(self.span)),
+            _ => Err(Error::new("expected a string", self.span)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map identifier ignored_any
+    }
+}
+
+/// Reads a node from any deserializer that supports [`toml::Spanned`], such as toml's own
+impl<'de> de::Deserialize<'de> for Node {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let value = toml::Spanned::<Value>::deserialize(d)?;
+        Ok(Node::new(value.span(), value.into_inner()))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, d: D) -> Result<Value, D::Error> {
+        de::Deserialize::deserialize(d)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Value, E> {
+        Ok(Value::Integer(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Value, E> {
+        i64::try_from(u)
+            .map(Value::Integer)
+            .map_err(|_| E::custom(format!("integer `{u}` is too large")))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Value, E> {
+        Ok(Value::Float(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_owned()))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Table(entries))
+    }
+}
+
+struct SeqAccess<'de>(std::slice::Iter<'de, Node>);
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|node| seed.deserialize(node).map_err(|e| e.or_span(node.span)))
+            .transpose()
+    }
+}
+
+struct MapAccess<'de> {
+    entries: std::slice::Iter<'de, (String, Node)>,
+    value: Option<&'de Node>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(BorrowedStrDeserializer::new(key.as_str()))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        // serde always asks for a key before its value
+        let node = self.value.take().unwrap();
+        seed.deserialize(node).map_err(|e| e.or_span(node.span))
+    }
+}
+
+/// Feeds a node to `toml::Spanned` as the map `{ start, end, value }`
+struct SpannedAccess<'de> {
+    node: &'de Node,
+    field: usize,
+}
+
+impl<'de> de::MapAccess<'de> for SpannedAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let key = match self.field {
+            0 => SPANNED_START,
+            1 => SPANNED_END,
+            2 => SPANNED_VALUE,
+            _ => return Ok(None),
+        };
+        seed.deserialize(BorrowedStrDeserializer::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        self.field += 1;
+        match self.field {
+            1 => seed.deserialize(self.node.span.0.into_deserializer()),
+            2 => seed.deserialize(self.node.span.1.into_deserializer()),
+            _ => seed
+                .deserialize(self.node)
+                .map_err(|e| e.or_span(self.node.span)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use toml::Spanned;
+
+    use super::{from_node, Node, Value};
+
+    #[test]
+    fn spans_are_preserved() {
+        #[derive(Deserialize)]
+        struct Doc {
+            name: Spanned<String>,
+            values: Spanned<Vec<Spanned<f32>>>,
+            missing: Option<Spanned<u16>>,
+        }
+
+        let node = Node::new(
+            (0, 30),
+            Value::Table(vec![
+                (
+                    "name".to_owned(),
+                    Node::new((2, 7), Value::String("Armed".to_owned())),
+                ),
+                (
+                    "values".to_owned(),
+                    Node::new(
+                        (10, 20),
+                        Value::Array(vec![
+                            Node::new((11, 14), Value::Float(1.5)),
+                            Node::new((16, 17), Value::Integer(3)),
+                        ]),
+                    ),
+                ),
+            ]),
+        );
+        let doc: Doc = from_node(&node).unwrap();
+        assert_eq!(doc.name.span(), (2, 7));
+        assert_eq!(doc.values.span(), (10, 20));
+        let values: Vec<_> = doc.values.get_ref().iter().map(|v| *v.get_ref()).collect();
+        assert_eq!(values, [1.5, 3.0]);
+        assert_eq!(doc.values.get_ref()[1].span(), (16, 17));
+        assert!(doc.missing.is_none());
+
+        // Errors point at the innermost node that failed
+        let bad = Node::new(
+            (0, 10),
+            Value::Table(vec![(
+                "name".to_owned(),
+                Node::new((4, 6), Value::Integer(1)),
+            )]),
+        );
+        let err = from_node::<Doc>(&bad).err().unwrap();
+        assert_eq!(err.span, Some((4, 6)));
+    }
+}
@@ -6,10 +6,222 @@
 //! exist, have negative timeouts, etc. This is the job of the low level verifier to check when it
 //! converts our [`ConfigFile`] to [`nova_software_common::index::ConfigFile`]
 
+use std::collections::BTreeMap;
+
+pub use crate::spanned::SpannedValue;
 use serde::{Deserialize, Serialize};
-use toml::Spanned;
 
-pub fn verify(context: &mut crate::Context) -> Result<ConfigFile, ()> {
+/// A syntax that config files can be written in. Every format produces the same [`ConfigFile`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum InputFormat {
+    #[default]
+    Toml,
+    Json,
+
+    /// Only available when built with the `yaml` feature
+    Yaml,
+
+    Ron,
+}
+
+impl InputFormat {
+    pub const ALL: [InputFormat; 4] = [
+        InputFormat::Toml,
+        InputFormat::Json,
+        InputFormat::Yaml,
+        InputFormat::Ron,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::Toml => "toml",
+            InputFormat::Json => "json",
+            InputFormat::Yaml => "yaml",
+            InputFormat::Ron => "ron",
+        }
+    }
+
+    /// File extensions that imply this format
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            InputFormat::Toml => &["toml"],
+            InputFormat::Json => &["json"],
+            InputFormat::Yaml => &["yaml", "yml"],
+            InputFormat::Ron => &["ron"],
+        }
+    }
+
+    /// Guesses the format of the file at `path` from its extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        InputFormat::ALL.into_iter().find(|f| {
+            f.extensions()
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+}
+
+impl std::fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputFormat::ALL
+            .into_iter()
+            .find(|f| f.name() == s)
+            .ok_or_else(|| format!("unknown input format `{s}`"))
+    }
+}
+
+/// Parses the file in `context` using the session's input format, or the format implied by the
+/// file's extension if none was set. Files with unknown extensions are parsed as toml.
+///
+/// Files included by the session are added to the config, see [`crate::include`], and if the
+/// session has an override file, it is merged over the config, see [`crate::overlay`]. Constants
+/// are substituted into the result, see [`crate::constants`].
+/// Files containing unresolved merge conflicts are rejected before parsing, see
+/// [`crate::conflict`].
+///
+/// If the file cannot be parsed, the phase is ended and its diagnostics are returned as
+/// [`crate::VerifyError::Parse`]. Otherwise the phase stays open for the phases that follow
+pub fn verify(context: &mut crate::Context) -> Result<ConfigFile, crate::VerifyError> {
+    match parse(context) {
+        Ok(config) => Ok(config),
+        Err(()) => Err(crate::VerifyError::Parse(
+            context.end_phase_and_emit().unwrap_err(),
+        )),
+    }
+}
+
+fn parse(context: &mut crate::Context) -> Result<ConfigFile, ()> {
+    crate::conflict::check(context)?;
+    let format = context
+        .session()
+        .input_format()
+        .or_else(|| InputFormat::from_path(context.file_name()))
+        .unwrap_or_default();
+    let includes = context.session().includes().to_vec();
+    let override_file = context.session().override_file().map(str::to_owned);
+    let config = if !includes.is_empty() || override_file.is_some() {
+        verify_layered(context, format, includes, override_file)
+    } else {
+        match format {
+            InputFormat::Toml if !declares_constants(context.source()) => verify_toml(context),
+            format => {
+                let parse = tree_parser(context, format)?;
+                verify_tree(context, parse)
+            }
+        }
+    }?;
+    if context.session().strict_keys() {
+        let parse = tree_parser(context, format)?;
+        // The file parsed above, so it parses again
+        if let Ok(document) = parse(context.source()) {
+            crate::strict_keys::check(&document, context)?;
+        }
+    }
+    Ok(config)
+}
+
+/// Returns true if the toml `source` has a `[constants]` table. Such configs are parsed through
+/// [`crate::tree`] so that expressions can be substituted before deserializing
+fn declares_constants(source: &str) -> bool {
+    crate::tree::parse_toml(source).is_ok_and(|config| crate::constants::declared(&config))
+}
+
+/// Returns the front end that parses `format` into a [`crate::tree::Node`]
+fn tree_parser(
+    context: &mut crate::Context,
+    format: InputFormat,
+) -> Result<crate::tree::Parser, ()> {
+    match format {
+        InputFormat::Toml => Ok(crate::tree::parse_toml),
+        InputFormat::Json => Ok(crate::json::parse),
+        InputFormat::Ron => Ok(crate::ron::parse),
+        #[cfg(feature = "yaml")]
+        InputFormat::Yaml => Ok(crate::yaml::parse),
+        #[cfg(not(feature = "yaml"))]
+        InputFormat::Yaml => {
+            context
+                .error("YAML configs are not supported by this build")
+                .code(crate::ErrorCode::YamlUnsupported)
+                .emit();
+            context
+                .help("rebuild the verifier with `--features yaml`, or convert the config to toml")
+                .emit();
+            Err(())
+        }
+    }
+}
+
+/// Reads the file at `path` that is layered over the config, and parses it in the format implied
+/// by its extension, or `format` if the extension is unknown
+fn read_layer(
+    context: &mut crate::Context,
+    format: InputFormat,
+    path: String,
+    kind: &str,
+) -> Result<crate::tree::Node, ()> {
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            context
+                .error(format!("failed to read {kind} `{path}`: {e}"))
+                .code(crate::ErrorCode::ReadFailed)
+                .emit();
+            return Err(());
+        }
+    };
+    let parse = tree_parser(context, InputFormat::from_path(&path).unwrap_or(format))?;
+    crate::overlay::parse_layer(context, source, path, parse)
+}
+
+/// Parses the file in `context`, adds the files in `includes` to it, and merges the override file
+/// at `override_file` over the result. The other files may be written in a different format,
+/// which is guessed from their extension
+fn verify_layered(
+    context: &mut crate::Context,
+    format: InputFormat,
+    includes: Vec<String>,
+    override_file: Option<String>,
+) -> Result<ConfigFile, ()> {
+    let parse = tree_parser(context, format)?;
+    let base = crate::overlay::parse_base(context, parse);
+    let parts: Vec<_> = includes
+        .into_iter()
+        .map(|path| read_layer(context, format, path, "included file"))
+        .collect();
+    let overlay = override_file
+        .map(|path| {
+            Ok((
+                read_layer(context, format, path.clone(), "override file")?,
+                path,
+            ))
+        })
+        .transpose();
+    let mut base = base?;
+    let parts: Result<Vec<_>, ()> = parts.into_iter().collect();
+    let (parts, overlay) = (parts?, overlay?);
+
+    let mut added = Ok(());
+    for part in parts {
+        added = added.and(crate::include::add(context, &mut base, part));
+    }
+    added?;
+    if let Some((overlay, path)) = overlay {
+        crate::overlay::apply(context, &mut base, overlay, &path);
+    }
+    crate::constants::substitute(context, &mut base)?;
+    crate::overlay::finish(context, &base)
+}
+
+fn verify_toml(context: &mut crate::Context) -> Result<ConfigFile, ()> {
     match toml::from_str(context.source()) {
         Ok(c) => Ok(c),
         Err(e) => {
@@ -17,6 +229,7 @@ pub fn verify(context: &mut crate::Context) -> Result<ConfigFile, ()> {
             let span = context.row_col_to_span(row_col);
             context
                 .error("failed to parse config file")
+                .code(crate::ErrorCode::InvalidConfig)
                 .set_primary_span(span, e.to_string())
                 .emit();
             Err(())
@@ -24,34 +237,285 @@ pub fn verify(context: &mut crate::Context) -> Result<ConfigFile, ()> {
     }
 }
 
+/// Parses the file in `context` with a front end that produces a [`crate::tree::Node`]
+fn verify_tree(context: &mut crate::Context, parse: crate::tree::Parser) -> Result<ConfigFile, ()> {
+    let mut config = crate::overlay::parse_base(context, parse)?;
+    crate::constants::substitute(context, &mut config)?;
+    crate::overlay::finish(context, &config)
+}
+
+/// The version of the JSON document produced by [`to_json`].
+/// This is bumped whenever [`ConfigFile`] changes in a way that would break existing consumers,
+/// such as renaming or removing a field. Adding new optional fields does not bump the version
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    format_version: u32,
+    config: &'a ConfigFile,
+}
+
+/// Serializes `config` to JSON so that external tools can consume the same structure the verifier
+/// sees. Spans are stripped, so states and checks are referenced by name only.
+///
+/// The document has the form `{ "format_version": 1, "config": { ... } }`, see
+/// [`JSON_FORMAT_VERSION`]
+pub fn to_json(config: &ConfigFile) -> String {
+    let document = JsonDocument {
+        format_version: JSON_FORMAT_VERSION,
+        config,
+    };
+    // All keys are strings and all values are plain data, so this cannot fail
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct ConfigFile {
-    pub default_state: Option<Spanned<String>>,
-    pub states: Spanned<Vec<Spanned<State>>>,
+    pub default_state: Option<SpannedValue<String>>,
+
+    /// The state that checks abort to when they use the shorthand `abort = true`.
+    /// This state must be marked with `abort_safe = true`
+    pub abort_state: Option<SpannedValue<String>>,
+
+    /// How many times a second the flight computer's control loop runs, see [`crate::tick`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick_rate: Option<SpannedValue<u16>>,
+
+    /// Thresholds used by the high level check kinds, see [`HIGH_LEVEL_CHECKS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<SpannedValue<Recovery>>,
+
+    /// Differences from the other flight computer's config that are intentional, see
+    /// [`crate::crosscheck`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crosscheck: Option<SpannedValue<Crosscheck>>,
+
+    /// The telemetry radio, used to check that every data rate fits in the link, see
+    /// [`crate::bandwidth`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radio: Option<SpannedValue<Radio>>,
+
+    /// Checks the flight computer runs on the pad before launch, see [`crate::self_test`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_test: Option<SpannedValue<SelfTest>>,
+
+    /// Commands that run at a fixed time after launch, regardless of state, see
+    /// [`crate::mission_events`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mission_events: Vec<SpannedValue<MissionEvent>>,
+
+    /// What to do when a sensor is declared failed, keyed by the name of the sensor, see
+    /// [`crate::fault_handling`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_handling: Option<SpannedValue<BTreeMap<String, SpannedValue<Fallback>>>>,
+
+    /// Deny by default lints that are overridden for this config, see [`crate::overrides`]
+    #[serde(default, rename = "override", skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<SpannedValue<Override>>,
+
+    /// The order that the flight must pass through groups of states, see [`crate::path_policy`]
+    #[serde(default, rename = "path_policy", skip_serializing_if = "Vec::is_empty")]
+    pub path_policies: Vec<SpannedValue<PathPolicy>>,
+
+    /// The levels of lints for this config, such as `infinite_loop = "deny"`, see [`crate::lint`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lints: BTreeMap<String, SpannedValue<String>>,
+
+    /// Checks defined once and added to states with `use_check`, keyed by the name that states
+    /// use, see [`crate::templates`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, SpannedValue<Check>>,
+
+    /// The stages of a multi-stage rocket, whose states are added to `states` with the stage's
+    /// name in front, see [`crate::stages`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<SpannedValue<Stage>>,
+
+    #[serde(default)]
+    pub states: SpannedValue<Vec<SpannedValue<State>>>,
+}
+
+/// A `[[stages]]` entry, the state machine of one stage of the rocket
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Stage {
+    /// The name of the stage, which is put in front of the names of its states
+    pub name: SpannedValue<String>,
+
+    /// The states of the stage. The first one is entered when another stage separates into it
+    pub states: Vec<SpannedValue<State>>,
+}
+
+/// An `[[override]]` entry
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// The name of a lint in [`crate::lint::LINTS`] with the error level
+    pub lint: SpannedValue<String>,
+
+    /// Why the config is safe to fly despite the lint, for reviewers
+    pub justification: SpannedValue<String>,
+}
+
+/// A `[[path_policy]]` entry
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PathPolicy {
+    /// What the policy is for, shown in diagnostics
+    pub name: SpannedValue<String>,
+
+    /// Groups of state names, in the order the flight must pass through them
+    pub order: SpannedValue<Vec<Vec<SpannedValue<String>>>>,
+}
+
+/// Check kinds that state the intent of a check rather than a raw comparison, such as
+/// `check = "apogee_passed"`. These take no condition, and expand to a low level check using the
+/// values in the `[recovery]` section
+pub const HIGH_LEVEL_CHECKS: [&str; 3] =
+    ["launch_detected", "apogee_passed", "below_main_altitude"];
+
+/// The `[recovery]` section
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Recovery {
+    /// Altitude in meters above the pad that `launch_detected` checks trip at
+    pub launch_altitude: Option<SpannedValue<f32>>,
+
+    /// Altitude in meters above the pad that `below_main_altitude` checks trip under
+    pub main_altitude: Option<SpannedValue<f32>>,
+}
+
+/// The `[crosscheck]` section
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Crosscheck {
+    /// Settings that may differ between redundant flight computers, named the same way
+    /// `crosscheck` reports them, such as `"recovery.main_altitude"`. Allowing a key also allows
+    /// every setting under it, so `"states.Drogue"` allows any difference in the `Drogue` state
+    #[serde(default)]
+    pub allow: Vec<SpannedValue<String>>,
+}
+
+/// The `[radio]` section
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Radio {
+    /// The size in bytes of each telemetry packet, including framing. A `data_rate` command sets
+    /// how many of these are sent each second
+    pub packet_bytes: Option<SpannedValue<u32>>,
+}
+
+/// The `[self_test]` section
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct SelfTest {
+    /// The checks to run, in order. These use the same keys as `[[states.checks]]`, but cannot
+    /// transition or abort
+    #[serde(default)]
+    pub steps: Vec<SpannedValue<Check>>,
+}
+
+/// A `[[mission_events]]` entry
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct MissionEvent {
+    /// What the event is for, such as `"Switch to low rate telemetry"`
+    pub name: Option<SpannedValue<String>>,
+
+    /// Seconds after launch that the command runs
+    pub time: SpannedValue<f32>,
+
+    /// The command to run. `delay` is not allowed, since `time` says when it runs
+    pub command: SpannedValue<Command>,
+}
+
+/// A value in the `[fault_handling]` section, such as `barometer = { action = "abort" }`
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Fallback {
+    /// `"abort"`, `"backup"`, or `"hold"`, see [`crate::fault_handling::Action`]
+    pub action: SpannedValue<String>,
+
+    /// The state to abort to. Defaults to the top level `abort_state`. Only for `"abort"`
+    pub state: Option<SpannedValue<String>>,
+
+    /// The sensor to switch to. Only for `"backup"`
+    pub backup: Option<SpannedValue<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct Timeout {
     /// How long this state can execute in seconds before the rocket automatically transitions to
     /// `state`
-    pub seconds: Option<Spanned<f32>>,
+    pub seconds: Option<SpannedValue<f32>>,
 
     /// The state to transition to when `state`
-    pub transition: Option<Spanned<String>>,
+    pub transition: Option<SpannedValue<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct State {
     /// The name of this state
-    pub name: Spanned<String>,
+    pub name: SpannedValue<String>,
+
+    #[serde(default)]
+    pub checks: Vec<SpannedValue<Check>>,
 
     #[serde(default)]
-    pub checks: Vec<Spanned<Check>>,
+    pub commands: Vec<SpannedValue<Command>>,
+
+    pub timeout: Option<SpannedValue<Timeout>>,
 
+    /// Marks this state as safe to abort to. Required for the top level `abort_state`
     #[serde(default)]
-    pub commands: Vec<Spanned<Command>>,
+    pub abort_safe: bool,
 
-    pub timeout: Option<Spanned<Timeout>>,
+    /// Checks from the top level `[checks]` table that are added after `checks`, such as
+    /// `use_check = "continuity"` or `use_check = ["continuity", "main_altitude"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_check: Option<SpannedValue<CheckNames>>,
+}
+
+/// The value of `use_check`, either one name or a list of names
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CheckNames(pub Vec<String>);
+
+impl<'de> Deserialize<'de> for CheckNames {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = CheckNames;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("the name of a check in `[checks]`, or a list of names")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<CheckNames, E> {
+                Ok(CheckNames(vec![name.to_owned()]))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<CheckNames, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut names = Vec::new();
+                while let Some(name) = seq.next_element()? {
+                    names.push(name);
+                }
+                Ok(CheckNames(names))
+            }
+        }
+
+        d.deserialize_any(Visitor)
+    }
+}
+
+impl Serialize for CheckNames {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(s)
+    }
 }
 
 /// Something relating to the external environment that the rocket will check to determine a future
@@ -59,33 +523,45 @@ pub struct State {
 /// - Transitioning from the `Ground` state to the `Launched` state if altitude is past a certain
 /// threshold
 /// - Aborting the flight if there is no continuity on the pyro channels
-#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct Check {
     /// The name describing this check
-    pub name: Spanned<String>,
+    pub name: SpannedValue<String>,
 
     /// The name of the thing to be checked
     /// Currently only the strings `altitude`, `pyro1`, `pyro2`, and `pyro3` are supported, and
-    /// enable specific filtering conditions
-    pub check: Spanned<String>,
+    /// enable specific filtering conditions.
+    /// The high level kinds in [`HIGH_LEVEL_CHECKS`] are also accepted
+    pub check: SpannedValue<String>,
 
     /// The name of the state to transition to when when the check is tripped
-    pub transition: Option<Spanned<String>>,
+    pub transition: Option<SpannedValue<String>>,
 
-    /// The name of the state to abort to when this check is trpped.
+    /// The name of the state to abort to when this check is trpped, or `true` to abort to the top
+    /// level `abort_state`.
     /// Muturallay exclusive with `transition`
-    pub abort: Option<Spanned<String>>,
+    pub abort: Option<SpannedValue<AbortTarget>>,
+
+    /// The name of a stage to separate into when this check is tripped, transitioning to the
+    /// stage's first state. Mutually exclusive with `transition` and `abort`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separate: Option<SpannedValue<String>>,
 
     /// If set, this check will execute when the value of `self.check` > the inner value
     /// Only available for `altitude` checks
-    pub greater_than: Option<Spanned<f32>>,
+    pub greater_than: Option<SpannedValue<f32>>,
+
+    /// If set, this check will execute when the value of `self.check` < the inner value
+    /// Only available for `altitude` checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub less_than: Option<SpannedValue<f32>>,
 
     /// Forms a check range with `lower_bound` that checks if `check` is in a particular range
     /// Only available for `altitude` checks
-    pub upper_bound: Option<Spanned<f32>>,
+    pub upper_bound: Option<SpannedValue<f32>>,
 
     /// Must be Some(...) if `upper_bound` is Some(...), and must be None if `upper_bound` is none
-    pub lower_bound: Option<Spanned<f32>>,
+    pub lower_bound: Option<SpannedValue<f32>>,
 
     /// Checks if a boolean flag is set or unset
     /// The pyro values are supported
@@ -93,7 +569,49 @@ pub struct Check {
     ///
     /// If this flag is missing and `check` is set to a pyro value, then this value will default to
     /// checking for "set"
-    pub flag: Option<Spanned<String>>,
+    pub flag: Option<SpannedValue<String>>,
+
+    /// The name of another check whose latched result must be set before this check can trip,
+    /// such as `requires = "Burnout"`, see [`crate::requires`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<SpannedValue<String>>,
+}
+
+/// Where a check aborts to
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum AbortTarget {
+    /// `abort = "Safe"`, aborts to the named state
+    State(String),
+
+    /// `abort = true`, aborts to the config's top level `abort_state`.
+    /// `abort = false` is the same as not specifying `abort`
+    Global(bool),
+
+    /// `abort = { state = "Safe", reason = "NO_CONTINUITY" }`, which records why the flight was
+    /// aborted, see [`crate::abort_reasons`]
+    Detailed(AbortDetails),
+}
+
+impl AbortTarget {
+    /// The reason code name given for this abort, if any
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            AbortTarget::Detailed(details) => details.reason.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// The table form of `abort`
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AbortDetails {
+    /// The state to abort to. Aborts to the top level `abort_state` if not given
+    pub state: Option<String>,
+
+    /// The name of a reason in [`crate::registry::ABORT_REASONS`]
+    pub reason: Option<String>,
 }
 
 /// Custom boolean that supports deserialising from toml booleans,
@@ -103,12 +621,12 @@ pub struct TomlBool(bool);
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct Command {
-    pub data_rate: Option<Spanned<u16>>,
-    pub delay: Option<Spanned<f32>>,
-    pub pyro1: Option<Spanned<TomlBool>>,
-    pub pyro2: Option<Spanned<TomlBool>>,
-    pub pyro3: Option<Spanned<TomlBool>>,
-    pub beacon: Option<Spanned<TomlBool>>,
+    pub data_rate: Option<SpannedValue<u16>>,
+    pub delay: Option<SpannedValue<f32>>,
+    pub pyro1: Option<SpannedValue<TomlBool>>,
+    pub pyro2: Option<SpannedValue<TomlBool>>,
+    pub pyro3: Option<SpannedValue<TomlBool>>,
+    pub beacon: Option<SpannedValue<TomlBool>>,
 }
 
 impl From<TomlBool> for bool {
@@ -150,42 +668,11 @@ impl Serialize for TomlBool {
     }
 }
 
-/// Creates a dummy `toml::Spanned` with `value` inside.
+/// Creates a `SpannedValue` with `value` inside and an empty span at the start of the source.
 /// Short for create_spanned
 #[cfg(test)]
-pub(crate) fn cs<T>(value: T) -> Spanned<T> {
-    // Very sad. Nothing about Spanned is public, so to make these tests work we need to do
-    // a nasty transume to create a dummy span
-    // We could avoid this by deserializing from a toml string, but we already do that as
-    // part of the integration tests, so we must do this wizardy to test this specific
-    // upper -> lower conversion code. Put your pitchforks away and stop crying
-    //
-    // Spanned struct as of `toml = "0.5.8"`:
-    // Lets hope the compiler chooses the same layout as Spanned<T>...
-    #[allow(dead_code)]
-    pub struct MySpanned<T> {
-        /// The start range.
-        start: usize,
-        /// The end range (exclusive).
-        end: usize,
-        /// The spanned value.
-        value: T,
-    }
-    let spanned = MySpanned {
-        start: 0, // We dont actually care about these values so use 0
-        end: 0,
-        value,
-    };
-    assert_eq!(
-        std::mem::size_of::<MySpanned<T>>(),
-        std::mem::size_of::<Spanned<T>>(),
-    );
-    let ptr: *const MySpanned<T> = &spanned;
-    let ptr: *const Spanned<T> = ptr as *const _;
-    let result: Spanned<T> = unsafe { std::ptr::read(ptr) };
-
-    std::mem::forget(spanned);
-    result
+pub(crate) fn cs<T>(value: T) -> SpannedValue<T> {
+    SpannedValue::new(value, 0..0)
 }
 
 #[cfg(test)]
@@ -211,11 +698,26 @@ mod tests {
         fn basic_serialize1() {
             let expected = ConfigFile {
                 default_state: Some(cs("PowerOn".to_owned())),
+                abort_state: None,
+                recovery: None,
+                crosscheck: None,
+                radio: None,
+                self_test: None,
+                mission_events: Vec::new(),
+                fault_handling: None,
+                tick_rate: None,
+                overrides: Vec::new(),
+                path_policies: Vec::new(),
+                lints: Default::default(),
+                checks: Default::default(),
+                stages: vec![],
                 states: cs(vec![cs(State {
                     name: cs("PowerOn".to_owned()),
                     checks: vec![],
                     commands: vec![],
                     timeout: None,
+                    abort_safe: false,
+                    use_check: None,
                 })]),
             };
             let config = r#"default_state = "PowerOn"
@@ -231,18 +733,36 @@ checks = []
         fn basic_serialize2() {
             let expected = ConfigFile {
                 default_state: Some(cs("PowerOn".to_owned())),
+                abort_state: None,
+                recovery: None,
+                crosscheck: None,
+                radio: None,
+                self_test: None,
+                mission_events: Vec::new(),
+                fault_handling: None,
+                tick_rate: None,
+                overrides: Vec::new(),
+                path_policies: Vec::new(),
+                lints: Default::default(),
+                checks: Default::default(),
+                stages: vec![],
                 states: cs(vec![cs(State {
                     name: cs("PowerOn".to_owned()),
                     timeout: None,
+                    abort_safe: false,
+                    use_check: None,
                     checks: vec![cs(Check {
                         name: cs("Takeoff".to_owned()),
                         check: cs("altitude".to_owned()),
                         greater_than: Some(cs(100.0)),
+                        less_than: None,
                         transition: None,
                         upper_bound: None,
                         flag: None,
+                        requires: None,
                         lower_bound: None,
                         abort: None,
+                        separate: None,
                     })],
                     commands: vec![],
                 })]),
@@ -263,6 +783,202 @@ greater_than = 100.0
         }
     }
 
+    mod json {
+        use crate::{upper, Session};
+
+        #[test]
+        fn format_is_stable() {
+            let config = r#"default_state = "PowerOn"
+
+[[states]]
+name = "PowerOn"
+
+[[states.checks]]
+name = "Pyro1Check"
+check = "pyro1_continuity"
+flag = "unset"
+abort = "PowerOn"
+
+[[states.commands]]
+beacon = "enable"
+"#;
+            let mut session = Session::new();
+            let mut context = session.testing(config);
+            let parsed = upper::verify(&mut context).unwrap();
+            context.end_phase_and_emit().unwrap();
+
+            let json: serde_json::Value = serde_json::from_str(&upper::to_json(&parsed)).unwrap();
+            let expected = serde_json::json!({
+                "format_version": 1,
+                "config": {
+                    "default_state": "PowerOn",
+                    "abort_state": null,
+                    "states": [{
+                        "name": "PowerOn",
+                        "checks": [{
+                            "name": "Pyro1Check",
+                            "check": "pyro1_continuity",
+                            "transition": null,
+                            "abort": "PowerOn",
+                            "greater_than": null,
+                            "upper_bound": null,
+                            "lower_bound": null,
+                            "flag": "unset",
+                        }],
+                        "commands": [{
+                            "data_rate": null,
+                            "delay": null,
+                            "pyro1": null,
+                            "pyro2": null,
+                            "pyro3": null,
+                            "beacon": true,
+                        }],
+                        "timeout": null,
+                        "abort_safe": false,
+                    }],
+                },
+            });
+            assert_eq!(json, expected);
+        }
+    }
+
+    mod input_format {
+        use crate::upper::{self, InputFormat};
+        use crate::Session;
+
+        const TOML: &str = r#"
+default_state = "Armed"
+
+[[states]]
+name = "Armed"
+
+[[states.checks]]
+name = "Launch"
+check = "altitude"
+greater_than = 30.0
+transition = "Flight"
+
+[[states]]
+name = "Flight"
+
+[[states.commands]]
+beacon = "enable"
+delay = 2
+"#;
+
+        const JSON: &str = r#"{
+  "default_state": "Armed",
+  "states": [
+    {
+      "name": "Armed",
+      "checks": [
+        { "name": "Launch", "check": "altitude", "greater_than": 30.0, "transition": "Flight" }
+      ]
+    },
+    { "name": "Flight", "commands": [{ "beacon": "enable", "delay": 2 }] }
+  ]
+}"#;
+
+        #[test]
+        fn json_matches_toml() {
+            let mut session = Session::new();
+            let expected = upper::verify(&mut session.testing(TOML)).unwrap();
+
+            session.set_input_format(Some(InputFormat::Json));
+            let json = upper::verify(&mut session.testing(JSON)).unwrap();
+            assert_eq!(json, expected);
+
+            let transition = json.states.get_ref()[0].get_ref().checks[0]
+                .get_ref()
+                .transition
+                .as_ref()
+                .unwrap();
+            let (start, end) = transition.span();
+            assert_eq!(&JSON[start..end], r#""Flight""#);
+        }
+
+        #[test]
+        fn ron_matches_toml() {
+            let ron = r#"(
+    default_state: "Armed",
+    states: [
+        (
+            name: "Armed",
+            checks: [
+                (name: "Launch", check: "altitude", greater_than: 30.0, transition: Flight),
+            ],
+        ),
+        (name: "Flight", commands: [(beacon: "enable", delay: 2)]),
+    ],
+)"#;
+            let mut session = Session::new();
+            let expected = upper::verify(&mut session.testing(TOML)).unwrap();
+
+            session.set_input_format(Some(InputFormat::Ron));
+            let config = upper::verify(&mut session.testing(ron)).unwrap();
+            assert_eq!(config, expected);
+        }
+
+        #[test]
+        fn json_errors() {
+            let mut session = Session::new();
+            session.set_input_format(Some(InputFormat::Json));
+            let source = r#"{ "states": [{ "name": 5 }] }"#;
+            let mut context = session.testing(source);
+            let err = upper::verify(&mut context).unwrap_err();
+            let diagnostics = err.into_diagnostics();
+            assert_eq!(diagnostics.len(), 1);
+        }
+
+        #[test]
+        fn from_path() {
+            assert_eq!(
+                InputFormat::from_path("a/rocket.JSON"),
+                Some(InputFormat::Json)
+            );
+            assert_eq!(
+                InputFormat::from_path("rocket.toml"),
+                Some(InputFormat::Toml)
+            );
+            assert_eq!(
+                InputFormat::from_path("rocket.yml"),
+                Some(InputFormat::Yaml)
+            );
+            assert_eq!(InputFormat::from_path("rocket"), None);
+        }
+    }
+
+    mod abort_target {
+        use crate::upper::{AbortDetails, AbortTarget, Check};
+
+        fn parse_abort(value: &str) -> Option<AbortTarget> {
+            let s = format!(
+                r#"name = "Pyro1Check"
+check = "pyro1_continuity"
+flag = "unset"
+abort = {value}"#
+            );
+            let check: Check = toml::from_str(&s).unwrap();
+            check.abort.map(|a| a.into_inner())
+        }
+
+        #[test]
+        fn de() {
+            assert_eq!(
+                parse_abort(r#""Safe""#),
+                Some(AbortTarget::State("Safe".to_owned()))
+            );
+            assert_eq!(parse_abort("true"), Some(AbortTarget::Global(true)));
+            assert_eq!(parse_abort("false"), Some(AbortTarget::Global(false)));
+            let detailed = parse_abort(r#"{ state = "Safe", reason = "NO_CONTINUITY" }"#).unwrap();
+            assert_eq!(detailed.reason(), Some("NO_CONTINUITY"));
+            assert!(matches!(
+                parse_abort(r#"{ reason = "NO_CONTINUITY" }"#),
+                Some(AbortTarget::Detailed(AbortDetails { state: None, .. }))
+            ));
+        }
+    }
+
     mod toml_bool {
         use crate::upper::TomlBool;
         use serde::Deserialize;
@@ -0,0 +1,90 @@
+//! Re-running verification whenever the config is saved, for `nova-verifier watch`.
+//!
+//! Files are polled rather than watched with OS notifications, since configs are small and editors
+//! save them in many different ways: writing in place, or writing a temporary file and renaming it
+//! over the config. Comparing the contents catches all of them, and ignores saves that change
+//! nothing.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How often [`Watcher::wait`] checks the files
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long to wait after a change for an editor to finish writing, so that one save verifies once
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// Watches a set of files for changes to their contents
+#[derive(Debug)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+
+    /// The contents of each file when it was last seen, or `None` if it could not be read
+    contents: Vec<Option<Vec<u8>>>,
+}
+
+impl Watcher {
+    /// Starts watching `paths`, treating their current contents as seen
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let contents = paths.iter().map(|p| std::fs::read(p).ok()).collect();
+        Self { paths, contents }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns true if the contents of any file changed since the last call. A file that cannot be
+    /// read, such as one that an editor is halfway through replacing, is not a change until it can
+    /// be read again
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, seen) in self.paths.iter().zip(&mut self.contents) {
+            let Ok(current) = std::fs::read(path) else {
+                continue;
+            };
+            if seen.as_ref() != Some(&current) {
+                *seen = Some(current);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Blocks until the contents of a file change
+    pub fn wait(&mut self) {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if self.poll() {
+                std::thread::sleep(SETTLE);
+                self.poll();
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Watcher;
+
+    #[test]
+    fn poll() {
+        let dir = std::env::temp_dir().join(format!("nova-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rocket.toml");
+        std::fs::write(&path, "[[states]]\n").unwrap();
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        assert!(!watcher.poll());
+        std::fs::write(&path, "[[states]]\n").unwrap();
+        assert!(!watcher.poll(), "saving without changes is not a change");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!watcher.poll(), "a file being replaced is not a change yet");
+        std::fs::write(&path, "[[states]]\nname = \"Armed\"\n").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,516 @@
+//! Workspaces of configs for several airframes.
+//!
+//! A project with several airframes keeps a `nova.toml` manifest at its root, which lists the
+//! config of every airframe along with which flight computer it is for:
+//!
+//! ```toml
+//! abort_state = "Safe"
+//! target = "nova"
+//!
+//! [[members]]
+//! name = "hyperion"
+//! config = "hyperion/rocket.toml"
+//! ```
+//!
+//! Artifacts are written to a fixed layout under the workspace root, so packaging scripts never
+//! have to guess at file names. Every member gets `out/<name>/<profile>/`, where the profile is
+//! `flight`, or `ground-test` for `--ground-test` builds. It holds `config.ncf`, its metadata
+//! sidecar, and any requested artifacts named `config.<extension>`, such as `config.aborts.json`.
+//! `clean` removes the whole `out` directory.
+//!
+//! `build --workspace` compiles every member, then runs lints that look across members. Airframes
+//! flown by the same team should agree on conventions such as the name of the state they abort to,
+//! so the manifest's `abort_state` is checked against every member. Without one, members are only
+//! checked against each other.
+
+use std::path::{Path, PathBuf};
+
+use crate::spanned::SpannedValue;
+use codemap_diagnostic::{Diagnostic, Level};
+use serde::Deserialize;
+
+use crate::{limits::Target, upper, EmitKind, ErrorCode, Session, Span};
+
+/// The file name of a workspace manifest
+pub const MANIFEST_NAME: &str = "nova.toml";
+
+/// The directory under the workspace root that artifacts are written to
+pub const OUT_DIR: &str = "out";
+
+/// The file name of every member's encoded config, without an extension. Other artifacts use the
+/// same name with their own extension
+pub const ARTIFACT_NAME: &str = "config";
+
+/// What a build is for, which separates its artifacts from other builds of the same member
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Profile {
+    Flight,
+
+    /// See [`crate::ground_test`]
+    GroundTest,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Flight => "flight",
+            Profile::GroundTest => "ground-test",
+        }
+    }
+
+    /// The profile that `session` builds
+    pub fn of(session: &Session) -> Self {
+        if session.ground_test() {
+            Profile::GroundTest
+        } else {
+            Profile::Flight
+        }
+    }
+}
+
+/// A `nova.toml` workspace manifest
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The abort state every member must declare as its top level `abort_state`
+    pub abort_state: Option<SpannedValue<String>>,
+
+    /// The flight computer members are built for, unless they set their own
+    pub target: Option<SpannedValue<String>>,
+
+    pub members: Vec<Member>,
+}
+
+/// A config in a workspace
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Member {
+    /// The name of the airframe, which names its directory under [`OUT_DIR`]. Defaults to the
+    /// name of the directory the config is in, or the config's file name if it is at the root
+    pub name: Option<String>,
+
+    /// The path of the config, relative to the manifest
+    pub config: SpannedValue<String>,
+
+    /// The flight computer this member is built for
+    pub target: Option<SpannedValue<String>>,
+}
+
+impl Member {
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let config = Path::new(self.config.get_ref());
+        let dir = config.parent().and_then(Path::file_name);
+        dir.or_else(|| config.file_stem())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// A member of a workspace, and its encoded config once it is built
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Built {
+    pub name: String,
+    pub config: PathBuf,
+
+    /// The directory the member's artifacts are written to
+    pub dir: PathBuf,
+    pub bytes: Vec<u8>,
+
+    /// The deny by default lints the member's config overrides
+    pub overrides: Vec<crate::overrides::Record>,
+}
+
+impl Built {
+    /// The path of the encoded config
+    pub fn output(&self) -> PathBuf {
+        self.artifact("ncf")
+    }
+
+    /// The path of the artifact with `extension`
+    pub fn artifact(&self, extension: &str) -> PathBuf {
+        self.dir.join(format!("{ARTIFACT_NAME}.{extension}"))
+    }
+}
+
+/// Returns the directory that the artifacts of the member `name` are written to
+pub fn artifact_dir(root: &Path, name: &str, profile: Profile) -> PathBuf {
+    root.join(OUT_DIR).join(name).join(profile.name())
+}
+
+/// Returns the manifest in `dir` or the closest directory above it
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(MANIFEST_NAME))
+        .find(|path| path.is_file())
+}
+
+fn error(message: String) -> Vec<Diagnostic> {
+    vec![Diagnostic {
+        level: Level::Error,
+        message,
+        code: None,
+        spans: vec![],
+    }]
+}
+
+/// A member's config after it was compiled
+struct Compiled {
+    config: upper::ConfigFile,
+
+    /// The span of the member's whole file in the session
+    file: codemap::Span,
+    member: Built,
+}
+
+/// Compiles `members`, each paired with the source of its config, then runs the cross member lints.
+/// `manifest` was parsed from `manifest_source`, which diagnostics point into. Members are compiled
+/// even if an earlier one fails, so that every error is reported at once. The artifacts in `emits`
+/// are written to each member's directory while it is compiled.
+///
+/// Returns every member with its encoded config, or `Err(diagnostics)` if any member failed
+pub fn build_sources(
+    session: &mut Session,
+    manifest: &Manifest,
+    (manifest_path, manifest_source): (String, String),
+    members: Vec<(Built, String)>,
+    emits: &[EmitKind],
+) -> Result<(Vec<Built>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut all_diagnostics = Vec::new();
+    let mut failed = Vec::new();
+    let mut compiled = Vec::new();
+    let mut targets = Vec::new();
+    for member in &manifest.members {
+        let target = member.target.as_ref().or(manifest.target.as_ref());
+        targets.push(match target.map(|t| t.get_ref().parse::<Target>()) {
+            Some(Ok(target)) => Ok(target),
+            Some(Err(err)) => Err((err, target.unwrap())),
+            None => Ok(Target::default()),
+        });
+    }
+
+    for ((mut member, source), target) in members.into_iter().zip(&targets) {
+        let target = match target {
+            Ok(target) => *target,
+            // Reported with the manifest below
+            Err(_) => {
+                compiled.push(None);
+                continue;
+            }
+        };
+        session.set_target(target);
+        for kind in emits {
+            let path = member.artifact(kind.default_extension());
+            session.add_emit(*kind, path.display().to_string());
+        }
+        let path = member.config.display().to_string();
+        let mut context = session.add_file(source, path);
+        let file = context.span();
+        match crate::encode_phases(&mut context, &mut all_diagnostics) {
+            Ok((config, _, bytes)) => {
+                member.bytes = bytes;
+                member.overrides = crate::overrides::records(&config);
+                compiled.push(Some(Compiled {
+                    config,
+                    file,
+                    member,
+                }))
+            }
+            Err(err) => {
+                failed.extend(err.into_diagnostics());
+                compiled.push(None);
+            }
+        }
+    }
+
+    let mut context = session.add_file(manifest_source, manifest_path);
+    let mut result = Ok(());
+    for target in &targets {
+        if let Err((err, target)) = target {
+            let span = Span::from_spanned(&context, target);
+            context
+                .error(err.clone())
+                .code(ErrorCode::UnknownTarget)
+                .set_primary_span(span, "not a flight computer this verifier supports")
+                .emit();
+            result = Err(());
+        }
+    }
+
+    let names: Vec<String> = manifest.members.iter().map(Member::name).collect();
+    for (i, member) in manifest.members.iter().enumerate() {
+        let name = &names[i];
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let span = Span::from_spanned(&context, &member.config);
+        if !valid {
+            context
+                .error(format!(
+                    "member name `{name}` cannot be used as a directory name"
+                ))
+                .code(ErrorCode::InvalidMemberName)
+                .set_primary_span(span, "for this member")
+                .emit();
+            context
+                .help("set `name` to a name with only letters, digits, `_`, and `-`")
+                .emit();
+            result = Err(());
+        } else if let Some(first) = names[..i].iter().position(|n| n == name) {
+            let first_span = Span::from_spanned(&context, &manifest.members[first].config);
+            context
+                .error(format!("two members are named `{name}`"))
+                .code(ErrorCode::DuplicateMember)
+                .set_primary_span(span, "this member")
+                .span_label(first_span, "has the same name as this one")
+                .emit();
+            context
+                .help("set `name` on one of them, so their artifacts go to different directories")
+                .emit();
+            result = Err(());
+        }
+    }
+
+    let members: Vec<&Compiled> = compiled.iter().flatten().collect();
+    let span_in = |member: &Compiled, value: &SpannedValue<String>| -> Span {
+        member
+            .file
+            .subspan(value.start() as u64, value.end() as u64)
+            .into()
+    };
+    let abort_states: Vec<(&Compiled, &SpannedValue<String>)> = members
+        .iter()
+        .filter_map(|m| m.config.abort_state.as_ref().map(|a| (*m, a)))
+        .collect();
+    match &manifest.abort_state {
+        Some(convention) => {
+            let convention_span = Span::from_spanned(&context, convention);
+            for (member, abort_state) in &abort_states {
+                if abort_state.get_ref() != convention.get_ref() {
+                    context
+                        .warn(format!(
+                            "member aborts to `{}` rather than the workspace's abort state `{}`",
+                            abort_state.get_ref(),
+                            convention.get_ref()
+                        ))
+                        .set_primary_span(span_in(member, abort_state), "declared here")
+                        .span_label(convention_span, "the workspace convention")
+                        .emit();
+                }
+            }
+        }
+        None => {
+            if let Some((first, first_state)) = abort_states.first() {
+                for (member, abort_state) in &abort_states[1..] {
+                    if abort_state.get_ref() != first_state.get_ref() {
+                        context
+                            .warn(format!(
+                                "members abort to different states, `{}` and `{}`",
+                                first_state.get_ref(),
+                                abort_state.get_ref()
+                            ))
+                            .set_primary_span(span_in(member, abort_state), "declared here")
+                            .span_label(span_in(first, first_state), "first declared here")
+                            .emit();
+                        context
+                            .help("settle on one name and record it as `abort_state` in the workspace manifest")
+                            .emit();
+                    }
+                }
+            }
+        }
+    }
+
+    match context.end_phase_and_emit() {
+        Ok(warnings) => all_diagnostics.extend(warnings),
+        Err(errors) => failed.extend(errors),
+    }
+    if result.is_err() || compiled.iter().any(Option::is_none) {
+        failed.extend(all_diagnostics);
+        return Err(failed);
+    }
+    let built = compiled.into_iter().flatten().map(|c| c.member).collect();
+    Ok((built, all_diagnostics))
+}
+
+/// Builds every member of the workspace with the manifest at `manifest_path`, writing each encoded
+/// config and the artifacts in `emits` to the member's directory. No config is written unless
+/// every member builds
+pub fn build(
+    session: &mut Session,
+    manifest_path: &Path,
+    emits: &[EmitKind],
+) -> Result<(Vec<Built>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let display = manifest_path.display().to_string();
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .map_err(|err| error(format!("failed to read file `{}`: {err:?}", path.display())))
+    };
+    let manifest_source = read(manifest_path)?;
+    let manifest: Manifest = toml::from_str(&manifest_source)
+        .map_err(|err| error(format!("failed to parse `{display}`: {err}")))?;
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    let profile = Profile::of(session);
+
+    let mut members = Vec::new();
+    for member in &manifest.members {
+        let name = member.name();
+        let config = root.join(member.config.get_ref());
+        let dir = artifact_dir(root, &name, profile);
+        let source = read(&config)?;
+        // Emitted artifacts are written while compiling, so the directory must already exist
+        if !emits.is_empty() {
+            std::fs::create_dir_all(&dir)
+                .map_err(|err| error(format!("failed to create `{}`: {err:?}", dir.display())))?;
+        }
+        let member = Built {
+            name,
+            config,
+            dir,
+            bytes: Vec::new(),
+            overrides: Vec::new(),
+        };
+        members.push((member, source));
+    }
+
+    let (built, mut diagnostics) = build_sources(
+        session,
+        &manifest,
+        (display, manifest_source),
+        members,
+        emits,
+    )?;
+    for member in &built {
+        let output = member.output();
+        let write = || -> Result<(), String> {
+            std::fs::create_dir_all(&member.dir).map_err(|err| format!("{err:?}"))?;
+            let path = output.display().to_string();
+            // The marker is written first, so a ground test config never exists without it
+            let ground_test = profile == Profile::GroundTest;
+            crate::approval::Metadata::mark_build(
+                &path,
+                &member.bytes,
+                ground_test,
+                &member.overrides,
+                None,
+            )
+            .map_err(|err| err.to_string())?;
+            std::fs::write(&output, &member.bytes).map_err(|err| format!("{err:?}"))
+        };
+        if let Err(err) = write() {
+            let message = format!("failed to write to file `{}`: {err}", output.display());
+            diagnostics.extend(error(message));
+            return Err(diagnostics);
+        }
+    }
+    Ok((built, diagnostics))
+}
+
+/// Removes the artifacts of the workspace with the manifest at `manifest_path`. Returns the
+/// directory that was removed, or `None` if there were no artifacts
+pub fn clean(manifest_path: &Path) -> std::io::Result<Option<PathBuf>> {
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    let out = root.join(OUT_DIR);
+    match std::fs::remove_dir_all(&out) {
+        Ok(()) => Ok(Some(out)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use codemap_diagnostic::{Diagnostic, Level};
+
+    use super::{artifact_dir, build_sources, Built, Manifest, Member, Profile};
+    use crate::{EmitKind, Session};
+
+    const MEMBER: &str = r#"
+abort_state = "ABORT"
+
+[[states]]
+name = "ABORT"
+abort_safe = true
+"#;
+
+    #[test]
+    fn abort_state_convention() {
+        let build = |manifest: &str, states: [&str; 2]| {
+            let members = states
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    let member = Built {
+                        name: i.to_string(),
+                        config: format!("{i}.toml").into(),
+                        dir: format!("out/{i}/flight").into(),
+                        bytes: Vec::new(),
+                        overrides: Vec::new(),
+                    };
+                    (member, MEMBER.replace("ABORT", s))
+                })
+                .collect();
+            let mut session = Session::new();
+            let source = format!(
+                "{manifest}\n[[members]]\nconfig = \"0.toml\"\n\n[[members]]\nconfig = \"1.toml\"\n"
+            );
+            let manifest = toml::from_str(&source).unwrap();
+            build_sources(
+                &mut session,
+                &manifest,
+                ("nova.toml".to_owned(), source),
+                members,
+                &[],
+            )
+        };
+        let warnings = |result: Result<(Vec<Built>, Vec<Diagnostic>), _>| -> Vec<String> {
+            let (built, diagnostics) = result.unwrap();
+            assert!(built.iter().all(|b| !b.bytes.is_empty()));
+            diagnostics
+                .into_iter()
+                .filter(|d| d.level == Level::Warning)
+                .map(|d| d.message)
+                .collect()
+        };
+
+        assert!(warnings(build("", ["Safe", "Safe"])).is_empty());
+        assert_eq!(
+            warnings(build("", ["Safe", "Abort"])),
+            ["members abort to different states, `Safe` and `Abort`"]
+        );
+        assert_eq!(
+            warnings(build("abort_state = \"Abort\"", ["Safe", "Abort"])),
+            ["member aborts to `Safe` rather than the workspace's abort state `Abort`"]
+        );
+        assert!(build("target = \"mars\"", ["Safe", "Safe"]).is_err());
+    }
+
+    #[test]
+    fn layout() {
+        let manifest: Manifest = toml::from_str(
+            "[[members]]\nconfig = \"hyperion/rocket.toml\"\n\n[[members]]\nname = \"icarus\"\nconfig = \"rocket.toml\"\n",
+        )
+        .unwrap();
+        let names: Vec<_> = manifest.members.iter().map(Member::name).collect();
+        assert_eq!(names, ["hyperion", "icarus"]);
+
+        let dir = artifact_dir(Path::new("project"), "hyperion", Profile::GroundTest);
+        assert_eq!(dir, Path::new("project/out/hyperion/ground-test"));
+        let built = Built {
+            name: "hyperion".to_owned(),
+            config: "project/hyperion/rocket.toml".into(),
+            dir,
+            bytes: Vec::new(),
+            overrides: Vec::new(),
+        };
+        assert_eq!(
+            built.artifact(EmitKind::AbortReasons.default_extension()),
+            Path::new("project/out/hyperion/ground-test/config.aborts.json")
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! YAML front end, enabled with the `yaml` feature.
+//!
+//! Configs can be written in YAML with exactly the same structure as the toml format. The document
+//! is built from `yaml-rust`'s event stream so that every value keeps the span of its source, and
+//! scalars are typed using the YAML 1.2 core schema.
+
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, TScalarStyle};
+
+use crate::tree::{Error, Node, Value};
+
+/// Parses the first document in `source`
+pub fn parse(source: &str) -> Result<Node, Error> {
+    // yaml-rust reports positions in characters, but spans are in bytes
+    let offsets: Vec<usize> = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(source.len()))
+        .collect();
+    let mut builder = Builder {
+        source,
+        offsets: &offsets,
+        stack: Vec::new(),
+        root: None,
+        error: None,
+    };
+    let mut parser = Parser::new(source.chars());
+    if let Err(e) = parser.load(&mut builder, false) {
+        let start = builder.offset(e.marker());
+        return Err(Error::new(e.to_string(), (start, start)));
+    }
+    if let Some(error) = builder.error {
+        return Err(error);
+    }
+    builder
+        .root
+        .ok_or_else(|| Error::new("expected a document", (0, 0)))
+}
+
+enum Frame {
+    Sequence(usize, Vec<Node>),
+
+    /// A mapping, with the key that is waiting for its value
+    Mapping(usize, Vec<(String, Node)>, Option<String>),
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    offsets: &'a [usize],
+    stack: Vec<Frame>,
+    root: Option<Node>,
+
+    /// The first error encountered. Later events are ignored
+    error: Option<Error>,
+}
+
+impl<'a> Builder<'a> {
+    fn offset(&self, mark: &Marker) -> usize {
+        self.offsets[mark.index().min(self.offsets.len() - 1)]
+    }
+
+    /// Returns the end of a collection that closed at `end`, not including trailing whitespace
+    fn collection_end(&self, start: usize, end: usize) -> usize {
+        let trimmed = self.source[start..end.max(start)].trim_end();
+        start + trimmed.len()
+    }
+
+    /// Returns the byte length of the scalar token starting at `start`
+    fn scalar_len(&self, start: usize, value: &str, style: TScalarStyle) -> usize {
+        let rest = &self.source[start..];
+        let quote = match style {
+            TScalarStyle::SingleQuoted => '\'',
+            TScalarStyle::DoubleQuoted => '"',
+            _ => {
+                // Plain and block scalars: the value is usually verbatim, otherwise fall back to
+                // the rest of the line
+                if rest.starts_with(value) {
+                    return value.len();
+                }
+                return rest.find('\n').unwrap_or(rest.len());
+            }
+        };
+        let mut chars = rest.char_indices().skip(1);
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if quote == '"' => {
+                    chars.next();
+                }
+                c if c == quote => {
+                    // `''` is an escaped quote inside single quoted strings
+                    if quote == '\'' && rest[i + 1..].starts_with('\'') {
+                        chars.next();
+                        continue;
+                    }
+                    return i + 1;
+                }
+                _ => {}
+            }
+        }
+        rest.len()
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            None => {
+                if self.root.is_none() {
+                    self.root = Some(node);
+                }
+            }
+            Some(Frame::Sequence(_, items)) => items.push(node),
+            Some(Frame::Mapping(_, entries, key)) => match key.take() {
+                Some(key) => {
+                    if entries.iter().any(|(k, _)| *k == key) {
+                        self.error = Some(Error::new(format!("duplicate key `{key}`"), node.span));
+                        return;
+                    }
+                    entries.push((key, node));
+                }
+                None => match node.value {
+                    Value::String(s) => *key = Some(s),
+                    _ => self.error = Some(Error::new("keys must be strings", node.span)),
+                },
+            },
+        }
+    }
+}
+
+/// Types a plain scalar using the YAML 1.2 core schema
+fn plain_scalar(value: String) -> Value {
+    match value.as_str() {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(i) = value.parse::<i64>() {
+        return Value::Integer(i);
+    }
+    let looks_numeric = value.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c));
+    match value.parse::<f64>() {
+        Ok(f) if looks_numeric && f.is_finite() => Value::Float(f),
+        _ => Value::String(value),
+    }
+}
+
+impl<'a> MarkedEventReceiver for Builder<'a> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+        let offset = self.offset(&mark);
+        match event {
+            Event::Scalar(value, style, _, _) => {
+                let end = offset + self.scalar_len(offset, &value, style);
+                let value = match style {
+                    TScalarStyle::Plain => plain_scalar(value),
+                    _ => Value::String(value),
+                };
+                self.push(Node::new((offset, end), value));
+            }
+            Event::SequenceStart(_) => self.stack.push(Frame::Sequence(offset, Vec::new())),
+            Event::MappingStart(_) => self.stack.push(Frame::Mapping(offset, Vec::new(), None)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let node = match self.stack.pop() {
+                    Some(Frame::Sequence(start, items)) => Node::new(
+                        (start, self.collection_end(start, offset)),
+                        Value::Array(items),
+                    ),
+                    Some(Frame::Mapping(start, entries, _)) => Node::new(
+                        (start, self.collection_end(start, offset)),
+                        Value::Table(entries),
+                    ),
+                    None => return,
+                };
+                self.push(node);
+            }
+            Event::Alias(_) => {
+                self.error = Some(Error::new(
+                    "aliases are not supported in configs",
+                    (offset, offset + 1),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::tree::Value;
+
+    #[test]
+    fn parse_with_spans() {
+        let source = "states:\n  - name: \"Armed\"\n    abort_safe: true\n    delay: 2.5\n";
+        let node = parse(source).unwrap();
+        let states = match &node.value {
+            Value::Table(entries) => &entries[0].1,
+            _ => panic!(),
+        };
+        let state = match &states.value {
+            Value::Array(items) => match &items[0].value {
+                Value::Table(entries) => entries.clone(),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        let name = &state[0].1;
+        assert_eq!(&source[name.span.0..name.span.1], "\"Armed\"");
+        assert_eq!(state[1].1.value, Value::Bool(true));
+        assert_eq!(state[2].1.value, Value::Float(2.5));
+
+        assert!(parse("a: &x 1\nb: *x\n").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Checks the configs from past flights in `corpus/`, see `corpus/README.md`.
+//!
+//! Every config must still verify and encode to the format version and payload recorded in the
+//! `.ncf` file next to it, and the recorded bytes must still decode to the same config. The version
+//! of the verifier in the header is not compared, so releases do not need the corpus re-recorded.
+//! The output must have been recorded against the `nova-software-common` in `Cargo.lock`, which is
+//! named in `corpus/recorded-with.txt`. Intentional changes are
+//! recorded by running `NOVA_BLESS_CORPUS=1 cargo test --test corpus` and describing the change in
+//! `corpus/CHANGES.md`.
+
+use std::path::{Path, PathBuf};
+
+use nova_verifier::{ncf, Session};
+
+mod support;
+
+const CORPUS: &str = "corpus";
+
+/// The file naming the `nova-software-common` that the corpus output was recorded against
+const RECORDED_WITH: &str = "recorded-with.txt";
+
+/// Every config in the corpus, sorted by path
+fn configs(dir: &Path) -> Vec<PathBuf> {
+    let mut configs: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    configs.sort();
+    configs
+}
+
+#[test]
+fn corpus() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(CORPUS);
+    let bless = std::env::var_os("NOVA_BLESS_CORPUS").is_some();
+    let configs = configs(&dir);
+    assert!(!configs.is_empty(), "`{CORPUS}` has no configs");
+
+    let common = support::common_source();
+    let recorded_with = dir.join(RECORDED_WITH);
+    let mut failures = Vec::new();
+    if bless {
+        std::fs::write(&recorded_with, format!("{common}\n")).unwrap();
+    } else {
+        // The payload is whatever nova-software-common encodes, so output recorded against another
+        // build of it, such as a local checkout, proves nothing
+        let recorded = std::fs::read_to_string(&recorded_with).unwrap_or_default();
+        if recorded.trim() != common {
+            failures.push(format!(
+                "the corpus output was not recorded against nova-software-common {common}"
+            ));
+        }
+    }
+    for path in &configs {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let source = std::fs::read_to_string(path).unwrap();
+        let mut session = Session::new();
+        let bytes = match nova_verifier::verify_inner(&mut session, source, format!("{name}.toml"))
+        {
+            Ok((bytes, _)) => bytes,
+            Err(err) => {
+                let messages: Vec<_> = err.diagnostics().iter().map(|d| &d.message).collect();
+                failures.push(format!("`{name}` no longer verifies: {messages:?}"));
+                continue;
+            }
+        };
+
+        let expected_path = path.with_extension("ncf");
+        if bless {
+            std::fs::write(&expected_path, &bytes).unwrap();
+            continue;
+        }
+        let expected = match std::fs::read(&expected_path) {
+            Ok(expected) => expected,
+            Err(_) => {
+                failures.push(format!("`{name}` has no recorded output"));
+                continue;
+            }
+        };
+        // The config as it is lowered now
+        let (_, config) = ncf::decode(&bytes).unwrap();
+        match ncf::decode(&expected) {
+            Ok((_, decoded)) if decoded == config => {}
+            Ok(_) => failures.push(format!(
+                "`{name}.ncf` decodes to a different config than `{name}.toml` lowers to"
+            )),
+            Err(err) => failures.push(format!("`{name}.ncf` no longer decodes: {err}")),
+        }
+        // The header also records the version of the verifier, which changes with every release
+        // without changing the encoding, so only the format version and payload are compared
+        let (header, payload) = ncf::split(&bytes).unwrap();
+        let (recorded, recorded_payload) = match ncf::split(&expected) {
+            Ok(split) => split,
+            Err(_) => continue,
+        };
+        if header.format_version != recorded.format_version {
+            failures.push(format!(
+                "`{name}` encodes to format version {} but format version {} was recorded",
+                header.format_version, recorded.format_version
+            ));
+        }
+        if payload != recorded_payload {
+            let first = payload
+                .iter()
+                .zip(recorded_payload)
+                .position(|(a, b)| a != b)
+                .unwrap_or(payload.len().min(recorded_payload.len()));
+            failures.push(format!(
+                "`{name}` encodes to {} bytes that differ from the {} recorded bytes, starting at byte {}",
+                bytes.len(),
+                expected.len(),
+                ncf::HEADER_LEN + first
+            ));
+        }
+    }
+    for recorded in std::fs::read_dir(&dir).unwrap() {
+        let recorded = recorded.unwrap().path();
+        if recorded.extension().is_some_and(|e| e == "ncf")
+            && !configs.contains(&recorded.with_extension("toml"))
+        {
+            failures.push(format!("`{}` has no config", recorded.display()));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nif these changes are intended, rerun with `NOVA_BLESS_CORPUS=1` and describe them in `{CORPUS}/CHANGES.md`",
+        failures.join("\n")
+    );
+}
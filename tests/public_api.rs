@@ -0,0 +1,214 @@
+//! Checks that the stable API, see `nova_verifier::prelude`, only changes on purpose.
+//!
+//! Every public item of the stable modules, with the fields of its structs and the variants of its
+//! enums, is recorded in `tests/public-api.txt`. An intentional change is recorded by running
+//! `NOVA_BLESS_PUBLIC_API=1 cargo test --test public_api`, and needs a major version bump if it
+//! removes or changes an item, or adds a field or variant.
+
+use std::path::PathBuf;
+
+use nova_verifier::prelude::*;
+
+const SNAPSHOT: &str = "tests/public-api.txt";
+
+/// The files that declare the stable API
+const STABLE: &[&str] = &[
+    "src/lib.rs",
+    "src/error.rs",
+    "src/prelude.rs",
+    "src/diagnostics.rs",
+    "src/limits.rs",
+    "src/ncf.rs",
+    "src/upper.rs",
+    "src/spanned.rs",
+];
+
+/// The signature of every public item in `source` that is not hidden from the docs, joined onto
+/// one line each, followed by the members of structs and enums. Items in test modules are skipped
+fn public_items(source: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut lines = source.lines().peekable();
+    let mut hidden = false;
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("mod tests") {
+            break;
+        }
+        if trimmed.starts_with("#[doc(hidden)]") || trimmed.starts_with("#[cfg(test)]") {
+            hidden = true;
+            continue;
+        }
+        if trimmed.starts_with("#[") || trimmed.starts_with("//") {
+            continue;
+        }
+        let is_item = [
+            "fn ", "struct ", "enum ", "trait ", "const ", "type ", "use ", "mod ",
+        ]
+        .iter()
+        .any(|kind| trimmed.starts_with(&format!("pub {kind}")));
+        if is_item && !hidden {
+            let mut signature = trimmed.to_owned();
+            let end: &[char] = if trimmed.starts_with("pub use ") {
+                &[';']
+            } else {
+                &['{', ';', ')']
+            };
+            while !signature.ends_with(end) {
+                let Some(next) = lines.next() else { break };
+                signature.push(' ');
+                signature.push_str(next.trim());
+            }
+            let body = signature.ends_with('{');
+            items.push(normalize(signature.trim_end_matches(['{', ' '])));
+            if body && (trimmed.starts_with("pub struct ") || trimmed.starts_with("pub enum ")) {
+                let public = trimmed.starts_with("pub enum ");
+                items.extend(members(lines.clone(), public));
+            }
+        }
+        hidden = false;
+    }
+    items
+}
+
+/// The fields or variants of the struct or enum whose body starts at `lines`, indented under the
+/// item. Private fields are recorded as a single `..`, since adding one stops the struct from being
+/// built outside the crate
+fn members<'a>(lines: impl Iterator<Item = &'a str>, public: bool) -> Vec<String> {
+    let mut members = Vec::new();
+    let mut private = false;
+    let mut member = String::new();
+    let mut depth = 1;
+    let mut attribute = 0;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+        // The brackets still open in an attribute, which may span several lines
+        if attribute > 0 || (depth == 1 && member.is_empty() && trimmed.starts_with("#[")) {
+            attribute += nesting(trimmed);
+            continue;
+        }
+        depth += nesting(trimmed);
+        if depth == 0 {
+            member.push_str(trimmed.trim_end_matches('}'));
+        } else {
+            member.push_str(trimmed);
+            member.push(' ');
+        }
+        if depth == 0 || (depth == 1 && trimmed.ends_with(',')) {
+            let finished = member.trim().trim_end_matches(',');
+            let finished = finished.replace("( ", "(").replace(", )", ")");
+            let finished = finished.replace(", }", " }");
+            member.clear();
+            match finished.as_str() {
+                "" => {}
+                _ if public || finished.starts_with("pub ") => {
+                    members.push(format!("    {finished}"))
+                }
+                _ => private = true,
+            }
+        }
+        if depth == 0 {
+            break;
+        }
+    }
+    if private {
+        members.push("    ..".to_owned());
+    }
+    members
+}
+
+/// How much deeper in brackets the end of `text` is than its start, ignoring string literals
+fn nesting(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Joins a signature that was split over several lines the way it would be written on one
+fn normalize(signature: &str) -> String {
+    let signature = signature.replace("( ", "(").replace("{ ", "{");
+    signature.replace(", )", ")").replace(", }", "}")
+}
+
+fn snapshot() -> String {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut snapshot = String::new();
+    for file in STABLE {
+        let source = std::fs::read_to_string(root.join(file)).unwrap();
+        snapshot.push_str(&format!("# {file}\n"));
+        for item in public_items(&source) {
+            snapshot.push_str(&item);
+            snapshot.push('\n');
+        }
+        snapshot.push('\n');
+    }
+    snapshot
+}
+
+#[test]
+fn public_api() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    let actual = snapshot();
+    if std::env::var_os("NOVA_BLESS_PUBLIC_API").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    if actual != expected {
+        let changed: Vec<_> = actual
+            .lines()
+            .filter(|l| !expected.lines().any(|e| e == *l))
+            .map(|l| format!("+ {l}"))
+            .chain(
+                expected
+                    .lines()
+                    .filter(|e| !actual.lines().any(|l| l == *e))
+                    .map(|e| format!("- {e}")),
+            )
+            .collect();
+        panic!(
+            "the stable API changed, rerun with NOVA_BLESS_PUBLIC_API=1 if this is intentional:\n{}",
+            changed.join("\n")
+        );
+    }
+}
+
+/// Fails to compile if the signatures of the entry points change
+#[test]
+fn entry_points() {
+    type Verify =
+        fn(&mut Session, String, String) -> Result<(Vec<u8>, Vec<Diagnostic>), VerifyError>;
+    type Load = fn(
+        &mut Session,
+        String,
+        String,
+    )
+        -> Result<(upper::ConfigFile, index::ConfigFile, Vec<Diagnostic>), VerifyError>;
+    let _: Verify = verify_inner;
+    let _: Load = load_config;
+
+    let mut session = Session::new();
+    let err = verify_inner(&mut session, "states = 1".to_owned(), "a.toml".to_owned()).unwrap_err();
+    assert!(matches!(err, VerifyError::Parse(_)));
+    assert!(err.diagnostics().iter().any(|d| d.level == Level::Error));
+}
@@ -0,0 +1,105 @@
+//! Compares the encoded size of reference configs against the baselines recorded in
+//! `tests/size_baselines.toml`.
+//!
+//! Flash headroom on the flight computer is tight, and changes to lowering or encoding have grown
+//! configs before without anyone noticing. This fails when a config grows by more than
+//! [`MAX_GROWTH_PERCENT`] over its baseline. Intentional changes are recorded by running
+//! `NOVA_BLESS_SIZES=1 cargo test --test size_regression`, which rewrites the baselines. The
+//! baselines name the `nova-software-common` they were recorded against, and must be recorded again
+//! when it is not the one in `Cargo.lock`.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use nova_verifier::{presets::Preset, Session};
+
+mod support;
+
+/// How much a config may grow over its baseline before the test fails
+const MAX_GROWTH_PERCENT: usize = 2;
+
+const BASELINES: &str = "tests/size_baselines.toml";
+
+/// The start of the comment in the baselines that names the `nova-software-common` they were
+/// recorded against
+const RECORDED_WITH: &str = "# Recorded against nova-software-common ";
+
+/// The name and source of every reference config
+fn corpus() -> Vec<(String, String)> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut corpus: Vec<_> = Preset::ALL
+        .iter()
+        .map(|p| (format!("preset-{p}"), p.source().to_owned()))
+        .collect();
+    let example = std::fs::read_to_string(root.join("rocket.toml")).unwrap();
+    corpus.push(("rocket".to_owned(), example));
+    corpus
+}
+
+fn encoded_len(name: &str, source: String) -> usize {
+    let mut session = Session::new();
+    let result = nova_verifier::verify_inner(&mut session, source, format!("{name}.toml"));
+    let (bytes, _) = result.unwrap_or_else(|d| panic!("`{name}` failed to verify: {d:#?}"));
+    bytes.len()
+}
+
+#[test]
+fn size_regression() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(BASELINES);
+    let sizes: BTreeMap<String, usize> = corpus()
+        .into_iter()
+        .map(|(name, source)| {
+            let len = encoded_len(&name, source);
+            (name, len)
+        })
+        .collect();
+
+    let common = support::common_source();
+    if std::env::var_os("NOVA_BLESS_SIZES").is_some() {
+        let mut out = String::from(
+            "# Encoded sizes in bytes of the reference configs, see tests/size_regression.rs\n",
+        );
+        out.push_str(&format!("{RECORDED_WITH}{common}\n"));
+        out.push_str(&toml::to_string(&sizes).unwrap());
+        std::fs::write(&path, out).unwrap();
+        return;
+    }
+
+    let baselines = std::fs::read_to_string(&path).unwrap();
+    let mut failures = Vec::new();
+    let recorded = baselines
+        .lines()
+        .find_map(|line| line.strip_prefix(RECORDED_WITH));
+    if recorded != Some(common.as_str()) {
+        failures.push(format!(
+            "the baselines were not recorded against nova-software-common {common}"
+        ));
+    }
+    let baselines: BTreeMap<String, usize> = toml::from_str(&baselines).unwrap();
+    for (name, &len) in &sizes {
+        let baseline = match baselines.get(name) {
+            Some(&baseline) => baseline,
+            None => {
+                failures.push(format!(
+                    "`{name}` has no baseline, it encodes to {len} bytes"
+                ));
+                continue;
+            }
+        };
+        if len * 100 > baseline * (100 + MAX_GROWTH_PERCENT) {
+            failures.push(format!(
+                "`{name}` grew from {baseline} to {len} bytes, more than {MAX_GROWTH_PERCENT}%"
+            ));
+        } else if len != baseline {
+            println!("`{name}` changed from {baseline} to {len} bytes");
+        }
+    }
+    for name in baselines.keys().filter(|n| !sizes.contains_key(*n)) {
+        failures.push(format!("`{name}` has a baseline but is not in the corpus"));
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nrerun with `NOVA_BLESS_SIZES=1` if these sizes are expected",
+        failures.join("\n")
+    );
+}
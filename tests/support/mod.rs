@@ -0,0 +1,22 @@
+//! Helpers shared by the tests that compare against recorded output.
+
+use std::path::PathBuf;
+
+/// The `nova-software-common` that this build resolved, as recorded next to output that depends on
+/// its encoding. Output recorded against any other build of the crate, such as a local checkout,
+/// has to be recorded again
+pub fn common_source() -> String {
+    let lock = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.lock");
+    let lock: toml::Value = toml::from_str(&std::fs::read_to_string(lock).unwrap()).unwrap();
+    let package = lock["package"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["name"].as_str() == Some("nova-software-common"))
+        .expect("`Cargo.lock` has no `nova-software-common`");
+    let version = package["version"].as_str().unwrap();
+    match package.get("source").and_then(|s| s.as_str()) {
+        Some(source) => format!("{version} from {source}"),
+        None => format!("{version} from a local path"),
+    }
+}